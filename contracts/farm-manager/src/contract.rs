@@ -12,6 +12,7 @@ use crate::error::ContractError;
 use crate::helpers::{
     validate_emergency_unlock_penalty, validate_farm_expiration_time, validate_unlocking_duration,
 };
+use crate::position::commands::{LiquidityMigration, LiquidityRedemption};
 use crate::state::{CONFIG, FARM_COUNTER};
 use crate::{farm, manager, position, queries};
 
@@ -130,9 +131,54 @@ pub fn execute(
             PositionAction::Withdraw {
                 identifier,
                 emergency_unlock,
-            } => {
-                position::commands::withdraw_position(deps, env, info, identifier, emergency_unlock)
-            }
+            } => position::commands::withdraw_position(
+                deps,
+                env,
+                info,
+                identifier,
+                emergency_unlock,
+                None,
+            ),
+            PositionAction::WithdrawLiquidity {
+                identifier,
+                emergency_unlock,
+                pool_identifier,
+                min_assets_out,
+                target_denom,
+            } => position::commands::withdraw_position(
+                deps,
+                env,
+                info,
+                identifier,
+                emergency_unlock,
+                Some(LiquidityRedemption {
+                    pool_identifier,
+                    min_assets_out,
+                    target_denom,
+                }),
+            ),
+            PositionAction::MigrateLiquidity {
+                identifier,
+                pool_identifier,
+                target_pool_identifier,
+                slippage_tolerance,
+                max_spread,
+                swap_operations,
+                excess_mode,
+            } => position::commands::migrate_position(
+                deps,
+                env,
+                info,
+                identifier,
+                LiquidityMigration {
+                    pool_identifier,
+                    target_pool_identifier,
+                    slippage_tolerance,
+                    max_spread,
+                    swap_operations,
+                    excess_mode,
+                },
+            ),
         },
         ExecuteMsg::UpdateConfig {
             fee_collector_addr,
@@ -162,6 +208,14 @@ pub fn execute(
                 emergency_unlock_penalty,
             )
         }
+        ExecuteMsg::UpdatePositionOperators { add, remove } => {
+            cw_utils::nonpayable(&info)?;
+            manager::commands::update_position_operators(deps, info, add, remove)
+        }
+        ExecuteMsg::ReceiveShares(msg) => {
+            cw_utils::nonpayable(&info)?;
+            position::commands::receive_shares(deps, env, info, msg)
+        }
     }
 }
 
@@ -202,6 +256,12 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
         } => Ok(to_json_binary(&queries::query_lp_weight(
             deps, address, denom, epoch_id,
         )?)?),
+        QueryMsg::PositionOperators {} => {
+            Ok(to_json_binary(&queries::query_position_operators(deps)?)?)
+        }
+        QueryMsg::IsPositionOperator { address } => Ok(to_json_binary(
+            &queries::query_is_position_operator(deps, address)?,
+        )?),
     }
 }
 
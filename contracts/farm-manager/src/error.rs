@@ -165,6 +165,9 @@ pub enum ContractError {
 
     #[error("Invalid identifier provided: {identifier}.")]
     InvalidIdentifier { identifier: String },
+
+    #[error("Only messages creating or expanding a position can be executed with the received LP shares")]
+    InvalidSharesMessage,
 }
 
 impl From<semver::Error> for ContractError {
@@ -10,6 +10,7 @@ use amm::coin::{get_factory_token_creator, is_factory_token};
 use amm::constants::MONTH_IN_SECONDS;
 use amm::epoch_manager::{EpochResponse, QueryMsg};
 use amm::farm_manager::{Config, Farm, FarmParams, Position, DEFAULT_FARM_DURATION};
+use amm::lp_common::{get_internal_lp_denom_parts, is_internal_lp_denom};
 
 use crate::ContractError;
 
@@ -191,10 +192,13 @@ pub(crate) fn validate_lp_denom(
     lp_denom: &str,
     pool_manager_addr: &str,
 ) -> Result<(), ContractError> {
-    ensure!(
-        is_factory_token(lp_denom) && get_factory_token_creator(lp_denom)? == pool_manager_addr,
-        ContractError::AssetMismatch
-    );
+    let is_pool_manager_lp = if is_internal_lp_denom(lp_denom) {
+        get_internal_lp_denom_parts(lp_denom)?.0 == pool_manager_addr
+    } else {
+        is_factory_token(lp_denom) && get_factory_token_creator(lp_denom)? == pool_manager_addr
+    };
+
+    ensure!(is_pool_manager_lp, ContractError::AssetMismatch);
 
     Ok(())
 }
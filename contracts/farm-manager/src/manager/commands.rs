@@ -1,6 +1,6 @@
 use cosmwasm_std::{
-    ensure, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, StdError,
-    Storage, Uint128, Uint64,
+    ensure, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, Empty, Env, MessageInfo, Response,
+    StdError, Storage, Uint128, Uint64,
 };
 
 use amm::farm_manager::MIN_FARM_AMOUNT;
@@ -12,7 +12,9 @@ use crate::helpers::{
     validate_emergency_unlock_penalty, validate_farm_epochs, validate_farm_expiration_time,
     validate_identifier, validate_lp_denom, validate_unlocking_duration,
 };
-use crate::state::{get_farm_by_identifier, get_farms_by_lp_denom, CONFIG, FARMS, FARM_COUNTER};
+use crate::state::{
+    get_farm_by_identifier, get_farms_by_lp_denom, CONFIG, FARMS, FARM_COUNTER, POSITION_OPERATORS,
+};
 use crate::ContractError;
 
 pub(crate) fn fill_farm(
@@ -390,3 +392,29 @@ pub(crate) fn update_config(
         ),
     ]))
 }
+
+/// Adds and removes the addresses allowed to manage positions on behalf of other receivers.
+pub(crate) fn update_position_operators(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Option<Vec<String>>,
+    remove: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let mut attributes = vec![("action", "update_position_operators".to_string())];
+
+    for operator in add.unwrap_or_default() {
+        let operator = deps.api.addr_validate(&operator)?;
+        POSITION_OPERATORS.save(deps.storage, &operator, &Empty {})?;
+        attributes.push(("added_operator", operator.to_string()));
+    }
+
+    for operator in remove.unwrap_or_default() {
+        let operator = deps.api.addr_validate(&operator)?;
+        POSITION_OPERATORS.remove(deps.storage, &operator);
+        attributes.push(("removed_operator", operator.to_string()));
+    }
+
+    Ok(Response::default().add_attributes(attributes))
+}
@@ -1,16 +1,18 @@
 use cosmwasm_std::{
-    coin, ensure, Addr, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response,
+    coin, ensure, from_json, Addr, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response,
     Uint128,
 };
 use std::collections::HashSet;
 
-use amm::farm_manager::Position;
+use amm::farm_manager::{ExecuteMsg, Position, PositionAction};
+use amm::lp_common::{execute_with_lp_msg, transfer_lp_msg};
+use amm::pool_manager::{ExcessDepositMode, PoolsResponse, SharesReceiveMsg, SwapOperation};
 
 use crate::helpers::{validate_identifier, validate_lp_denom};
 use crate::position::helpers::{
-    calculate_weight, create_penalty_share_msg, get_latest_address_weight, reconcile_user_state,
-    validate_no_pending_rewards, AUTO_POSITION_ID_PREFIX, EXPLICIT_POSITION_ID_PREFIX,
-    PENALTY_FEE_SHARE,
+    calculate_weight, create_penalty_share_msg, get_latest_address_weight, is_position_operator,
+    reconcile_user_state, validate_no_pending_rewards, AUTO_POSITION_ID_PREFIX,
+    EXPLICIT_POSITION_ID_PREFIX, PENALTY_FEE_SHARE,
 };
 use crate::position::helpers::{
     validate_positions_limit, validate_unlocking_duration_for_position,
@@ -39,13 +41,15 @@ pub(crate) fn create_position(
     // validate unlocking duration
     validate_unlocking_duration_for_position(&config, unlocking_duration)?;
 
-    // if a receiver was specified, check that it was the pool manager who
-    // is sending the message, as it has the possibility to lock LP tokens on
+    // if a receiver was specified, check that it was the pool manager or a position operator who
+    // is sending the message, as they have the possibility to lock LP tokens on
     // behalf of the user
     let receiver = if let Some(ref receiver) = receiver {
         let receiver = deps.api.addr_validate(receiver)?;
         ensure!(
-            info.sender == config.pool_manager_addr || info.sender == receiver,
+            info.sender == config.pool_manager_addr
+                || info.sender == receiver
+                || is_position_operator(deps.storage, &info.sender),
             ContractError::Unauthorized
         );
 
@@ -107,6 +111,41 @@ pub(crate) fn create_position(
     ]))
 }
 
+/// Creates or expands a position with the LP shares of a pool with internal LP accounting, sent
+/// by the pool manager on behalf of the original sender.
+pub(crate) fn receive_shares(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: SharesReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure!(
+        info.sender == config.pool_manager_addr,
+        ContractError::Unauthorized
+    );
+
+    let info = MessageInfo {
+        sender: deps.api.addr_validate(&msg.sender)?,
+        funds: vec![msg.lp_asset],
+    };
+
+    match from_json(&msg.msg)? {
+        ExecuteMsg::ManagePosition {
+            action:
+                PositionAction::Create {
+                    identifier,
+                    unlocking_duration,
+                    receiver,
+                },
+        } => create_position(deps, &env, info, identifier, unlocking_duration, receiver),
+        ExecuteMsg::ManagePosition {
+            action: PositionAction::Expand { identifier },
+        } => expand_position(deps, &env, info, identifier),
+        _ => Err(ContractError::InvalidSharesMessage),
+    }
+}
+
 /// Expands an existing position
 pub(crate) fn expand_position(
     deps: DepsMut,
@@ -139,7 +178,8 @@ pub(crate) fn expand_position(
         }
     );
 
-    // ensure only the receiver itself or the pool manager can refill the position
+    // ensure only the receiver itself or the pool manager can refill the position. Position
+    // operators can only create positions on behalf of others, not top up existing ones
     ensure!(
         position.receiver == info.sender || info.sender == config.pool_manager_addr,
         ContractError::Unauthorized
@@ -290,12 +330,15 @@ fn close_position_in_full(position: &mut Position, expires_at: u64) -> Uint128 {
 
 /// Withdraws the given position. If the position has not expired, i.e. the unlocking period has not
 /// passed, the position can be withdrawn with a penalty fee using the`emergency_unlock` param.
+/// If a `liquidity_redemption` is given, the LP tokens are redeemed for the pool assets through the
+/// pool manager instead of being sent back.
 pub(crate) fn withdraw_position(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     identifier: String,
     emergency_unlock: Option<bool>,
+    liquidity_redemption: Option<LiquidityRedemption>,
 ) -> Result<Response, ContractError> {
     cw_utils::nonpayable(&info)?;
 
@@ -377,7 +420,7 @@ pub(crate) fn withdraw_position(
                         position.lp_asset.denom.to_string(),
                         penalty_fee_share_per_farm_owner,
                         &farm_owner,
-                    ));
+                    )?);
                 }
             } else {
                 // if the penalty fee share per farm owner is zero, then the whole penalty fee goes
@@ -392,7 +435,7 @@ pub(crate) fn withdraw_position(
                 position.lp_asset.denom.to_string(),
                 penalty_fee_fee_collector,
                 &fee_collector_addr,
-            ));
+            )?);
         }
 
         // if the position is open, update the weights when doing the emergency withdrawal
@@ -422,14 +465,33 @@ pub(crate) fn withdraw_position(
 
     // sanity check
     if !position.lp_asset.amount.is_zero() {
-        // withdraw the remaining LP tokens
-        messages.push(
-            BankMsg::Send {
-                to_address: position.receiver.to_string(),
-                amount: vec![position.lp_asset.clone()],
+        match liquidity_redemption {
+            // redeem the remaining LP tokens for the pool assets, sending them to the receiver
+            Some(LiquidityRedemption {
+                pool_identifier,
+                min_assets_out,
+                target_denom,
+            }) => {
+                let pool_manager_addr = CONFIG.load(deps.storage)?.pool_manager_addr;
+
+                messages.push(execute_with_lp_msg(
+                    pool_manager_addr.as_str(),
+                    &amm::pool_manager::ExecuteMsg::WithdrawLiquidity {
+                        pool_identifier,
+                        min_assets_out,
+                        receiver: Some(position.receiver.to_string()),
+                        deadline: None,
+                        target_denom,
+                    },
+                    position.lp_asset.clone(),
+                )?);
             }
-            .into(),
-        );
+            // withdraw the remaining LP tokens
+            None => messages.push(transfer_lp_msg(
+                position.lp_asset.clone(),
+                position.receiver.as_str(),
+            )?),
+        }
     }
 
     POSITIONS.remove(deps.storage, &identifier)?;
@@ -449,6 +511,135 @@ pub(crate) fn withdraw_position(
         .add_messages(messages))
 }
 
+/// The parameters to redeem the LP tokens of a withdrawn position for the underlying pool assets.
+pub(crate) struct LiquidityRedemption {
+    /// The identifier of the pool the LP tokens belong to.
+    pub pool_identifier: String,
+    /// The minimum amounts of the pool assets to receive.
+    pub min_assets_out: Option<Vec<Coin>>,
+    /// The asset to withdraw the liquidity into, if any.
+    pub target_denom: Option<String>,
+}
+
+/// Migrates the LP tokens of an open position to another pool through the pool manager. The
+/// position is switched to the LP denom of the target pool with no LP tokens, and the pool manager
+/// expands it with the LP tokens minted by the target pool in the same transaction.
+pub(crate) fn migrate_position(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    identifier: String,
+    liquidity_migration: LiquidityMigration,
+) -> Result<Response, ContractError> {
+    cw_utils::nonpayable(&info)?;
+
+    // the weights of the position move to another LP denom, so the pending rewards must be claimed
+    // first as when closing a position
+    validate_no_pending_rewards(deps.as_ref(), &env, &info)?;
+
+    let mut position = get_position(deps.storage, Some(identifier.clone()))?.ok_or(
+        ContractError::NoPositionFound {
+            identifier: identifier.clone(),
+        },
+    )?;
+
+    ensure!(
+        position.receiver == info.sender,
+        ContractError::Unauthorized
+    );
+
+    ensure!(
+        position.open,
+        ContractError::PositionAlreadyClosed { identifier }
+    );
+
+    let LiquidityMigration {
+        pool_identifier,
+        target_pool_identifier,
+        slippage_tolerance,
+        max_spread,
+        swap_operations,
+        excess_mode,
+    } = liquidity_migration;
+
+    let pool_manager_addr = CONFIG.load(deps.storage)?.pool_manager_addr;
+
+    let pools_response: PoolsResponse = deps.querier.query_wasm_smart(
+        pool_manager_addr.to_string(),
+        &amm::pool_manager::QueryMsg::Pools {
+            pool_identifier: Some(target_pool_identifier.clone()),
+            start_after: None,
+            limit: None,
+        },
+    )?;
+
+    let target_lp_denom = pools_response
+        .pools
+        .first()
+        .map(|pool| pool.pool_info.lp_denom.clone())
+        .ok_or(ContractError::AssetMismatch)?;
+
+    validate_lp_denom(&target_lp_denom, pool_manager_addr.as_str())?;
+
+    update_weights(
+        deps.branch(),
+        &env,
+        &info.sender,
+        &position.lp_asset,
+        position.unlocking_duration,
+        false,
+    )?;
+
+    let migrated_position = position.clone();
+    position.lp_asset = coin(0u128, target_lp_denom);
+    POSITIONS.save(deps.storage, &position.identifier, &position)?;
+
+    reconcile_user_state(deps, &info.sender, &migrated_position)?;
+
+    let migrate_msg = execute_with_lp_msg(
+        pool_manager_addr.as_str(),
+        &amm::pool_manager::ExecuteMsg::MigrateLiquidity {
+            pool_identifier,
+            target_pool_identifier,
+            slippage_tolerance,
+            max_spread,
+            swap_operations,
+            receiver: Some(position.receiver.to_string()),
+            unlocking_duration: Some(position.unlocking_duration),
+            lock_position_identifier: Some(position.identifier.clone()),
+            excess_mode,
+            concentrated_position: None,
+        },
+        migrated_position.lp_asset.clone(),
+    )?;
+
+    Ok(Response::default()
+        .add_message(migrate_msg)
+        .add_attributes(vec![
+            ("action", "migrate_position".to_string()),
+            ("receiver", info.sender.to_string()),
+            ("identifier", position.identifier),
+            ("lp_asset", migrated_position.lp_asset.to_string()),
+            ("target_lp_denom", position.lp_asset.denom),
+        ]))
+}
+
+/// The parameters to migrate the LP tokens of a position to another pool.
+pub(crate) struct LiquidityMigration {
+    /// The identifier of the pool the LP tokens belong to.
+    pub pool_identifier: String,
+    /// The identifier of the pool to migrate the liquidity to.
+    pub target_pool_identifier: String,
+    /// The slippage tolerance of the liquidity provision to the target pool.
+    pub slippage_tolerance: Option<Decimal>,
+    /// The maximum spread of each swap performed by the migration.
+    pub max_spread: Option<Decimal>,
+    /// The swap operations for each withdrawn asset the target pool doesn't hold.
+    pub swap_operations: Option<Vec<Vec<SwapOperation>>>,
+    /// How the excess of the deposit into the target pool is handled.
+    pub excess_mode: Option<ExcessDepositMode>,
+}
+
 /// Updates the weights when managing a position. Computes what the weight is gonna be in the next epoch.
 fn update_weights(
     deps: DepsMut,
@@ -1,15 +1,16 @@
 use cosmwasm_std::{
-    ensure, Addr, BankMsg, Coin, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo,
-    Order, StdError, Storage, Uint128,
+    ensure, Addr, Coin, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo, Order,
+    StdError, StdResult, Storage, Uint128,
 };
 
 use amm::farm_manager::{Config, EpochId, Position, RewardsResponse};
+use amm::lp_common::transfer_lp_msg;
 
 use crate::farm::commands::sync_address_lp_weight_history;
 use crate::queries::query_rewards;
 use crate::state::{
     get_positions_by_receiver, has_any_lp_weight, CONFIG, LAST_CLAIMED_EPOCH, LP_WEIGHT_HISTORY,
-    MAX_ITEMS_LIMIT,
+    MAX_ITEMS_LIMIT, POSITION_OPERATORS,
 };
 use crate::ContractError;
 
@@ -145,22 +146,23 @@ pub(crate) fn validate_positions_limit(
     Ok(())
 }
 
+/// Whether the given address is allowed to manage positions on behalf of other receivers.
+pub(crate) fn is_position_operator(storage: &dyn Storage, address: &Addr) -> bool {
+    POSITION_OPERATORS.has(storage, address)
+}
+
 /// Validates the amount of positions a user can have either open or closed at a given time.
 pub(crate) fn create_penalty_share_msg(
     lp_asset_denom: String,
     commission: Uint128,
     receiver: &Addr,
-) -> CosmosMsg {
+) -> StdResult<CosmosMsg> {
     let penalty = Coin {
         denom: lp_asset_denom,
         amount: commission,
     };
 
-    BankMsg::Send {
-        to_address: receiver.to_string(),
-        amount: vec![penalty],
-    }
-    .into()
+    transfer_lp_msg(penalty, receiver.as_str())
 }
 
 /// Validates that the user has no pending rewards before performing an operation.
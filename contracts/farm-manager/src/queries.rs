@@ -1,9 +1,9 @@
-use cosmwasm_std::{Coin, Deps, Env};
+use cosmwasm_std::{Coin, Deps, Env, Order, StdResult};
 
 use amm::coin::aggregate_coins;
 use amm::farm_manager::{
-    Config, EpochId, FarmsBy, FarmsResponse, LpWeightResponse, PositionsBy, PositionsResponse,
-    RewardsResponse,
+    Config, EpochId, FarmsBy, FarmsResponse, IsPositionOperatorResponse, LpWeightResponse,
+    PositionOperatorsResponse, PositionsBy, PositionsResponse, RewardsResponse,
 };
 
 use crate::farm::commands::calculate_rewards;
@@ -11,7 +11,7 @@ use crate::helpers::get_unique_lp_asset_denoms_from_positions;
 use crate::state::{
     get_farm_by_identifier, get_farms, get_farms_by_farm_asset, get_farms_by_lp_denom,
     get_position, get_positions, get_positions_by_receiver, CONFIG, LP_WEIGHT_HISTORY,
-    MAX_ITEMS_LIMIT,
+    MAX_ITEMS_LIMIT, POSITION_OPERATORS,
 };
 use crate::ContractError;
 
@@ -148,3 +148,26 @@ pub(crate) fn query_lp_weight(
         epoch_id,
     })
 }
+
+/// Queries the addresses allowed to create positions on behalf of other receivers.
+pub(crate) fn query_position_operators(
+    deps: Deps,
+) -> Result<PositionOperatorsResponse, ContractError> {
+    let operators = POSITION_OPERATORS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(PositionOperatorsResponse { operators })
+}
+
+/// Queries whether an address is allowed to create positions on behalf of other receivers.
+pub(crate) fn query_is_position_operator(
+    deps: Deps,
+    address: String,
+) -> Result<IsPositionOperatorResponse, ContractError> {
+    let address = deps.api.addr_validate(&address)?;
+
+    Ok(IsPositionOperatorResponse {
+        is_operator: POSITION_OPERATORS.has(deps.storage, &address),
+    })
+}
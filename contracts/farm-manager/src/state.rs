@@ -1,7 +1,7 @@
 use std::clone::Clone;
 use std::string::ToString;
 
-use cosmwasm_std::{Addr, Order, StdResult, Storage, Uint128};
+use cosmwasm_std::{Addr, Empty, Order, StdResult, Storage, Uint128};
 use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 use amm::farm_manager::{Config, EpochId, Farm, Position};
@@ -44,6 +44,9 @@ impl<'a> IndexList<Position> for PositionIndexes<'a> {
     }
 }
 
+/// The addresses allowed to create and expand positions on behalf of other receivers.
+pub const POSITION_OPERATORS: Map<&Addr, Empty> = Map::new("position_operators");
+
 /// The last epoch an address claimed rewards
 pub const LAST_CLAIMED_EPOCH: Map<&Addr, EpochId> = Map::new("last_claimed_epoch");
 
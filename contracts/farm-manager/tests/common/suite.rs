@@ -12,8 +12,9 @@ use crate::common::suite_contracts::{
 use crate::common::MOCK_CONTRACT_ADDR_1;
 use amm::epoch_manager::{EpochConfig, EpochResponse};
 use amm::farm_manager::{
-    Config, FarmAction, FarmsBy, FarmsResponse, InstantiateMsg, LpWeightResponse, PositionAction,
-    PositionsResponse, RewardsResponse,
+    Config, FarmAction, FarmsBy, FarmsResponse, InstantiateMsg, IsPositionOperatorResponse,
+    LpWeightResponse, PositionAction, PositionOperatorsResponse, PositionsResponse,
+    RewardsResponse,
 };
 use common_testing::multi_test::stargate_mock::StargateMock;
 
@@ -356,6 +357,26 @@ impl TestingSuite {
         self
     }
 
+    #[track_caller]
+    pub(crate) fn update_position_operators(
+        &mut self,
+        sender: &Addr,
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::farm_manager::ExecuteMsg::UpdatePositionOperators { add, remove };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.farm_manager_addr.clone(),
+            &msg,
+            &[],
+        ));
+
+        self
+    }
+
     #[track_caller]
     pub(crate) fn manage_farm(
         &mut self,
@@ -445,6 +466,39 @@ impl TestingSuite {
         self
     }
 
+    #[track_caller]
+    pub(crate) fn query_position_operators(
+        &mut self,
+        result: impl Fn(StdResult<PositionOperatorsResponse>),
+    ) -> &mut Self {
+        let response: StdResult<PositionOperatorsResponse> = self.app.wrap().query_wasm_smart(
+            &self.farm_manager_addr,
+            &amm::farm_manager::QueryMsg::PositionOperators {},
+        );
+
+        result(response);
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn query_is_position_operator(
+        &mut self,
+        address: &Addr,
+        result: impl Fn(StdResult<IsPositionOperatorResponse>),
+    ) -> &mut Self {
+        let response: StdResult<IsPositionOperatorResponse> = self.app.wrap().query_wasm_smart(
+            &self.farm_manager_addr,
+            &amm::farm_manager::QueryMsg::IsPositionOperator {
+                address: address.to_string(),
+            },
+        );
+
+        result(response);
+
+        self
+    }
+
     #[track_caller]
     pub(crate) fn query_farms(
         &mut self,
@@ -8587,3 +8587,172 @@ fn farm_owners_get_penalty_fees() {
             assert_eq!(balance, Uint128::new(1_000_000_000u128 + 25u128));
         });
 }
+
+#[test]
+fn position_operators_can_manage_positions_for_other_receivers() {
+    let lp_denom = format!("factory/{MOCK_CONTRACT_ADDR_1}/{LP_SYMBOL}").to_string();
+    let mut suite = TestingSuite::default_with_balances(vec![
+        coin(1_000_000_000u128, "uom"),
+        coin(1_000_000_000u128, lp_denom.clone()),
+    ]);
+
+    let creator = suite.creator();
+    let alice = suite.senders[1].clone();
+    let vault = suite.senders[2].clone();
+    suite.instantiate_default();
+
+    // only the owner can update the position operators
+    suite
+        .update_position_operators(&vault, Some(vec![vault.to_string()]), None, |result| {
+            let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+            match err {
+                ContractError::OwnershipError { .. } => {}
+                _ => panic!("Wrong error type, should return ContractError::OwnershipError"),
+            }
+        })
+        // the vault can't create positions for alice until it's an operator
+        .manage_position(
+            &vault,
+            PositionAction::Create {
+                identifier: Some("vault_position".to_string()),
+                unlocking_duration: 86_400,
+                receiver: Some(alice.to_string()),
+            },
+            vec![coin(5_000, lp_denom.clone())],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::Unauthorized { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::Unauthorized"),
+                }
+            },
+        )
+        .update_position_operators(&creator, Some(vec![vault.to_string()]), None, |result| {
+            result.unwrap();
+        })
+        .query_position_operators(|result| {
+            assert_eq!(result.unwrap().operators, vec![vault.clone()]);
+        })
+        .query_is_position_operator(&vault, |result| {
+            assert!(result.unwrap().is_operator);
+        })
+        .query_is_position_operator(&alice, |result| {
+            assert!(!result.unwrap().is_operator);
+        })
+        .manage_position(
+            &vault,
+            PositionAction::Create {
+                identifier: Some("vault_position".to_string()),
+                unlocking_duration: 86_400,
+                receiver: Some(alice.to_string()),
+            },
+            vec![coin(5_000, lp_denom.clone())],
+            |result| {
+                result.unwrap();
+            },
+        )
+        // the operator can't top up positions, not even the ones it created
+        .manage_position(
+            &vault,
+            PositionAction::Expand {
+                identifier: "u-vault_position".to_string(),
+            },
+            vec![coin(5_000, lp_denom.clone())],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::Unauthorized { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::Unauthorized"),
+                }
+            },
+        )
+        .manage_position(
+            &alice,
+            PositionAction::Expand {
+                identifier: "u-vault_position".to_string(),
+            },
+            vec![coin(5_000, lp_denom.clone())],
+            |result| {
+                result.unwrap();
+            },
+        )
+        // the position belongs to alice, the operator can't close it
+        .manage_position(
+            &vault,
+            PositionAction::Close {
+                identifier: "u-vault_position".to_string(),
+                lp_asset: None,
+            },
+            vec![],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::Unauthorized { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::Unauthorized"),
+                }
+            },
+        )
+        .query_positions(
+            Some(PositionsBy::Receiver(alice.to_string())),
+            Some(true),
+            None,
+            None,
+            |result| {
+                let positions = result.unwrap();
+                assert_eq!(positions.positions.len(), 1);
+                assert_eq!(
+                    positions.positions[0],
+                    Position {
+                        identifier: "u-vault_position".to_string(),
+                        lp_asset: Coin {
+                            denom: lp_denom.clone(),
+                            amount: Uint128::new(10_000),
+                        },
+                        unlocking_duration: 86_400,
+                        open: true,
+                        expiring_at: None,
+                        receiver: alice.clone(),
+                    }
+                );
+            },
+        )
+        .manage_position(
+            &alice,
+            PositionAction::Close {
+                identifier: "u-vault_position".to_string(),
+                lp_asset: None,
+            },
+            vec![],
+            |result| {
+                result.unwrap();
+            },
+        );
+
+    // once removed, the vault can't create positions for others anymore
+    suite
+        .update_position_operators(&creator, None, Some(vec![vault.to_string()]), |result| {
+            result.unwrap();
+        })
+        .query_position_operators(|result| {
+            assert!(result.unwrap().operators.is_empty());
+        })
+        .query_is_position_operator(&vault, |result| {
+            assert!(!result.unwrap().is_operator);
+        })
+        .manage_position(
+            &vault,
+            PositionAction::Create {
+                identifier: Some("another_vault_position".to_string()),
+                unlocking_duration: 86_400,
+                receiver: Some(alice.to_string()),
+            },
+            vec![coin(5_000, lp_denom.clone())],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::Unauthorized { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::Unauthorized"),
+                }
+            },
+        );
+}
@@ -131,14 +131,19 @@ impl Model {
             .unwrap();
     }
 
+    pub fn sim_add_liquidity(&self, amounts: Vec<u128>) -> u128 {
+        let gil = Python::acquire_gil();
+        return self
+            .call1(gil.python(), "add_liquidity", (amounts,))
+            .unwrap()
+            .extract(gil.python())
+            .unwrap();
+    }
+
     pub fn sim_remove_liquidity_imbalance(&self, amounts: Vec<u128>) -> u128 {
         let gil = Python::acquire_gil();
         return self
-            .call1(
-                gil.python(),
-                "remove_liquidity_imbalance",
-                PyTuple::new(gil.python(), amounts.to_vec()),
-            )
+            .call1(gil.python(), "remove_liquidity_imbalance", (amounts,))
             .unwrap()
             .extract(gil.python())
             .unwrap();
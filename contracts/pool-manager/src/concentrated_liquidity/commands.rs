@@ -0,0 +1,652 @@
+use cosmwasm_std::{
+    ensure, BankMsg, Coin, CosmosMsg, DepsMut, Int128, MessageInfo, Response, Storage, Uint128,
+    Uint256, Uint512,
+};
+
+use amm::coin::aggregate_coins;
+use amm::pool_manager::{ConcentratedPosition, ConcentratedPositionAction, PoolInfo, PoolType};
+
+use crate::concentrated_liquidity::math::{
+    amounts_for_liquidity, ensure_tick_in_range, liquidity_for_amounts, mul_div,
+    sqrt_price_at_tick, FEE_GROWTH_SCALE,
+};
+use crate::state::{
+    get_concentrated_position, get_pool_by_identifier, ConcentratedLiquidityState, TickInfo,
+    CONCENTRATED_LIQUIDITY_STATES, CONCENTRATED_POSITIONS, CONCENTRATED_POSITION_ID_COUNTER,
+    CONFIG, POOLS, TICKS,
+};
+use crate::ContractError;
+
+/// The prefix used when creating a concentrated liquidity position with an explicitly provided ID
+pub const EXPLICIT_POSITION_ID_PREFIX: &str = "u-";
+
+/// The prefix used when creating a concentrated liquidity position with an auto-generated ID
+pub const AUTO_POSITION_ID_PREFIX: &str = "p-";
+
+/// The maximum length of a concentrated liquidity position identifier
+const MAX_POSITION_IDENTIFIER_LENGTH: usize = 64;
+
+/// Manages a concentrated liquidity position.
+pub fn manage_concentrated_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    action: ConcentratedPositionAction,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    match action {
+        ConcentratedPositionAction::Create {
+            pool_identifier,
+            lower_tick,
+            upper_tick,
+            identifier,
+        } => {
+            ensure!(
+                config.feature_toggle.deposits_enabled,
+                ContractError::OperationDisabled("create_concentrated_position".to_string())
+            );
+            create_position(
+                deps,
+                info,
+                pool_identifier,
+                lower_tick,
+                upper_tick,
+                identifier,
+            )
+        }
+        ConcentratedPositionAction::Expand { identifier } => {
+            ensure!(
+                config.feature_toggle.deposits_enabled,
+                ContractError::OperationDisabled("expand_concentrated_position".to_string())
+            );
+            expand_position(deps, info, identifier)
+        }
+        ConcentratedPositionAction::Withdraw {
+            identifier,
+            liquidity,
+        } => {
+            ensure!(
+                config.feature_toggle.withdrawals_enabled,
+                ContractError::OperationDisabled("withdraw_concentrated_position".to_string())
+            );
+            withdraw_from_position(deps, info, identifier, liquidity)
+        }
+        ConcentratedPositionAction::CollectFees { identifier } => {
+            ensure!(
+                config.feature_toggle.withdrawals_enabled,
+                ContractError::OperationDisabled("collect_concentrated_fees".to_string())
+            );
+            collect_fees(deps, info, identifier)
+        }
+    }
+}
+
+/// Creates a concentrated liquidity position.
+fn create_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_identifier: String,
+    lower_tick: i64,
+    upper_tick: i64,
+    identifier: Option<String>,
+) -> Result<Response, ContractError> {
+    let pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    let PoolType::ConcentratedLiquidity { tick_spacing, .. } = pool.pool_type else {
+        return Err(ContractError::NotAConcentratedLiquidityPool);
+    };
+
+    validate_tick_range(lower_tick, upper_tick, tick_spacing)?;
+
+    // compute the identifier for this position
+    let identifier = if let Some(identifier) = identifier {
+        format!("{EXPLICIT_POSITION_ID_PREFIX}{identifier}")
+    } else {
+        let position_id_counter = CONCENTRATED_POSITION_ID_COUNTER
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            + 1u64;
+        CONCENTRATED_POSITION_ID_COUNTER.save(deps.storage, &position_id_counter)?;
+        format!("{AUTO_POSITION_ID_PREFIX}{position_id_counter}")
+    };
+
+    validate_position_identifier(&identifier)?;
+
+    ensure!(
+        !CONCENTRATED_POSITIONS.has(deps.storage, &identifier),
+        ContractError::ConcentratedPositionAlreadyExists { identifier }
+    );
+
+    let position = ConcentratedPosition {
+        identifier,
+        pool_identifier,
+        owner: info.sender.clone(),
+        lower_tick,
+        upper_tick,
+        liquidity: Uint128::zero(),
+        fee_growth_inside_last: vec![Uint256::zero(); pool.assets.len()],
+        fees_owed: pool
+            .asset_denoms
+            .iter()
+            .map(|denom| Coin::new(Uint128::zero(), denom))
+            .collect(),
+    };
+
+    add_liquidity_to_position(deps, info, pool, position, "create_concentrated_position")
+}
+
+/// Expands an existing concentrated liquidity position.
+fn expand_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String,
+) -> Result<Response, ContractError> {
+    let position = get_concentrated_position(&deps.as_ref(), &identifier)?;
+    ensure!(position.owner == info.sender, ContractError::Unauthorized);
+
+    let pool = get_pool_by_identifier(&deps.as_ref(), &position.pool_identifier)?;
+
+    add_liquidity_to_position(deps, info, pool, position, "expand_concentrated_position")
+}
+
+/// Adds the maximum liquidity that can be provided with the funds sent to the given position,
+/// refunding the excess.
+fn add_liquidity_to_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    mut pool: PoolInfo,
+    mut position: ConcentratedPosition,
+    action: &str,
+) -> Result<Response, ContractError> {
+    let deposits = aggregate_coins(info.funds.clone())?;
+    ensure!(!deposits.is_empty(), ContractError::EmptyAssets);
+
+    // verify that the assets sent match the ones from the pool
+    ensure!(
+        deposits
+            .iter()
+            .all(|deposit| pool.asset_denoms.contains(&deposit.denom)),
+        ContractError::AssetMismatch
+    );
+
+    let deposited_amounts: Vec<Uint128> = pool
+        .asset_denoms
+        .iter()
+        .map(|denom| {
+            deposits
+                .iter()
+                .find(|deposit| &deposit.denom == denom)
+                .map(|deposit| deposit.amount)
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let mut state = CONCENTRATED_LIQUIDITY_STATES.load(deps.storage, &pool.pool_identifier)?;
+
+    let liquidity = liquidity_for_amounts(
+        state.sqrt_price,
+        sqrt_price_at_tick(position.lower_tick)?,
+        sqrt_price_at_tick(position.upper_tick)?,
+        deposited_amounts[0],
+        deposited_amounts[1],
+    )?;
+    ensure!(
+        !liquidity.is_zero(),
+        ContractError::InvalidConcentratedLiquidityAmount
+    );
+
+    let (amount_0, amount_1) = amounts_for_liquidity(
+        state.sqrt_price,
+        state.tick,
+        position.lower_tick,
+        position.upper_tick,
+        liquidity,
+        true,
+    )?;
+
+    update_tick(
+        deps.storage,
+        &pool.pool_identifier,
+        position.lower_tick,
+        &state,
+        liquidity,
+        true,
+        false,
+    )?;
+    update_tick(
+        deps.storage,
+        &pool.pool_identifier,
+        position.upper_tick,
+        &state,
+        liquidity,
+        true,
+        true,
+    )?;
+
+    // accrue the fees owed to the position with its liquidity before the expansion
+    let fee_growth_inside = get_fee_growth_inside(
+        deps.storage,
+        &pool.pool_identifier,
+        position.lower_tick,
+        position.upper_tick,
+        &state,
+    )?;
+    update_position_fees(&mut position, fee_growth_inside)?;
+
+    position.liquidity = position.liquidity.checked_add(liquidity)?;
+
+    if is_in_range(&state, position.lower_tick, position.upper_tick) {
+        state.liquidity = state.liquidity.checked_add(liquidity)?;
+        CONCENTRATED_LIQUIDITY_STATES.save(deps.storage, &pool.pool_identifier, &state)?;
+    }
+
+    pool.assets[0].amount = pool.assets[0].amount.checked_add(amount_0)?;
+    pool.assets[1].amount = pool.assets[1].amount.checked_add(amount_1)?;
+    POOLS.save(deps.storage, &pool.pool_identifier, &pool)?;
+
+    CONCENTRATED_POSITIONS.save(deps.storage, &position.identifier, &position)?;
+
+    // refund what was not used to provide liquidity
+    let refunds: Vec<Coin> = pool
+        .asset_denoms
+        .iter()
+        .zip(deposited_amounts)
+        .zip([amount_0, amount_1])
+        .map(|((denom, deposited), used)| Ok(Coin::new(deposited.checked_sub(used)?, denom)))
+        .collect::<Result<Vec<Coin>, ContractError>>()?
+        .into_iter()
+        .filter(|refund| !refund.amount.is_zero())
+        .collect();
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !refunds.is_empty() {
+        messages.push(
+            BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: refunds.clone(),
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::default()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", action.to_string()),
+            ("sender", info.sender.to_string()),
+            ("identifier", position.identifier),
+            ("pool_identifier", pool.pool_identifier),
+            ("lower_tick", position.lower_tick.to_string()),
+            ("upper_tick", position.upper_tick.to_string()),
+            ("liquidity", liquidity.to_string()),
+            (
+                "assets",
+                [
+                    Coin::new(amount_0, &pool.asset_denoms[0]),
+                    Coin::new(amount_1, &pool.asset_denoms[1]),
+                ]
+                .iter()
+                .map(|asset| asset.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            ),
+        ]))
+}
+
+/// Withdraws liquidity from a concentrated liquidity position, together with the fees it accrued.
+fn withdraw_from_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String,
+    liquidity: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let mut position = get_concentrated_position(&deps.as_ref(), &identifier)?;
+    ensure!(position.owner == info.sender, ContractError::Unauthorized);
+
+    let liquidity = liquidity.unwrap_or(position.liquidity);
+    ensure!(
+        !liquidity.is_zero() && liquidity <= position.liquidity,
+        ContractError::InvalidConcentratedLiquidityAmount
+    );
+
+    let mut pool = get_pool_by_identifier(&deps.as_ref(), &position.pool_identifier)?;
+    let mut state = CONCENTRATED_LIQUIDITY_STATES.load(deps.storage, &pool.pool_identifier)?;
+
+    // accrue the fees owed to the position before the ticks are potentially cleared
+    let fee_growth_inside = get_fee_growth_inside(
+        deps.storage,
+        &pool.pool_identifier,
+        position.lower_tick,
+        position.upper_tick,
+        &state,
+    )?;
+    update_position_fees(&mut position, fee_growth_inside)?;
+
+    update_tick(
+        deps.storage,
+        &pool.pool_identifier,
+        position.lower_tick,
+        &state,
+        liquidity,
+        false,
+        false,
+    )?;
+    update_tick(
+        deps.storage,
+        &pool.pool_identifier,
+        position.upper_tick,
+        &state,
+        liquidity,
+        false,
+        true,
+    )?;
+
+    let (amount_0, amount_1) = amounts_for_liquidity(
+        state.sqrt_price,
+        state.tick,
+        position.lower_tick,
+        position.upper_tick,
+        liquidity,
+        false,
+    )?;
+
+    if is_in_range(&state, position.lower_tick, position.upper_tick) {
+        state.liquidity = state.liquidity.checked_sub(liquidity)?;
+        CONCENTRATED_LIQUIDITY_STATES.save(deps.storage, &pool.pool_identifier, &state)?;
+    }
+
+    pool.assets[0].amount = pool.assets[0].amount.checked_sub(amount_0)?;
+    pool.assets[1].amount = pool.assets[1].amount.checked_sub(amount_1)?;
+    POOLS.save(deps.storage, &pool.pool_identifier, &pool)?;
+
+    position.liquidity = position.liquidity.checked_sub(liquidity)?;
+
+    // send the withdrawn liquidity together with the fees owed
+    let withdrawn_assets: Vec<Coin> = position
+        .fees_owed
+        .iter()
+        .zip([amount_0, amount_1])
+        .map(|(fees, amount)| Ok(Coin::new(fees.amount.checked_add(amount)?, &fees.denom)))
+        .collect::<Result<Vec<Coin>, ContractError>>()?
+        .into_iter()
+        .filter(|asset| !asset.amount.is_zero())
+        .collect();
+
+    let collected_fees = take_fees_owed(&mut position);
+    release_fees_held(deps.storage, &pool.pool_identifier, &collected_fees)?;
+
+    if position.liquidity.is_zero() {
+        CONCENTRATED_POSITIONS.remove(deps.storage, &identifier)?;
+    } else {
+        CONCENTRATED_POSITIONS.save(deps.storage, &identifier, &position)?;
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !withdrawn_assets.is_empty() {
+        messages.push(
+            BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: withdrawn_assets,
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::default()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", "withdraw_concentrated_position".to_string()),
+            ("sender", info.sender.to_string()),
+            ("identifier", identifier),
+            ("liquidity", liquidity.to_string()),
+            (
+                "assets",
+                [
+                    Coin::new(amount_0, &pool.asset_denoms[0]),
+                    Coin::new(amount_1, &pool.asset_denoms[1]),
+                ]
+                .iter()
+                .map(|asset| asset.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            ),
+            ("fees", coins_to_string(&collected_fees)),
+        ]))
+}
+
+/// Collects the fees accrued by a concentrated liquidity position.
+fn collect_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String,
+) -> Result<Response, ContractError> {
+    let mut position = get_concentrated_position(&deps.as_ref(), &identifier)?;
+    ensure!(position.owner == info.sender, ContractError::Unauthorized);
+
+    let state = CONCENTRATED_LIQUIDITY_STATES.load(deps.storage, &position.pool_identifier)?;
+    let fee_growth_inside = get_fee_growth_inside(
+        deps.storage,
+        &position.pool_identifier,
+        position.lower_tick,
+        position.upper_tick,
+        &state,
+    )?;
+    update_position_fees(&mut position, fee_growth_inside)?;
+
+    let collected_fees = take_fees_owed(&mut position);
+    release_fees_held(deps.storage, &position.pool_identifier, &collected_fees)?;
+    let collected_fees: Vec<Coin> = collected_fees
+        .into_iter()
+        .filter(|fee| !fee.amount.is_zero())
+        .collect();
+
+    CONCENTRATED_POSITIONS.save(deps.storage, &identifier, &position)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !collected_fees.is_empty() {
+        messages.push(
+            BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: collected_fees.clone(),
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::default()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", "collect_concentrated_fees".to_string()),
+            ("sender", info.sender.to_string()),
+            ("identifier", identifier),
+            ("fees", coins_to_string(&collected_fees)),
+        ]))
+}
+
+/// Validates that the ticks are a valid range for a position in a pool with the given tick spacing.
+fn validate_tick_range(
+    lower_tick: i64,
+    upper_tick: i64,
+    tick_spacing: u64,
+) -> Result<(), ContractError> {
+    let tick_spacing = i64::try_from(tick_spacing).map_err(|_| ContractError::DecimalOverflow)?;
+
+    ensure!(
+        lower_tick < upper_tick
+            && ensure_tick_in_range(lower_tick).is_ok()
+            && ensure_tick_in_range(upper_tick).is_ok()
+            && lower_tick % tick_spacing == 0
+            && upper_tick % tick_spacing == 0,
+        ContractError::InvalidTickRange {
+            lower_tick,
+            upper_tick,
+        }
+    );
+
+    Ok(())
+}
+
+/// Validates that the position identifier doesn't exceed 64 characters, it's alphanumeric, and
+/// can contain '.', '-' and '_'.
+fn validate_position_identifier(identifier: &str) -> Result<(), ContractError> {
+    ensure!(
+        identifier.len() <= MAX_POSITION_IDENTIFIER_LENGTH
+            && identifier
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_'),
+        ContractError::InvalidConcentratedPositionIdentifier {
+            identifier: identifier.to_string()
+        }
+    );
+
+    Ok(())
+}
+
+/// Whether the current tick of the pool is within the given range, i.e. the liquidity of the range
+/// is active.
+fn is_in_range(state: &ConcentratedLiquidityState, lower_tick: i64, upper_tick: i64) -> bool {
+    lower_tick <= state.tick && state.tick < upper_tick
+}
+
+/// Adds or removes liquidity to/from the given tick, initializing it if needed and clearing it
+/// when no position uses it anymore.
+fn update_tick(
+    storage: &mut dyn Storage,
+    pool_identifier: &str,
+    tick: i64,
+    state: &ConcentratedLiquidityState,
+    liquidity: Uint128,
+    add: bool,
+    is_upper: bool,
+) -> Result<(), ContractError> {
+    let mut tick_info = TICKS
+        .may_load(storage, (pool_identifier, tick))?
+        .unwrap_or_else(|| TickInfo {
+            liquidity_gross: Uint128::zero(),
+            liquidity_net: Int128::zero(),
+            // by convention, all the fees accrued so far are assumed to be below the tick
+            fee_growth_outside: if tick <= state.tick {
+                state.fee_growth_global.clone()
+            } else {
+                vec![Uint256::zero(); state.fee_growth_global.len()]
+            },
+        });
+
+    let liquidity_delta = Int128::try_from(liquidity)?;
+
+    if add {
+        tick_info.liquidity_gross = tick_info.liquidity_gross.checked_add(liquidity)?;
+    } else {
+        tick_info.liquidity_gross = tick_info.liquidity_gross.checked_sub(liquidity)?;
+    }
+
+    // the liquidity is activated when crossing the lower tick upwards, and deactivated when
+    // crossing the upper tick upwards
+    tick_info.liquidity_net = if add != is_upper {
+        tick_info.liquidity_net.checked_add(liquidity_delta)?
+    } else {
+        tick_info.liquidity_net.checked_sub(liquidity_delta)?
+    };
+
+    if tick_info.liquidity_gross.is_zero() {
+        TICKS.remove(storage, (pool_identifier, tick));
+    } else {
+        TICKS.save(storage, (pool_identifier, tick), &tick_info)?;
+    }
+
+    Ok(())
+}
+
+/// Gets the fee growth per unit of liquidity inside the given tick range.
+pub fn get_fee_growth_inside(
+    storage: &dyn Storage,
+    pool_identifier: &str,
+    lower_tick: i64,
+    upper_tick: i64,
+    state: &ConcentratedLiquidityState,
+) -> Result<Vec<Uint256>, ContractError> {
+    let lower = TICKS.load(storage, (pool_identifier, lower_tick))?;
+    let upper = TICKS.load(storage, (pool_identifier, upper_tick))?;
+
+    Ok(state
+        .fee_growth_global
+        .iter()
+        .enumerate()
+        .map(|(i, global)| {
+            let below = if state.tick >= lower_tick {
+                lower.fee_growth_outside[i]
+            } else {
+                global.wrapping_sub(lower.fee_growth_outside[i])
+            };
+            let above = if state.tick < upper_tick {
+                upper.fee_growth_outside[i]
+            } else {
+                global.wrapping_sub(upper.fee_growth_outside[i])
+            };
+
+            global.wrapping_sub(below).wrapping_sub(above)
+        })
+        .collect())
+}
+
+/// Accrues the fees owed to the position since it was last updated.
+pub fn update_position_fees(
+    position: &mut ConcentratedPosition,
+    fee_growth_inside: Vec<Uint256>,
+) -> Result<(), ContractError> {
+    for (i, fee_growth) in fee_growth_inside.iter().enumerate() {
+        let fee_growth_delta = fee_growth.wrapping_sub(position.fee_growth_inside_last[i]);
+        let fees = mul_div(
+            Uint512::from(fee_growth_delta),
+            Uint512::from(position.liquidity.u128()),
+            Uint512::from(FEE_GROWTH_SCALE),
+            false,
+        )?;
+
+        position.fees_owed[i].amount = position.fees_owed[i]
+            .amount
+            .checked_add(Uint128::try_from(fees)?)?;
+    }
+
+    position.fee_growth_inside_last = fee_growth_inside;
+
+    Ok(())
+}
+
+/// Resets the fees owed to the position, returning them.
+fn take_fees_owed(position: &mut ConcentratedPosition) -> Vec<Coin> {
+    let fees = position.fees_owed.clone();
+    position
+        .fees_owed
+        .iter_mut()
+        .for_each(|fee| fee.amount = Uint128::zero());
+
+    fees
+}
+
+/// Releases the fees collected by a position from the fees held for the positions of the pool.
+fn release_fees_held(
+    storage: &mut dyn Storage,
+    pool_identifier: &str,
+    collected_fees: &[Coin],
+) -> Result<(), ContractError> {
+    let mut state = CONCENTRATED_LIQUIDITY_STATES.load(storage, pool_identifier)?;
+    state
+        .fees_held
+        .resize(collected_fees.len(), Uint128::zero());
+
+    for (fees_held, collected_fee) in state.fees_held.iter_mut().zip(collected_fees) {
+        *fees_held = fees_held.saturating_sub(collected_fee.amount);
+    }
+
+    CONCENTRATED_LIQUIDITY_STATES.save(storage, pool_identifier, &state)?;
+
+    Ok(())
+}
+
+fn coins_to_string(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
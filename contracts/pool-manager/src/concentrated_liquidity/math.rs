@@ -0,0 +1,353 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Decimal256, Fraction, Uint128, Uint256, Uint512};
+
+use crate::ContractError;
+
+/// The lowest tick supported by concentrated liquidity pools, where the price is ~1e-12.
+pub const MIN_TICK: i64 = -276_324;
+/// The highest tick supported by concentrated liquidity pools, where the price is ~1e12.
+pub const MAX_TICK: i64 = 276_324;
+
+/// The base of the price at a given tick, i.e. `price(i) = 1.0001^i`.
+const TICK_BASE: &str = "1.0001";
+
+/// The scale of the fee growth values, i.e. the amount of fees accrued per unit of liquidity,
+/// stored as integers.
+pub const FEE_GROWTH_SCALE: Uint256 =
+    Uint256::from_u128(1_000_000_000_000_000_000_000_000_000_000_000_000u128);
+
+/// The atomics of one unit of a [Decimal256].
+fn decimal_fractional() -> Uint512 {
+    Uint512::from(Decimal256::one().atomics())
+}
+
+/// Computes `a * b / c` with a 512-bit intermediate product, rounding up if `round_up` is true.
+pub(crate) fn mul_div(
+    a: Uint512,
+    b: Uint512,
+    c: Uint512,
+    round_up: bool,
+) -> Result<Uint256, ContractError> {
+    let product = a.checked_mul(b)?;
+    let mut result = product.checked_div(c)?;
+
+    if round_up && !product.checked_rem(c)?.is_zero() {
+        result = result.checked_add(Uint512::one())?;
+    }
+
+    Ok(Uint256::try_from(result)?)
+}
+
+/// Gets the square root of the price at the given tick, i.e. `1.0001^(tick / 2)`.
+pub fn sqrt_price_at_tick(tick: i64) -> Result<Decimal256, ContractError> {
+    ensure_tick_in_range(tick)?;
+
+    let base = Decimal256::from_str(TICK_BASE)?;
+    let price = base.checked_pow(tick.unsigned_abs() as u32)?;
+    let sqrt_price = price.sqrt();
+
+    if tick < 0 {
+        sqrt_price.inv().ok_or(ContractError::DecimalOverflow)
+    } else {
+        Ok(sqrt_price)
+    }
+}
+
+/// Gets the greatest tick whose square root price is lower than or equal to the given one.
+pub fn tick_at_sqrt_price(sqrt_price: Decimal256) -> Result<i64, ContractError> {
+    ensure_sqrt_price_in_range(sqrt_price)?;
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        // round up so the range always shrinks
+        let mid = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(mid)? <= sqrt_price {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    Ok(low)
+}
+
+/// Ensures the tick is within the supported range.
+pub fn ensure_tick_in_range(tick: i64) -> Result<(), ContractError> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(ContractError::InvalidConcentratedLiquidityPool);
+    }
+
+    Ok(())
+}
+
+/// Ensures the square root of the price is within the supported range.
+pub fn ensure_sqrt_price_in_range(sqrt_price: Decimal256) -> Result<(), ContractError> {
+    if sqrt_price < sqrt_price_at_tick(MIN_TICK)? || sqrt_price > sqrt_price_at_tick(MAX_TICK)? {
+        return Err(ContractError::InvalidConcentratedLiquidityPool);
+    }
+
+    Ok(())
+}
+
+/// Gets the amount of the first asset between two square root prices for the given liquidity,
+/// i.e. `liquidity * (sqrt_price_b - sqrt_price_a) / (sqrt_price_a * sqrt_price_b)`.
+pub fn amount_0_delta(
+    sqrt_price_a: Decimal256,
+    sqrt_price_b: Decimal256,
+    liquidity: Uint128,
+    round_up: bool,
+) -> Result<Uint256, ContractError> {
+    let (sqrt_price_a, sqrt_price_b) = sort_sqrt_prices(sqrt_price_a, sqrt_price_b);
+
+    if sqrt_price_a.is_zero() {
+        return Err(ContractError::InvalidConcentratedLiquidityPool);
+    }
+
+    mul_div(
+        Uint512::from(liquidity.u128()).checked_mul(Uint512::from(
+            sqrt_price_b.checked_sub(sqrt_price_a)?.atomics(),
+        ))?,
+        decimal_fractional(),
+        Uint512::from(sqrt_price_a.atomics()).checked_mul(Uint512::from(sqrt_price_b.atomics()))?,
+        round_up,
+    )
+}
+
+/// Gets the amount of the second asset between two square root prices for the given liquidity,
+/// i.e. `liquidity * (sqrt_price_b - sqrt_price_a)`.
+pub fn amount_1_delta(
+    sqrt_price_a: Decimal256,
+    sqrt_price_b: Decimal256,
+    liquidity: Uint128,
+    round_up: bool,
+) -> Result<Uint256, ContractError> {
+    let (sqrt_price_a, sqrt_price_b) = sort_sqrt_prices(sqrt_price_a, sqrt_price_b);
+
+    mul_div(
+        Uint512::from(liquidity.u128()),
+        Uint512::from(sqrt_price_b.checked_sub(sqrt_price_a)?.atomics()),
+        decimal_fractional(),
+        round_up,
+    )
+}
+
+/// Gets the square root price after adding (or removing) the given amount of the first asset to
+/// the pool, i.e. `liquidity * sqrt_price / (liquidity +- amount * sqrt_price)`. Rounds up, so the
+/// price doesn't move more than it should in favor of the swapper.
+pub fn next_sqrt_price_from_amount_0(
+    sqrt_price: Decimal256,
+    liquidity: Uint128,
+    amount: Uint256,
+    add: bool,
+) -> Result<Decimal256, ContractError> {
+    let liquidity = Uint512::from(liquidity.u128()).checked_mul(decimal_fractional())?;
+    let product = Uint512::from(amount).checked_mul(Uint512::from(sqrt_price.atomics()))?;
+
+    let denominator = if add {
+        liquidity.checked_add(product)?
+    } else {
+        liquidity
+            .checked_sub(product)
+            .map_err(|_| ContractError::InsufficientConcentratedLiquidity)?
+    };
+
+    if denominator.is_zero() {
+        return Err(ContractError::InsufficientConcentratedLiquidity);
+    }
+
+    Ok(Decimal256::new(mul_div(
+        liquidity,
+        Uint512::from(sqrt_price.atomics()),
+        denominator,
+        true,
+    )?))
+}
+
+/// Gets the square root price after adding (or removing) the given amount of the second asset to
+/// the pool, i.e. `sqrt_price +- amount / liquidity`. Rounds down, so the price doesn't move more
+/// than it should in favor of the swapper.
+pub fn next_sqrt_price_from_amount_1(
+    sqrt_price: Decimal256,
+    liquidity: Uint128,
+    amount: Uint256,
+    add: bool,
+) -> Result<Decimal256, ContractError> {
+    let liquidity = Uint512::from(liquidity.u128());
+
+    if add {
+        let delta = mul_div(
+            Uint512::from(amount),
+            decimal_fractional(),
+            liquidity,
+            false,
+        )?;
+        Ok(Decimal256::new(sqrt_price.atomics().checked_add(delta)?))
+    } else {
+        let delta = mul_div(Uint512::from(amount), decimal_fractional(), liquidity, true)?;
+        Ok(Decimal256::new(
+            sqrt_price
+                .atomics()
+                .checked_sub(delta)
+                .map_err(|_| ContractError::InsufficientConcentratedLiquidity)?,
+        ))
+    }
+}
+
+/// Computes the maximum liquidity that can be provided within the range
+/// `[sqrt_price_lower, sqrt_price_upper)` with the given amounts at the current price.
+pub fn liquidity_for_amounts(
+    sqrt_price: Decimal256,
+    sqrt_price_lower: Decimal256,
+    sqrt_price_upper: Decimal256,
+    amount_0: Uint128,
+    amount_1: Uint128,
+) -> Result<Uint128, ContractError> {
+    let liquidity_for_amount_0 = |sqrt_price_a: Decimal256| -> Result<Uint256, ContractError> {
+        // amount_0 * sqrt_price_a * sqrt_price_upper / (sqrt_price_upper - sqrt_price_a)
+        mul_div(
+            Uint512::from(amount_0.u128()).checked_mul(Uint512::from(sqrt_price_a.atomics()))?,
+            Uint512::from(sqrt_price_upper.atomics()),
+            Uint512::from(sqrt_price_upper.checked_sub(sqrt_price_a)?.atomics())
+                .checked_mul(decimal_fractional())?,
+            false,
+        )
+    };
+    let liquidity_for_amount_1 = |sqrt_price_b: Decimal256| -> Result<Uint256, ContractError> {
+        // amount_1 / (sqrt_price_b - sqrt_price_lower)
+        mul_div(
+            Uint512::from(amount_1.u128()),
+            decimal_fractional(),
+            Uint512::from(sqrt_price_b.checked_sub(sqrt_price_lower)?.atomics()),
+            false,
+        )
+    };
+
+    let liquidity = if sqrt_price <= sqrt_price_lower {
+        liquidity_for_amount_0(sqrt_price_lower)?
+    } else if sqrt_price < sqrt_price_upper {
+        liquidity_for_amount_0(sqrt_price)?.min(liquidity_for_amount_1(sqrt_price)?)
+    } else {
+        liquidity_for_amount_1(sqrt_price_upper)?
+    };
+
+    Ok(Uint128::try_from(liquidity)?)
+}
+
+/// Computes the amounts of the assets backing the given liquidity within the range
+/// `[lower_tick, upper_tick)` at the current tick and price.
+pub fn amounts_for_liquidity(
+    sqrt_price: Decimal256,
+    tick: i64,
+    lower_tick: i64,
+    upper_tick: i64,
+    liquidity: Uint128,
+    round_up: bool,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let sqrt_price_lower = sqrt_price_at_tick(lower_tick)?;
+    let sqrt_price_upper = sqrt_price_at_tick(upper_tick)?;
+
+    let (amount_0, amount_1) = if tick < lower_tick {
+        (
+            amount_0_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+            Uint256::zero(),
+        )
+    } else if tick < upper_tick {
+        (
+            amount_0_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            amount_1_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?,
+        )
+    } else {
+        (
+            Uint256::zero(),
+            amount_1_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+        )
+    };
+
+    Ok((Uint128::try_from(amount_0)?, Uint128::try_from(amount_1)?))
+}
+
+/// Sorts two square root prices in ascending order.
+fn sort_sqrt_prices(a: Decimal256, b: Decimal256) -> (Decimal256, Decimal256) {
+    if a > b {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_math_is_consistent() {
+        assert_eq!(sqrt_price_at_tick(0).unwrap(), Decimal256::one());
+        assert_eq!(tick_at_sqrt_price(Decimal256::one()).unwrap(), 0);
+
+        for tick in [MIN_TICK, -100_000, -1, 1, 60, 23_028, MAX_TICK] {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+            assert!(sqrt_price_at_tick(tick - 1).map_or(true, |p| p < sqrt_price));
+        }
+
+        // 1.0001^23028 ~= 10
+        let price = sqrt_price_at_tick(23_028).unwrap().pow(2);
+        assert!(price > Decimal256::percent(999) && price < Decimal256::percent(1001));
+
+        assert_eq!(
+            sqrt_price_at_tick(MAX_TICK + 1).unwrap_err(),
+            ContractError::InvalidConcentratedLiquidityPool
+        );
+    }
+
+    #[test]
+    fn liquidity_round_trips_through_amounts() {
+        let lower_tick = -6_000;
+        let upper_tick = 6_000;
+        let sqrt_price_lower = sqrt_price_at_tick(lower_tick).unwrap();
+        let sqrt_price_upper = sqrt_price_at_tick(upper_tick).unwrap();
+
+        // in range, both assets are needed
+        let liquidity = liquidity_for_amounts(
+            Decimal256::one(),
+            sqrt_price_lower,
+            sqrt_price_upper,
+            Uint128::new(1_000_000),
+            Uint128::new(1_000_000),
+        )
+        .unwrap();
+        let (amount_0, amount_1) = amounts_for_liquidity(
+            Decimal256::one(),
+            0,
+            lower_tick,
+            upper_tick,
+            liquidity,
+            true,
+        )
+        .unwrap();
+        assert!(amount_0 <= Uint128::new(1_000_000) && amount_1 <= Uint128::new(1_000_000));
+        assert!(amount_0 >= Uint128::new(999_990) && amount_1 >= Uint128::new(999_990));
+
+        // below the range, only the first asset is needed
+        let liquidity = liquidity_for_amounts(
+            sqrt_price_at_tick(-7_000).unwrap(),
+            sqrt_price_lower,
+            sqrt_price_upper,
+            Uint128::new(1_000_000),
+            Uint128::new(1_000_000),
+        )
+        .unwrap();
+        let (amount_0, amount_1) = amounts_for_liquidity(
+            sqrt_price_at_tick(-7_000).unwrap(),
+            -7_000,
+            lower_tick,
+            upper_tick,
+            liquidity,
+            true,
+        )
+        .unwrap();
+        assert!(amount_0 <= Uint128::new(1_000_000) && amount_0 >= Uint128::new(999_990));
+        assert_eq!(amount_1, Uint128::zero());
+    }
+}
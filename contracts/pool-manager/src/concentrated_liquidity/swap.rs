@@ -0,0 +1,389 @@
+use cosmwasm_std::{Decimal256, Order, Storage, Uint128, Uint256, Uint512};
+use cw_storage_plus::Bound;
+
+use amm::pool_manager::PoolInfo;
+
+use crate::concentrated_liquidity::math::{
+    amount_0_delta, amount_1_delta, mul_div, next_sqrt_price_from_amount_0,
+    next_sqrt_price_from_amount_1, sqrt_price_at_tick, tick_at_sqrt_price, FEE_GROWTH_SCALE,
+    MAX_TICK, MIN_TICK,
+};
+use crate::helpers::{compute_fees, get_swap_computation, OfferAmountComputation, SwapComputation};
+use crate::state::{ConcentratedLiquidityState, CONCENTRATED_LIQUIDITY_STATES, TICKS};
+use crate::ContractError;
+
+/// A step of a swap on a concentrated liquidity pool, i.e. a part of the swap performed with a
+/// constant active liquidity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcentratedSwapStep {
+    /// The active liquidity during the step.
+    pub liquidity: Uint128,
+    /// The amount of the ask asset swapped out during the step, before fees.
+    pub amount_out: Uint256,
+    /// The tick crossed at the end of the step, if any.
+    pub crossed_tick: Option<i64>,
+}
+
+/// The result of a swap on a concentrated liquidity pool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcentratedSwap {
+    /// The amount of the offer asset swapped in.
+    pub amount_in: Uint256,
+    /// The amount of the ask asset swapped out, before fees.
+    pub amount_out: Uint256,
+    /// Whether the first asset of the pool is swapped for the second one, i.e. the price goes
+    /// down.
+    pub zero_for_one: bool,
+    /// The state of the pool after the swap. The fee growth is not updated yet.
+    pub state: ConcentratedLiquidityState,
+    /// The steps the swap was split into.
+    pub steps: Vec<ConcentratedSwapStep>,
+}
+
+/// Whether the swap is performed given the amount of the offer asset or the amount of the ask
+/// asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConcentratedSwapKind {
+    ExactIn,
+    ExactOut,
+}
+
+/// Gets the next initialized tick in the direction of the swap.
+fn next_initialized_tick(
+    storage: &dyn Storage,
+    pool_identifier: &str,
+    tick: i64,
+    zero_for_one: bool,
+) -> Result<Option<i64>, ContractError> {
+    let ticks = TICKS.prefix(pool_identifier);
+
+    let next = if zero_for_one {
+        ticks
+            .keys(
+                storage,
+                None,
+                Some(Bound::inclusive(tick)),
+                Order::Descending,
+            )
+            .next()
+    } else {
+        ticks
+            .keys(
+                storage,
+                Some(Bound::exclusive(tick)),
+                None,
+                Order::Ascending,
+            )
+            .next()
+    };
+
+    Ok(next.transpose()?)
+}
+
+/// Simulates a swap on a concentrated liquidity pool, crossing the initialized ticks as the price
+/// moves. Doesn't modify the storage.
+pub fn simulate_concentrated_swap(
+    storage: &dyn Storage,
+    pool_identifier: &str,
+    zero_for_one: bool,
+    amount: Uint256,
+    kind: ConcentratedSwapKind,
+) -> Result<ConcentratedSwap, ContractError> {
+    let mut state = CONCENTRATED_LIQUIDITY_STATES.load(storage, pool_identifier)?;
+    let mut remaining = amount;
+    let mut amount_in = Uint256::zero();
+    let mut amount_out = Uint256::zero();
+    let mut steps = vec![];
+
+    while !remaining.is_zero() {
+        let next_tick = next_initialized_tick(storage, pool_identifier, state.tick, zero_for_one)?;
+        let target_sqrt_price = match next_tick {
+            Some(tick) => sqrt_price_at_tick(tick)?,
+            None if zero_for_one => sqrt_price_at_tick(MIN_TICK)?,
+            None => sqrt_price_at_tick(MAX_TICK)?,
+        };
+
+        // amounts needed to move the price up to the target, in and out respectively
+        let (max_in, max_out) = if state.liquidity.is_zero() {
+            (Uint256::zero(), Uint256::zero())
+        } else if zero_for_one {
+            (
+                amount_0_delta(target_sqrt_price, state.sqrt_price, state.liquidity, true)?,
+                amount_1_delta(target_sqrt_price, state.sqrt_price, state.liquidity, false)?,
+            )
+        } else {
+            (
+                amount_1_delta(state.sqrt_price, target_sqrt_price, state.liquidity, true)?,
+                amount_0_delta(state.sqrt_price, target_sqrt_price, state.liquidity, false)?,
+            )
+        };
+
+        let reaches_target = match kind {
+            ConcentratedSwapKind::ExactIn => remaining >= max_in,
+            ConcentratedSwapKind::ExactOut => remaining >= max_out,
+        };
+
+        if reaches_target {
+            // the swap can't go beyond the supported price range
+            let Some(tick) = next_tick else {
+                return Err(ContractError::InsufficientConcentratedLiquidity);
+            };
+
+            let (step_in, step_out) = (max_in, max_out);
+            remaining = remaining.checked_sub(match kind {
+                ConcentratedSwapKind::ExactIn => step_in,
+                ConcentratedSwapKind::ExactOut => step_out,
+            })?;
+            amount_in = amount_in.checked_add(step_in)?;
+            amount_out = amount_out.checked_add(step_out)?;
+
+            steps.push(ConcentratedSwapStep {
+                liquidity: state.liquidity,
+                amount_out: step_out,
+                crossed_tick: Some(tick),
+            });
+
+            // cross the tick, updating the active liquidity
+            let liquidity_net = TICKS.load(storage, (pool_identifier, tick))?.liquidity_net;
+            let liquidity_net_abs = liquidity_net.unsigned_abs();
+            state.liquidity = if liquidity_net.is_negative() == zero_for_one {
+                state.liquidity.checked_add(liquidity_net_abs)?
+            } else {
+                state.liquidity.checked_sub(liquidity_net_abs)?
+            };
+            state.sqrt_price = target_sqrt_price;
+            state.tick = if zero_for_one { tick - 1 } else { tick };
+        } else {
+            let next_sqrt_price = match (kind, zero_for_one) {
+                (ConcentratedSwapKind::ExactIn, true) => next_sqrt_price_from_amount_0(
+                    state.sqrt_price,
+                    state.liquidity,
+                    remaining,
+                    true,
+                )?,
+                (ConcentratedSwapKind::ExactIn, false) => next_sqrt_price_from_amount_1(
+                    state.sqrt_price,
+                    state.liquidity,
+                    remaining,
+                    true,
+                )?,
+                (ConcentratedSwapKind::ExactOut, true) => next_sqrt_price_from_amount_1(
+                    state.sqrt_price,
+                    state.liquidity,
+                    remaining,
+                    false,
+                )?,
+                (ConcentratedSwapKind::ExactOut, false) => next_sqrt_price_from_amount_0(
+                    state.sqrt_price,
+                    state.liquidity,
+                    remaining,
+                    false,
+                )?,
+            };
+
+            let (step_in, step_out) = match kind {
+                ConcentratedSwapKind::ExactIn => {
+                    let step_out = if zero_for_one {
+                        amount_1_delta(next_sqrt_price, state.sqrt_price, state.liquidity, false)?
+                    } else {
+                        amount_0_delta(state.sqrt_price, next_sqrt_price, state.liquidity, false)?
+                    };
+                    (remaining, step_out)
+                }
+                ConcentratedSwapKind::ExactOut => {
+                    let step_in = if zero_for_one {
+                        amount_0_delta(next_sqrt_price, state.sqrt_price, state.liquidity, true)?
+                    } else {
+                        amount_1_delta(state.sqrt_price, next_sqrt_price, state.liquidity, true)?
+                    };
+                    (step_in, remaining)
+                }
+            };
+
+            remaining = Uint256::zero();
+            amount_in = amount_in.checked_add(step_in)?;
+            amount_out = amount_out.checked_add(step_out)?;
+
+            steps.push(ConcentratedSwapStep {
+                liquidity: state.liquidity,
+                amount_out: step_out,
+                crossed_tick: None,
+            });
+
+            state.sqrt_price = next_sqrt_price;
+            state.tick = tick_at_sqrt_price(next_sqrt_price)?;
+        }
+    }
+
+    Ok(ConcentratedSwap {
+        amount_in,
+        amount_out,
+        zero_for_one,
+        state,
+        steps,
+    })
+}
+
+/// Gets the spot exchange rate of a concentrated liquidity pool, i.e. ask asset per offer asset.
+fn get_concentrated_exchange_rate(
+    sqrt_price: Decimal256,
+    zero_for_one: bool,
+) -> Result<Decimal256, ContractError> {
+    let price = sqrt_price.checked_mul(sqrt_price)?;
+
+    if zero_for_one {
+        Ok(price)
+    } else {
+        Ok(Decimal256::one().checked_div(price)?)
+    }
+}
+
+/// Computes a swap of the given offer amount on a concentrated liquidity pool.
+pub fn compute_concentrated_swap(
+    storage: &dyn Storage,
+    pool_info: &PoolInfo,
+    offer_index: usize,
+    offer_amount: Uint128,
+) -> Result<(SwapComputation, ConcentratedSwap), ContractError> {
+    let zero_for_one = offer_index == 0;
+    let sqrt_price = CONCENTRATED_LIQUIDITY_STATES
+        .load(storage, &pool_info.pool_identifier)?
+        .sqrt_price;
+
+    let concentrated_swap = simulate_concentrated_swap(
+        storage,
+        &pool_info.pool_identifier,
+        zero_for_one,
+        offer_amount.into(),
+        ConcentratedSwapKind::ExactIn,
+    )?;
+
+    let return_amount = concentrated_swap.amount_out;
+    let exchange_rate = get_concentrated_exchange_rate(sqrt_price, zero_for_one)?;
+    let spread_amount = Decimal256::from_ratio(offer_amount, Uint128::one())
+        .checked_mul(exchange_rate)?
+        .to_uint_floor()
+        .saturating_sub(return_amount);
+
+    let fees_computation = compute_fees(pool_info.pool_fees.clone(), return_amount)?;
+
+    Ok((
+        get_swap_computation(return_amount, spread_amount, fees_computation)?,
+        concentrated_swap,
+    ))
+}
+
+/// Computes the amount of the offer asset needed to get the given ask amount out of a
+/// concentrated liquidity pool.
+pub fn compute_concentrated_offer_amount(
+    storage: &dyn Storage,
+    pool_info: &PoolInfo,
+    offer_index: usize,
+    ask_amount: Uint128,
+) -> Result<OfferAmountComputation, ContractError> {
+    let zero_for_one = offer_index == 0;
+    let pool_fees = pool_info.pool_fees.clone();
+    let sqrt_price = CONCENTRATED_LIQUIDITY_STATES
+        .load(storage, &pool_info.pool_identifier)?
+        .sqrt_price;
+
+    let mut fees = pool_fees
+        .swap_fee
+        .to_decimal_256()
+        .checked_add(pool_fees.protocol_fee.to_decimal_256())?
+        .checked_add(pool_fees.burn_fee.to_decimal_256())?;
+
+    for extra_fee in pool_fees.extra_fees.iter() {
+        fees = fees.checked_add(extra_fee.to_decimal_256())?;
+    }
+
+    let before_commission_deduction: Uint256 = Decimal256::from_ratio(ask_amount, Uint256::one())
+        .checked_div(Decimal256::one().checked_sub(fees)?)?
+        .to_uint_floor();
+
+    let concentrated_swap = simulate_concentrated_swap(
+        storage,
+        &pool_info.pool_identifier,
+        zero_for_one,
+        before_commission_deduction,
+        ConcentratedSwapKind::ExactOut,
+    )?;
+
+    let offer_amount = concentrated_swap.amount_in;
+    let exchange_rate = get_concentrated_exchange_rate(sqrt_price, zero_for_one)?;
+    let spread_amount = Decimal256::from_ratio(offer_amount, Uint256::one())
+        .checked_mul(exchange_rate)?
+        .to_uint_floor()
+        .saturating_sub(before_commission_deduction);
+
+    Ok(OfferAmountComputation {
+        offer_amount: offer_amount.try_into()?,
+        spread_amount: spread_amount.try_into()?,
+        swap_fee_amount: pool_fees
+            .swap_fee
+            .compute(before_commission_deduction)?
+            .try_into()?,
+        protocol_fee_amount: pool_fees
+            .protocol_fee
+            .compute(before_commission_deduction)?
+            .try_into()?,
+        burn_fee_amount: pool_fees
+            .burn_fee
+            .compute(before_commission_deduction)?
+            .try_into()?,
+    })
+}
+
+/// Applies a swap performed on a concentrated liquidity pool to the storage, distributing the
+/// fees that stay in the pool, i.e. `lp_fee_amount`, among the liquidity of each step and crossing
+/// the ticks.
+pub fn apply_concentrated_swap(
+    storage: &mut dyn Storage,
+    pool_identifier: &str,
+    concentrated_swap: ConcentratedSwap,
+    ask_index: usize,
+    lp_fee_amount: Uint128,
+) -> Result<(), ContractError> {
+    let mut fee_growth_global = CONCENTRATED_LIQUIDITY_STATES
+        .load(storage, pool_identifier)?
+        .fee_growth_global;
+
+    for step in concentrated_swap.steps {
+        if !step.liquidity.is_zero() && !step.amount_out.is_zero() {
+            // the fees of the step are proportional to the amount swapped out during the step
+            let fee_growth = mul_div(
+                Uint512::from(lp_fee_amount.u128()).checked_mul(Uint512::from(step.amount_out))?,
+                Uint512::from(FEE_GROWTH_SCALE),
+                Uint512::from(concentrated_swap.amount_out)
+                    .checked_mul(Uint512::from(step.liquidity.u128()))?,
+                false,
+            )?;
+
+            fee_growth_global[ask_index] = fee_growth_global[ask_index].wrapping_add(fee_growth);
+        }
+
+        if let Some(tick) = step.crossed_tick {
+            TICKS.update(storage, (pool_identifier, tick), |tick_info| {
+                let mut tick_info =
+                    tick_info.ok_or(ContractError::InsufficientConcentratedLiquidity)?;
+                tick_info.fee_growth_outside = fee_growth_global
+                    .iter()
+                    .zip(tick_info.fee_growth_outside.iter())
+                    .map(|(global, outside)| global.wrapping_sub(*outside))
+                    .collect();
+
+                Ok::<_, ContractError>(tick_info)
+            })?;
+        }
+    }
+
+    let mut state = concentrated_swap.state;
+    state.fee_growth_global = fee_growth_global;
+    state
+        .fees_held
+        .resize(state.fee_growth_global.len(), Uint128::zero());
+    state.fees_held[ask_index] = state.fees_held[ask_index].checked_add(lp_fee_amount)?;
+    CONCENTRATED_LIQUIDITY_STATES.save(storage, pool_identifier, &state)?;
+
+    Ok(())
+}
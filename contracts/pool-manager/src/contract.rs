@@ -9,11 +9,12 @@ use mantra_utils::validate_contract;
 
 use crate::error::ContractError;
 use crate::helpers::validate_asset_balance;
+use crate::liquidity::commands::simulate_single_side_liquidity_provision_swap;
 use crate::state::{
     Config, SingleSideLiquidityProvisionBuffer, CONFIG, POOL_COUNTER,
     SINGLE_SIDE_LIQUIDITY_PROVISION_BUFFER,
 };
-use crate::{liquidity, manager, queries, router, swap};
+use crate::{concentrated_liquidity, liquidity, manager, queries, router, shares, swap};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "mantra:pool-manager";
@@ -37,6 +38,7 @@ pub fn instantiate(
             deposits_enabled: true,
             swaps_enabled: true,
         },
+        lp_token_mode: msg.lp_token_mode.unwrap_or_default(),
     };
     CONFIG.save(deps.storage, &config)?;
     // initialize pool counter
@@ -49,6 +51,7 @@ pub fn instantiate(
         ("fee_collector_addr", msg.fee_collector_addr),
         ("farm_manager_addr", msg.farm_manager_addr),
         ("pool_creation_fee", msg.pool_creation_fee.to_string()),
+        ("lp_token_mode", format!("{:?}", config.lp_token_mode)),
     ]))
 }
 
@@ -56,20 +59,49 @@ pub fn instantiate(
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
     match msg.id {
         SINGLE_SIDE_LIQUIDITY_PROVISION_REPLY_ID => {
-            let SingleSideLiquidityProvisionBuffer {
-                receiver,
-                expected_offer_asset_balance_in_contract,
-                expected_ask_asset_balance_in_contract,
-                offer_asset_half,
-                expected_ask_asset,
-                liquidity_provision_data,
-            } = SINGLE_SIDE_LIQUIDITY_PROVISION_BUFFER.load(deps.storage)?;
+            let mut buffer = SINGLE_SIDE_LIQUIDITY_PROVISION_BUFFER.load(deps.storage)?;
+
+            validate_asset_balance(
+                &deps,
+                &env,
+                &buffer.expected_offer_asset_balance_in_contract,
+            )?;
+            validate_asset_balance(&deps, &env, &buffer.expected_ask_asset_balance_in_contract)?;
+
+            buffer
+                .swapped_assets
+                .push(buffer.expected_ask_asset.clone());
+
+            // swap the offer asset portion for the next asset, if any
+            if !buffer.pending_ask_asset_denoms.is_empty() {
+                let ask_asset_denom = buffer.pending_ask_asset_denoms.remove(0);
+                let (expected_ask_asset_balance_in_contract, expected_ask_asset, swap_msg) =
+                    simulate_single_side_liquidity_provision_swap(
+                        deps.as_ref(),
+                        &env,
+                        &buffer.offer_asset_portion,
+                        ask_asset_denom,
+                        &buffer.liquidity_provision_data,
+                    )?;
 
-            validate_asset_balance(&deps, &env, &expected_offer_asset_balance_in_contract)?;
-            validate_asset_balance(&deps, &env, &expected_ask_asset_balance_in_contract)?;
+                buffer.expected_ask_asset_balance_in_contract =
+                    expected_ask_asset_balance_in_contract;
+                buffer.expected_ask_asset = expected_ask_asset;
+                SINGLE_SIDE_LIQUIDITY_PROVISION_BUFFER.save(deps.storage, &buffer)?;
+
+                return Ok(Response::default().add_submessage(swap_msg));
+            }
 
             SINGLE_SIDE_LIQUIDITY_PROVISION_BUFFER.remove(deps.storage);
 
+            let SingleSideLiquidityProvisionBuffer {
+                receiver,
+                offer_asset_remainder,
+                swapped_assets,
+                liquidity_provision_data,
+                ..
+            } = buffer;
+
             Ok(Response::default().add_message(wasm_execute(
                 env.contract.address.into_string(),
                 &ExecuteMsg::ProvideLiquidity {
@@ -79,8 +111,9 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
                     pool_identifier: liquidity_provision_data.pool_identifier,
                     unlocking_duration: liquidity_provision_data.unlocking_duration,
                     lock_position_identifier: liquidity_provision_data.lock_position_identifier,
+                    excess_mode: None,
                 },
-                vec![offer_asset_half, expected_ask_asset],
+                [vec![offer_asset_remainder], swapped_assets].concat(),
             )?))
         }
         _ => Err(StdError::generic_err("reply id not found").into()),
@@ -118,6 +151,7 @@ pub fn execute(
             pool_identifier,
             unlocking_duration,
             lock_position_identifier,
+            excess_mode,
         } => liquidity::commands::provide_liquidity(
             deps,
             env,
@@ -128,6 +162,7 @@ pub fn execute(
             pool_identifier,
             unlocking_duration,
             lock_position_identifier,
+            excess_mode,
         ),
         ExecuteMsg::Swap {
             ask_asset_denom,
@@ -137,6 +172,7 @@ pub fn execute(
             pool_identifier,
         } => swap::commands::swap(
             deps,
+            env,
             info.clone(),
             info.sender,
             ask_asset_denom,
@@ -145,9 +181,60 @@ pub fn execute(
             receiver,
             pool_identifier,
         ),
-        ExecuteMsg::WithdrawLiquidity { pool_identifier } => {
-            liquidity::commands::withdraw_liquidity(deps, env, info, pool_identifier)
-        }
+        ExecuteMsg::SwapExactOut {
+            ask_asset,
+            max_offer_amount,
+            receiver,
+            pool_identifier,
+        } => swap::commands::swap_exact_out(
+            deps,
+            env,
+            info,
+            ask_asset,
+            max_offer_amount,
+            receiver,
+            pool_identifier,
+        ),
+        ExecuteMsg::WithdrawLiquidity {
+            pool_identifier,
+            min_assets_out,
+            receiver,
+            deadline,
+            target_denom,
+        } => liquidity::commands::withdraw_liquidity(
+            deps,
+            env,
+            info,
+            pool_identifier,
+            min_assets_out,
+            receiver,
+            deadline,
+            target_denom,
+        ),
+        ExecuteMsg::WithdrawLiquidityOneCoin {
+            pool_identifier,
+            denom,
+            min_amount,
+        } => liquidity::commands::withdraw_liquidity_one_coin(
+            deps,
+            env,
+            info,
+            pool_identifier,
+            denom,
+            min_amount,
+        ),
+        ExecuteMsg::WithdrawLiquidityImbalance {
+            pool_identifier,
+            amounts,
+            max_lp_burn,
+        } => liquidity::commands::withdraw_liquidity_imbalance(
+            deps,
+            env,
+            info,
+            pool_identifier,
+            amounts,
+            max_lp_burn,
+        ),
         ExecuteMsg::UpdateOwnership(action) => {
             mantra_utils::ownership::update_ownership(deps, env, info, action).map_err(Into::into)
         }
@@ -158,12 +245,149 @@ pub fn execute(
             max_spread,
         } => router::commands::execute_swap_operations(
             deps,
+            env,
             info,
             operations,
             minimum_receive,
             receiver,
             max_spread,
         ),
+        ExecuteMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer_amount,
+            receiver,
+            max_spread,
+        } => router::commands::execute_swap_operations_exact_out(
+            deps,
+            env,
+            info,
+            operations,
+            ask_amount,
+            max_offer_amount,
+            receiver,
+            max_spread,
+        ),
+        ExecuteMsg::ExecuteSplitSwapOperations {
+            routes,
+            minimum_receive,
+            receiver,
+            max_spread,
+        } => router::commands::execute_split_swap_operations(
+            deps,
+            env,
+            info,
+            routes,
+            minimum_receive,
+            receiver,
+            max_spread,
+        ),
+        ExecuteMsg::SwapViaRoute {
+            ask_asset_denom,
+            minimum_receive,
+            receiver,
+            max_spread,
+        } => router::commands::swap_via_route(
+            deps,
+            env,
+            info,
+            ask_asset_denom,
+            minimum_receive,
+            receiver,
+            max_spread,
+        ),
+        ExecuteMsg::AddSwapRoutes { swap_routes } => {
+            router::commands::add_swap_routes(deps, info, swap_routes)
+        }
+        ExecuteMsg::RemoveSwapRoutes { swap_routes } => {
+            router::commands::remove_swap_routes(deps, info, swap_routes)
+        }
+        ExecuteMsg::SwapUnderlying {
+            ask_asset_denom,
+            minimum_receive,
+            receiver,
+            max_spread,
+            pool_identifier,
+        } => swap::commands::swap_underlying(
+            deps,
+            env,
+            info,
+            ask_asset_denom,
+            minimum_receive,
+            receiver,
+            max_spread,
+            pool_identifier,
+        ),
+        ExecuteMsg::MigrateLiquidity {
+            pool_identifier,
+            target_pool_identifier,
+            slippage_tolerance,
+            max_spread,
+            swap_operations,
+            receiver,
+            unlocking_duration,
+            lock_position_identifier,
+            excess_mode,
+            concentrated_position,
+        } => liquidity::commands::migrate_liquidity(
+            deps,
+            env,
+            info,
+            pool_identifier,
+            target_pool_identifier,
+            slippage_tolerance,
+            max_spread,
+            swap_operations,
+            receiver,
+            unlocking_duration,
+            lock_position_identifier,
+            excess_mode,
+            concentrated_position,
+        ),
+        ExecuteMsg::ManageConcentratedPosition { action } => {
+            concentrated_liquidity::commands::manage_concentrated_position(deps, info, action)
+        }
+        ExecuteMsg::RampAmp {
+            pool_identifier,
+            target_amp,
+            end_time,
+        } => manager::ramp_amp(deps, env, info, pool_identifier, target_amp, end_time),
+        ExecuteMsg::StopRampAmp { pool_identifier } => {
+            manager::stop_ramp_amp(deps, env, info, pool_identifier)
+        }
+        ExecuteMsg::Skim {
+            pool_identifier,
+            recipient,
+        } => manager::skim(deps, env, info, pool_identifier, recipient),
+        ExecuteMsg::TransferShares {
+            pool_identifier,
+            recipient,
+            amount,
+        } => shares::commands::transfer_shares(deps, info, pool_identifier, recipient, amount),
+        ExecuteMsg::SendShares {
+            pool_identifier,
+            amount,
+            contract,
+            msg,
+        } => shares::commands::send_shares(deps, env, info, pool_identifier, amount, contract, msg),
+        ExecuteMsg::ApproveShares {
+            pool_identifier,
+            spender,
+            amount,
+        } => shares::commands::approve_shares(deps, info, pool_identifier, spender, amount),
+        ExecuteMsg::TransferSharesFrom {
+            pool_identifier,
+            owner,
+            recipient,
+            amount,
+        } => shares::commands::transfer_shares_from(
+            deps,
+            info,
+            pool_identifier,
+            owner,
+            recipient,
+            amount,
+        ),
         ExecuteMsg::UpdateConfig {
             fee_collector_addr,
             farm_manager_addr,
@@ -181,7 +405,7 @@ pub fn execute(
 }
 
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::Config {} => Ok(to_json_binary(&queries::query_config(deps)?)?),
         QueryMsg::AssetDecimals {
@@ -198,6 +422,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             pool_identifier,
         } => Ok(to_json_binary(&queries::query_simulation(
             deps,
+            &env,
             offer_asset,
             ask_asset_denom,
             pool_identifier,
@@ -208,6 +433,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             pool_identifier,
         } => Ok(to_json_binary(&queries::query_reverse_simulation(
             deps,
+            &env,
             ask_asset,
             offer_asset_denom,
             pool_identifier,
@@ -217,6 +443,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             operations,
         } => Ok(to_json_binary(&queries::simulate_swap_operations(
             deps,
+            &env,
             offer_amount,
             operations,
         )?)?),
@@ -224,7 +451,86 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             ask_amount,
             operations,
         } => Ok(to_json_binary(&queries::reverse_simulate_swap_operations(
-            deps, ask_amount, operations,
+            deps, &env, ask_amount, operations,
+        )?)?),
+        QueryMsg::BestRoute {
+            offer_asset,
+            ask_asset_denom,
+            max_hops,
+            max_pools,
+        } => Ok(to_json_binary(&queries::query_best_route(
+            deps,
+            &env,
+            offer_asset,
+            ask_asset_denom,
+            max_hops,
+            max_pools,
+        )?)?),
+        QueryMsg::SwapRoute {
+            offer_asset_denom,
+            ask_asset_denom,
+        } => Ok(to_json_binary(&queries::get_swap_route(
+            deps,
+            offer_asset_denom,
+            ask_asset_denom,
+        )?)?),
+        QueryMsg::SwapRoutes { start_after, limit } => Ok(to_json_binary(
+            &queries::get_swap_routes(deps, start_after, limit)?,
+        )?),
+        QueryMsg::SwapRouteCreator {
+            offer_asset_denom,
+            ask_asset_denom,
+        } => Ok(to_json_binary(&queries::get_swap_route_creator(
+            deps,
+            offer_asset_denom,
+            ask_asset_denom,
+        )?)?),
+        QueryMsg::ReserveHealth { pool_identifier } => Ok(to_json_binary(
+            &queries::query_reserve_health(deps, &env, pool_identifier)?,
+        )?),
+        QueryMsg::ShareBalance {
+            pool_identifier,
+            address,
+        } => Ok(to_json_binary(&queries::query_share_balance(
+            deps,
+            pool_identifier,
+            address,
+        )?)?),
+        QueryMsg::ShareAllowance {
+            pool_identifier,
+            owner,
+            spender,
+        } => Ok(to_json_binary(&queries::query_share_allowance(
+            deps,
+            pool_identifier,
+            owner,
+            spender,
+        )?)?),
+        QueryMsg::SimulateProvideLiquidity {
+            pool_identifier,
+            deposits,
+            slippage_tolerance,
+            max_spread,
+            excess_mode,
+        } => Ok(to_json_binary(&queries::simulate_provide_liquidity(
+            deps,
+            &env,
+            pool_identifier,
+            deposits,
+            slippage_tolerance,
+            max_spread,
+            excess_mode,
+        )?)?),
+        QueryMsg::SimulateWithdrawLiquidity {
+            pool_identifier,
+            lp_amount,
+            target_denom,
+        } => Ok(to_json_binary(&queries::simulate_withdraw_liquidity(
+            deps,
+            &env,
+            pool_identifier,
+            lp_amount,
+            target_denom,
         )?)?),
         QueryMsg::Ownership {} => Ok(to_json_binary(&cw_ownable::get_ownership(deps.storage)?)?),
         QueryMsg::Pools {
@@ -233,10 +539,24 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             limit,
         } => Ok(to_json_binary(&queries::get_pools(
             deps,
+            &env,
             pool_identifier,
             start_after,
             limit,
         )?)?),
+        QueryMsg::ConcentratedPoolState { pool_identifier } => Ok(to_json_binary(
+            &queries::query_concentrated_pool_state(deps, pool_identifier)?,
+        )?),
+        QueryMsg::ConcentratedPositions {
+            filter_by,
+            start_after,
+            limit,
+        } => Ok(to_json_binary(&queries::query_concentrated_positions(
+            deps,
+            filter_by,
+            start_after,
+            limit,
+        )?)?),
     }
 }
 
@@ -1,8 +1,8 @@
 use crate::manager::commands::MAX_ASSETS_PER_POOL;
 use cosmwasm_std::{
     CheckedFromRatioError, CheckedMultiplyFractionError, CheckedMultiplyRatioError,
-    ConversionOverflowError, DivideByZeroError, Instantiate2AddressError, OverflowError, StdError,
-    Uint128,
+    ConversionOverflowError, Decimal, DivideByZeroError, Instantiate2AddressError, OverflowError,
+    StdError, Timestamp, Uint128,
 };
 use cw_migrate_error_derive::cw_migrate_invalid_version_error;
 use cw_ownable::OwnershipError;
@@ -136,6 +136,35 @@ pub enum ContractError {
         next_input: String,
     },
 
+    #[error("The weight of a split swap route must be greater than zero")]
+    InvalidSplitSwapRouteWeight,
+
+    #[error("The split swap routes must all swap {offer_asset_denom} for {ask_asset_denom}")]
+    MismatchedSplitSwapRoutes {
+        offer_asset_denom: String,
+        ask_asset_denom: String,
+    },
+
+    #[error("A swap route to swap {offer_asset_denom} for {ask_asset_denom} already exists")]
+    SwapRouteAlreadyExists {
+        offer_asset_denom: String,
+        ask_asset_denom: String,
+    },
+
+    #[error("No swap route exists to swap {offer_asset_denom} for {ask_asset_denom}")]
+    NoSwapRouteForAssets {
+        offer_asset_denom: String,
+        ask_asset_denom: String,
+    },
+
+    #[error(
+        "The operations of the swap route don't swap {offer_asset_denom} for {ask_asset_denom}"
+    )]
+    MalformedSwapRoute {
+        offer_asset_denom: String,
+        ask_asset_denom: String,
+    },
+
     #[error("Invalid pool creation fee, expected {expected} got {amount}")]
     InvalidPoolCreationFee { amount: Uint128, expected: Uint128 },
 
@@ -162,6 +191,197 @@ pub enum ContractError {
 
     #[error("The token factory lp denom creation fee was not paid.")]
     TokenFactoryFeeNotPaid,
+
+    #[error("Invalid pool weights, there must be one non-zero weight per asset and they must add up to 1")]
+    InvalidPoolWeights,
+
+    #[error(
+        "The swap amount exceeds the maximum ratio of the pool balance allowed in a weighted pool"
+    )]
+    MaxWeightedSwapRatioExceeded,
+
+    #[error("Invalid concentrated liquidity pool, it must have two assets, a non-zero tick spacing and an initial price within the supported range")]
+    InvalidConcentratedLiquidityPool,
+
+    #[error("The pool is not a concentrated liquidity pool")]
+    NotAConcentratedLiquidityPool,
+
+    #[error("Operation not supported on concentrated liquidity pools, {0}")]
+    UnsupportedConcentratedLiquidityOperation(String),
+
+    #[error("Invalid tick range [{lower_tick}, {upper_tick}), ticks must be multiples of the tick spacing within the supported range")]
+    InvalidTickRange { lower_tick: i64, upper_tick: i64 },
+
+    #[error("The concentrated liquidity position with identifier {identifier} already exists")]
+    ConcentratedPositionAlreadyExists { identifier: String },
+
+    #[error("The concentrated liquidity position with identifier {identifier} doesn't exist")]
+    NonExistentConcentratedPosition { identifier: String },
+
+    #[error("Invalid concentrated liquidity position identifier {identifier}")]
+    InvalidConcentratedPositionIdentifier { identifier: String },
+
+    #[error("Invalid liquidity amount for the concentrated liquidity position")]
+    InvalidConcentratedLiquidityAmount,
+
+    #[error(
+        "There is not enough liquidity in the concentrated liquidity pool to perform the swap"
+    )]
+    InsufficientConcentratedLiquidity,
+
+    #[error("Invalid weight schedule, the start time must be before the end time and the schedule can't be over")]
+    InvalidWeightSchedule,
+
+    #[error(
+        "The weights of the liquidity bootstrapping pool must be resolved at the current time"
+    )]
+    UnresolvedPoolWeights,
+
+    #[error("Invalid Solidly stable pool, it must have two assets")]
+    InvalidSolidlyStablePool,
+
+    #[error("Invalid rate providers, there must be one rate provider per asset and fixed rates must be non-zero")]
+    InvalidRateProviders,
+
+    #[error("Invalid exchange rate for {denom}, it must be non-zero")]
+    InvalidExchangeRate { denom: String },
+
+    #[error("The pool is not a stable swap pool")]
+    NotAStableSwapPool,
+
+    #[error(
+        "The amount withdrawn {amount}{denom} is below the minimum amount of {min_amount}{denom}"
+    )]
+    WithdrawAmountBelowMinimum {
+        denom: String,
+        amount: Uint128,
+        min_amount: Uint128,
+    },
+
+    #[error("A minimum amount of {target_denom} to receive must be given when withdrawing liquidity into it")]
+    MissingTargetMinimumOut { target_denom: String },
+
+    #[error("The excess of a deposit can only be refunded or swapped on constant product pools")]
+    InvalidExcessDepositMode,
+
+    #[error(
+        "Withdrawing liquidity into a single asset is only supported on constant product pools"
+    )]
+    InvalidPoolTypeForZapOut,
+
+    #[error("The deadline {deadline} has passed, the current block time is {block_time}")]
+    DeadlineExceeded {
+        deadline: Timestamp,
+        block_time: Timestamp,
+    },
+
+    #[error(
+        "The withdrawal requires burning {lp_burn} LP tokens, above the maximum of {max_lp_burn}"
+    )]
+    LpBurnAboveMaximum {
+        lp_burn: Uint128,
+        max_lp_burn: Uint128,
+    },
+
+    #[error("Invalid withdrawal amounts, the assets must be in the pool and can't drain it")]
+    InvalidWithdrawAmounts,
+
+    #[error("Invalid oracle pegged pool, it must have two assets, a maximum staleness above zero and a maximum deviation between 0 and 1")]
+    InvalidOraclePeggedPool,
+
+    #[error("The oracle price must be resolved before computing swaps on oracle pegged pools")]
+    UnresolvedOraclePrice,
+
+    #[error("The oracle returned an invalid price")]
+    InvalidOraclePrice,
+
+    #[error("The oracle price is stale, it was last updated at {updated_at} and can't be older than {max_staleness} seconds")]
+    StaleOraclePrice { updated_at: u64, max_staleness: u64 },
+
+    #[error(
+        "The oracle price was updated at {updated_at}, after the current block time {block_time}"
+    )]
+    FutureOraclePrice { updated_at: u64, block_time: u64 },
+
+    #[error("The swap deviates {deviation} from the oracle price, above the maximum deviation of {max_deviation}")]
+    OraclePriceDeviationExceeded {
+        deviation: Decimal,
+        max_deviation: Decimal,
+    },
+
+    #[error("Invalid metapool, it must be a stable swap pool with two assets, the second being the LP token of a stable swap pool that isn't a metapool")]
+    InvalidMetapool,
+
+    #[error("The pool is not a metapool")]
+    NotAMetapool,
+
+    #[error("Invalid target amp {target_amp}, it must be between 1 and {max_amp}")]
+    InvalidTargetAmp { target_amp: u64, max_amp: u64 },
+
+    #[error("The amp can't be ramped more than once every {min_ramp_time} seconds")]
+    AmpRampTooSoon { min_ramp_time: u64 },
+
+    #[error("The amp ramp must last at least {min_ramp_time} seconds")]
+    InvalidAmpRampEndTime { min_ramp_time: u64 },
+
+    #[error("The amp can't change by more than {max_amp_change}x in a single ramp, from {current_amp} to {target_amp}")]
+    AmpChangeTooLarge {
+        current_amp: u64,
+        target_amp: u64,
+        max_amp_change: u64,
+    },
+
+    #[error("The pool {pool_identifier} has no balances to skim")]
+    NothingToSkim { pool_identifier: String },
+
+    #[error("Liquidity can't be migrated into the pool it's withdrawn from")]
+    SameMigrationPool,
+
+    #[error("The position to create must be provided when migrating liquidity into a concentrated liquidity pool, and only then, without locking it nor sending it to a receiver")]
+    InvalidConcentratedMigration,
+
+    #[error("No swap operations were given for {denom}, which is withdrawn but not held by the target pool")]
+    MissingMigrationSwapOperations { denom: String },
+
+    #[error("The swap operations of a migration must each swap a withdrawn asset the target pool doesn't hold into one of its assets")]
+    InvalidMigrationSwapOperations,
+
+    #[error("The pool {pool_identifier} doesn't account for its LP shares internally")]
+    NotInternalLpPool { pool_identifier: String },
+
+    #[error("Insufficient LP shares, the balance is {balance} but {amount} are needed")]
+    InsufficientShares { balance: Uint128, amount: Uint128 },
+
+    #[error(
+        "Insufficient LP share allowance, the allowance is {allowance} but {amount} are needed"
+    )]
+    InsufficientShareAllowance { allowance: Uint128, amount: Uint128 },
+
+    #[error("Only withdrawal and migration messages can be executed with the LP shares sent to the pool manager")]
+    InvalidSharesMessage,
+
+    #[error(
+        "The swap requires an offer of {offer_amount}, above the maximum of {max_offer_amount}"
+    )]
+    MaxOfferAmountExceeded {
+        offer_amount: Uint128,
+        max_offer_amount: Uint128,
+    },
+
+    #[error("The pool can't return the exact amount asked for")]
+    ExactOutputUnreachable,
+
+    #[error("The amount of the ask asset must be greater than zero")]
+    ZeroAskAmount,
+
+    #[error("No route was found to swap {offer_asset_denom} for {ask_asset_denom}")]
+    NoRouteFound {
+        offer_asset_denom: String,
+        ask_asset_denom: String,
+    },
+
+    #[error("The route search went over the limit of {max_simulations} simulated swaps, lower the maximum hops or pools")]
+    RouteSearchLimitExceeded { max_simulations: u32 },
 }
 
 impl From<semver::Error> for ContractError {
@@ -3,19 +3,33 @@ use std::ops::Mul;
 use amm::coin::{aggregate_coins, FACTORY_MAX_SUBDENOM_SIZE};
 use amm::constants::LP_SYMBOL;
 use amm::fee::PoolFee;
-use amm::pool_manager::{PoolInfo, PoolType, SimulationResponse};
+use amm::lp_common::MINIMUM_LIQUIDITY_AMOUNT;
+use amm::pool_manager::{
+    ExchangeRateResponse, OraclePriceResponse, OracleQueryMsg, PoolInfo, PoolType, RateProvider,
+    RateProviderQueryMsg, ReserveHealth, SimulationResponse,
+};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    coin, ensure, Addr, Coin, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo, StdError,
-    StdResult, Uint128, Uint256, Uint512,
+    coin, ensure, Addr, Coin, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo, Order,
+    StdError, Storage, Uint128, Uint256, Uint512,
 };
 
+use crate::concentrated_liquidity::math::{ensure_sqrt_price_in_range, MAX_TICK};
 use crate::error::ContractError;
 use crate::math::Decimal256Helper;
+use crate::shares::ledger::{get_balance, get_total_share};
+use crate::state::{AmpRamp, AMP_RAMPS, CONCENTRATED_LIQUIDITY_STATES, POOLS};
 
 /// The amount of iterations to perform when calculating the Newton-Raphson approximation.
 const NEWTON_ITERATIONS: u64 = 32;
 
+/// The amount of iterations to perform when solving the Solidly stable invariant with Newton's
+/// method. The cubic converges slowly when starting far from the solution, i.e. on large swaps.
+const SOLIDLY_NEWTON_ITERATIONS: u64 = 255;
+
+/// The precision the balances of a Solidly stable pool are normalized to.
+const SOLIDLY_PRECISION: u8 = 18;
+
 /// Encodes all results of swapping from a source token to a destination token.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct SwapResult {
@@ -163,6 +177,9 @@ pub fn compute_swap(
     swap_type: &PoolType,
     offer_precision: u8,
     ask_precision: u8,
+    offer_index: usize,
+    ask_index: usize,
+    rates: &[Decimal256],
 ) -> Result<SwapComputation, ContractError> {
     let offer_pool: Uint256 = offer_pool.into();
     let ask_pool: Uint256 = ask_pool.into();
@@ -192,10 +209,18 @@ pub fn compute_swap(
                 fees_computation,
             )?)
         }
-        PoolType::StableSwap { amp } => {
-            let offer_pool = Decimal256::decimal_with_precision(offer_pool, offer_precision)?;
-            let ask_pool = Decimal256::decimal_with_precision(ask_pool, ask_precision)?;
-            let offer_amount = Decimal256::decimal_with_precision(offer_amount, offer_precision)?;
+        PoolType::StableSwap { amp, .. } => {
+            // the balances are scaled by the exchange rates of the assets, so the math below
+            // operates on amounts pegged 1:1
+            let offer_rate = get_asset_rate(rates, offer_index);
+            let ask_rate = get_asset_rate(rates, ask_index);
+
+            let offer_pool = Decimal256::decimal_with_precision(offer_pool, offer_precision)?
+                .checked_mul(offer_rate)?;
+            let ask_pool = Decimal256::decimal_with_precision(ask_pool, ask_precision)?
+                .checked_mul(ask_rate)?;
+            let offer_amount = Decimal256::decimal_with_precision(offer_amount, offer_precision)?
+                .checked_mul(offer_rate)?;
 
             let new_pool = calculate_stableswap_y(
                 n_coins,
@@ -207,15 +232,64 @@ pub fn compute_swap(
                 StableSwapDirection::Simulate,
             )?;
 
-            let return_amount = ask_pool
+            let scaled_return_amount = ask_pool
                 .to_uint256_with_precision(u32::from(ask_precision))?
                 .checked_sub(Uint256::from_uint128(new_pool))?;
 
-            // the spread is the loss from 1:1 conversion
+            // the spread is the loss from the conversion at the exchange rate
             // thus is it the offer_amount - return_amount
-            let spread_amount = offer_amount
+            let scaled_spread_amount = offer_amount
                 .to_uint256_with_precision(u32::from(ask_precision))?
-                .saturating_sub(return_amount);
+                .saturating_sub(scaled_return_amount);
+
+            // scale the amounts back to the ask asset
+            let return_amount = Decimal256::from_ratio(scaled_return_amount, Uint256::one())
+                .checked_div(ask_rate)?
+                .to_uint_floor();
+            let spread_amount = Decimal256::from_ratio(scaled_spread_amount, Uint256::one())
+                .checked_div(ask_rate)?
+                .to_uint_floor();
+
+            let fees_computation = compute_fees(pool_fees, return_amount)?;
+
+            Ok(get_swap_computation(
+                return_amount,
+                spread_amount,
+                fees_computation,
+            )?)
+        }
+        PoolType::Weighted { weights } => {
+            let (offer_weight, ask_weight) = get_weights_in_pool(weights, offer_index, ask_index)?;
+
+            // offer => ask
+            // ask_amount = ask_pool * (1 - (offer_pool / (offer_pool + offer_amount)) ^ (offer_weight / ask_weight))
+            ensure!(
+                offer_amount <= offer_pool.mul_floor(max_weighted_swap_ratio()),
+                ContractError::MaxWeightedSwapRatioExceeded
+            );
+
+            let base = Decimal256::checked_from_ratio(offer_pool, offer_pool + offer_amount)
+                .map_err(|_| ContractError::PoolHasNoAssets)?;
+            let exponent = offer_weight.checked_div(ask_weight)?;
+            let power = base.checked_pow_up(exponent)?;
+
+            let return_amount: Uint256 = Decimal256::one()
+                .saturating_sub(power)
+                .checked_mul(Decimal256::from_ratio(ask_pool, Uint256::one()))?
+                .to_uint_floor();
+
+            ensure!(
+                return_amount <= ask_pool.mul_floor(max_weighted_swap_ratio()),
+                ContractError::MaxWeightedSwapRatioExceeded
+            );
+
+            // calculate spread, swap and protocol fees
+            let exchange_rate =
+                get_weighted_exchange_rate(offer_pool, ask_pool, offer_weight, ask_weight)?;
+            let spread_amount: Uint256 = (Decimal256::from_ratio(offer_amount, Uint256::one())
+                .checked_mul(exchange_rate)?
+                .to_uint_floor())
+            .saturating_sub(return_amount);
 
             let fees_computation = compute_fees(pool_fees, return_amount)?;
 
@@ -225,11 +299,607 @@ pub fn compute_swap(
                 fees_computation,
             )?)
         }
+        PoolType::SolidlyStable => {
+            // offer => ask
+            // ask_amount = ask_pool - y, where x³y + xy³ = k for x = offer_pool + offer_amount
+            let offer_pool_normalized = to_solidly_balance(offer_pool, offer_precision)?;
+            let ask_pool_normalized = to_solidly_balance(ask_pool, ask_precision)?;
+            let offer_amount_normalized = to_solidly_balance(offer_amount, offer_precision)?;
+
+            let k = compute_solidly_k(offer_pool_normalized, ask_pool_normalized)?;
+            let new_ask_pool = compute_solidly_y(
+                offer_pool_normalized.checked_add(offer_amount_normalized)?,
+                k,
+                ask_pool_normalized,
+            )?;
+
+            let return_amount = from_solidly_balance(
+                ask_pool_normalized.checked_sub(new_ask_pool)?,
+                ask_precision,
+                false,
+            )?;
+
+            // calculate spread, swap and protocol fees
+            let exchange_rate = get_solidly_exchange_rate(
+                offer_pool_normalized,
+                ask_pool_normalized,
+                offer_precision,
+                ask_precision,
+            )?;
+            let spread_amount: Uint256 = (Decimal256::from_ratio(offer_amount, Uint256::one())
+                .checked_mul(exchange_rate)?
+                .to_uint_floor())
+            .saturating_sub(return_amount);
+
+            let fees_computation = compute_fees(pool_fees, return_amount)?;
+
+            Ok(get_swap_computation(
+                return_amount,
+                spread_amount,
+                fees_computation,
+            )?)
+        }
+        // the weights of liquidity bootstrapping pools depend on the time, the swap must be
+        // computed with the pool type from get_effective_pool_type
+        PoolType::LiquidityBootstrapping { .. } => Err(ContractError::UnresolvedPoolWeights),
+        // the curve of oracle pegged pools depends on the oracle price, the swap must be computed
+        // with the pool type from get_effective_pool_type
+        PoolType::OraclePegged { .. } => Err(ContractError::UnresolvedOraclePrice),
+        // concentrated liquidity swaps depend on the ticks of the pool, not only on its balances
+        PoolType::ConcentratedLiquidity { .. } => {
+            Err(ContractError::UnsupportedConcentratedLiquidityOperation(
+                "swaps must be computed with the pool ticks".to_string(),
+            ))
+        }
+    }
+}
+
+/// Computes the amplification of a ramp at the given time, interpolating linearly between the
+/// initial and target amplifications.
+pub fn get_current_amp(ramp: &AmpRamp, now: u64) -> Result<u64, ContractError> {
+    if now >= ramp.end_time {
+        return Ok(ramp.target_amp);
+    }
+
+    let elapsed = Uint128::from(now.saturating_sub(ramp.initial_time));
+    let duration = Uint128::from(ramp.end_time - ramp.initial_time);
+    let initial_amp = Uint128::from(ramp.initial_amp);
+    let target_amp = Uint128::from(ramp.target_amp);
+
+    let current_amp = if target_amp > initial_amp {
+        initial_amp.checked_add(
+            target_amp
+                .checked_sub(initial_amp)?
+                .multiply_ratio(elapsed, duration),
+        )?
+    } else {
+        initial_amp.checked_sub(
+            initial_amp
+                .checked_sub(target_amp)?
+                .multiply_ratio(elapsed, duration),
+        )?
+    };
+
+    Ok(current_amp.u128() as u64)
+}
+
+/// Sets the amplification of a stable swap pool to its current value, given the amplification
+/// ramp of the pool, if any.
+pub fn apply_amp_ramp(
+    storage: &dyn Storage,
+    env: &Env,
+    pool_info: &mut PoolInfo,
+) -> Result<(), ContractError> {
+    if let PoolType::StableSwap { amp, .. } = &mut pool_info.pool_type {
+        if let Some(ramp) = AMP_RAMPS.may_load(storage, &pool_info.pool_identifier)? {
+            *amp = get_current_amp(&ramp, env.block.time.seconds())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the weights of a liquidity bootstrapping pool at the given time, interpolating
+/// linearly between the start and end weights. The last weight absorbs the rounding so the
+/// weights keep adding up to 1.
+pub fn get_current_weights(
+    start_weights: &[Decimal],
+    end_weights: &[Decimal],
+    start_time: u64,
+    end_time: u64,
+    now: u64,
+) -> Result<Vec<Decimal>, ContractError> {
+    if now <= start_time {
+        return Ok(start_weights.to_vec());
+    }
+    if now >= end_time {
+        return Ok(end_weights.to_vec());
+    }
+
+    let elapsed = Uint128::from(now - start_time);
+    let duration = Uint128::from(end_time - start_time);
+
+    let mut weights = start_weights
+        .iter()
+        .zip(end_weights.iter())
+        .map(|(start_weight, end_weight)| {
+            let weight = if end_weight > start_weight {
+                start_weight.checked_add(
+                    end_weight
+                        .checked_sub(*start_weight)?
+                        .checked_mul(Decimal::from_ratio(elapsed, duration))?,
+                )?
+            } else {
+                start_weight.checked_sub(
+                    start_weight
+                        .checked_sub(*end_weight)?
+                        .checked_mul(Decimal::from_ratio(elapsed, duration))?,
+                )?
+            };
+
+            Ok(weight)
+        })
+        .collect::<Result<Vec<Decimal>, ContractError>>()?;
+
+    if let Some((last_weight, weights)) = weights.split_last_mut() {
+        *last_weight = Decimal::one().checked_sub(
+            weights
+                .iter()
+                .try_fold(Decimal::zero(), |acc, weight| acc.checked_add(*weight))?,
+        )?;
+    }
+
+    Ok(weights)
+}
+
+/// Gets the pool type to compute swaps and liquidity provisions with at the current block time,
+/// i.e. the weighted pool a liquidity bootstrapping pool is at the moment, or the stable swap pool
+/// an oracle pegged pool is at the given oracle price, as returned by [`get_oracle_price`]. Other
+/// pool types are returned as they are.
+pub fn get_effective_pool_type(
+    env: &Env,
+    pool_info: &PoolInfo,
+    oracle_price: Option<Decimal>,
+) -> Result<PoolType, ContractError> {
+    match &pool_info.pool_type {
+        PoolType::LiquidityBootstrapping {
+            start_weights,
+            end_weights,
+            start_time,
+            end_time,
+        } => Ok(PoolType::Weighted {
+            weights: get_current_weights(
+                start_weights,
+                end_weights,
+                *start_time,
+                *end_time,
+                env.block.time.seconds(),
+            )?,
+        }),
+        PoolType::OraclePegged { amp, .. } => {
+            let price = oracle_price.ok_or(ContractError::InvalidOraclePrice)?;
+
+            // the first asset is worth the oracle price in terms of the second one
+            Ok(PoolType::StableSwap {
+                amp: *amp,
+                rate_providers: Some(vec![
+                    RateProvider::Fixed { rate: price },
+                    RateProvider::Fixed {
+                        rate: Decimal::one(),
+                    },
+                ]),
+            })
+        }
+        _ => Ok(pool_info.pool_type.clone()),
+    }
+}
+
+/// Gets the oracle price an oracle pegged pool is centred on at the current block time, or `None`
+/// for other pool types.
+pub fn get_oracle_price(
+    deps: &Deps,
+    env: &Env,
+    pool_info: &PoolInfo,
+) -> Result<Option<Decimal>, ContractError> {
+    match &pool_info.pool_type {
+        PoolType::OraclePegged {
+            oracle_addr,
+            max_staleness,
+            ..
+        } => Ok(Some(query_oracle_price(
+            deps,
+            env,
+            oracle_addr,
+            &pool_info.asset_denoms,
+            *max_staleness,
+        )?)),
+        _ => Ok(None),
+    }
+}
+
+/// Queries the oracle for the price of the first asset in terms of the second one, making sure it
+/// isn't older than `max_staleness` seconds nor set in the future.
+pub fn query_oracle_price(
+    deps: &Deps,
+    env: &Env,
+    oracle_addr: &str,
+    asset_denoms: &[String],
+    max_staleness: u64,
+) -> Result<Decimal, ContractError> {
+    let [base_denom, quote_denom] = asset_denoms else {
+        return Err(ContractError::InvalidOraclePeggedPool);
+    };
+
+    let response: OraclePriceResponse = deps.querier.query_wasm_smart(
+        oracle_addr,
+        &OracleQueryMsg::Price {
+            base_denom: base_denom.clone(),
+            quote_denom: quote_denom.clone(),
+        },
+    )?;
+
+    ensure!(!response.price.is_zero(), ContractError::InvalidOraclePrice);
+    ensure!(
+        response.updated_at <= env.block.time.seconds(),
+        ContractError::FutureOraclePrice {
+            updated_at: response.updated_at,
+            block_time: env.block.time.seconds(),
+        }
+    );
+    ensure!(
+        env.block.time.seconds() - response.updated_at <= max_staleness,
+        ContractError::StaleOraclePrice {
+            updated_at: response.updated_at,
+            max_staleness,
+        }
+    );
+
+    Ok(response.price)
+}
+
+/// Ensures the execution price of a swap on an oracle pegged pool doesn't deviate from the oracle
+/// price more than the pool allows. The spread of these swaps is measured against the oracle
+/// price, so the deviation is the spread relative to the amount returned at the oracle price.
+pub fn assert_oracle_price_deviation(
+    pool_type: &PoolType,
+    swap_computation: &SwapComputation,
+) -> Result<(), ContractError> {
+    let PoolType::OraclePegged { max_deviation, .. } = pool_type else {
+        return Ok(());
+    };
+
+    let oracle_return_amount = swap_computation
+        .return_amount
+        .checked_add(swap_computation.swap_fee_amount)?
+        .checked_add(swap_computation.protocol_fee_amount)?
+        .checked_add(swap_computation.burn_fee_amount)?
+        .checked_add(swap_computation.extra_fees_amount)?
+        .checked_add(swap_computation.spread_amount)?;
+
+    if oracle_return_amount.is_zero() {
+        return Ok(());
+    }
+
+    let deviation = Decimal::from_ratio(swap_computation.spread_amount, oracle_return_amount);
+    ensure!(
+        deviation <= *max_deviation,
+        ContractError::OraclePriceDeviationExceeded {
+            deviation,
+            max_deviation: *max_deviation,
+        }
+    );
+
+    Ok(())
+}
+
+/// Gets the exchange rate of the asset at the given index. Assets without a rate are pegged 1:1.
+pub(crate) fn get_asset_rate(rates: &[Decimal256], index: usize) -> Decimal256 {
+    rates.get(index).copied().unwrap_or_else(Decimal256::one)
+}
+
+/// Gets the exchange rates of the assets of a pool, in the same order as the asset denoms. Only
+/// stable swap pools have rates other than 1, fetching them from the rate provider contracts if
+/// needed. In a metapool, the LP token of the base pool is also worth the base pool's virtual
+/// price.
+pub fn get_asset_rates(
+    deps: &Deps,
+    env: &Env,
+    pool_type: &PoolType,
+    asset_denoms: &[String],
+) -> Result<Vec<Decimal256>, ContractError> {
+    let PoolType::StableSwap { rate_providers, .. } = pool_type else {
+        return Ok(vec![Decimal256::one(); asset_denoms.len()]);
+    };
+
+    if let Some(rate_providers) = rate_providers {
+        ensure!(
+            rate_providers.len() == asset_denoms.len(),
+            ContractError::InvalidRateProviders
+        );
     }
+
+    asset_denoms
+        .iter()
+        .enumerate()
+        .map(|(index, denom)| {
+            let rate = match rate_providers
+                .as_ref()
+                .and_then(|rate_providers| rate_providers.get(index))
+            {
+                Some(RateProvider::Fixed { rate }) => *rate,
+                Some(RateProvider::Contract { contract_addr }) => {
+                    let response: ExchangeRateResponse = deps.querier.query_wasm_smart(
+                        contract_addr,
+                        &RateProviderQueryMsg::ExchangeRate {
+                            denom: denom.clone(),
+                        },
+                    )?;
+                    response.rate
+                }
+                None => Decimal::one(),
+            };
+
+            ensure!(
+                !rate.is_zero(),
+                ContractError::InvalidExchangeRate {
+                    denom: denom.clone()
+                }
+            );
+
+            match get_base_pool(deps, denom)? {
+                Some(base_pool) if matches!(base_pool.pool_type, PoolType::StableSwap { .. }) => {
+                    Ok(Decimal256::from(rate)
+                        .checked_mul(get_virtual_price(deps, env, &base_pool)?)?)
+                }
+                _ => Ok(rate.into()),
+            }
+        })
+        .collect()
+}
+
+/// Gets the pool whose LP token is the given denom, if any.
+pub fn get_base_pool(deps: &Deps, denom: &str) -> Result<Option<PoolInfo>, ContractError> {
+    Ok(POOLS
+        .idx
+        .lp_asset
+        .item(deps.storage, denom.to_string())?
+        .map(|(_, pool_info)| pool_info))
+}
+
+/// Gets the base pool of a metapool, i.e. the stable swap pool whose LP token is the second asset
+/// of the given stable swap pool.
+pub fn get_metapool_base_pool(
+    deps: &Deps,
+    pool_info: &PoolInfo,
+) -> Result<PoolInfo, ContractError> {
+    ensure!(
+        matches!(pool_info.pool_type, PoolType::StableSwap { .. })
+            && pool_info.asset_denoms.len() == 2,
+        ContractError::NotAMetapool
+    );
+
+    get_base_pool(deps, &pool_info.asset_denoms[1])?
+        .filter(|base_pool| matches!(base_pool.pool_type, PoolType::StableSwap { .. }))
+        .ok_or(ContractError::NotAMetapool)
+}
+
+/// Gets the virtual price of a stable swap pool, i.e. the value of its invariant per LP token.
+/// The invariant is computed with the current amplification of the pool, i.e. with its ongoing
+/// amp ramp applied, as when swapping on it.
+pub fn get_virtual_price(
+    deps: &Deps,
+    env: &Env,
+    pool_info: &PoolInfo,
+) -> Result<Decimal256, ContractError> {
+    let mut pool_info = pool_info.clone();
+    apply_amp_ramp(deps.storage, env, &mut pool_info)?;
+
+    let PoolType::StableSwap { amp, .. } = &pool_info.pool_type else {
+        return Err(ContractError::NotAStableSwapPool);
+    };
+
+    let total_share = get_total_share(deps, pool_info.lp_denom.clone())?;
+    ensure!(!total_share.is_zero(), ContractError::PoolHasNoAssets);
+
+    let rates = get_asset_rates(deps, env, &pool_info.pool_type, &pool_info.asset_denoms)?;
+    let d = compute_d(
+        amp,
+        &scale_by_rates(&pool_info.assets, &pool_info.asset_denoms, &rates)?,
+    )
+    .ok_or(ContractError::StableInvariantError)?;
+
+    Ok(Decimal256::checked_from_ratio(
+        Uint256::try_from(d)?,
+        total_share,
+    )?)
+}
+
+/// Scales the given coins by the exchange rates of the matching assets in the pool.
+pub fn scale_by_rates(
+    coins: &[Coin],
+    asset_denoms: &[String],
+    rates: &[Decimal256],
+) -> Result<Vec<Coin>, ContractError> {
+    coins
+        .iter()
+        .map(|c| {
+            let index = asset_denoms
+                .iter()
+                .position(|denom| denom == &c.denom)
+                .ok_or(ContractError::AssetMismatch)?;
+
+            let amount: Uint256 = Uint256::from(c.amount).mul_floor(get_asset_rate(rates, index));
+
+            Ok(coin(Uint128::try_from(amount)?.u128(), &c.denom))
+        })
+        .collect()
+}
+
+/// The maximum ratio of the pool balance that can be swapped in or out of a weighted pool in a
+/// single swap. Bounds the error of the fractional power used by the weighted math. Both sides are
+/// checked, as with unequal weights a swap within the limit on one side can exceed it on the other.
+fn max_weighted_swap_ratio() -> Decimal256 {
+    Decimal256::percent(30)
+}
+
+/// Gets the weights of the offer and ask assets of a weighted pool.
+fn get_weights_in_pool(
+    weights: &[Decimal],
+    offer_index: usize,
+    ask_index: usize,
+) -> Result<(Decimal256, Decimal256), ContractError> {
+    let offer_weight = weights
+        .get(offer_index)
+        .ok_or(ContractError::InvalidPoolWeights)?;
+    let ask_weight = weights
+        .get(ask_index)
+        .ok_or(ContractError::InvalidPoolWeights)?;
+
+    Ok(((*offer_weight).into(), (*ask_weight).into()))
+}
+
+/// Gets the spot exchange rate, i.e. ask asset per offer asset, of a weighted pool.
+fn get_weighted_exchange_rate(
+    offer_pool: Uint256,
+    ask_pool: Uint256,
+    offer_weight: Decimal256,
+    ask_weight: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    Decimal256::checked_from_ratio(ask_pool, offer_pool)
+        .map_err(|_| ContractError::PoolHasNoAssets)?
+        .checked_multiply_ratio(offer_weight, ask_weight)
+}
+
+/// Normalizes an amount of a Solidly stable pool asset to [SOLIDLY_PRECISION] decimals.
+fn to_solidly_balance(amount: Uint256, precision: u8) -> Result<Uint256, ContractError> {
+    Ok(Decimal256::decimal_with_precision(amount, precision)?.atomics())
+}
+
+/// Converts an amount normalized with [to_solidly_balance] back into the asset precision,
+/// rounding up if `round_up` is set, or down otherwise.
+fn from_solidly_balance(
+    amount: Uint256,
+    precision: u8,
+    round_up: bool,
+) -> Result<Uint256, ContractError> {
+    let factor = Uint256::from(10u128).checked_pow(u32::from(SOLIDLY_PRECISION - precision))?;
+    let value = amount.checked_div(factor)?;
+
+    if round_up && !amount.checked_rem(factor)?.is_zero() {
+        Ok(value.checked_add(Uint256::one())?)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Computes the invariant of a Solidly stable pool, i.e. `x³y + xy³ = k`, for the given balances
+/// normalized to [SOLIDLY_PRECISION] decimals.
+pub fn compute_solidly_k(x: Uint256, y: Uint256) -> Result<Uint512, ContractError> {
+    let unit = Uint512::from(10u128.pow(u32::from(SOLIDLY_PRECISION)));
+    let (x, y) = (Uint512::from(x), Uint512::from(y));
+
+    let xy = x.checked_mul(y)?.checked_div(unit)?;
+    let x2_plus_y2 = x
+        .checked_mul(x)?
+        .checked_div(unit)?
+        .checked_add(y.checked_mul(y)?.checked_div(unit)?)?;
+
+    Ok(xy.checked_mul(x2_plus_y2)?.checked_div(unit)?)
+}
+
+/// Computes the derivative of the Solidly stable invariant with respect to `y`, i.e.
+/// `3x0y² + x0³`.
+fn compute_solidly_dk_dy(x0: Uint512, y: Uint512) -> Result<Uint512, ContractError> {
+    let unit = Uint512::from(10u128.pow(u32::from(SOLIDLY_PRECISION)));
+
+    let three_x0_y2 = Uint512::from(3u128)
+        .checked_mul(x0)?
+        .checked_mul(y.checked_mul(y)?.checked_div(unit)?)?
+        .checked_div(unit)?;
+    let x0_3 = x0
+        .checked_mul(x0)?
+        .checked_div(unit)?
+        .checked_mul(x0)?
+        .checked_div(unit)?;
+
+    Ok(three_x0_y2.checked_add(x0_3)?)
+}
+
+/// Solves the Solidly stable invariant for the balance of one asset given the balance `x0` of
+/// the other asset and the invariant `k`, using Newton's method starting at `y`. The result is
+/// the smallest balance that keeps the invariant, so it rounds in favor of the pool. Since the
+/// invariant is symmetric, it is used to solve for either asset.
+pub fn compute_solidly_y(x0: Uint256, k: Uint512, y: Uint256) -> Result<Uint256, ContractError> {
+    let unit = Uint512::from(10u128.pow(u32::from(SOLIDLY_PRECISION)));
+    let x0_512 = Uint512::from(x0);
+    let mut y = Uint512::from(y);
+
+    for _ in 0..SOLIDLY_NEWTON_ITERATIONS {
+        let current_k = compute_solidly_k(x0, Uint256::try_from(y)?)?;
+        let dk_dy = compute_solidly_dk_dy(x0_512, y)?;
+
+        if current_k < k {
+            let mut dy = k
+                .checked_sub(current_k)?
+                .checked_mul(unit)?
+                .checked_div(dk_dy)?;
+            if dy.is_zero() {
+                let next_y = y.checked_add(Uint512::one())?;
+                if compute_solidly_k(x0, Uint256::try_from(next_y)?)? > k {
+                    return Ok(Uint256::try_from(next_y)?);
+                }
+                dy = Uint512::one();
+            }
+            y = y.checked_add(dy)?;
+        } else {
+            let mut dy = current_k
+                .checked_sub(k)?
+                .checked_mul(unit)?
+                .checked_div(dk_dy)?;
+            if dy.is_zero() {
+                if current_k == k
+                    || compute_solidly_k(x0, Uint256::try_from(y.checked_sub(Uint512::one())?)?)?
+                        < k
+                {
+                    return Ok(Uint256::try_from(y)?);
+                }
+                dy = Uint512::one();
+            }
+            y = y.checked_sub(dy)?;
+        }
+    }
+
+    Err(ContractError::ConvergeError)
+}
+
+/// Gets the spot exchange rate, i.e. ask asset per offer asset, of a Solidly stable pool, i.e.
+/// `(3x²y + y³) / (x³ + 3xy²)` for the normalized balances, expressed in the asset precisions.
+fn get_solidly_exchange_rate(
+    offer_pool: Uint256,
+    ask_pool: Uint256,
+    offer_precision: u8,
+    ask_precision: u8,
+) -> Result<Decimal256, ContractError> {
+    // the derivatives of the invariant with respect to each balance
+    let offer_derivative =
+        compute_solidly_dk_dy(Uint512::from(ask_pool), Uint512::from(offer_pool))?;
+    let ask_derivative = compute_solidly_dk_dy(Uint512::from(offer_pool), Uint512::from(ask_pool))?;
+
+    let ask_factor = Uint512::from(10u128.pow(u32::from(ask_precision)));
+    let offer_factor = Uint512::from(10u128.pow(u32::from(offer_precision)));
+
+    Decimal256::checked_from_ratio(
+        Uint256::try_from(offer_derivative.checked_mul(ask_factor)?)?,
+        Uint256::try_from(ask_derivative.checked_mul(offer_factor)?)?,
+    )
+    .map_err(|_| ContractError::PoolHasNoAssets)
 }
 
 /// Computes the pool fees for a given (return) amount
-fn compute_fees(pool_fees: PoolFee, amount: Uint256) -> Result<FeesComputation, ContractError> {
+pub(crate) fn compute_fees(
+    pool_fees: PoolFee,
+    amount: Uint256,
+) -> Result<FeesComputation, ContractError> {
     let swap_fee_amount: Uint256 = pool_fees.swap_fee.compute(amount)?;
     let protocol_fee_amount: Uint256 = pool_fees.protocol_fee.compute(amount)?;
     let burn_fee_amount: Uint256 = pool_fees.burn_fee.compute(amount)?;
@@ -255,7 +925,7 @@ fn compute_fees(pool_fees: PoolFee, amount: Uint256) -> Result<FeesComputation,
 }
 
 /// Builds the swap computation struct, subtracting the fees from the return amount.
-fn get_swap_computation(
+pub(crate) fn get_swap_computation(
     return_amount: Uint256,
     spread_amount: Uint256,
     fees_computation: FeesComputation,
@@ -322,22 +992,29 @@ impl SwapComputation {
             protocol_fee_amount: self.protocol_fee_amount,
             burn_fee_amount: self.burn_fee_amount,
             extra_fees_amount: self.extra_fees_amount,
+            oracle_price: None,
         }
     }
 }
 
+/// Computes the amount of the offer asset needed to get the given ask amount out of a constant
+/// product, weighted or Solidly stable pool.
+#[allow(clippy::too_many_arguments)]
 pub fn compute_offer_amount(
     offer_asset_in_pool: Uint128,
     ask_asset_in_pool: Uint128,
     ask_amount: Uint128,
     pool_fees: PoolFee,
-) -> StdResult<OfferAmountComputation> {
+    pool_type: &PoolType,
+    offer_precision: u8,
+    ask_precision: u8,
+    offer_index: usize,
+    ask_index: usize,
+) -> Result<OfferAmountComputation, ContractError> {
     let offer_asset_in_pool: Uint256 = offer_asset_in_pool.into();
     let ask_asset_in_pool: Uint256 = ask_asset_in_pool.into();
     let ask_amount: Uint256 = ask_amount.into();
 
-    // ask => offer
-    // offer_amount = cp / (ask_pool - ask_amount / (1 - fees)) - offer_pool
     let mut fees = pool_fees
         .swap_fee
         .to_decimal_256()
@@ -351,26 +1028,109 @@ pub fn compute_offer_amount(
     let one_minus_commission = Decimal256::one() - fees;
     let inv_one_minus_commission = Decimal256::one() / one_minus_commission;
 
-    let cp: Uint256 = offer_asset_in_pool * ask_asset_in_pool;
-    let offer_amount: Uint256 = Uint256::one()
-        .multiply_ratio(
-            cp,
-            ask_asset_in_pool.checked_sub(
-                Decimal256::from_ratio(ask_amount, Uint256::one())
-                    .checked_mul(inv_one_minus_commission)?
-                    .to_uint_floor(),
-            )?,
-        )
-        .checked_sub(offer_asset_in_pool)?;
-
     let before_commission_deduction: Uint256 = Decimal256::from_ratio(ask_amount, Uint256::one())
         .checked_mul(inv_one_minus_commission)?
         .to_uint_floor();
+
+    let (offer_amount, exchange_rate) = match pool_type {
+        PoolType::Weighted { weights } => {
+            let (offer_weight, ask_weight) = get_weights_in_pool(weights, offer_index, ask_index)?;
+
+            // ask => offer
+            // offer_amount = offer_pool * ((ask_pool / (ask_pool - ask_amount / (1 - fees))) ^ (ask_weight / offer_weight) - 1)
+            ensure!(
+                before_commission_deduction
+                    <= ask_asset_in_pool.mul_floor(max_weighted_swap_ratio()),
+                ContractError::MaxWeightedSwapRatioExceeded
+            );
+
+            let base = Decimal256::checked_from_ratio(
+                ask_asset_in_pool,
+                ask_asset_in_pool.checked_sub(before_commission_deduction)?,
+            )?;
+            let exponent = ask_weight.checked_div(offer_weight)?;
+            let power = base.checked_pow_up(exponent)?;
+
+            let offer_amount = power
+                .checked_sub(Decimal256::one())?
+                .checked_mul(Decimal256::from_ratio(offer_asset_in_pool, Uint256::one()))?
+                .to_uint_ceil();
+
+            ensure!(
+                offer_amount <= offer_asset_in_pool.mul_floor(max_weighted_swap_ratio()),
+                ContractError::MaxWeightedSwapRatioExceeded
+            );
+
+            let exchange_rate = get_weighted_exchange_rate(
+                offer_asset_in_pool,
+                ask_asset_in_pool,
+                offer_weight,
+                ask_weight,
+            )?;
+
+            (offer_amount, exchange_rate)
+        }
+        PoolType::SolidlyStable => {
+            // ask => offer
+            // offer_amount = x - offer_pool, where x³y + xy³ = k for y = ask_pool - ask_amount / (1 - fees)
+            let offer_pool_normalized = to_solidly_balance(offer_asset_in_pool, offer_precision)?;
+            let ask_pool_normalized = to_solidly_balance(ask_asset_in_pool, ask_precision)?;
+            let ask_amount_normalized =
+                to_solidly_balance(before_commission_deduction, ask_precision)?;
+
+            let k = compute_solidly_k(offer_pool_normalized, ask_pool_normalized)?;
+            // the invariant is symmetric, so the offer balance is solved like the ask balance
+            let new_offer_pool = compute_solidly_y(
+                ask_pool_normalized.checked_sub(ask_amount_normalized)?,
+                k,
+                offer_pool_normalized,
+            )?;
+
+            let offer_amount = from_solidly_balance(
+                new_offer_pool.checked_sub(offer_pool_normalized)?,
+                offer_precision,
+                true,
+            )?;
+
+            let exchange_rate = get_solidly_exchange_rate(
+                offer_pool_normalized,
+                ask_pool_normalized,
+                offer_precision,
+                ask_precision,
+            )?;
+
+            (offer_amount, exchange_rate)
+        }
+        // the weights of liquidity bootstrapping pools depend on the time, the offer amount must
+        // be computed with the pool type from get_effective_pool_type
+        PoolType::LiquidityBootstrapping { .. } => {
+            return Err(ContractError::UnresolvedPoolWeights);
+        }
+        // the curve of oracle pegged pools depends on the oracle price, the offer amount must be
+        // computed with the pool type from get_effective_pool_type
+        PoolType::OraclePegged { .. } => {
+            return Err(ContractError::UnresolvedOraclePrice);
+        }
+        _ => {
+            // ask => offer
+            // offer_amount = cp / (ask_pool - ask_amount / (1 - fees)) - offer_pool
+            let cp: Uint256 = offer_asset_in_pool * ask_asset_in_pool;
+            let offer_amount: Uint256 = Uint256::one()
+                .multiply_ratio(
+                    cp,
+                    ask_asset_in_pool.checked_sub(before_commission_deduction)?,
+                )
+                .checked_sub(offer_asset_in_pool)?;
+
+            (
+                offer_amount,
+                Decimal256::from_ratio(ask_asset_in_pool, offer_asset_in_pool),
+            )
+        }
+    };
+
     let before_spread_deduction: Uint256 = Decimal256::from_ratio(offer_amount, Uint256::one())
-        .checked_mul(Decimal256::from_ratio(
-            ask_asset_in_pool,
-            offer_asset_in_pool,
-        ))?
+        .checked_mul(exchange_rate)?
         .to_uint_floor();
 
     let spread_amount = before_spread_deduction.saturating_sub(before_commission_deduction);
@@ -400,6 +1160,72 @@ pub struct OfferAmountComputation {
     pub burn_fee_amount: Uint128,
 }
 
+/// Computes the amount of the over-supplied asset of a deposit into a constant product pool that
+/// has to be swapped into the other asset, so that the deposit matches the ratio of the pool after
+/// the swap and can be provided as liquidity in full.
+///
+/// Swapping `s` of the offer asset returns `o = k * B * s / (A + s)`, with `k` being one minus the
+/// fees charged on the output. Solving `(a - s) / (A + s) = (b + o) / (B - o)` for `s` gives the
+/// quadratic equation below, with the coefficients divided by `B`:
+///
+/// ```text
+/// (1 + b/B) * s**2 + (2*b*A/B + A*(1 + k) - a*(1 - k)) * s - A * (a - b*A/B) = 0
+/// ```
+///
+/// where `A` and `B` are the pool balances and `a` and `b` the deposits of the offer and ask
+/// assets respectively.
+pub fn compute_excess_deposit_swap_amount(
+    offer_pool: Uint128,
+    ask_pool: Uint128,
+    offer_deposit: Uint128,
+    ask_deposit: Uint128,
+    pool_fees: &PoolFee,
+) -> Result<Uint128, ContractError> {
+    ensure!(
+        !offer_pool.is_zero() && !ask_pool.is_zero(),
+        ContractError::PoolHasNoAssets
+    );
+
+    let fee_share = [
+        &pool_fees.protocol_fee,
+        &pool_fees.swap_fee,
+        &pool_fees.burn_fee,
+    ]
+    .into_iter()
+    .chain(pool_fees.extra_fees.iter())
+    .try_fold(Decimal256::zero(), |acc, fee| {
+        acc.checked_add(fee.to_decimal_256())
+    })?;
+    let k = Decimal256::one().checked_sub(fee_share)?;
+
+    let offer_pool = Decimal256::from_ratio(offer_pool, Uint256::one());
+    let offer_deposit = Decimal256::from_ratio(offer_deposit, Uint256::one());
+    let ask_ratio = Decimal256::from_ratio(ask_deposit, ask_pool);
+    // the ask deposit valued in the offer asset at the pool price
+    let ask_deposit_value = offer_pool.checked_mul(ask_ratio)?;
+
+    let a = Decimal256::one().checked_add(ask_ratio)?;
+    let b_pos = ask_deposit_value
+        .checked_mul(Decimal256::percent(200))?
+        .checked_add(offer_pool.checked_mul(Decimal256::one().checked_add(k)?)?)?;
+    let b_neg = offer_deposit.checked_mul(Decimal256::one().checked_sub(k)?)?;
+    let c = offer_pool.checked_mul(offer_deposit.saturating_sub(ask_deposit_value))?;
+
+    let discriminant = b_pos
+        .abs_diff(b_neg)
+        .checked_pow(2)?
+        .checked_add(a.checked_mul(c)?.checked_mul(Decimal256::percent(400))?)?;
+
+    let swap_amount = discriminant
+        .sqrt()
+        .checked_add(b_neg)?
+        .saturating_sub(b_pos)
+        .checked_div(a.checked_mul(Decimal256::percent(200))?)?
+        .min(offer_deposit);
+
+    Ok(Uint128::try_from(swap_amount.to_uint_floor())?)
+}
+
 pub fn assert_slippage_tolerance(
     slippage_tolerance: &Option<Decimal>,
     deposits: &[Coin],
@@ -414,52 +1240,96 @@ pub fn assert_slippage_tolerance(
             return Err(StdError::generic_err("slippage_tolerance cannot bigger than 1").into());
         }
 
-        let one_minus_slippage_tolerance = Decimal256::one() - slippage_tolerance;
-        let deposits: Vec<Uint256> = deposits.iter().map(|coin| coin.amount.into()).collect();
-        let pools: Vec<Uint256> = pools.iter().map(|coin| coin.amount.into()).collect();
-
-        // Ensure each prices are not dropped as much as slippage tolerance rate
-        match pool_type {
-            PoolType::StableSwap { .. } => {
-                let pools_total: Uint256 = pools
-                    .into_iter()
-                    .fold(Uint256::zero(), |acc, x| acc.checked_add(x).unwrap());
-                let deposits_total: Uint256 = deposits
-                    .into_iter()
-                    .fold(Uint256::zero(), |acc, x| acc.checked_add(x).unwrap());
-
-                let pool_ratio = Decimal256::from_ratio(pools_total, pool_token_supply);
-                let deposit_ratio = Decimal256::from_ratio(deposits_total, amount);
-
-                // the slippage tolerance for the stableswap can't use a simple ratio for calculating
-                // slippage when adding liquidity. Due to the math behind the stableswap, the amp factor
-                // needs to be in as well, much like when swaps are done
-                if pool_ratio * one_minus_slippage_tolerance > deposit_ratio {
-                    return Err(ContractError::MaxSlippageAssertion);
-                }
-            }
-            PoolType::ConstantProduct => {
-                if deposits.len() != 2 || pools.len() != 2 {
-                    return Err(ContractError::InvalidPoolAssetsLength {
-                        expected: 2,
-                        actual: deposits.len(),
-                    });
-                }
-                if Decimal256::from_ratio(deposits[0], deposits[1]) * one_minus_slippage_tolerance
-                    > Decimal256::from_ratio(pools[0], pools[1])
-                    || Decimal256::from_ratio(deposits[1], deposits[0])
-                        * one_minus_slippage_tolerance
-                        > Decimal256::from_ratio(pools[1], pools[0])
-                {
-                    return Err(ContractError::MaxSlippageAssertion);
-                }
-            }
+        let slippage =
+            compute_deposit_slippage(deposits, pools, &pool_type, amount, pool_token_supply)?;
+
+        if slippage > slippage_tolerance {
+            return Err(ContractError::MaxSlippageAssertion);
         }
     }
 
     Ok(())
 }
 
+/// Computes the slippage of a deposit of `deposits` minting `amount` LP tokens, given the pool
+/// balances before the deposit and the LP token supply. On stable swap pools it is the shortfall of
+/// the LP tokens minted per deposited asset against the pool's, and on the other pools how far the
+/// ratio of the deposited assets deviates from the pool's, which mints the LP tokens for the
+/// smallest proportional share. Deposits into empty pools have no slippage.
+pub fn compute_deposit_slippage(
+    deposits: &[Coin],
+    pools: &[Coin],
+    pool_type: &PoolType,
+    amount: Uint128,
+    pool_token_supply: Uint128,
+) -> Result<Decimal256, ContractError> {
+    if pool_token_supply.is_zero() {
+        return Ok(Decimal256::zero());
+    }
+
+    let deposits: Vec<Uint256> = deposits.iter().map(|coin| coin.amount.into()).collect();
+    let pools: Vec<Uint256> = pools.iter().map(|coin| coin.amount.into()).collect();
+
+    // the relative shortfall of `actual` against `expected`, if any
+    let shortfall = |actual: Uint256, expected: Uint256| -> Decimal256 {
+        Decimal256::checked_from_ratio(actual, expected)
+            .map(|ratio| Decimal256::one().saturating_sub(ratio))
+            .unwrap_or_else(|_| Decimal256::zero())
+    };
+
+    match pool_type {
+        PoolType::StableSwap { .. } | PoolType::OraclePegged { .. } => {
+            let pools_total = pools
+                .into_iter()
+                .try_fold(Uint256::zero(), |acc, x| acc.checked_add(x))?;
+            let deposits_total = deposits
+                .into_iter()
+                .try_fold(Uint256::zero(), |acc, x| acc.checked_add(x))?;
+
+            // the slippage for the stableswap can't use a simple ratio of the deposited assets.
+            // Due to the math behind the stableswap, the amp factor needs to be in as well, so
+            // the LP tokens minted are compared instead, i.e.
+            // (amount / deposits_total) against (pool_token_supply / pools_total)
+            Ok(shortfall(
+                Uint256::from(amount).checked_mul(pools_total)?,
+                Uint256::from(pool_token_supply).checked_mul(deposits_total)?,
+            ))
+        }
+        // constant product pools with more than 2 assets are checked like weighted pools
+        PoolType::ConstantProduct
+        | PoolType::SolidlyStable
+        | PoolType::Weighted { .. }
+        | PoolType::LiquidityBootstrapping { .. } => {
+            // the deposits are expected to be in the same order as the pool assets
+            if deposits.len() != pools.len() {
+                return Err(ContractError::InvalidPoolAssetsLength {
+                    expected: pools.len(),
+                    actual: deposits.len(),
+                });
+            }
+
+            // compare the ratio of each asset against the first one, in both directions
+            let mut slippage = Decimal256::zero();
+            for i in 1..deposits.len() {
+                let first_ratio = pools[0].checked_mul(deposits[i])?;
+                let other_ratio = pools[i].checked_mul(deposits[0])?;
+
+                slippage = slippage
+                    .max(shortfall(first_ratio, other_ratio))
+                    .max(shortfall(other_ratio, first_ratio));
+            }
+
+            Ok(slippage)
+        }
+        // liquidity is provided through positions on concentrated liquidity pools
+        PoolType::ConcentratedLiquidity { .. } => {
+            Err(ContractError::UnsupportedConcentratedLiquidityOperation(
+                "slippage tolerance".to_string(),
+            ))
+        }
+    }
+}
+
 /// This function compares the address of the message sender with the contract admin
 /// address. This provides a convenient way to verify if the sender
 /// is the admin in a single line.
@@ -512,6 +1382,85 @@ pub fn validate_pool_identifier(identifier: &str) -> Result<(), ContractError> {
     Ok(())
 }
 
+/// Validates the parameters of the given pool type for a pool with `n_assets` assets.
+pub fn validate_pool_type(pool_type: &PoolType, n_assets: usize) -> Result<(), ContractError> {
+    match pool_type {
+        PoolType::Weighted { weights } => {
+            validate_pool_weights(weights, n_assets)?;
+        }
+        PoolType::LiquidityBootstrapping {
+            start_weights,
+            end_weights,
+            start_time,
+            end_time,
+        } => {
+            validate_pool_weights(start_weights, n_assets)?;
+            validate_pool_weights(end_weights, n_assets)?;
+            ensure!(start_time < end_time, ContractError::InvalidWeightSchedule);
+        }
+        PoolType::ConcentratedLiquidity {
+            tick_spacing,
+            initial_price,
+        } => {
+            ensure!(
+                n_assets == 2 && *tick_spacing > 0 && *tick_spacing <= MAX_TICK as u64,
+                ContractError::InvalidConcentratedLiquidityPool
+            );
+            ensure_sqrt_price_in_range(Decimal256::from(*initial_price).sqrt())?;
+        }
+        PoolType::StableSwap {
+            rate_providers: Some(rate_providers),
+            ..
+        } => {
+            ensure!(
+                rate_providers.len() == n_assets
+                    && rate_providers
+                        .iter()
+                        .all(|rate_provider| match rate_provider {
+                            RateProvider::Fixed { rate } => !rate.is_zero(),
+                            RateProvider::Contract { .. } => true,
+                        }),
+                ContractError::InvalidRateProviders
+            );
+        }
+        PoolType::SolidlyStable => {
+            ensure!(n_assets == 2, ContractError::InvalidSolidlyStablePool);
+        }
+        PoolType::OraclePegged {
+            max_staleness,
+            max_deviation,
+            ..
+        } => {
+            ensure!(
+                n_assets == 2
+                    && *max_staleness > 0
+                    && !max_deviation.is_zero()
+                    && *max_deviation <= Decimal::one(),
+                ContractError::InvalidOraclePeggedPool
+            );
+        }
+        PoolType::ConstantProduct | PoolType::StableSwap { .. } => {}
+    }
+
+    Ok(())
+}
+
+/// Validates the weights of a weighted pool with `n_assets` assets, i.e. there is one non-zero
+/// weight per asset and they add up to 1.
+fn validate_pool_weights(weights: &[Decimal], n_assets: usize) -> Result<(), ContractError> {
+    ensure!(
+        weights.len() == n_assets
+            && weights.iter().all(|weight| !weight.is_zero())
+            && weights
+                .iter()
+                .try_fold(Decimal::zero(), |acc, weight| acc.checked_add(*weight))?
+                == Decimal::one(),
+        ContractError::InvalidPoolWeights
+    );
+
+    Ok(())
+}
+
 /// Aggregates the fees from a simulation response that go out of the contract, i.e. protocol fee and burn fee.
 /// Doesn't know about the denom, just the amount.
 pub fn aggregate_outgoing_fees(
@@ -752,63 +1701,349 @@ pub fn compute_d(amp_factor: &u64, deposits: &[Coin]) -> Option<Uint512> {
     }
 }
 
-#[allow(clippy::unwrap_used)]
-fn compute_next_d(
+#[allow(clippy::unwrap_used)]
+fn compute_next_d(
+    amp_factor: &u64,
+    d_init: Uint512,
+    d_prod: Uint512,
+    sum_x: Uint128,
+    n_coins: Uint128,
+) -> Option<Uint512> {
+    let ann = amp_factor.checked_mul(n_coins.u128() as u64)?;
+    let leverage = Uint512::from(sum_x).checked_mul(ann.into()).unwrap();
+    // d = (ann * sum_x + d_prod * n_coins) * d / ((ann - 1) * d + (n_coins + 1) * d_prod)
+    let numerator = d_init
+        .checked_mul(
+            d_prod
+                .checked_mul(n_coins.into())
+                .unwrap()
+                .checked_add(leverage)
+                .unwrap(),
+        )
+        .unwrap();
+    let denominator = d_init
+        .checked_mul(ann.checked_sub(1)?.into())
+        .unwrap()
+        .checked_add(
+            d_prod
+                .checked_mul((n_coins.checked_add(1u128.into()).unwrap()).into())
+                .unwrap(),
+        )
+        .unwrap();
+    Some(numerator.checked_div(denominator).unwrap())
+}
+
+/// Computes the amount of lp tokens to mint after a deposit for a stableswap pool.
+/// Assumes the deposits have already been credited to the pool_assets.
+#[allow(clippy::unwrap_used, clippy::too_many_arguments)]
+pub fn compute_lp_mint_amount_for_stableswap_deposit(
+    amp_factor: &u64,
+    old_pool_assets: &[Coin],
+    new_pool_assets: &[Coin],
+    pool_lp_token_total_supply: Uint128,
+) -> Result<Option<Uint128>, ContractError> {
+    // Initial invariant
+    let d_0 = compute_d(amp_factor, old_pool_assets).ok_or(ContractError::StableInvariantError)?;
+
+    // Invariant after change, i.e. after deposit
+    // notice that new_pool_assets already added the new deposits to the pool
+    let d_1 = compute_d(amp_factor, new_pool_assets).ok_or(ContractError::StableInvariantError)?;
+
+    // If the invariant didn't change, return None
+    if d_1 <= d_0 {
+        Ok(None)
+    } else {
+        let amount = Uint512::from(pool_lp_token_total_supply)
+            .checked_mul(d_1.checked_sub(d_0)?)?
+            .checked_div(d_0)?;
+        Ok(Some(Uint128::try_from(amount)?))
+    }
+}
+
+/// Computes the balance of the asset at `index` that keeps the invariant of a stable swap pool at
+/// `d`, given the balances of the other assets, i.e. Curve's `get_y_D`.
+///
+/// Solve for `y`:
+///
+/// ```text
+/// y**2 + y * (sum' - (A*n**n - 1) * D / (A * n**n)) = D ** (n + 1) / (n ** (2 * n) * prod' * A)
+/// y**2 + b*y = c
+/// ```
+#[allow(clippy::many_single_char_names)]
+pub fn compute_y_d(
+    amp_factor: &u64,
+    balances: &[Uint128],
+    index: usize,
+    d: Uint512,
+) -> Result<Uint512, ContractError> {
+    let n_coins = Uint512::from(balances.len() as u128);
+    let ann = Uint512::from(
+        amp_factor
+            .checked_mul(balances.len() as u64)
+            .ok_or(ContractError::StableInvariantError)?,
+    );
+
+    let mut c = d;
+    let mut sum = Uint512::zero();
+    for (_, balance) in balances.iter().enumerate().filter(|(i, _)| *i != index) {
+        sum = sum.checked_add((*balance).into())?;
+        c = c
+            .checked_mul(d)?
+            .checked_div(Uint512::from(*balance).checked_mul(n_coins)?)?;
+    }
+    c = c.checked_mul(d)?.checked_div(n_coins.checked_mul(ann)?)?;
+    let b = sum.checked_add(d.checked_div(ann)?)?;
+
+    // Solve for y by approximating: y**2 + b*y = c
+    let mut y = d;
+    for _ in 0..NEWTON_ITERATIONS {
+        let y_prev = y;
+        // y = (y * y + c) / (2 * y + b - d);
+        y = y
+            .checked_mul(y)?
+            .checked_add(c)?
+            .checked_div(y.checked_add(y)?.checked_add(b)?.checked_sub(d)?)?;
+
+        if y.abs_diff(y_prev) <= Uint512::one() {
+            return Ok(y);
+        }
+    }
+
+    Err(ContractError::ConvergeError)
+}
+
+/// Gets the balances of a stable swap pool normalized to the same precision, i.e. the largest
+/// decimals among the assets, and scaled by the exchange rates of the assets.
+pub fn get_stableswap_balances(
+    pool_info: &PoolInfo,
+    rates: &[Decimal256],
+) -> Result<Vec<Uint128>, ContractError> {
+    let max_decimals = pool_info.asset_decimals.iter().max().copied().unwrap_or(0);
+
+    pool_info
+        .assets
+        .iter()
+        .zip(pool_info.asset_decimals.iter())
+        .enumerate()
+        .map(|(index, (asset, decimals))| {
+            let factor = Uint256::from(10u128.pow(u32::from(max_decimals - decimals)));
+            let balance = Uint256::from(asset.amount)
+                .checked_mul(factor)?
+                .mul_floor(get_asset_rate(rates, index));
+
+            Ok(Uint128::try_from(balance)?)
+        })
+        .collect()
+}
+
+/// Converts an amount normalized with [get_stableswap_balances] back into the asset at `index`.
+pub fn denormalize_stableswap_amount(
+    pool_info: &PoolInfo,
+    rates: &[Decimal256],
+    index: usize,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    let max_decimals = pool_info.asset_decimals.iter().max().copied().unwrap_or(0);
+    let factor =
+        Uint256::from(10u128.pow(u32::from(max_decimals - pool_info.asset_decimals[index])));
+
+    let amount = Decimal256::from_ratio(amount, Uint256::one())
+        .checked_div(get_asset_rate(rates, index))?
+        .to_uint_floor()
+        .checked_div(factor)?;
+
+    Ok(Uint128::try_from(amount)?)
+}
+
+/// The imbalance fee charged when withdrawing liquidity from a stable swap pool in different
+/// proportions than the pool balances, derived from the swap fee as in Curve, i.e.
+/// `fee * n / (4 * (n - 1))`.
+pub fn get_stableswap_imbalance_fee(
+    pool_fees: &PoolFee,
+    n_coins: usize,
+) -> Result<Decimal256, ContractError> {
+    Ok(pool_fees
+        .swap_fee
+        .to_decimal_256()
+        .checked_mul(Decimal256::from_ratio(
+            n_coins as u128,
+            4u128 * (n_coins as u128 - 1),
+        ))?)
+}
+
+/// Computes the amount of the asset at `index` to return when burning `burn_amount` LP tokens out
+/// of `total_share` from a stable swap pool with the given (normalized) balances, i.e. Curve's
+/// `calc_withdraw_one_coin`. Returns the amount to withdraw and the imbalance fee charged, both
+/// in normalized units.
+pub fn compute_withdraw_one_coin(
+    amp_factor: &u64,
+    balances: &[Uint128],
+    index: usize,
+    burn_amount: Uint128,
+    total_share: Uint128,
+    imbalance_fee: Decimal256,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let to_coins = |balances: &[Uint128]| -> Vec<Coin> {
+        balances
+            .iter()
+            .enumerate()
+            .map(|(i, balance)| coin(balance.u128(), format!("asset{i}")))
+            .collect()
+    };
+
+    let d_0 =
+        compute_d(amp_factor, &to_coins(balances)).ok_or(ContractError::StableInvariantError)?;
+    let d_1 = d_0.checked_sub(
+        Uint512::from(burn_amount)
+            .checked_mul(d_0)?
+            .checked_div(total_share.into())?,
+    )?;
+    let new_y = compute_y_d(amp_factor, balances, index, d_1)?;
+
+    // charge the imbalance fee on the amounts each balance deviates from a proportional withdrawal
+    let reduced_balances = balances
+        .iter()
+        .enumerate()
+        .map(|(j, balance)| {
+            let expected_balance = Uint512::from(*balance).checked_mul(d_1)?.checked_div(d_0)?;
+            let dx_expected = if j == index {
+                expected_balance.saturating_sub(new_y)
+            } else {
+                Uint512::from(*balance).saturating_sub(expected_balance)
+            };
+            let fee = Uint128::try_from(Uint256::try_from(dx_expected)?.mul_floor(imbalance_fee))?;
+
+            Ok(balance.checked_sub(fee)?)
+        })
+        .collect::<Result<Vec<Uint128>, ContractError>>()?;
+
+    // withdraw one less to account for rounding errors
+    let dy = Uint512::from(reduced_balances[index])
+        .checked_sub(compute_y_d(amp_factor, &reduced_balances, index, d_1)?)?
+        .saturating_sub(Uint512::one());
+    let dy_0 = Uint512::from(balances[index]).checked_sub(new_y)?;
+
+    Ok((
+        Uint128::try_from(dy)?,
+        Uint128::try_from(dy_0.saturating_sub(dy))?,
+    ))
+}
+
+/// Computes the amount of LP tokens to burn when withdrawing from a stable swap pool so its
+/// (normalized) balances go from `old_balances` to `new_balances`, i.e. Curve's
+/// `remove_liquidity_imbalance`. The imbalance fee is charged on the amounts each balance deviates
+/// from a proportional withdrawal, and one extra LP token is burned to account for rounding errors.
+pub fn compute_withdraw_imbalance_burn_amount(
+    amp_factor: &u64,
+    old_balances: &[Uint128],
+    new_balances: &[Uint128],
+    total_share: Uint128,
+    imbalance_fee: Decimal256,
+) -> Result<Uint128, ContractError> {
+    let (d_0, d_2) = compute_imbalance_charged_invariants(
+        amp_factor,
+        old_balances,
+        new_balances,
+        imbalance_fee,
+    )?;
+
+    let burn_amount = d_0
+        .checked_sub(d_2)?
+        .checked_mul(total_share.into())?
+        .checked_div(d_0)?
+        .checked_add(Uint512::one())?;
+
+    Ok(Uint128::try_from(burn_amount)?)
+}
+
+/// Computes the amount of LP tokens to mint when depositing into a stable swap pool so its
+/// (normalized) balances go from `old_balances` to `new_balances`, i.e. Curve's `add_liquidity`.
+/// The imbalance fee is charged on the amounts each balance deviates from a proportional deposit.
+pub fn compute_deposit_imbalance_mint_amount(
+    amp_factor: &u64,
+    old_balances: &[Uint128],
+    new_balances: &[Uint128],
+    total_share: Uint128,
+    imbalance_fee: Decimal256,
+) -> Result<Uint128, ContractError> {
+    let (d_0, d_2) = compute_imbalance_charged_invariants(
+        amp_factor,
+        old_balances,
+        new_balances,
+        imbalance_fee,
+    )?;
+
+    let mint_amount = d_2
+        .checked_sub(d_0)?
+        .checked_mul(total_share.into())?
+        .checked_div(d_0)?;
+
+    Ok(Uint128::try_from(mint_amount)?)
+}
+
+/// Computes the invariant of a stable swap pool before an imbalanced deposit or withdrawal, and
+/// the invariant after it once the new balances are charged the imbalance fee on the amounts they
+/// deviate from a proportional change.
+fn compute_imbalance_charged_invariants(
     amp_factor: &u64,
-    d_init: Uint512,
-    d_prod: Uint512,
-    sum_x: Uint128,
-    n_coins: Uint128,
-) -> Option<Uint512> {
-    let ann = amp_factor.checked_mul(n_coins.u128() as u64)?;
-    let leverage = Uint512::from(sum_x).checked_mul(ann.into()).unwrap();
-    // d = (ann * sum_x + d_prod * n_coins) * d / ((ann - 1) * d + (n_coins + 1) * d_prod)
-    let numerator = d_init
-        .checked_mul(
-            d_prod
-                .checked_mul(n_coins.into())
-                .unwrap()
-                .checked_add(leverage)
-                .unwrap(),
-        )
-        .unwrap();
-    let denominator = d_init
-        .checked_mul(ann.checked_sub(1)?.into())
-        .unwrap()
-        .checked_add(
-            d_prod
-                .checked_mul((n_coins.checked_add(1u128.into()).unwrap()).into())
-                .unwrap(),
-        )
-        .unwrap();
-    Some(numerator.checked_div(denominator).unwrap())
+    old_balances: &[Uint128],
+    new_balances: &[Uint128],
+    imbalance_fee: Decimal256,
+) -> Result<(Uint512, Uint512), ContractError> {
+    let to_coins = |balances: &[Uint128]| -> Vec<Coin> {
+        balances
+            .iter()
+            .enumerate()
+            .map(|(i, balance)| coin(balance.u128(), format!("asset{i}")))
+            .collect()
+    };
+
+    let d_0 = compute_d(amp_factor, &to_coins(old_balances))
+        .ok_or(ContractError::StableInvariantError)?;
+    let d_1 = compute_d(amp_factor, &to_coins(new_balances))
+        .ok_or(ContractError::StableInvariantError)?;
+
+    let charged_balances = old_balances
+        .iter()
+        .zip(new_balances.iter())
+        .map(|(old_balance, new_balance)| {
+            let ideal_balance = Uint512::from(*old_balance)
+                .checked_mul(d_1)?
+                .checked_div(d_0)?;
+            let difference = ideal_balance.abs_diff(Uint512::from(*new_balance));
+            let fee = Uint128::try_from(Uint256::try_from(difference)?.mul_floor(imbalance_fee))?;
+
+            Ok(new_balance.checked_sub(fee)?)
+        })
+        .collect::<Result<Vec<Uint128>, ContractError>>()?;
+
+    let d_2 = compute_d(amp_factor, &to_coins(&charged_balances))
+        .ok_or(ContractError::StableInvariantError)?;
+
+    Ok((d_0, d_2))
 }
 
-/// Computes the amount of lp tokens to mint after a deposit for a stableswap pool.
-/// Assumes the deposits have already been credited to the pool_assets.
-#[allow(clippy::unwrap_used, clippy::too_many_arguments)]
-pub fn compute_lp_mint_amount_for_stableswap_deposit(
-    amp_factor: &u64,
-    old_pool_assets: &[Coin],
-    new_pool_assets: &[Coin],
-    pool_lp_token_total_supply: Uint128,
-) -> Result<Option<Uint128>, ContractError> {
-    // Initial invariant
-    let d_0 = compute_d(amp_factor, old_pool_assets).ok_or(ContractError::StableInvariantError)?;
+/// Computes the invariant of a weighted pool, i.e. `prod(balance_i ^ weight_i)`, for the given
+/// balances. The balances are expected to be in the same order as the weights.
+pub fn compute_weighted_invariant(
+    weights: &[Decimal],
+    balances: &[Coin],
+) -> Result<Uint128, ContractError> {
+    ensure!(
+        weights.len() == balances.len(),
+        ContractError::InvalidPoolWeights
+    );
 
-    // Invariant after change, i.e. after deposit
-    // notice that new_pool_assets already added the new deposits to the pool
-    let d_1 = compute_d(amp_factor, new_pool_assets).ok_or(ContractError::StableInvariantError)?;
+    let invariant = weights.iter().zip(balances.iter()).try_fold(
+        Decimal256::one(),
+        |acc, (weight, balance)| -> Result<Decimal256, ContractError> {
+            let power = Decimal256::from_ratio(balance.amount, Uint256::one())
+                .checked_pow_down((*weight).into())?;
+            Ok(acc.checked_mul(power)?)
+        },
+    )?;
 
-    // If the invariant didn't change, return None
-    if d_1 <= d_0 {
-        Ok(None)
-    } else {
-        let amount = Uint512::from(pool_lp_token_total_supply)
-            .checked_mul(d_1.checked_sub(d_0)?)?
-            .checked_div(d_0)?;
-        Ok(Some(Uint128::try_from(amount)?))
-    }
+    Ok(Uint128::try_from(invariant.to_uint_floor())?)
 }
 
 /// Compute the swap amount `y` in proportion to `x`.
@@ -939,6 +2174,59 @@ pub fn swap_to(
     })
 }
 
+/// Computes the reserve health of the given denoms, comparing the amount of each denom the contract
+/// should hold with its balance. The contract should hold the reserves of all the pools, the fees
+/// owed to the positions of concentrated liquidity pools, and the [`MINIMUM_LIQUIDITY_AMOUNT`] LP
+/// tokens locked on the first deposit into each pool.
+pub fn get_reserve_health(
+    deps: Deps,
+    env: &Env,
+    denoms: Vec<String>,
+) -> Result<Vec<ReserveHealth>, ContractError> {
+    let mut accounted = vec![Uint128::zero(); denoms.len()];
+    let mut account = |denom: &str, amount: Uint128| -> Result<(), ContractError> {
+        if let Some(index) = denoms.iter().position(|d| d == denom) {
+            accounted[index] = accounted[index].checked_add(amount)?;
+        }
+        Ok(())
+    };
+
+    for item in POOLS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, pool) = item?;
+
+        for asset in &pool.assets {
+            account(&asset.denom, asset.amount)?;
+        }
+
+        if let PoolType::ConcentratedLiquidity { .. } = pool.pool_type {
+            let state = CONCENTRATED_LIQUIDITY_STATES.load(deps.storage, &pool.pool_identifier)?;
+            for (denom, fees_held) in pool.asset_denoms.iter().zip(state.fees_held) {
+                account(denom, fees_held)?;
+            }
+        } else if denoms.contains(&pool.lp_denom)
+            && !get_total_share(&deps, pool.lp_denom.clone())?.is_zero()
+        {
+            account(&pool.lp_denom, MINIMUM_LIQUIDITY_AMOUNT)?;
+        }
+    }
+
+    denoms
+        .into_iter()
+        .zip(accounted)
+        .map(|(denom, accounted)| {
+            let balance = get_balance(&deps, &env.contract.address, &denom)?.amount;
+
+            Ok(ReserveHealth {
+                denom,
+                accounted,
+                balance,
+                surplus: balance.saturating_sub(accounted),
+                deficit: accounted.saturating_sub(balance),
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -946,7 +2234,7 @@ pub fn swap_to(
     clippy::too_many_arguments
 )]
 mod tests {
-    use cosmwasm_std::coin;
+    use cosmwasm_std::{assert_approx_eq, coin};
     use proptest::prelude::*;
     use rand::Rng;
 
@@ -1044,6 +2332,599 @@ mod tests {
         assert_eq!(actual_mint_amount, expected_mint_amount);
     }
 
+    #[test]
+    fn test_withdraw_one_coin_with_random_inputs() {
+        let imbalance_fee = get_stableswap_imbalance_fee(
+            &PoolFee {
+                protocol_fee: amm::fee::Fee {
+                    share: Decimal::zero(),
+                },
+                swap_fee: amm::fee::Fee {
+                    share: Decimal::from_ratio(
+                        sim::MODEL_FEE_NUMERATOR,
+                        sim::MODEL_FEE_DENOMINATOR,
+                    ),
+                },
+                burn_fee: amm::fee::Fee {
+                    share: Decimal::zero(),
+                },
+                extra_fees: vec![],
+            },
+            2,
+        )
+        .unwrap();
+
+        for _ in 0..100 {
+            let mut rng = rand::thread_rng();
+
+            let amp_factor: u64 = rng.gen_range(MIN_AMP..=10_000);
+            let amount_a = rng.gen_range(1_000_000..=MAX_TOKENS_IN.u128() >> 16);
+            let amount_b = rng.gen_range(amount_a / 2..=amount_a * 2);
+            let total_share = amount_a + amount_b;
+            let burn_amount = rng.gen_range(1..=total_share / 2);
+            let index = rng.gen_range(0..2usize);
+            println!(
+                "amp_factor: {}, amount_a: {}, amount_b: {}, burn_amount: {}, index: {}",
+                amp_factor, amount_a, amount_b, burn_amount, index
+            );
+
+            let model =
+                Model::new_with_pool_tokens(amp_factor, vec![amount_a, amount_b], 2, total_share);
+
+            let (dy, fee) = compute_withdraw_one_coin(
+                &amp_factor,
+                &[Uint128::new(amount_a), Uint128::new(amount_b)],
+                index,
+                Uint128::new(burn_amount),
+                Uint128::new(total_share),
+                imbalance_fee,
+            )
+            .unwrap();
+
+            let (expected_dy, expected_fee) =
+                model.sim_calc_withdraw_one_coin(burn_amount, index as u128);
+
+            assert_eq!(dy.u128(), expected_dy);
+            assert_eq!(fee.u128(), expected_fee);
+        }
+    }
+
+    #[test]
+    fn test_withdraw_imbalance_with_random_inputs() {
+        let imbalance_fee = get_stableswap_imbalance_fee(
+            &PoolFee {
+                protocol_fee: amm::fee::Fee {
+                    share: Decimal::zero(),
+                },
+                swap_fee: amm::fee::Fee {
+                    share: Decimal::from_ratio(
+                        sim::MODEL_FEE_NUMERATOR,
+                        sim::MODEL_FEE_DENOMINATOR,
+                    ),
+                },
+                burn_fee: amm::fee::Fee {
+                    share: Decimal::zero(),
+                },
+                extra_fees: vec![],
+            },
+            2,
+        )
+        .unwrap();
+
+        for _ in 0..100 {
+            let mut rng = rand::thread_rng();
+
+            let amp_factor: u64 = rng.gen_range(MIN_AMP..=10_000);
+            let amount_a = rng.gen_range(1_000_000..=MAX_TOKENS_IN.u128() >> 16);
+            let amount_b = rng.gen_range(amount_a / 2..=amount_a * 2);
+            let total_share = amount_a + amount_b;
+            let withdraw_a = rng.gen_range(0..=amount_a / 2);
+            let withdraw_b = rng.gen_range(0..=amount_b / 2);
+            println!(
+                "amp_factor: {}, amount_a: {}, amount_b: {}, withdraw_a: {}, withdraw_b: {}",
+                amp_factor, amount_a, amount_b, withdraw_a, withdraw_b
+            );
+
+            let model =
+                Model::new_with_pool_tokens(amp_factor, vec![amount_a, amount_b], 2, total_share);
+
+            let burn_amount = compute_withdraw_imbalance_burn_amount(
+                &amp_factor,
+                &[Uint128::new(amount_a), Uint128::new(amount_b)],
+                &[
+                    Uint128::new(amount_a - withdraw_a),
+                    Uint128::new(amount_b - withdraw_b),
+                ],
+                Uint128::new(total_share),
+                imbalance_fee,
+            )
+            .unwrap();
+
+            let expected_burn_amount =
+                model.sim_remove_liquidity_imbalance(vec![withdraw_a, withdraw_b]);
+
+            // one extra LP token is burned to account for rounding errors
+            assert_eq!(burn_amount.u128(), expected_burn_amount + 1);
+        }
+    }
+
+    #[test]
+    fn test_deposit_imbalance_with_random_inputs() {
+        let imbalance_fee = get_stableswap_imbalance_fee(
+            &PoolFee {
+                protocol_fee: amm::fee::Fee {
+                    share: Decimal::zero(),
+                },
+                swap_fee: amm::fee::Fee {
+                    share: Decimal::from_ratio(
+                        sim::MODEL_FEE_NUMERATOR,
+                        sim::MODEL_FEE_DENOMINATOR,
+                    ),
+                },
+                burn_fee: amm::fee::Fee {
+                    share: Decimal::zero(),
+                },
+                extra_fees: vec![],
+            },
+            2,
+        )
+        .unwrap();
+
+        for _ in 0..100 {
+            let mut rng = rand::thread_rng();
+
+            let amp_factor: u64 = rng.gen_range(MIN_AMP..=10_000);
+            let amount_a = rng.gen_range(1_000_000..=MAX_TOKENS_IN.u128() >> 16);
+            let amount_b = rng.gen_range(amount_a / 2..=amount_a * 2);
+            let total_share = amount_a + amount_b;
+            let deposit_a = rng.gen_range(0..=amount_a / 2);
+            let deposit_b = rng.gen_range(0..=amount_b / 2);
+            println!(
+                "amp_factor: {}, amount_a: {}, amount_b: {}, deposit_a: {}, deposit_b: {}",
+                amp_factor, amount_a, amount_b, deposit_a, deposit_b
+            );
+
+            let model =
+                Model::new_with_pool_tokens(amp_factor, vec![amount_a, amount_b], 2, total_share);
+
+            let mint_amount = compute_deposit_imbalance_mint_amount(
+                &amp_factor,
+                &[Uint128::new(amount_a), Uint128::new(amount_b)],
+                &[
+                    Uint128::new(amount_a + deposit_a),
+                    Uint128::new(amount_b + deposit_b),
+                ],
+                Uint128::new(total_share),
+                imbalance_fee,
+            )
+            .unwrap();
+
+            assert_eq!(
+                mint_amount.u128(),
+                model.sim_add_liquidity(vec![deposit_a, deposit_b])
+            );
+        }
+    }
+
+    #[test]
+    fn test_weighted_swap_with_equal_weights_matches_constant_product() {
+        let pool_fees = PoolFee {
+            protocol_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: amm::fee::Fee {
+                share: Decimal::permille(3),
+            },
+            burn_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        };
+
+        let offer_pool = Uint128::new(1_000_000_000_000u128);
+        let ask_pool = Uint128::new(2_500_000_000_000u128);
+        let offer_amount = Uint128::new(3_000_000_000u128);
+
+        let constant_product = compute_swap(
+            Uint256::from(2u128),
+            offer_pool,
+            ask_pool,
+            offer_amount,
+            pool_fees.clone(),
+            &PoolType::ConstantProduct,
+            6,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap();
+
+        let weighted = compute_swap(
+            Uint256::from(2u128),
+            offer_pool,
+            ask_pool,
+            offer_amount,
+            pool_fees,
+            &PoolType::Weighted {
+                weights: vec![Decimal::percent(50), Decimal::percent(50)],
+            },
+            6,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap();
+
+        // the weighted math rounds in favor of the pool, so it can only return slightly less
+        assert!(weighted.return_amount <= constant_product.return_amount);
+        assert_approx_eq!(
+            weighted.return_amount,
+            constant_product.return_amount,
+            "0.000001"
+        );
+    }
+
+    #[test]
+    fn test_weighted_swap_rejects_large_swaps() {
+        let pool_fees = PoolFee {
+            protocol_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            burn_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        };
+
+        let err = compute_swap(
+            Uint256::from(2u128),
+            Uint128::new(1_000_000u128),
+            Uint128::new(1_000_000u128),
+            Uint128::new(300_001u128),
+            pool_fees,
+            &PoolType::Weighted {
+                weights: vec![Decimal::percent(80), Decimal::percent(20)],
+            },
+            6,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap_err();
+
+        assert_eq!(err, ContractError::MaxWeightedSwapRatioExceeded);
+    }
+
+    #[test]
+    fn test_weighted_swap_rejects_large_outputs() {
+        let pool_fees = PoolFee {
+            protocol_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            burn_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        };
+        let pool_type = PoolType::Weighted {
+            weights: vec![Decimal::percent(80), Decimal::percent(20)],
+        };
+
+        // 1_000_000 * (1 - (1_000_000 / 1_090_000) ^ 4) ~= 291_580, within 30% of the ask balance
+        let swap = compute_swap(
+            Uint256::from(2u128),
+            Uint128::new(1_000_000u128),
+            Uint128::new(1_000_000u128),
+            Uint128::new(90_000u128),
+            pool_fees.clone(),
+            &pool_type,
+            6,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap();
+        assert!(swap.return_amount < Uint128::new(300_000u128));
+
+        // offering 10% of the offer balance returns ~31.7% of the ask balance with an 80/20 pool
+        let err = compute_swap(
+            Uint256::from(2u128),
+            Uint128::new(1_000_000u128),
+            Uint128::new(1_000_000u128),
+            Uint128::new(100_000u128),
+            pool_fees.clone(),
+            &pool_type,
+            6,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::MaxWeightedSwapRatioExceeded);
+
+        // the other way around, asking for 10% of the ask balance requires ~52.4% of the offer
+        // balance
+        let err = compute_offer_amount(
+            Uint128::new(1_000_000u128),
+            Uint128::new(1_000_000u128),
+            Uint128::new(100_000u128),
+            pool_fees,
+            &pool_type,
+            6,
+            6,
+            1,
+            0,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::MaxWeightedSwapRatioExceeded);
+    }
+
+    #[test]
+    fn test_weighted_invariant() {
+        let weights = vec![Decimal::percent(80), Decimal::percent(20)];
+
+        // with equal balances the invariant is the balance itself
+        let invariant = compute_weighted_invariant(
+            &weights,
+            &[coin(1_000_000u128, "denom1"), coin(1_000_000u128, "denom2")],
+        )
+        .unwrap();
+        assert_approx_eq!(invariant, Uint128::new(1_000_000u128), "0.000002");
+
+        // 1_600_000 ^ 0.8 * 100_000 ^ 0.2 ~= 918_958.68
+        let invariant = compute_weighted_invariant(
+            &weights,
+            &[coin(1_600_000u128, "denom1"), coin(100_000u128, "denom2")],
+        )
+        .unwrap();
+        assert_approx_eq!(invariant, Uint128::new(918_958u128), "0.000001");
+    }
+
+    #[test]
+    fn test_liquidity_bootstrapping_weights_move_linearly() {
+        let start_weights = vec![Decimal::percent(90), Decimal::percent(10)];
+        let end_weights = vec![Decimal::percent(30), Decimal::percent(70)];
+
+        // before and after the schedule
+        assert_eq!(
+            get_current_weights(&start_weights, &end_weights, 1_000, 2_000, 500).unwrap(),
+            start_weights
+        );
+        assert_eq!(
+            get_current_weights(&start_weights, &end_weights, 1_000, 2_000, 2_500).unwrap(),
+            end_weights
+        );
+
+        // a quarter of the way through the schedule
+        assert_eq!(
+            get_current_weights(&start_weights, &end_weights, 1_000, 2_000, 1_250).unwrap(),
+            vec![Decimal::percent(75), Decimal::percent(25)]
+        );
+
+        // the weights keep adding up to 1 when the interpolation rounds
+        let weights = get_current_weights(
+            &[
+                Decimal::percent(50),
+                Decimal::percent(25),
+                Decimal::percent(25),
+            ],
+            &[
+                Decimal::percent(10),
+                Decimal::percent(45),
+                Decimal::percent(45),
+            ],
+            0,
+            3,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            weights.iter().fold(Decimal::zero(), |acc, w| acc + *w),
+            Decimal::one()
+        );
+    }
+
+    #[test]
+    fn test_solidly_swap_with_random_inputs() {
+        let pool_fees = PoolFee {
+            protocol_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            burn_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        };
+
+        for _ in 0..100 {
+            let mut rng = rand::thread_rng();
+
+            let offer_precision: u8 = *[6u8, 18u8].get(rng.gen_range(0..2)).unwrap();
+            let ask_precision: u8 = *[6u8, 18u8].get(rng.gen_range(0..2)).unwrap();
+            let offer_pool = rng.gen_range(1_000_000u128..=1_000_000_000_000u128)
+                * 10u128.pow(u32::from(offer_precision - 6));
+            let ask_pool = rng.gen_range(1_000_000u128..=1_000_000_000_000u128)
+                * 10u128.pow(u32::from(ask_precision - 6));
+            let offer_amount = rng.gen_range(1u128..=offer_pool * 10);
+            println!(
+                "offer_pool: {}, ask_pool: {}, offer_amount: {}, offer_precision: {}, ask_precision: {}",
+                offer_pool, ask_pool, offer_amount, offer_precision, ask_precision
+            );
+
+            let swap = compute_swap(
+                Uint256::from(2u128),
+                Uint128::new(offer_pool),
+                Uint128::new(ask_pool),
+                Uint128::new(offer_amount),
+                pool_fees.clone(),
+                &PoolType::SolidlyStable,
+                offer_precision,
+                ask_precision,
+                0,
+                1,
+                &[],
+            )
+            .unwrap();
+
+            assert!(swap.return_amount.u128() < ask_pool);
+
+            // the invariant can't decrease after the swap
+            let k_before = compute_solidly_k(
+                to_solidly_balance(Uint256::from(offer_pool), offer_precision).unwrap(),
+                to_solidly_balance(Uint256::from(ask_pool), ask_precision).unwrap(),
+            )
+            .unwrap();
+            let k_after = compute_solidly_k(
+                to_solidly_balance(Uint256::from(offer_pool + offer_amount), offer_precision)
+                    .unwrap(),
+                to_solidly_balance(
+                    Uint256::from(ask_pool - swap.return_amount.u128()),
+                    ask_precision,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            assert!(k_after >= k_before);
+        }
+    }
+
+    #[test]
+    fn test_solidly_swap_has_less_slippage_than_constant_product() {
+        let pool_fees = PoolFee {
+            protocol_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: amm::fee::Fee {
+                share: Decimal::permille(3),
+            },
+            burn_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        };
+
+        let pool = Uint128::new(1_000_000_000_000u128);
+        let offer_amount = Uint128::new(100_000_000_000u128);
+
+        let constant_product = compute_swap(
+            Uint256::from(2u128),
+            pool,
+            pool,
+            offer_amount,
+            pool_fees.clone(),
+            &PoolType::ConstantProduct,
+            6,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap();
+
+        let solidly = compute_swap(
+            Uint256::from(2u128),
+            pool,
+            pool,
+            offer_amount,
+            pool_fees,
+            &PoolType::SolidlyStable,
+            6,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap();
+
+        // a 10% swap in a balanced pool gets almost 1:1 on the flat part of the curve
+        assert!(solidly.return_amount > constant_product.return_amount);
+        assert_approx_eq!(
+            solidly.return_amount + solidly.swap_fee_amount,
+            offer_amount,
+            "0.001"
+        );
+        assert!(solidly.spread_amount < constant_product.spread_amount);
+    }
+
+    #[test]
+    fn test_solidly_offer_amount_matches_swap() {
+        let pool_fees = PoolFee {
+            protocol_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: amm::fee::Fee {
+                share: Decimal::permille(3),
+            },
+            burn_fee: amm::fee::Fee {
+                share: Decimal::permille(1),
+            },
+            extra_fees: vec![],
+        };
+
+        let offer_pool = Uint128::new(1_200_000_000_000_000_000_000u128);
+        let ask_pool = Uint128::new(1_000_000_000_000u128);
+        let ask_amount = Uint128::new(250_000_000_000u128);
+
+        let offer = compute_offer_amount(
+            offer_pool,
+            ask_pool,
+            ask_amount,
+            pool_fees.clone(),
+            &PoolType::SolidlyStable,
+            18,
+            6,
+            0,
+            1,
+        )
+        .unwrap();
+
+        let swap = compute_swap(
+            Uint256::from(2u128),
+            offer_pool,
+            ask_pool,
+            offer.offer_amount,
+            pool_fees,
+            &PoolType::SolidlyStable,
+            18,
+            6,
+            0,
+            1,
+            &[],
+        )
+        .unwrap();
+
+        // offering the computed amount returns at least the requested amount
+        assert!(swap.return_amount >= ask_amount);
+        assert_approx_eq!(swap.return_amount, ask_amount, "0.000001");
+    }
+
+    #[test]
+    fn test_solidly_y_converges_on_large_swaps() {
+        let x = Uint256::from(1_000_000_000_000_000_000u128);
+        let y = Uint256::from(1_000_000_000_000_000_000u128);
+        let k = compute_solidly_k(x, y).unwrap();
+
+        // offering 1000x the pool balance drains almost all of the other asset
+        let new_y = compute_solidly_y(x * Uint256::from(1_001u128), k, y).unwrap();
+
+        assert!(new_y < y / Uint256::from(1_000_000u128));
+        assert!(compute_solidly_k(x * Uint256::from(1_001u128), new_y).unwrap() >= k);
+    }
+
     #[test]
     fn test_curve_math_with_random_inputs() {
         for _ in 0..100 {
@@ -1283,6 +3164,66 @@ mod tests {
         }
     }
 
+    fn excess_deposit_pool_fees(swap_fee: Decimal) -> PoolFee {
+        PoolFee {
+            protocol_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: amm::fee::Fee { share: swap_fee },
+            burn_fee: amm::fee::Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        }
+    }
+
+    #[test]
+    fn test_excess_deposit_swap_amount_without_fees() {
+        // with no ask deposit nor fees, s**2 + 2*A*s - a*A = 0, i.e. s = sqrt(A**2 + a*A) - A
+        let swap_amount = compute_excess_deposit_swap_amount(
+            Uint128::new(1_000_000),
+            Uint128::new(1_000_000),
+            Uint128::new(440_000),
+            Uint128::zero(),
+            &excess_deposit_pool_fees(Decimal::zero()),
+        )
+        .unwrap();
+
+        assert_eq!(swap_amount, Uint128::new(200_000));
+    }
+
+    #[test]
+    fn test_excess_deposit_swap_amount_matches_pool_ratio() {
+        let (offer_pool, ask_pool) = (3_000_000_000u128, 1_000_000_000u128);
+        let (offer_deposit, ask_deposit) = (900_000_000u128, 100_000_000u128);
+        let swap_fee = Decimal::percent(3);
+
+        let swap_amount = compute_excess_deposit_swap_amount(
+            Uint128::new(offer_pool),
+            Uint128::new(ask_pool),
+            Uint128::new(offer_deposit),
+            Uint128::new(ask_deposit),
+            &excess_deposit_pool_fees(swap_fee),
+        )
+        .unwrap()
+        .u128();
+
+        // the fees are charged on the output of the swap, and stay in the pool
+        let return_amount = ask_pool * swap_amount / (offer_pool + swap_amount);
+        let fee_amount = return_amount * 3 / 100;
+
+        let deposit_ratio = Decimal::from_ratio(
+            offer_deposit - swap_amount,
+            ask_deposit + return_amount - fee_amount,
+        );
+        let pool_ratio = Decimal::from_ratio(
+            offer_pool + swap_amount,
+            ask_pool - return_amount + fee_amount,
+        );
+
+        assert_approx_eq!(deposit_ratio.atomics(), pool_ratio.atomics(), "0.001");
+    }
+
     proptest! {
         #[test]
         fn test_virtual_price_does_not_decrease_from_swap(
@@ -2,12 +2,14 @@ pub mod contract;
 pub mod error;
 pub mod state;
 pub use crate::error::ContractError;
+pub mod concentrated_liquidity;
 pub mod helpers;
 pub mod liquidity;
 pub mod manager;
 pub mod math;
 pub mod queries;
 pub mod router;
+pub mod shares;
 pub mod swap;
 #[cfg(test)]
 #[cfg(not(target_arch = "wasm32"))]
@@ -1,16 +1,22 @@
+use std::str::FromStr;
+
 use cosmwasm_std::{
-    coin, coins, ensure, to_json_binary, wasm_execute, BankMsg, Coin, CosmosMsg, Decimal256,
-    DepsMut, Env, MessageInfo, Response, StdResult, SubMsg, Uint256,
+    coin, ensure, to_json_binary, wasm_execute, Addr, BankMsg, Coin, CosmosMsg, Decimal256, Deps,
+    DepsMut, Env, MessageInfo, Response, StdResult, SubMsg, Timestamp, Uint256,
 };
 use cosmwasm_std::{Decimal, Uint128};
 
-use amm::coin::{add_coins, aggregate_coins};
+use amm::coin::{add_coins, aggregate_coins, burn_coin_msg};
 use amm::common::validate_addr_or_default;
-use amm::farm_manager::{PositionsBy, PositionsResponse};
+use amm::farm_manager::{IsPositionOperatorResponse, PositionsBy, PositionsResponse};
 use amm::lp_common::MINIMUM_LIQUIDITY_AMOUNT;
-use amm::pool_manager::{get_total_share, ExecuteMsg, PoolType};
+use amm::pool_manager::{
+    ConcentratedPositionAction, ConcentratedPositionRange, Config, ExcessDepositMode, ExecuteMsg,
+    PoolInfo, PoolType, SwapOperation,
+};
 use amm::U256;
 
+use crate::concentrated_liquidity::commands::manage_concentrated_position;
 use crate::{
     helpers::{self},
     state::get_pool_by_identifier,
@@ -23,13 +29,23 @@ use crate::{
 // break it down into smaller modules which house some things like swap, liquidity etc
 use crate::contract::SINGLE_SIDE_LIQUIDITY_PROVISION_REPLY_ID;
 use crate::helpers::{
-    aggregate_outgoing_fees, compute_d, compute_lp_mint_amount_for_stableswap_deposit,
+    aggregate_outgoing_fees, apply_amp_ramp, compute_d, compute_deposit_imbalance_mint_amount,
+    compute_deposit_slippage, compute_excess_deposit_swap_amount,
+    compute_lp_mint_amount_for_stableswap_deposit, compute_weighted_invariant,
+    compute_withdraw_imbalance_burn_amount, compute_withdraw_one_coin,
+    denormalize_stableswap_amount, get_asset_rates, get_effective_pool_type, get_oracle_price,
+    get_stableswap_balances, get_stableswap_imbalance_fee, scale_by_rates,
 };
 use crate::queries::query_simulation;
+use crate::router::commands::assert_operations;
+use crate::shares::ledger::{burn_lp, get_total_share, mint_lp, send_lp, transfer_lp};
 use crate::state::{
     LiquidityProvisionData, SingleSideLiquidityProvisionBuffer,
     SINGLE_SIDE_LIQUIDITY_PROVISION_BUFFER,
 };
+use crate::swap::perform_swap::{
+    compute_swap_result, perform_swap, SwapResult, MAX_ALLOWED_SLIPPAGE,
+};
 
 #[allow(clippy::too_many_arguments)]
 pub fn provide_liquidity(
@@ -42,6 +58,7 @@ pub fn provide_liquidity(
     pool_identifier: String,
     unlocking_duration: Option<u64>,
     lock_position_identifier: Option<String>,
+    excess_mode: Option<ExcessDepositMode>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     // check if the deposit feature is enabled
@@ -52,87 +69,59 @@ pub fn provide_liquidity(
 
     // Get the pool by the pool_identifier
     let mut pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    apply_amp_ramp(deps.storage, &env, &mut pool)?;
 
-    let mut pool_assets = pool.assets.clone();
-    let deposits = aggregate_coins(info.funds.clone())?;
-
-    ensure!(!deposits.is_empty(), ContractError::EmptyAssets);
-
-    // verify that the assets sent match the ones from the pool
-    ensure!(
-        deposits.iter().all(|asset| pool_assets
-            .iter()
-            .any(|pool_asset| pool_asset.denom == asset.denom)),
-        ContractError::AssetMismatch
-    );
+    let excess_mode = excess_mode.unwrap_or(ExcessDepositMode::Donate);
+    let (deposits, is_single_side_swap_provision) =
+        validate_deposits(&pool, info.funds.clone(), &excess_mode)?;
 
     let receiver =
         validate_addr_or_default(&deps.as_ref(), receiver, info.sender.clone()).to_string();
 
-    //let receiver = receiver.unwrap_or_else(|| info.sender.to_string());
-    // check if the user is providing liquidity with a single asset
-    let is_single_asset_provision = deposits.len() == 1usize;
-
-    if is_single_asset_provision {
-        ensure!(
-            !pool_assets.iter().any(|asset| asset.amount.is_zero()),
-            ContractError::EmptyPoolForSingleSideLiquidityProvision
-        );
-
-        // can't provide single side liquidity on a pool with more than 2 assets
-        ensure!(
-            pool_assets.len() == 2,
-            ContractError::InvalidPoolAssetsForSingleSideLiquidityProvision
-        );
+    // LP tokens can only be locked on behalf of another receiver by a position operator of the
+    // farm manager, in a new position
+    if unlocking_duration.is_some() {
+        ensure_can_lock_for_receiver(
+            deps.as_ref(),
+            &env,
+            &config,
+            &info.sender,
+            &receiver,
+            &lock_position_identifier,
+        )?;
+    }
 
+    if is_single_side_swap_provision {
         let deposit = deposits[0].clone();
 
-        let ask_asset_denom = pool_assets
-            .iter()
-            .find(|pool_asset| pool_asset.denom != deposit.denom)
-            .ok_or(ContractError::AssetMismatch)?
-            .denom
-            .clone();
-
-        // swap half of the deposit asset for the other asset in the pool
-        let swap_half = Coin {
-            denom: deposit.denom.clone(),
-            amount: deposit.amount.checked_div_floor((2u64, 1u64))?,
-        };
-
-        let swap_simulation_response = query_simulation(
-            deps.as_ref(),
-            swap_half.clone(),
-            ask_asset_denom.clone(),
-            pool_identifier.clone(),
-        )?;
+        let (offer_asset_portion, offer_asset_remainder, mut pending_ask_asset_denoms) =
+            split_single_side_deposit(&deposit, &pool.assets)?;
+        let ask_asset_denom = pending_ask_asset_denoms.remove(0);
 
         // let's compute the expected offer asset balance in the contract after the swap and liquidity
-        // provision takes place. This should be the same value as of now. Even though half of it
-        // will be swapped, eventually all of it will be sent to the contract in the second step of
+        // provision takes place. This should be the same value as of now. Even though part of it
+        // will be swapped, eventually all of it will be sent to the contract in the last step of
         // the single side liquidity provision
         let expected_offer_asset_balance_in_contract = deps
             .querier
             .query_balance(&env.contract.address, deposit.denom)?;
 
-        // let's compute the expected ask asset balance in the contract after the swap and liquidity
-        // provision takes place. It should be the current balance minus the fees that will be sent
-        // off the contract.
-        let mut expected_ask_asset_balance_in_contract = deps
-            .querier
-            .query_balance(&env.contract.address, ask_asset_denom.clone())?;
-
-        expected_ask_asset_balance_in_contract.amount = expected_ask_asset_balance_in_contract
-            .amount
-            .saturating_sub(aggregate_outgoing_fees(&swap_simulation_response)?);
+        let liquidity_provision_data = LiquidityProvisionData {
+            max_spread,
+            slippage_tolerance,
+            pool_identifier,
+            unlocking_duration,
+            lock_position_identifier,
+        };
 
-        // sanity check. Theoretically, with the given conditions of min LP, pool fees and max spread assertion,
-        // the expected ask asset balance in the contract will always be greater than zero after
-        // subtracting the fees.
-        ensure!(
-            !expected_ask_asset_balance_in_contract.amount.is_zero(),
-            ContractError::MaxSpreadAssertion
-        );
+        let (expected_ask_asset_balance_in_contract, expected_ask_asset, swap_msg) =
+            simulate_single_side_liquidity_provision_swap(
+                deps.as_ref(),
+                &env,
+                &offer_asset_portion,
+                ask_asset_denom,
+                &liquidity_provision_data,
+            )?;
 
         SINGLE_SIDE_LIQUIDITY_PROVISION_BUFFER.save(
             deps.storage,
@@ -140,155 +129,80 @@ pub fn provide_liquidity(
                 receiver,
                 expected_offer_asset_balance_in_contract,
                 expected_ask_asset_balance_in_contract,
-                offer_asset_half: swap_half.clone(),
-                expected_ask_asset: coin(
-                    swap_simulation_response.return_amount.u128(),
-                    ask_asset_denom.clone(),
-                ),
-                liquidity_provision_data: LiquidityProvisionData {
-                    max_spread,
-                    slippage_tolerance,
-                    pool_identifier: pool_identifier.clone(),
-                    unlocking_duration,
-                    lock_position_identifier,
-                },
+                offer_asset_portion,
+                offer_asset_remainder,
+                expected_ask_asset,
+                swapped_assets: vec![],
+                pending_ask_asset_denoms,
+                liquidity_provision_data,
             },
         )?;
 
         Ok(Response::default()
-            .add_submessage(SubMsg::reply_on_success(
-                wasm_execute(
-                    env.contract.address.into_string(),
-                    &ExecuteMsg::Swap {
-                        ask_asset_denom,
-                        belief_price: None,
-                        max_spread,
-                        receiver: None,
-                        pool_identifier,
-                    },
-                    vec![swap_half],
-                )?,
-                SINGLE_SIDE_LIQUIDITY_PROVISION_REPLY_ID,
-            ))
+            .add_submessage(swap_msg)
             .add_attributes(vec![("action", "single_side_liquidity_provision")]))
     } else {
+        let LiquidityProvision {
+            share,
+            is_initial_deposit,
+            excess_refund,
+            excess_swap,
+            pool,
+            ..
+        } = compute_liquidity_provision(
+            deps.as_ref(),
+            &env,
+            pool,
+            deposits,
+            &slippage_tolerance,
+            max_spread,
+            &excess_mode,
+        )?;
+
         let mut messages: Vec<CosmosMsg> = vec![];
 
         let liquidity_token = pool.lp_denom.clone();
 
-        // Compute share and other logic based on the number of assets
-        let total_share = get_total_share(&deps.as_ref(), liquidity_token.clone())?;
-
-        let share = match &pool.pool_type {
-            PoolType::ConstantProduct => {
-                if total_share == Uint128::zero() {
-                    // Make sure at least MINIMUM_LIQUIDITY_AMOUNT is deposited to mitigate the risk of the first
-                    // depositor preventing small liquidity providers from joining the pool
-                    let share = Uint128::new(
-                        (U256::from(deposits[0].amount.u128())
-                            .checked_mul(U256::from(deposits[1].amount.u128()))
-                            .ok_or::<ContractError>(
-                                ContractError::LiquidityShareComputationFailed,
-                            ))?
-                        .integer_sqrt()
-                        .as_u128(),
-                    )
-                    .saturating_sub(MINIMUM_LIQUIDITY_AMOUNT);
-
-                    // share should be above zero after subtracting the MINIMUM_LIQUIDITY_AMOUNT
-                    if share.is_zero() {
-                        return Err(ContractError::InvalidInitialLiquidityAmount(
-                            MINIMUM_LIQUIDITY_AMOUNT,
-                        ));
-                    }
-
-                    messages.push(amm::lp_common::mint_lp_token_msg(
-                        liquidity_token.clone(),
-                        &env.contract.address,
-                        &env.contract.address,
-                        MINIMUM_LIQUIDITY_AMOUNT,
-                    )?);
-
-                    share
-                } else {
-                    let mut asset_shares = vec![];
-
-                    for deposit in deposits.iter() {
-                        let asset_denom = &deposit.denom;
-                        let pool_asset_index = pool_assets
-                            .iter()
-                            .position(|pool_asset| &pool_asset.denom == asset_denom)
-                            .ok_or(ContractError::AssetMismatch)?;
-
-                        asset_shares.push(
-                            deposit
-                                .amount
-                                .multiply_ratio(total_share, pool_assets[pool_asset_index].amount),
-                        );
-                    }
+        // Make sure at least MINIMUM_LIQUIDITY_AMOUNT is deposited to mitigate the risk of the first
+        // depositor preventing small liquidity providers from joining the pool
+        if is_initial_deposit {
+            messages.extend(mint_lp(
+                deps.storage,
+                &env,
+                &liquidity_token,
+                &env.contract.address,
+                MINIMUM_LIQUIDITY_AMOUNT,
+            )?);
+        }
 
-                    std::cmp::min(asset_shares[0], asset_shares[1])
-                }
+        if let Some((_, swap_result)) = &excess_swap {
+            // the fees are routed as with a regular swap
+            if !swap_result.burn_fee_asset.amount.is_zero() {
+                messages.push(burn_coin_msg(swap_result.burn_fee_asset.clone()));
             }
-            PoolType::StableSwap { amp: amp_factor } => {
-                if total_share == Uint128::zero() {
-                    // Make sure at least MINIMUM_LIQUIDITY_AMOUNT is deposited to mitigate the risk of the first
-                    // depositor preventing small liquidity providers from joining the pool
-                    let share = Uint128::try_from(compute_d(amp_factor, &deposits).unwrap())?
-                        .saturating_sub(MINIMUM_LIQUIDITY_AMOUNT);
-
-                    // share should be above zero after subtracting the min_lp_token_amount
-                    if share.is_zero() {
-                        return Err(ContractError::InvalidInitialLiquidityAmount(
-                            MINIMUM_LIQUIDITY_AMOUNT,
-                        ));
-                    }
-
-                    // mint the lp tokens to the contract
-                    messages.push(amm::lp_common::mint_lp_token_msg(
-                        liquidity_token.clone(),
-                        &env.contract.address,
-                        &env.contract.address,
-                        MINIMUM_LIQUIDITY_AMOUNT,
-                    )?);
 
-                    share
-                } else {
-                    compute_lp_mint_amount_for_stableswap_deposit(
-                        amp_factor,
-                        // pool_assets hold the balances before the deposit was made
-                        &pool_assets,
-                        // add the deposit to the pool_assets to calculate the new balances
-                        &add_coins(pool_assets.clone(), deposits.clone())?,
-                        total_share,
-                    )?
-                    .ok_or(ContractError::StableLpMintError)?
-                }
+            if !swap_result.protocol_fee_asset.amount.is_zero() {
+                messages.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: config.fee_collector_addr.to_string(),
+                    amount: vec![swap_result.protocol_fee_asset.clone()],
+                }));
             }
-        };
+        }
 
-        // assert slippage tolerance
-        helpers::assert_slippage_tolerance(
-            &slippage_tolerance,
-            &deposits,
-            &pool_assets,
-            pool.pool_type.clone(),
-            share,
-            total_share,
-        )?;
+        if !excess_refund.is_empty() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: excess_refund.clone(),
+            }));
+        }
 
         // if the unlocking duration is set, lock the LP tokens in the farm manager
         if let Some(unlocking_duration) = unlocking_duration {
-            // check if receiver is the same as the sender of the tx
-            ensure!(
-                receiver == info.sender.to_string(),
-                ContractError::Unauthorized
-            );
-
             // mint the lp tokens to the contract
-            messages.push(amm::lp_common::mint_lp_token_msg(
-                liquidity_token.clone(),
-                &env.contract.address,
+            messages.extend(mint_lp(
+                deps.storage,
+                &env,
+                &liquidity_token,
                 &env.contract.address,
                 share,
             )?);
@@ -316,188 +230,1424 @@ pub fn provide_liquidity(
                         ContractError::Unauthorized
                     );
 
-                    messages.push(
-                        wasm_execute(
-                            config.farm_manager_addr,
-                            &amm::farm_manager::ExecuteMsg::ManagePosition {
-                                action: amm::farm_manager::PositionAction::Expand {
-                                    identifier: position_identifier,
-                                },
+                    messages.push(send_lp(
+                        deps.storage,
+                        &env,
+                        coin(share.u128(), liquidity_token),
+                        &config.farm_manager_addr,
+                        to_json_binary(&amm::farm_manager::ExecuteMsg::ManagePosition {
+                            action: amm::farm_manager::PositionAction::Expand {
+                                identifier: position_identifier,
                             },
-                            coins(share.u128(), liquidity_token),
-                        )?
-                        .into(),
-                    );
+                        })?,
+                    )?);
                 } else {
                     // a position with the given identifier does not exist, create a new position
                     // for the user
-                    messages.push(
-                        wasm_execute(
-                            config.farm_manager_addr,
-                            &amm::farm_manager::ExecuteMsg::ManagePosition {
-                                action: amm::farm_manager::PositionAction::Create {
-                                    identifier: Some(position_identifier),
-                                    unlocking_duration,
-                                    receiver: Some(receiver.clone()),
-                                },
-                            },
-                            coins(share.u128(), liquidity_token),
-                        )?
-                        .into(),
-                    );
-                }
-            } else {
-                // no lock_position_identifier was set, create a new position for the user
-                messages.push(
-                    wasm_execute(
-                        config.farm_manager_addr,
-                        &amm::farm_manager::ExecuteMsg::ManagePosition {
+                    messages.push(send_lp(
+                        deps.storage,
+                        &env,
+                        coin(share.u128(), liquidity_token),
+                        &config.farm_manager_addr,
+                        to_json_binary(&amm::farm_manager::ExecuteMsg::ManagePosition {
                             action: amm::farm_manager::PositionAction::Create {
-                                identifier: lock_position_identifier,
+                                identifier: Some(position_identifier),
                                 unlocking_duration,
                                 receiver: Some(receiver.clone()),
                             },
+                        })?,
+                    )?);
+                }
+            } else {
+                // no lock_position_identifier was set, create a new position for the user
+                messages.push(send_lp(
+                    deps.storage,
+                    &env,
+                    coin(share.u128(), liquidity_token),
+                    &config.farm_manager_addr,
+                    to_json_binary(&amm::farm_manager::ExecuteMsg::ManagePosition {
+                        action: amm::farm_manager::PositionAction::Create {
+                            identifier: lock_position_identifier,
+                            unlocking_duration,
+                            receiver: Some(receiver.clone()),
                         },
-                        coins(share.u128(), liquidity_token),
-                    )?
-                    .into(),
-                );
+                    })?,
+                )?);
             }
         } else {
             // if no unlocking duration is set, just mint the LP tokens to the receiver
-            messages.push(amm::lp_common::mint_lp_token_msg(
-                liquidity_token,
+            messages.extend(mint_lp(
+                deps.storage,
+                &env,
+                &liquidity_token,
                 &deps.api.addr_validate(&receiver)?,
-                &env.contract.address,
                 share,
             )?);
         }
 
-        // Increment the pool asset amount by the amount sent
-        for asset in deposits.iter() {
-            let asset_denom = &asset.denom;
-            let pool_asset_index = pool_assets
-                .iter()
-                .position(|pool_asset| &pool_asset.denom == asset_denom)
-                .ok_or(ContractError::AssetMismatch)?;
-
-            pool_assets[pool_asset_index].amount = pool_assets[pool_asset_index]
-                .amount
-                .checked_add(asset.amount)?;
-        }
-
-        pool.assets = pool_assets.clone();
-
         POOLS.save(deps.storage, &pool_identifier, &pool)?;
 
-        Ok(Response::new().add_messages(messages).add_attributes(vec![
+        let mut response = Response::new().add_messages(messages).add_attributes(vec![
             ("action", "provide_liquidity"),
             ("sender", info.sender.as_str()),
             ("receiver", receiver.as_str()),
             (
                 "assets",
-                &pool_assets
+                &pool
+                    .assets
                     .iter()
                     .map(|asset| asset.to_string())
                     .collect::<Vec<_>>()
                     .join(", "),
             ),
             ("share", &share.to_string()),
-        ]))
+        ]);
+
+        if let Some((offer_asset, swap_result)) = excess_swap {
+            response = response.add_attributes(vec![
+                ("excess_swap_offer_asset", offer_asset.to_string()),
+                (
+                    "excess_swap_return_asset",
+                    swap_result.return_asset.to_string(),
+                ),
+            ]);
+        }
+
+        if !excess_refund.is_empty() {
+            response = response.add_attribute(
+                "excess_refund",
+                excess_refund
+                    .iter()
+                    .map(|asset| asset.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        }
+
+        Ok(response)
     }
 }
 
-/// Withdraws the liquidity. The user burns the LP tokens in exchange for the tokens provided, including
-/// the swap fees accrued by its share of the pool.
-pub fn withdraw_liquidity(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    pool_identifier: String,
-) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    // check if the withdraw feature is enabled
-    if !config.feature_toggle.withdrawals_enabled {
-        return Err(ContractError::OperationDisabled(
-            "withdraw_liquidity".to_string(),
-        ));
-    }
+/// Validates the assets deposited into a pool, returning them aggregated, together with whether
+/// the deposit is a single asset to be swapped into the other assets of the pool before providing
+/// the liquidity.
+pub(crate) fn validate_deposits(
+    pool: &PoolInfo,
+    funds: Vec<Coin>,
+    excess_mode: &ExcessDepositMode,
+) -> Result<(Vec<Coin>, bool), ContractError> {
+    // liquidity is provided through positions on concentrated liquidity pools
+    ensure!(
+        !matches!(pool.pool_type, PoolType::ConcentratedLiquidity { .. }),
+        ContractError::UnsupportedConcentratedLiquidityOperation(
+            "use ManageConcentratedPosition to provide liquidity".to_string()
+        )
+    );
 
-    // Get the pool by the pool_identifier
-    let mut pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
-    let liquidity_token = pool.lp_denom.clone();
-    // Verify that the LP token was sent
-    let amount = cw_utils::must_pay(&info, &liquidity_token)?;
+    let deposits = aggregate_coins(funds)?;
 
-    // Get the total share of the pool
-    let total_share = get_total_share(&deps.as_ref(), liquidity_token.clone())?;
+    ensure!(!deposits.is_empty(), ContractError::EmptyAssets);
 
-    // Get the ratio of the amount to withdraw to the total share
-    let share_ratio: Decimal256 = Decimal256::from_ratio(amount, total_share);
+    // verify that the assets sent match the ones from the pool
+    ensure!(
+        deposits.iter().all(|asset| pool
+            .assets
+            .iter()
+            .any(|pool_asset| pool_asset.denom == asset.denom)),
+        ContractError::AssetMismatch
+    );
 
-    // sanity check, the share_ratio cannot possibly be greater than 1
+    // the excess of a deposit can only be refunded or swapped on constant product pools
     ensure!(
-        share_ratio <= Decimal256::one(),
-        ContractError::InvalidLpShareToWithdraw
+        *excess_mode == ExcessDepositMode::Donate
+            || matches!(pool.pool_type, PoolType::ConstantProduct),
+        ContractError::InvalidExcessDepositMode
     );
 
-    // Use the ratio to calculate the amount of each pool asset to refund
-    let refund_assets: Vec<Coin> = pool
-        .assets
+    // check if the user is providing liquidity with a single asset
+    let is_single_asset_provision = deposits.len() == 1usize;
+
+    if is_single_asset_provision {
+        ensure!(
+            !pool.assets.iter().any(|asset| asset.amount.is_zero()),
+            ContractError::EmptyPoolForSingleSideLiquidityProvision
+        );
+    }
+
+    // single asset deposits into stable swap pools with more than 2 assets mint the LP tokens
+    // directly, charging the imbalance fee, instead of swapping the deposit into the other assets
+    let is_single_side_swap_provision =
+        is_single_asset_provision && !is_single_asset_stableswap_deposit(pool, &deposits);
+
+    if is_single_side_swap_provision {
+        // can't provide single side liquidity on a pool with more than 2 assets, unless it's a
+        // constant product pool, where the deposit is split evenly across the assets
+        ensure!(
+            pool.assets.len() == 2 || matches!(pool.pool_type, PoolType::ConstantProduct),
+            ContractError::InvalidPoolAssetsForSingleSideLiquidityProvision
+        );
+    }
+
+    Ok((deposits, is_single_side_swap_provision))
+}
+
+/// Ensures the sender can lock LP tokens in the farm manager on behalf of the receiver, i.e. it's
+/// the receiver itself or a position operator of the farm manager. Position operators can only
+/// lock LP tokens in new positions, not top up existing ones. The contract itself is allowed too,
+/// as it provides the liquidity of single side provisions, which are checked beforehand.
+fn ensure_can_lock_for_receiver(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    sender: &Addr,
+    receiver: &str,
+    lock_position_identifier: &Option<String>,
+) -> Result<(), ContractError> {
+    if sender.as_str() == receiver || *sender == env.contract.address {
+        return Ok(());
+    }
+
+    let operator_response: IsPositionOperatorResponse = deps.querier.query_wasm_smart(
+        config.farm_manager_addr.to_string(),
+        &amm::farm_manager::QueryMsg::IsPositionOperator {
+            address: sender.to_string(),
+        },
+    )?;
+
+    ensure!(operator_response.is_operator, ContractError::Unauthorized);
+
+    if let Some(position_identifier) = lock_position_identifier {
+        let positions_result: StdResult<PositionsResponse> = deps.querier.query_wasm_smart(
+            config.farm_manager_addr.to_string(),
+            &amm::farm_manager::QueryMsg::Positions {
+                filter_by: Some(PositionsBy::Identifier(position_identifier.clone())),
+                open_state: None,
+                start_after: None,
+                limit: None,
+            },
+        );
+
+        ensure!(
+            positions_result.map_or(true, |positions_response| positions_response
+                .positions
+                .is_empty()),
+            ContractError::Unauthorized
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether the deposit is a single asset deposit into a stable swap pool with more than 2 assets.
+fn is_single_asset_stableswap_deposit(pool: &PoolInfo, deposits: &[Coin]) -> bool {
+    deposits.len() == 1usize
+        && pool.assets.len() > 2
+        && matches!(pool.pool_type, PoolType::StableSwap { .. })
+}
+
+/// Splits a single asset deposit into the portion to swap for each of the other assets in the
+/// pool, i.e. half of it on pools with 2 assets, and the remainder provided as is. Returns both
+/// together with the denoms of the other assets, in the order they are swapped for.
+pub(crate) fn split_single_side_deposit(
+    deposit: &Coin,
+    pool_assets: &[Coin],
+) -> Result<(Coin, Coin, Vec<String>), ContractError> {
+    let ask_asset_denoms: Vec<String> = pool_assets
         .iter()
-        .map(|pool_asset| {
-            Ok(Coin {
-                denom: pool_asset.denom.clone(),
-                amount: Uint128::try_from(
-                    Decimal256::from_ratio(pool_asset.amount, Uint256::one())
-                        .checked_mul(share_ratio)?
-                        .to_uint_floor(),
-                )?,
-            })
-        })
-        .collect::<Result<Vec<Coin>, ContractError>>()?
-        .into_iter()
-        // filter out assets with zero amount
-        .filter(|coin| coin.amount > Uint128::zero())
+        .filter(|pool_asset| pool_asset.denom != deposit.denom)
+        .map(|pool_asset| pool_asset.denom.clone())
         .collect();
 
-    let mut messages: Vec<CosmosMsg> = vec![];
+    let offer_asset_portion = Coin {
+        denom: deposit.denom.clone(),
+        amount: deposit
+            .amount
+            .checked_div_floor((pool_assets.len() as u64, 1u64))?,
+    };
+    let offer_asset_remainder = Coin {
+        denom: deposit.denom.clone(),
+        amount: deposit.amount.checked_sub(
+            offer_asset_portion
+                .amount
+                .checked_mul(Uint128::new(pool_assets.len() as u128 - 1))?,
+        )?,
+    };
 
-    // Transfer the refund assets to the sender
-    messages.push(CosmosMsg::Bank(BankMsg::Send {
-        to_address: info.sender.to_string(),
-        amount: refund_assets.clone(),
-    }));
+    Ok((offer_asset_portion, offer_asset_remainder, ask_asset_denoms))
+}
 
-    // Deduct balances on pool_info by the amount of each refund asset
-    for refund_asset in refund_assets.iter() {
-        let refund_asset_denom = &refund_asset.denom;
-        let pool_asset_index = pool
-            .assets
-            .iter()
-            .position(|pool_asset| &pool_asset.denom == refund_asset_denom)
-            .ok_or(ContractError::AssetMismatch)?;
+/// The outcome of providing liquidity to a pool, computed without touching the state.
+pub(crate) struct LiquidityProvision {
+    /// The LP tokens minted for the deposit.
+    pub share: Uint128,
+    /// Whether it's the first deposit into the pool, which locks [`MINIMUM_LIQUIDITY_AMOUNT`] LP
+    /// tokens in the contract.
+    pub is_initial_deposit: bool,
+    /// The assets added to the pool.
+    pub deposits: Vec<Coin>,
+    /// The excess of the deposit refunded to the sender.
+    pub excess_refund: Vec<Coin>,
+    /// The portion of the excess of the deposit swapped into the other asset, and the swap result.
+    pub excess_swap: Option<(Coin, SwapResult)>,
+    /// The slippage of the deposit, as checked against the slippage tolerance.
+    pub slippage: Decimal256,
+    /// The pool after the liquidity provision.
+    pub pool: PoolInfo,
+}
 
-        pool.assets[pool_asset_index].amount = pool.assets[pool_asset_index]
-            .amount
-            .checked_sub(refund_asset.amount)?;
+/// Computes the liquidity provision of the given deposits into a pool. Both the `ProvideLiquidity`
+/// message and its simulation go through this function, so they can't diverge. Single asset
+/// deposits swapped into the other assets are expected to be swapped beforehand.
+pub(crate) fn compute_liquidity_provision(
+    deps: Deps,
+    env: &Env,
+    mut pool: PoolInfo,
+    mut deposits: Vec<Coin>,
+    slippage_tolerance: &Option<Decimal>,
+    max_spread: Option<Decimal>,
+    excess_mode: &ExcessDepositMode,
+) -> Result<LiquidityProvision, ContractError> {
+    let mut pool_assets = pool.assets.clone();
+
+    // Compute share and other logic based on the number of assets
+    let total_share = get_total_share(&deps, pool.lp_denom.clone())?;
+    let is_initial_deposit = total_share.is_zero();
+
+    // liquidity bootstrapping pools mint LP tokens as the weighted pool they are at the moment,
+    // and oracle pegged pools as the stable swap pool they are at the current oracle price
+    let pool_type = get_effective_pool_type(env, &pool, get_oracle_price(&deps, env, &pool)?)?;
+
+    let mut excess_swap: Option<(Coin, SwapResult)> = None;
+    if *excess_mode == ExcessDepositMode::Swap && !is_initial_deposit {
+        ensure!(
+            pool_assets.len() == 2 && deposits.len() == 2,
+            ContractError::InvalidPoolAssetsLength {
+                expected: 2,
+                actual: deposits.len(),
+            }
+        );
+
+        deposits = sort_deposits_by_pool_assets(&deposits, &pool_assets)?;
+
+        // the asset deposited in excess is the one above the pool ratio
+        let (offer_index, ask_index) = if deposits[0].amount.full_mul(pool_assets[1].amount)
+            > deposits[1].amount.full_mul(pool_assets[0].amount)
+        {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+
+        let swap_amount = compute_excess_deposit_swap_amount(
+            pool_assets[offer_index].amount,
+            pool_assets[ask_index].amount,
+            deposits[offer_index].amount,
+            deposits[ask_index].amount,
+            &pool.pool_fees,
+        )?;
+
+        if !swap_amount.is_zero() {
+            let offer_asset = coin(swap_amount.u128(), &deposits[offer_index].denom);
+            let (swap_result, _) = compute_swap_result(
+                deps,
+                env,
+                pool.clone(),
+                offer_asset.clone(),
+                deposits[ask_index].denom.clone(),
+                None,
+                max_spread,
+            )?;
+
+            deposits[offer_index].amount = deposits[offer_index].amount.checked_sub(swap_amount)?;
+            deposits[ask_index].amount = deposits[ask_index]
+                .amount
+                .checked_add(swap_result.return_asset.amount)?;
+
+            // the swap updated the pool balances
+            pool = swap_result.pool_info.clone();
+            pool_assets = pool.assets.clone();
+
+            excess_swap = Some((offer_asset, swap_result));
+        }
     }
 
-    POOLS.save(deps.storage, &pool_identifier, &pool)?;
+    let share = match &pool_type {
+        // Solidly stable pools mint LP tokens like constant product pools
+        PoolType::ConstantProduct | PoolType::SolidlyStable => {
+            // deposits must include all the assets in the pool, and are sorted in the same
+            // order as the pool assets
+            ensure!(
+                deposits.len() == pool_assets.len(),
+                ContractError::InvalidPoolAssetsLength {
+                    expected: pool_assets.len(),
+                    actual: deposits.len(),
+                }
+            );
 
-    // Burn the LP tokens
-    messages.push(amm::lp_common::burn_lp_asset_msg(
-        liquidity_token,
-        env.contract.address,
-        amount,
-    )?);
-    // update pool info
-    Ok(Response::new()
-        .add_messages(messages)
-        .set_data(to_json_binary(&refund_assets)?)
-        .add_attributes(vec![
-            ("action", "withdraw_liquidity"),
-            ("sender", info.sender.as_str()),
-            ("withdrawn_share", &amount.to_string()),
+            deposits = sort_deposits_by_pool_assets(&deposits, &pool_assets)?;
+
+            if total_share == Uint128::zero() {
+                // Make sure at least MINIMUM_LIQUIDITY_AMOUNT is deposited to mitigate the risk of the first
+                // depositor preventing small liquidity providers from joining the pool
+                let share = Uint128::new(
+                    (U256::from(deposits[0].amount.u128())
+                        .checked_mul(U256::from(deposits[1].amount.u128()))
+                        .ok_or::<ContractError>(ContractError::LiquidityShareComputationFailed))?
+                    .integer_sqrt()
+                    .as_u128(),
+                )
+                .saturating_sub(MINIMUM_LIQUIDITY_AMOUNT);
+
+                // share should be above zero after subtracting the MINIMUM_LIQUIDITY_AMOUNT
+                if share.is_zero() {
+                    return Err(ContractError::InvalidInitialLiquidityAmount(
+                        MINIMUM_LIQUIDITY_AMOUNT,
+                    ));
+                }
+
+                share
+            } else {
+                let mut asset_shares = vec![];
+
+                for deposit in deposits.iter() {
+                    let asset_denom = &deposit.denom;
+                    let pool_asset_index = pool_assets
+                        .iter()
+                        .position(|pool_asset| &pool_asset.denom == asset_denom)
+                        .ok_or(ContractError::AssetMismatch)?;
+
+                    asset_shares.push(
+                        deposit
+                            .amount
+                            .multiply_ratio(total_share, pool_assets[pool_asset_index].amount),
+                    );
+                }
+
+                asset_shares
+                    .into_iter()
+                    .min()
+                    .ok_or(ContractError::LiquidityShareComputationFailed)?
+            }
+        }
+        PoolType::StableSwap {
+            amp: amp_factor, ..
+        } => {
+            // the invariant is computed on the balances scaled by the exchange rates of the assets
+            let rates = get_asset_rates(&deps, env, &pool_type, &pool.asset_denoms)?;
+            let scaled_deposits = scale_by_rates(&deposits, &pool.asset_denoms, &rates)?;
+
+            if total_share == Uint128::zero() {
+                // Make sure at least MINIMUM_LIQUIDITY_AMOUNT is deposited to mitigate the risk of the first
+                // depositor preventing small liquidity providers from joining the pool
+                let share = Uint128::try_from(compute_d(amp_factor, &scaled_deposits).unwrap())?
+                    .saturating_sub(MINIMUM_LIQUIDITY_AMOUNT);
+
+                // share should be above zero after subtracting the min_lp_token_amount
+                if share.is_zero() {
+                    return Err(ContractError::InvalidInitialLiquidityAmount(
+                        MINIMUM_LIQUIDITY_AMOUNT,
+                    ));
+                }
+
+                share
+            } else if is_single_asset_stableswap_deposit(&pool, &deposits) {
+                // the pool is added the deposit below, so it's computed on a copy
+                add_liquidity_one_coin(&deps, env, &mut pool.clone(), &deposits[0])?
+            } else {
+                let scaled_pool_assets = scale_by_rates(&pool_assets, &pool.asset_denoms, &rates)?;
+
+                compute_lp_mint_amount_for_stableswap_deposit(
+                    amp_factor,
+                    // pool_assets hold the balances before the deposit was made
+                    &scaled_pool_assets,
+                    // add the deposit to the pool_assets to calculate the new balances
+                    &add_coins(scaled_pool_assets.clone(), scaled_deposits)?,
+                    total_share,
+                )?
+                .ok_or(ContractError::StableLpMintError)?
+            }
+        }
+        PoolType::Weighted { weights } => {
+            // deposits must include all the assets in the pool, and are sorted in the same
+            // order as the pool assets
+            ensure!(
+                deposits.len() == pool_assets.len(),
+                ContractError::InvalidPoolAssetsLength {
+                    expected: pool_assets.len(),
+                    actual: deposits.len(),
+                }
+            );
+
+            deposits = sort_deposits_by_pool_assets(&deposits, &pool_assets)?;
+
+            if total_share == Uint128::zero() {
+                // Make sure at least MINIMUM_LIQUIDITY_AMOUNT is deposited to mitigate the risk of the first
+                // depositor preventing small liquidity providers from joining the pool
+                let share = compute_weighted_invariant(weights, &deposits)?
+                    .saturating_sub(MINIMUM_LIQUIDITY_AMOUNT);
+
+                // share should be above zero after subtracting the MINIMUM_LIQUIDITY_AMOUNT
+                if share.is_zero() {
+                    return Err(ContractError::InvalidInitialLiquidityAmount(
+                        MINIMUM_LIQUIDITY_AMOUNT,
+                    ));
+                }
+
+                share
+            } else {
+                // the share is the smallest proportional share across all the deposited assets,
+                // the excess of the other assets is added to the pool
+                deposits
+                    .iter()
+                    .zip(pool_assets.iter())
+                    .map(|(deposit, pool_asset)| {
+                        deposit
+                            .amount
+                            .multiply_ratio(total_share, pool_asset.amount)
+                    })
+                    .min()
+                    .ok_or(ContractError::LiquidityShareComputationFailed)?
+            }
+        }
+        PoolType::LiquidityBootstrapping { .. } => {
+            return Err(ContractError::UnresolvedPoolWeights);
+        }
+        PoolType::OraclePegged { .. } => {
+            return Err(ContractError::UnresolvedOraclePrice);
+        }
+        PoolType::ConcentratedLiquidity { .. } => {
+            return Err(ContractError::UnsupportedConcentratedLiquidityOperation(
+                "use ManageConcentratedPosition to provide liquidity".to_string(),
+            ));
+        }
+    };
+
+    // only the part of the deposit matching the pool ratio is added to the pool, the rest is
+    // refunded to the sender
+    let mut excess_refund: Vec<Coin> = vec![];
+    if *excess_mode != ExcessDepositMode::Donate && !is_initial_deposit {
+        for deposit in deposits.iter_mut() {
+            let pool_asset = pool_assets
+                .iter()
+                .find(|pool_asset| pool_asset.denom == deposit.denom)
+                .ok_or(ContractError::AssetMismatch)?;
+
+            // round up in favor of the pool
+            let used_amount = share
+                .checked_mul_ceil((pool_asset.amount, total_share))?
+                .min(deposit.amount);
+            let refund_amount = deposit.amount.checked_sub(used_amount)?;
+
+            if !refund_amount.is_zero() {
+                excess_refund.push(coin(refund_amount.u128(), &deposit.denom));
+            }
+
+            deposit.amount = used_amount;
+        }
+    }
+
+    // assert slippage tolerance
+    helpers::assert_slippage_tolerance(
+        slippage_tolerance,
+        &deposits,
+        &pool_assets,
+        pool_type.clone(),
+        share,
+        total_share,
+    )?;
+
+    let slippage =
+        compute_deposit_slippage(&deposits, &pool_assets, &pool_type, share, total_share)?;
+
+    // Increment the pool asset amount by the amount sent
+    for asset in deposits.iter() {
+        let asset_denom = &asset.denom;
+        let pool_asset_index = pool_assets
+            .iter()
+            .position(|pool_asset| &pool_asset.denom == asset_denom)
+            .ok_or(ContractError::AssetMismatch)?;
+
+        pool_assets[pool_asset_index].amount = pool_assets[pool_asset_index]
+            .amount
+            .checked_add(asset.amount)?;
+    }
+
+    pool.assets = pool_assets;
+
+    Ok(LiquidityProvision {
+        share,
+        is_initial_deposit,
+        deposits,
+        excess_refund,
+        excess_swap,
+        slippage,
+        pool,
+    })
+}
+
+/// Sorts the deposits in the same order as the pool assets, failing if any pool asset wasn't
+/// deposited.
+fn sort_deposits_by_pool_assets(
+    deposits: &[Coin],
+    pool_assets: &[Coin],
+) -> Result<Vec<Coin>, ContractError> {
+    pool_assets
+        .iter()
+        .map(|pool_asset| {
+            deposits
+                .iter()
+                .find(|deposit| deposit.denom == pool_asset.denom)
+                .cloned()
+                .ok_or(ContractError::AssetMismatch)
+        })
+        .collect()
+}
+
+/// Simulates the swap of the offer asset portion of a single side liquidity provision for the
+/// given ask asset. Returns the expected ask asset balance in the contract after the swap, the
+/// expected ask asset, and the swap message, which replies to the contract once done.
+pub(crate) fn simulate_single_side_liquidity_provision_swap(
+    deps: Deps,
+    env: &Env,
+    offer_asset_portion: &Coin,
+    ask_asset_denom: String,
+    liquidity_provision_data: &LiquidityProvisionData,
+) -> Result<(Coin, Coin, SubMsg), ContractError> {
+    let swap_simulation_response = query_simulation(
+        deps,
+        env,
+        offer_asset_portion.clone(),
+        ask_asset_denom.clone(),
+        liquidity_provision_data.pool_identifier.clone(),
+    )?;
+
+    // let's compute the expected ask asset balance in the contract after the swap and liquidity
+    // provision takes place. It should be the current balance minus the fees that will be sent
+    // off the contract.
+    let mut expected_ask_asset_balance_in_contract = deps
+        .querier
+        .query_balance(&env.contract.address, ask_asset_denom.clone())?;
+
+    expected_ask_asset_balance_in_contract.amount = expected_ask_asset_balance_in_contract
+        .amount
+        .saturating_sub(aggregate_outgoing_fees(&swap_simulation_response)?);
+
+    // sanity check. Theoretically, with the given conditions of min LP, pool fees and max spread assertion,
+    // the expected ask asset balance in the contract will always be greater than zero after
+    // subtracting the fees.
+    ensure!(
+        !expected_ask_asset_balance_in_contract.amount.is_zero(),
+        ContractError::MaxSpreadAssertion
+    );
+
+    let swap_msg = SubMsg::reply_on_success(
+        wasm_execute(
+            env.contract.address.to_string(),
+            &ExecuteMsg::Swap {
+                ask_asset_denom: ask_asset_denom.clone(),
+                belief_price: None,
+                max_spread: liquidity_provision_data.max_spread,
+                receiver: None,
+                pool_identifier: liquidity_provision_data.pool_identifier.clone(),
+            },
+            vec![offer_asset_portion.clone()],
+        )?,
+        SINGLE_SIDE_LIQUIDITY_PROVISION_REPLY_ID,
+    );
+
+    Ok((
+        expected_ask_asset_balance_in_contract,
+        coin(
+            swap_simulation_response.return_amount.u128(),
+            ask_asset_denom,
+        ),
+        swap_msg,
+    ))
+}
+
+/// Withdraws the liquidity. The user burns the LP tokens in exchange for the tokens provided, including
+/// the swap fees accrued by its share of the pool. If a `target_denom` is given, the other assets
+/// withdrawn are swapped into it in the same pool, so the user exits in a single asset. The minimum
+/// amount of the target asset to receive is then required, bounding the slippage of the withdrawal
+/// and the swaps combined.
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+    min_assets_out: Option<Vec<Coin>>,
+    receiver: Option<String>,
+    deadline: Option<Timestamp>,
+    target_denom: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the withdraw feature is enabled
+    if !config.feature_toggle.withdrawals_enabled {
+        return Err(ContractError::OperationDisabled(
+            "withdraw_liquidity".to_string(),
+        ));
+    }
+
+    let min_assets_out = aggregate_coins(min_assets_out.unwrap_or_default())?;
+
+    if let Some(target_denom) = &target_denom {
+        // zapping out swaps the withdrawn assets, so swaps must be enabled too
+        ensure!(
+            config.feature_toggle.swaps_enabled,
+            ContractError::OperationDisabled("swap".to_string())
+        );
+
+        ensure!(
+            min_assets_out
+                .iter()
+                .any(|min_asset_out| &min_asset_out.denom == target_denom
+                    && !min_asset_out.amount.is_zero()),
+            ContractError::MissingTargetMinimumOut {
+                target_denom: target_denom.clone(),
+            }
+        );
+    }
+
+    if let Some(deadline) = deadline {
+        ensure!(
+            env.block.time <= deadline,
+            ContractError::DeadlineExceeded {
+                deadline,
+                block_time: env.block.time,
+            }
+        );
+    }
+
+    let receiver = validate_addr_or_default(&deps.as_ref(), receiver, info.sender.clone());
+
+    // Get the pool by the pool_identifier
+    let pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+
+    let liquidity_token = pool.lp_denom.clone();
+    // Verify that the LP token was sent
+    let amount = cw_utils::must_pay(&info, &liquidity_token)?;
+
+    let LiquidityWithdrawal {
+        refund_assets,
+        swaps,
+        pool,
+    } = compute_liquidity_withdrawal(deps.as_ref(), &env, pool, amount, target_denom)?;
+
+    // Make sure the refund assets are not below the minimum amounts to receive
+    for min_asset_out in min_assets_out {
+        ensure!(
+            pool.asset_denoms.contains(&min_asset_out.denom),
+            ContractError::AssetMismatch
+        );
+
+        let amount = refund_assets
+            .iter()
+            .find(|refund_asset| refund_asset.denom == min_asset_out.denom)
+            .map(|refund_asset| refund_asset.amount)
+            .unwrap_or_default();
+
+        ensure!(
+            amount >= min_asset_out.amount,
+            ContractError::WithdrawAmountBelowMinimum {
+                denom: min_asset_out.denom,
+                amount,
+                min_amount: min_asset_out.amount,
+            }
+        );
+    }
+
+    POOLS.save(deps.storage, &pool_identifier, &pool)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    for (_, swap_result) in swaps {
+        // the fees are routed as with a regular swap
+        if !swap_result.burn_fee_asset.amount.is_zero() {
+            messages.push(burn_coin_msg(swap_result.burn_fee_asset));
+        }
+
+        if !swap_result.protocol_fee_asset.amount.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: config.fee_collector_addr.to_string(),
+                amount: vec![swap_result.protocol_fee_asset],
+            }));
+        }
+    }
+
+    // Transfer the refund assets to the receiver
+    messages.push(CosmosMsg::Bank(BankMsg::Send {
+        to_address: receiver.to_string(),
+        amount: refund_assets.clone(),
+    }));
+
+    // Burn the LP tokens
+    messages.extend(burn_lp(deps.storage, &env, &liquidity_token, amount)?);
+    // update pool info
+    Ok(Response::new()
+        .add_messages(messages)
+        .set_data(to_json_binary(&refund_assets)?)
+        .add_attributes(vec![
+            ("action", "withdraw_liquidity"),
+            ("sender", info.sender.as_str()),
+            ("receiver", receiver.as_str()),
+            ("withdrawn_share", &amount.to_string()),
+        ]))
+}
+
+/// Migrates the liquidity of the LP tokens sent from a pool to another. The liquidity is withdrawn
+/// from the pool and the assets, which never leave the contract, are provided to the target pool
+/// through the same paths as `ProvideLiquidity` and `ManageConcentratedPosition`. The withdrawn
+/// assets the target pool doesn't hold are swapped into its assets beforehand, through the swap
+/// operations given for each of them.
+#[allow(clippy::too_many_arguments)]
+pub fn migrate_liquidity(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+    target_pool_identifier: String,
+    slippage_tolerance: Option<Decimal>,
+    max_spread: Option<Decimal>,
+    swap_operations: Option<Vec<Vec<SwapOperation>>>,
+    receiver: Option<String>,
+    unlocking_duration: Option<u64>,
+    lock_position_identifier: Option<String>,
+    excess_mode: Option<ExcessDepositMode>,
+    concentrated_position: Option<ConcentratedPositionRange>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the withdraw feature is enabled, the deposit one is checked when providing
+    ensure!(
+        config.feature_toggle.withdrawals_enabled,
+        ContractError::OperationDisabled("migrate_liquidity".to_string())
+    );
+
+    ensure!(
+        pool_identifier != target_pool_identifier,
+        ContractError::SameMigrationPool
+    );
+
+    let pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    let target_pool = get_pool_by_identifier(&deps.as_ref(), &target_pool_identifier)?;
+
+    let liquidity_token = pool.lp_denom.clone();
+    // Verify that the LP token was sent
+    let amount = cw_utils::must_pay(&info, &liquidity_token)?;
+
+    let LiquidityWithdrawal {
+        refund_assets,
+        pool,
+        ..
+    } = compute_liquidity_withdrawal(deps.as_ref(), &env, pool, amount, None)?;
+
+    POOLS.save(deps.storage, &pool_identifier, &pool)?;
+
+    // Burn the LP tokens
+    let burn_messages = burn_lp(deps.storage, &env, &liquidity_token, amount)?;
+
+    let withdrawn_assets: Vec<Coin> = refund_assets
+        .into_iter()
+        .filter(|asset| !asset.amount.is_zero())
+        .collect();
+
+    // each withdrawn asset the target pool doesn't hold is swapped into one of its assets through
+    // its own swap operations
+    let swap_operations = swap_operations.unwrap_or_default();
+    let swapped_denoms: Vec<&String> = withdrawn_assets
+        .iter()
+        .map(|asset| &asset.denom)
+        .filter(|denom| !target_pool.asset_denoms.contains(denom))
+        .collect();
+
+    for denom in &swapped_denoms {
+        ensure!(
+            swap_operations.iter().any(|operations| operations
+                .first()
+                .is_some_and(|operation| operation.get_input_asset_info() == *denom)),
+            ContractError::MissingMigrationSwapOperations {
+                denom: denom.to_string(),
+            }
+        );
+    }
+
+    ensure!(
+        swap_operations.len() == swapped_denoms.len(),
+        ContractError::InvalidMigrationSwapOperations
+    );
+
+    for operations in &swap_operations {
+        assert_operations(operations.clone())?;
+        ensure!(
+            operations.last().is_some_and(|operation| target_pool
+                .asset_denoms
+                .contains(&operation.get_target_asset_info())),
+            ContractError::InvalidMigrationSwapOperations
+        );
+    }
+
+    if !swap_operations.is_empty() {
+        ensure!(
+            config.feature_toggle.swaps_enabled,
+            ContractError::OperationDisabled("swap".to_string())
+        );
+    }
+
+    let mut provision_assets = vec![];
+    // stores messages for sending fees after the swaps
+    let mut fee_messages: Vec<CosmosMsg> = vec![];
+    // stores swap attributes to add to tx info
+    let mut swap_attributes = vec![];
+
+    for withdrawn_asset in &withdrawn_assets {
+        let Some(operations) = swap_operations.iter().find(|operations| {
+            operations
+                .first()
+                .is_some_and(|operation| operation.get_input_asset_info() == &withdrawn_asset.denom)
+        }) else {
+            provision_assets.push(withdrawn_asset.clone());
+            continue;
+        };
+
+        let mut previous_swap_output = withdrawn_asset.clone();
+
+        for operation in operations {
+            match operation {
+                SwapOperation::MantraSwap {
+                    token_out_denom,
+                    pool_identifier,
+                    ..
+                } => {
+                    let swap_result = perform_swap(
+                        deps.branch(),
+                        &env,
+                        previous_swap_output.clone(),
+                        token_out_denom.clone(),
+                        pool_identifier.clone(),
+                        None,
+                        max_spread,
+                    )?;
+                    swap_attributes.push((
+                        "swap",
+                        format!(
+                            "in={}, out={}, burn_fee={}, protocol_fee={}, swap_fee={}",
+                            previous_swap_output,
+                            swap_result.return_asset,
+                            swap_result.burn_fee_asset,
+                            swap_result.protocol_fee_asset,
+                            swap_result.swap_fee_asset
+                        ),
+                    ));
+
+                    previous_swap_output = swap_result.return_asset;
+
+                    if !swap_result.burn_fee_asset.amount.is_zero() {
+                        fee_messages.push(burn_coin_msg(swap_result.burn_fee_asset));
+                    }
+                    if !swap_result.protocol_fee_asset.amount.is_zero() {
+                        fee_messages.push(CosmosMsg::Bank(BankMsg::Send {
+                            to_address: config.fee_collector_addr.to_string(),
+                            amount: vec![swap_result.protocol_fee_asset],
+                        }));
+                    }
+                }
+            }
+        }
+
+        provision_assets.push(previous_swap_output);
+    }
+
+    // the farm manager migrates the liquidity of positions on behalf of their receivers, so the
+    // receiver provides the liquidity and gets the excess of the deposit refunded
+    let provider = match &receiver {
+        Some(receiver) if info.sender == config.farm_manager_addr => {
+            deps.api.addr_validate(receiver)?
+        }
+        _ => info.sender.clone(),
+    };
+
+    let provision_info = MessageInfo {
+        sender: provider,
+        funds: aggregate_coins(provision_assets)?,
+    };
+
+    let provision_response = if matches!(
+        target_pool.pool_type,
+        PoolType::ConcentratedLiquidity { .. }
+    ) {
+        ensure!(
+            receiver.is_none()
+                && unlocking_duration.is_none()
+                && lock_position_identifier.is_none(),
+            ContractError::InvalidConcentratedMigration
+        );
+
+        let ConcentratedPositionRange {
+            lower_tick,
+            upper_tick,
+            identifier,
+        } = concentrated_position.ok_or(ContractError::InvalidConcentratedMigration)?;
+
+        manage_concentrated_position(
+            deps,
+            provision_info,
+            ConcentratedPositionAction::Create {
+                pool_identifier: target_pool_identifier.clone(),
+                lower_tick,
+                upper_tick,
+                identifier,
+            },
+        )?
+    } else {
+        ensure!(
+            concentrated_position.is_none(),
+            ContractError::InvalidConcentratedMigration
+        );
+
+        provide_liquidity(
+            deps,
+            env.clone(),
+            provision_info,
+            slippage_tolerance,
+            max_spread,
+            receiver,
+            target_pool_identifier.clone(),
+            unlocking_duration,
+            lock_position_identifier,
+            excess_mode,
+        )?
+    };
+
+    Ok(Response::new()
+        .add_messages(burn_messages)
+        .add_messages(fee_messages)
+        .add_submessages(provision_response.messages)
+        .add_attributes(vec![
+            ("action", "migrate_liquidity".to_string()),
+            ("sender", info.sender.to_string()),
+            ("pool_identifier", pool_identifier),
+            ("target_pool_identifier", target_pool_identifier),
+            ("withdrawn_share", amount.to_string()),
+            (
+                "withdrawn_assets",
+                withdrawn_assets
+                    .iter()
+                    .map(|asset| asset.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        ])
+        .add_attributes(swap_attributes)
+        .add_attributes(
+            provision_response
+                .attributes
+                .into_iter()
+                .filter(|attribute| attribute.key != "action" && attribute.key != "sender"),
+        ))
+}
+
+/// The outcome of withdrawing liquidity from a pool, computed without touching the state.
+pub(crate) struct LiquidityWithdrawal {
+    /// The assets returned for the LP tokens.
+    pub refund_assets: Vec<Coin>,
+    /// The withdrawn assets swapped into the target asset, and the swap results, when withdrawing
+    /// into a single asset.
+    pub swaps: Vec<(Coin, SwapResult)>,
+    /// The pool after the withdrawal.
+    pub pool: PoolInfo,
+}
+
+/// Computes the withdrawal of `amount` LP tokens from a pool. Both the `WithdrawLiquidity` message
+/// and its simulation go through this function, so they can't diverge.
+pub(crate) fn compute_liquidity_withdrawal(
+    deps: Deps,
+    env: &Env,
+    mut pool: PoolInfo,
+    amount: Uint128,
+    target_denom: Option<String>,
+) -> Result<LiquidityWithdrawal, ContractError> {
+    // liquidity is withdrawn through positions on concentrated liquidity pools
+    ensure!(
+        !matches!(pool.pool_type, PoolType::ConcentratedLiquidity { .. }),
+        ContractError::UnsupportedConcentratedLiquidityOperation(
+            "use ManageConcentratedPosition to withdraw liquidity".to_string()
+        )
+    );
+
+    if let Some(target_denom) = &target_denom {
+        ensure!(
+            matches!(pool.pool_type, PoolType::ConstantProduct),
+            ContractError::InvalidPoolTypeForZapOut
+        );
+        ensure!(
+            pool.asset_denoms.contains(target_denom),
+            ContractError::AssetMismatch
+        );
+    }
+
+    // Get the total share of the pool
+    let total_share = get_total_share(&deps, pool.lp_denom.clone())?;
+
+    // Get the ratio of the amount to withdraw to the total share
+    let share_ratio: Decimal256 = Decimal256::from_ratio(amount, total_share);
+
+    // sanity check, the share_ratio cannot possibly be greater than 1
+    ensure!(
+        share_ratio <= Decimal256::one(),
+        ContractError::InvalidLpShareToWithdraw
+    );
+
+    // Use the ratio to calculate the amount of each pool asset to refund
+    let mut refund_assets: Vec<Coin> = pool
+        .assets
+        .iter()
+        .map(|pool_asset| {
+            Ok(Coin {
+                denom: pool_asset.denom.clone(),
+                amount: Uint128::try_from(
+                    Decimal256::from_ratio(pool_asset.amount, Uint256::one())
+                        .checked_mul(share_ratio)?
+                        .to_uint_floor(),
+                )?,
+            })
+        })
+        .collect::<Result<Vec<Coin>, ContractError>>()?
+        .into_iter()
+        // filter out assets with zero amount
+        .filter(|coin| coin.amount > Uint128::zero())
+        .collect();
+
+    // Deduct balances on pool_info by the amount of each refund asset
+    for refund_asset in refund_assets.iter() {
+        let refund_asset_denom = &refund_asset.denom;
+        let pool_asset_index = pool
+            .assets
+            .iter()
+            .position(|pool_asset| &pool_asset.denom == refund_asset_denom)
+            .ok_or(ContractError::AssetMismatch)?;
+
+        pool.assets[pool_asset_index].amount = pool.assets[pool_asset_index]
+            .amount
+            .checked_sub(refund_asset.amount)?;
+    }
+
+    let mut swaps = vec![];
+
+    if let Some(target_denom) = target_denom {
+        let mut target_amount = Uint128::zero();
+
+        for refund_asset in refund_assets {
+            if refund_asset.denom == target_denom {
+                target_amount = target_amount.checked_add(refund_asset.amount)?;
+                continue;
+            }
+
+            // the combined output is bounded by the minimum amount of the target asset to receive,
+            // so the spread of each swap is only capped by the maximum allowed
+            let (swap_result, _) = compute_swap_result(
+                deps,
+                env,
+                pool,
+                refund_asset.clone(),
+                target_denom.clone(),
+                None,
+                Some(Decimal::from_str(MAX_ALLOWED_SLIPPAGE)?),
+            )?;
+
+            target_amount = target_amount.checked_add(swap_result.return_asset.amount)?;
+
+            pool = swap_result.pool_info.clone();
+            swaps.push((refund_asset, swap_result));
+        }
+
+        refund_assets = vec![coin(target_amount.u128(), target_denom)];
+    }
+
+    Ok(LiquidityWithdrawal {
+        refund_assets,
+        swaps,
+        pool,
+    })
+}
+
+/// Withdraws liquidity from a stable swap pool in a single asset. The user burns the LP tokens in
+/// exchange for the given asset, paying an imbalance fee that stays in the pool.
+pub fn withdraw_liquidity_one_coin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+    denom: String,
+    min_amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the withdraw feature is enabled
+    ensure!(
+        config.feature_toggle.withdrawals_enabled,
+        ContractError::OperationDisabled("withdraw_liquidity_one_coin".to_string())
+    );
+
+    // Get the pool by the pool_identifier
+    let mut pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    apply_amp_ramp(deps.storage, &env, &mut pool)?;
+
+    ensure!(
+        matches!(pool.pool_type, PoolType::StableSwap { .. }),
+        ContractError::NotAStableSwapPool
+    );
+
+    let liquidity_token = pool.lp_denom.clone();
+    // Verify that the LP token was sent
+    let amount = cw_utils::must_pay(&info, &liquidity_token)?;
+
+    let (refund_asset, imbalance_fee) =
+        remove_liquidity_one_coin(&deps.as_ref(), &env, &mut pool, amount, &denom)?;
+
+    ensure!(
+        refund_asset.amount >= min_amount,
+        ContractError::WithdrawAmountBelowMinimum {
+            denom,
+            amount: refund_asset.amount,
+            min_amount,
+        }
+    );
+
+    POOLS.save(deps.storage, &pool_identifier, &pool)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    // Transfer the refund asset to the sender
+    if !refund_asset.amount.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![refund_asset.clone()],
+        }));
+    }
+
+    // Burn the LP tokens
+    messages.extend(burn_lp(deps.storage, &env, &liquidity_token, amount)?);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .set_data(to_json_binary(&vec![refund_asset.clone()])?)
+        .add_attributes(vec![
+            ("action", "withdraw_liquidity_one_coin".to_string()),
+            ("sender", info.sender.to_string()),
+            ("withdrawn_share", amount.to_string()),
+            ("refund_asset", refund_asset.to_string()),
+            ("imbalance_fee", imbalance_fee.to_string()),
+        ]))
+}
+
+/// Removes the liquidity of the given amount of LP tokens from a stable swap pool in a single
+/// asset, deducting it from the pool balances without saving the pool. Returns the asset to send
+/// out and the imbalance fee, which stays in the pool. Burning the LP tokens is up to the caller.
+pub(crate) fn remove_liquidity_one_coin(
+    deps: &Deps,
+    env: &Env,
+    pool: &mut PoolInfo,
+    amount: Uint128,
+    denom: &str,
+) -> Result<(Coin, Coin), ContractError> {
+    let amp_factor = match pool.pool_type {
+        PoolType::StableSwap { amp, .. } => amp,
+        _ => return Err(ContractError::NotAStableSwapPool),
+    };
+
+    // Get the total share of the pool
+    let total_share = get_total_share(deps, pool.lp_denom.clone())?;
+
+    // sanity check, can't withdraw more than the total share
+    ensure!(
+        amount <= total_share,
+        ContractError::InvalidLpShareToWithdraw
+    );
+
+    let asset_index = pool
+        .assets
+        .iter()
+        .position(|pool_asset| pool_asset.denom == denom)
+        .ok_or(ContractError::AssetMismatch)?;
+
+    // the math operates on the balances normalized to the same precision and exchange rate
+    let rates = get_asset_rates(deps, env, &pool.pool_type, &pool.asset_denoms)?;
+    let balances = get_stableswap_balances(pool, &rates)?;
+
+    let (withdraw_amount, imbalance_fee) = compute_withdraw_one_coin(
+        &amp_factor,
+        &balances,
+        asset_index,
+        amount,
+        total_share,
+        get_stableswap_imbalance_fee(&pool.pool_fees, pool.assets.len())?,
+    )?;
+
+    let refund_asset = coin(
+        denormalize_stableswap_amount(pool, &rates, asset_index, withdraw_amount)?.u128(),
+        denom,
+    );
+    let imbalance_fee = coin(
+        denormalize_stableswap_amount(pool, &rates, asset_index, imbalance_fee)?.u128(),
+        denom,
+    );
+
+    // Deduct the refund asset from the pool, the imbalance fee stays in the pool
+    pool.assets[asset_index].amount = pool.assets[asset_index]
+        .amount
+        .checked_sub(refund_asset.amount)?;
+
+    Ok((refund_asset, imbalance_fee))
+}
+
+/// Adds the given single asset deposit to a stable swap pool that already has liquidity, without
+/// saving the pool. Returns the amount of LP tokens to mint for the deposit, which is charged the
+/// imbalance fee that stays in the pool. Minting the LP tokens is up to the caller.
+pub(crate) fn add_liquidity_one_coin(
+    deps: &Deps,
+    env: &Env,
+    pool: &mut PoolInfo,
+    deposit: &Coin,
+) -> Result<Uint128, ContractError> {
+    let amp_factor = match pool.pool_type {
+        PoolType::StableSwap { amp, .. } => amp,
+        _ => return Err(ContractError::NotAStableSwapPool),
+    };
+
+    let total_share = get_total_share(deps, pool.lp_denom.clone())?;
+    ensure!(
+        !total_share.is_zero(),
+        ContractError::EmptyPoolForSingleSideLiquidityProvision
+    );
+
+    let asset_index = pool
+        .assets
+        .iter()
+        .position(|pool_asset| pool_asset.denom == deposit.denom)
+        .ok_or(ContractError::AssetMismatch)?;
+
+    // the math operates on the balances normalized to the same precision and exchange rate
+    let rates = get_asset_rates(deps, env, &pool.pool_type, &pool.asset_denoms)?;
+    let old_balances = get_stableswap_balances(pool, &rates)?;
+
+    pool.assets[asset_index].amount = pool.assets[asset_index]
+        .amount
+        .checked_add(deposit.amount)?;
+    let new_balances = get_stableswap_balances(pool, &rates)?;
+
+    compute_deposit_imbalance_mint_amount(
+        &amp_factor,
+        &old_balances,
+        &new_balances,
+        total_share,
+        get_stableswap_imbalance_fee(&pool.pool_fees, pool.assets.len())?,
+    )
+}
+
+/// Withdraws the exact amounts of assets given from a stable swap pool. The user burns the LP
+/// tokens needed under the stable swap invariant, including the imbalance fees, and gets the
+/// remaining LP tokens sent back.
+pub fn withdraw_liquidity_imbalance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+    amounts: Vec<Coin>,
+    max_lp_burn: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the withdraw feature is enabled
+    ensure!(
+        config.feature_toggle.withdrawals_enabled,
+        ContractError::OperationDisabled("withdraw_liquidity_imbalance".to_string())
+    );
+
+    // Get the pool by the pool_identifier
+    let mut pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    apply_amp_ramp(deps.storage, &env, &mut pool)?;
+
+    let amp_factor = match pool.pool_type {
+        PoolType::StableSwap { amp, .. } => amp,
+        _ => return Err(ContractError::NotAStableSwapPool),
+    };
+
+    let amounts: Vec<Coin> = aggregate_coins(amounts)?
+        .into_iter()
+        .filter(|amount| !amount.amount.is_zero())
+        .collect();
+    ensure!(!amounts.is_empty(), ContractError::EmptyAssets);
+
+    let liquidity_token = pool.lp_denom.clone();
+    // Verify that the LP token was sent
+    let lp_sent = cw_utils::must_pay(&info, &liquidity_token)?;
+
+    // Get the total share of the pool
+    let total_share = get_total_share(&deps.as_ref(), liquidity_token.clone())?;
+
+    // the math operates on the balances normalized to the same precision and exchange rate
+    let rates = get_asset_rates(&deps.as_ref(), &env, &pool.pool_type, &pool.asset_denoms)?;
+    let old_balances = get_stableswap_balances(&pool, &rates)?;
+
+    // Deduct the withdrawn assets from the pool, which can't be drained
+    for amount in amounts.iter() {
+        let pool_asset = pool
+            .assets
+            .iter_mut()
+            .find(|pool_asset| pool_asset.denom == amount.denom)
+            .ok_or(ContractError::InvalidWithdrawAmounts)?;
+
+        ensure!(
+            amount.amount < pool_asset.amount,
+            ContractError::InvalidWithdrawAmounts
+        );
+
+        pool_asset.amount = pool_asset.amount.checked_sub(amount.amount)?;
+    }
+
+    let new_balances = get_stableswap_balances(&pool, &rates)?;
+
+    let lp_burn = compute_withdraw_imbalance_burn_amount(
+        &amp_factor,
+        &old_balances,
+        &new_balances,
+        total_share,
+        get_stableswap_imbalance_fee(&pool.pool_fees, pool.assets.len())?,
+    )?;
+
+    ensure!(
+        lp_burn <= max_lp_burn && lp_burn <= lp_sent,
+        ContractError::LpBurnAboveMaximum {
+            lp_burn,
+            max_lp_burn: max_lp_burn.min(lp_sent),
+        }
+    );
+
+    POOLS.save(deps.storage, &pool_identifier, &pool)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
+        to_address: info.sender.to_string(),
+        amount: amounts.clone(),
+    })];
+
+    // Return the LP tokens that were not burned
+    let lp_refund = lp_sent.checked_sub(lp_burn)?;
+    if !lp_refund.is_zero() {
+        messages.extend(transfer_lp(
+            deps.storage,
+            &env,
+            coin(lp_refund.u128(), &liquidity_token),
+            &info.sender,
+        )?);
+    }
+
+    // Burn the LP tokens
+    messages.extend(burn_lp(deps.storage, &env, &liquidity_token, lp_burn)?);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .set_data(to_json_binary(&amounts)?)
+        .add_attributes(vec![
+            ("action", "withdraw_liquidity_imbalance".to_string()),
+            ("sender", info.sender.to_string()),
+            ("withdrawn_share", lp_burn.to_string()),
         ]))
 }
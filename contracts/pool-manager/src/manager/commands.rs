@@ -1,18 +1,24 @@
 use cosmwasm_std::{
-    attr, ensure, Attribute, BankMsg, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response, Uint128,
+    attr, ensure, Attribute, BankMsg, Coin, CosmosMsg, Decimal256, DepsMut, Env, MessageInfo,
+    Response, Uint128, Uint256,
 };
 
 use amm::coin::is_factory_token;
 use amm::constants::LP_SYMBOL;
 use amm::fee::PoolFee;
-use amm::pool_manager::{PoolInfo, PoolType};
+use amm::lp_common::internal_lp_denom;
+use amm::pool_manager::{LpTokenMode, PoolInfo, PoolType, RateProvider};
 use amm::tokenfactory::utils::get_factory_denom_creation_fee;
 
+use crate::concentrated_liquidity::math::tick_at_sqrt_price;
 use crate::helpers::{
-    validate_fees_are_paid, validate_no_additional_funds_sent_with_pool_creation,
-    validate_pool_identifier,
+    get_asset_rates, get_base_pool, query_oracle_price, validate_fees_are_paid,
+    validate_no_additional_funds_sent_with_pool_creation, validate_pool_identifier,
+    validate_pool_type,
+};
+use crate::state::{
+    get_pool_by_identifier, ConcentratedLiquidityState, CONCENTRATED_LIQUIDITY_STATES, POOL_COUNTER,
 };
-use crate::state::{get_pool_by_identifier, POOL_COUNTER};
 use crate::{
     state::{Config, CONFIG, POOLS},
     ContractError,
@@ -101,12 +107,14 @@ pub fn create_pool(
         }
     );
 
+    // pools accounting for their LP shares internally don't create a token factory denom
+    let denom_creation_fee = match config.lp_token_mode {
+        LpTokenMode::TokenFactory => get_factory_denom_creation_fee(deps.as_ref())?,
+        LpTokenMode::Internal => vec![],
+    };
+
     // check if the pool and token factory fees were paid
-    let total_fees = validate_fees_are_paid(
-        &config.pool_creation_fee,
-        get_factory_denom_creation_fee(deps.as_ref())?,
-        &info,
-    )?;
+    let total_fees = validate_fees_are_paid(&config.pool_creation_fee, denom_creation_fee, &info)?;
 
     // make sure the user doesn't accidentally send more tokens than needed
     validate_no_additional_funds_sent_with_pool_creation(&info, total_fees)?;
@@ -135,6 +143,80 @@ pub fn create_pool(
     // Verify pool fees
     pool_fees.is_valid()?;
 
+    // Verify the pool type parameters
+    validate_pool_type(&pool_type, asset_denoms.len())?;
+
+    // Make sure the weight schedule of a liquidity bootstrapping pool isn't over already
+    if let PoolType::LiquidityBootstrapping { end_time, .. } = &pool_type {
+        ensure!(
+            *end_time > env.block.time.seconds(),
+            ContractError::InvalidWeightSchedule
+        );
+    }
+
+    // Make sure the rate provider contracts, if any, are valid and return a valid exchange rate
+    if let PoolType::StableSwap {
+        rate_providers: Some(rate_providers),
+        ..
+    } = &pool_type
+    {
+        for rate_provider in rate_providers {
+            if let RateProvider::Contract { contract_addr } = rate_provider {
+                deps.api.addr_validate(contract_addr)?;
+            }
+        }
+
+        get_asset_rates(&deps.as_ref(), &env, &pool_type, &asset_denoms)?;
+    }
+
+    // Make sure the oracle of an oracle pegged pool is valid and returns a fresh price
+    if let PoolType::OraclePegged {
+        oracle_addr,
+        max_staleness,
+        ..
+    } = &pool_type
+    {
+        deps.api.addr_validate(oracle_addr)?;
+        query_oracle_price(
+            &deps.as_ref(),
+            &env,
+            oracle_addr,
+            &asset_denoms,
+            *max_staleness,
+        )?;
+    }
+
+    // Make sure a metapool, i.e. a stable swap pool paired against the LP token of another pool,
+    // pairs a single asset against the LP token of a stable swap pool that isn't a metapool itself
+    if let PoolType::StableSwap { .. } = &pool_type {
+        let base_pools = asset_denoms
+            .iter()
+            .map(|denom| get_base_pool(&deps.as_ref(), denom))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if base_pools.iter().any(Option::is_some) {
+            ensure!(
+                asset_denoms.len() == 2 && base_pools[0].is_none(),
+                ContractError::InvalidMetapool
+            );
+            let is_valid_base_pool = match &base_pools[1] {
+                Some(base_pool) => {
+                    matches!(base_pool.pool_type, PoolType::StableSwap { .. })
+                        && is_factory_token(&base_pool.lp_denom)
+                        && base_pool
+                            .asset_denoms
+                            .iter()
+                            .map(|denom| get_base_pool(&deps.as_ref(), denom))
+                            .collect::<Result<Vec<_>, _>>()?
+                            .iter()
+                            .all(Option::is_none)
+                }
+                None => false,
+            };
+            ensure!(is_valid_base_pool, ContractError::InvalidMetapool);
+        }
+    }
+
     let identifier = if let Some(id) = pool_identifier {
         format!("{EXPLICIT_POOL_ID_PREFIX}{id}")
     } else {
@@ -174,15 +256,30 @@ pub fn create_pool(
         .collect::<Vec<_>>();
 
     let lp_symbol = format!("{identifier}.{LP_SYMBOL}");
-    let lp_asset = format!("{}/{}/{}", "factory", env.contract.address, lp_symbol);
+    let lp_asset = match config.lp_token_mode {
+        LpTokenMode::TokenFactory => {
+            let lp_asset = format!("{}/{}/{}", "factory", env.contract.address, lp_symbol);
 
-    // sanity check for LP asset
-    ensure!(
-        is_factory_token(&lp_asset),
-        ContractError::InvalidLpAsset {
-            lp_asset: lp_asset.clone()
+            // sanity check for LP asset
+            ensure!(
+                is_factory_token(&lp_asset),
+                ContractError::InvalidLpAsset {
+                    lp_asset: lp_asset.clone()
+                }
+            );
+
+            // create the LP denom with the token factory
+            messages.push(amm::tokenfactory::create_denom::create_denom(
+                env.contract.address.clone(),
+                lp_symbol,
+            ));
+
+            lp_asset
         }
-    );
+        LpTokenMode::Internal => internal_lp_denom(&env.contract.address, &identifier),
+    };
+
+    let asset_denoms_len = asset_denoms.len();
 
     #[allow(clippy::redundant_clone)]
     POOLS.save(
@@ -199,12 +296,24 @@ pub fn create_pool(
         },
     )?;
 
-    attributes.push(attr("lp_asset", lp_asset));
+    // initialize the state of the concentrated liquidity pool at the initial price
+    if let PoolType::ConcentratedLiquidity { initial_price, .. } = &pool_type {
+        let sqrt_price = Decimal256::from(*initial_price).sqrt();
 
-    messages.push(amm::tokenfactory::create_denom::create_denom(
-        env.contract.address,
-        lp_symbol,
-    ));
+        CONCENTRATED_LIQUIDITY_STATES.save(
+            deps.storage,
+            &identifier,
+            &ConcentratedLiquidityState {
+                sqrt_price,
+                tick: tick_at_sqrt_price(sqrt_price)?,
+                liquidity: Uint128::zero(),
+                fee_growth_global: vec![Uint256::zero(); asset_denoms_len],
+                fees_held: vec![Uint128::zero(); asset_denoms_len],
+            },
+        )?;
+    }
+
+    attributes.push(attr("lp_asset", lp_asset));
 
     attributes.push(attr("action", "create_pool"));
     attributes.push(attr("pool_identifier", identifier.as_str()));
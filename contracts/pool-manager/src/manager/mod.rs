@@ -1,4 +1,8 @@
 pub mod commands;
 
+mod ramp_amp;
+mod skim;
 mod update_config;
+pub use ramp_amp::{ramp_amp, stop_ramp_amp, MAX_AMP, MAX_AMP_CHANGE, MIN_RAMP_TIME};
+pub use skim::skim;
 pub use update_config::update_config;
@@ -0,0 +1,136 @@
+use amm::pool_manager::PoolType;
+use cosmwasm_std::{ensure, DepsMut, Env, MessageInfo, Response};
+
+use crate::helpers::apply_amp_ramp;
+use crate::state::{get_pool_by_identifier, AmpRamp, AMP_RAMPS, POOLS};
+use crate::ContractError;
+
+/// The maximum amplification of a stable swap pool.
+pub const MAX_AMP: u64 = 1_000_000;
+/// The maximum factor the amplification can change by in a single ramp.
+pub const MAX_AMP_CHANGE: u64 = 10;
+/// The minimum duration of a ramp, which is also the minimum time between ramps.
+pub const MIN_RAMP_TIME: u64 = 86_400;
+
+/// Ramps the amplification of a stable swap pool from its current value to `target_amp` by
+/// `end_time`. Only the owner can ramp the amplification.
+pub fn ramp_amp(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+    target_amp: u64,
+    end_time: u64,
+) -> Result<Response, ContractError> {
+    // permission check
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let now = env.block.time.seconds();
+
+    if let Some(ramp) = AMP_RAMPS.may_load(deps.storage, &pool_identifier)? {
+        ensure!(
+            now >= ramp.initial_time.saturating_add(MIN_RAMP_TIME),
+            ContractError::AmpRampTooSoon {
+                min_ramp_time: MIN_RAMP_TIME
+            }
+        );
+    }
+
+    ensure!(
+        end_time >= now.saturating_add(MIN_RAMP_TIME),
+        ContractError::InvalidAmpRampEndTime {
+            min_ramp_time: MIN_RAMP_TIME
+        }
+    );
+
+    ensure!(
+        target_amp > 0 && target_amp < MAX_AMP,
+        ContractError::InvalidTargetAmp {
+            target_amp,
+            max_amp: MAX_AMP
+        }
+    );
+
+    let current_amp = update_pool_amp(&mut deps, &env, &pool_identifier)?;
+
+    ensure!(
+        target_amp <= current_amp.saturating_mul(MAX_AMP_CHANGE)
+            && target_amp.saturating_mul(MAX_AMP_CHANGE) >= current_amp,
+        ContractError::AmpChangeTooLarge {
+            current_amp,
+            target_amp,
+            max_amp_change: MAX_AMP_CHANGE,
+        }
+    );
+
+    AMP_RAMPS.save(
+        deps.storage,
+        &pool_identifier,
+        &AmpRamp {
+            initial_amp: current_amp,
+            initial_time: now,
+            target_amp,
+            end_time,
+        },
+    )?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "ramp_amp".to_string()),
+        ("pool_identifier", pool_identifier),
+        ("initial_amp", current_amp.to_string()),
+        ("target_amp", target_amp.to_string()),
+        ("end_time", end_time.to_string()),
+    ]))
+}
+
+/// Stops the amplification ramp of a stable swap pool, freezing the amplification at its current
+/// value. Only the owner can stop a ramp.
+pub fn stop_ramp_amp(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+) -> Result<Response, ContractError> {
+    // permission check
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let now = env.block.time.seconds();
+    let current_amp = update_pool_amp(&mut deps, &env, &pool_identifier)?;
+
+    // the stopped ramp is kept so the time between ramps is still enforced
+    AMP_RAMPS.save(
+        deps.storage,
+        &pool_identifier,
+        &AmpRamp {
+            initial_amp: current_amp,
+            initial_time: now,
+            target_amp: current_amp,
+            end_time: now,
+        },
+    )?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "stop_ramp_amp".to_string()),
+        ("pool_identifier", pool_identifier),
+        ("amp", current_amp.to_string()),
+    ]))
+}
+
+/// Updates the amplification of the given stable swap pool to its current value, returning it.
+fn update_pool_amp(
+    deps: &mut DepsMut,
+    env: &Env,
+    pool_identifier: &str,
+) -> Result<u64, ContractError> {
+    let mut pool = get_pool_by_identifier(&deps.as_ref(), pool_identifier)?;
+    apply_amp_ramp(deps.storage, env, &mut pool)?;
+
+    let current_amp = match pool.pool_type {
+        PoolType::StableSwap { amp, .. } => amp,
+        _ => return Err(ContractError::NotAStableSwapPool),
+    };
+
+    POOLS.save(deps.storage, pool_identifier, &pool)?;
+
+    Ok(current_amp)
+}
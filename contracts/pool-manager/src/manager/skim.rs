@@ -0,0 +1,51 @@
+use cosmwasm_std::{BankMsg, Coin, DepsMut, Env, MessageInfo, Response};
+
+use crate::helpers::get_reserve_health;
+use crate::state::get_pool_by_identifier;
+use crate::ContractError;
+
+/// Sends the balances of the assets of a pool the contract holds beyond what the pools account for
+/// to the `recipient`. As the pools can share assets, the surplus of each asset is computed against
+/// the reserves of all the pools. Only the owner can skim.
+pub fn skim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    // permission check
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+
+    let skimmed: Vec<Coin> = get_reserve_health(deps.as_ref(), &env, pool.asset_denoms)?
+        .into_iter()
+        .filter(|reserve| !reserve.surplus.is_zero())
+        .map(|reserve| Coin::new(reserve.surplus, reserve.denom))
+        .collect();
+
+    if skimmed.is_empty() {
+        return Err(ContractError::NothingToSkim { pool_identifier });
+    }
+
+    Ok(Response::default()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: skimmed.clone(),
+        })
+        .add_attributes(vec![
+            ("action", "skim".to_string()),
+            ("pool_identifier", pool_identifier),
+            ("recipient", recipient.to_string()),
+            (
+                "skimmed",
+                skimmed
+                    .iter()
+                    .map(|coin| coin.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        ]))
+}
@@ -1,7 +1,18 @@
-use cosmwasm_std::{Decimal256, Uint256};
+use cosmwasm_std::{Decimal256, Uint128, Uint256};
 
 use crate::error::ContractError;
 
+/// ln(2) with 18 decimal places.
+const LN_2: Decimal256 = Decimal256::raw(693_147_180_559_945_309u128);
+
+/// The maximum relative error of [Decimal256Helper::checked_pow_up] and
+/// [Decimal256Helper::checked_pow_down], used to round the result of a fractional power in favor of
+/// the pool.
+const MAX_POW_RELATIVE_ERROR: Decimal256 = Decimal256::raw(10_000u128);
+
+/// The maximum amount of terms to compute when approximating the series used by `ln` and `exp`.
+const MAX_SERIES_TERMS: u32 = 128;
+
 pub trait Decimal256Helper {
     fn decimal_with_precision(
         value: impl Into<Uint256>,
@@ -15,6 +26,12 @@ pub trait Decimal256Helper {
     ) -> Result<Decimal256, ContractError>;
 
     fn to_uint256_with_precision(&self, precision: u32) -> Result<Uint256, ContractError>;
+
+    /// Raises the decimal to a fractional power, rounding the result up.
+    fn checked_pow_up(&self, exponent: Decimal256) -> Result<Decimal256, ContractError>;
+
+    /// Raises the decimal to a fractional power, rounding the result down.
+    fn checked_pow_down(&self, exponent: Decimal256) -> Result<Decimal256, ContractError>;
 }
 
 impl Decimal256Helper for Decimal256 {
@@ -42,4 +59,121 @@ impl Decimal256Helper for Decimal256 {
 
         Ok(value.checked_div(10u128.pow(self.decimal_places() - precision).into())?)
     }
+
+    fn checked_pow_up(&self, exponent: Decimal256) -> Result<Decimal256, ContractError> {
+        let pow = checked_pow(*self, exponent)?;
+        let max_error = pow
+            .checked_mul(MAX_POW_RELATIVE_ERROR)?
+            .checked_add(Decimal256::raw(1u128))?;
+
+        Ok(pow.checked_add(max_error)?)
+    }
+
+    fn checked_pow_down(&self, exponent: Decimal256) -> Result<Decimal256, ContractError> {
+        let pow = checked_pow(*self, exponent)?;
+        let max_error = pow
+            .checked_mul(MAX_POW_RELATIVE_ERROR)?
+            .checked_add(Decimal256::raw(1u128))?;
+
+        Ok(pow.saturating_sub(max_error))
+    }
+}
+
+/// Computes `base ^ exponent` for a fractional exponent as `exp(exponent * ln(base))`.
+fn checked_pow(base: Decimal256, exponent: Decimal256) -> Result<Decimal256, ContractError> {
+    if exponent.is_zero() {
+        return Ok(Decimal256::one());
+    }
+
+    if base.is_zero() {
+        return Ok(Decimal256::zero());
+    }
+
+    let (is_negative, ln_base) = ln(base)?;
+    let power = checked_exp(exponent.checked_mul(ln_base)?)?;
+
+    if is_negative {
+        Ok(Decimal256::one().checked_div(power)?)
+    } else {
+        Ok(power)
+    }
+}
+
+/// Computes the natural logarithm of the given value. As [Decimal256] is unsigned, the sign of the
+/// result is returned separately, `true` meaning the logarithm is negative, i.e. `x < 1`.
+///
+/// The value is split into `x = m * 2^k` with `m` in `[1, 2)`, so that
+/// `ln(x) = ln(m) + k * ln(2)`, where `ln(m)` is computed with the fast converging series
+/// `ln(m) = 2 * (z + z^3 / 3 + z^5 / 5 + ...)` with `z = (m - 1) / (m + 1)`.
+fn ln(x: Decimal256) -> Result<(bool, Decimal256), ContractError> {
+    let one = Decimal256::one().atomics();
+    let two = one.checked_add(one)?;
+
+    let mut mantissa = x.atomics();
+    let mut k = 0u32;
+    let is_negative = mantissa < one;
+
+    if is_negative {
+        while mantissa < one {
+            mantissa = mantissa.checked_shl(1)?;
+            k += 1;
+        }
+    } else {
+        while mantissa >= two {
+            mantissa = mantissa.checked_shr(1)?;
+            k += 1;
+        }
+    }
+
+    let mantissa = Decimal256::new(mantissa);
+    let z = (mantissa.checked_sub(Decimal256::one())?)
+        .checked_div(mantissa.checked_add(Decimal256::one())?)?;
+    let z_squared = z.checked_mul(z)?;
+
+    let mut term = z;
+    let mut series = Decimal256::zero();
+    let mut n = 1u32;
+    while !term.is_zero() && n < MAX_SERIES_TERMS {
+        series = series.checked_add(term.checked_div(Decimal256::from_ratio(n, 1u8))?)?;
+        term = term.checked_mul(z_squared)?;
+        n += 2;
+    }
+
+    let ln_mantissa = series.checked_add(series)?;
+    let ln_two_k = LN_2.checked_mul(Decimal256::from_ratio(k, 1u8))?;
+
+    if is_negative {
+        // ln(x) = ln(m) - k * ln(2), which is negative as x < 1
+        Ok((true, ln_two_k.checked_sub(ln_mantissa)?))
+    } else {
+        Ok((false, ln_mantissa.checked_add(ln_two_k)?))
+    }
+}
+
+/// Computes `e^y`. The exponent is split into `y = k * ln(2) + r` with `r` in `[0, ln(2))`, so that
+/// `e^y = 2^k * e^r`, where `e^r` is computed with its Taylor series.
+fn checked_exp(y: Decimal256) -> Result<Decimal256, ContractError> {
+    let k = y.checked_div(LN_2)?.to_uint_floor();
+    let r = y.checked_sub(LN_2.checked_mul(Decimal256::from_ratio(k, 1u8))?)?;
+
+    let mut term = Decimal256::one();
+    let mut series = Decimal256::one();
+    let mut n = 1u32;
+    while !term.is_zero() && n < MAX_SERIES_TERMS {
+        term = term
+            .checked_mul(r)?
+            .checked_div(Decimal256::from_ratio(n, 1u8))?;
+        series = series.checked_add(term)?;
+        n += 1;
+    }
+
+    let k: u32 = Uint128::try_from(k)?
+        .u128()
+        .try_into()
+        .map_err(|_| ContractError::DecimalOverflow)?;
+    let two_k = Uint256::one()
+        .checked_shl(k)
+        .map_err(|_| ContractError::DecimalOverflow)?;
+
+    Ok(Decimal256::new(series.atomics().checked_mul(two_k)?))
 }
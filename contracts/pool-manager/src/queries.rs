@@ -1,20 +1,45 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 
+use amm::coin::aggregate_coins;
+use amm::lp_common::is_internal_lp_denom;
 use amm::pool_manager::{
-    AssetDecimalsResponse, Config, PoolInfoResponse, PoolType, PoolsResponse,
-    ReverseSimulationResponse, SimulateSwapOperationsResponse, SimulationResponse, SwapOperation,
+    AssetDecimalsResponse, BestRouteResponse, ConcentratedPoolStateResponse,
+    ConcentratedPositionsBy, ConcentratedPositionsResponse, Config, ExcessDepositMode,
+    LiquiditySwap, PoolInfo, PoolInfoResponse, PoolType, PoolsResponse, ReserveHealthResponse,
+    ReverseSimulationResponse, ShareAllowanceResponse, ShareBalanceResponse,
+    SimulateProvideLiquidityResponse, SimulateSwapOperationsResponse,
+    SimulateWithdrawLiquidityResponse, SimulationResponse, SwapOperation, SwapRoute,
+    SwapRouteCreatorResponse, SwapRouteResponse, SwapRoutesResponse,
 };
 use cosmwasm_std::{
-    coin, ensure, Coin, Decimal256, Deps, Fraction, Order, StdResult, Uint128, Uint256,
+    coin, ensure, Coin, Decimal, Decimal256, Deps, Env, Fraction, Order, StdResult, Uint128,
+    Uint256,
 };
 use cw_storage_plus::Bound;
 
-use crate::helpers::get_asset_indexes_in_pool;
+use crate::concentrated_liquidity::commands::{get_fee_growth_inside, update_position_fees};
+use crate::concentrated_liquidity::swap::{
+    compute_concentrated_offer_amount, compute_concentrated_swap,
+};
+use crate::helpers::{
+    apply_amp_ramp, assert_oracle_price_deviation, get_asset_indexes_in_pool, get_asset_rate,
+    get_asset_rates, get_effective_pool_type, get_oracle_price, get_reserve_health,
+};
+use crate::liquidity::commands::{
+    compute_liquidity_provision, compute_liquidity_withdrawal, split_single_side_deposit,
+    validate_deposits,
+};
 use crate::math::Decimal256Helper;
-use crate::state::{CONFIG, POOLS};
+use crate::shares::ledger::{get_balance, get_total_share};
+use crate::state::{
+    get_concentrated_position, CONCENTRATED_LIQUIDITY_STATES, CONCENTRATED_POSITIONS, CONFIG,
+    LP_SHARES_ALLOWANCES, POOLS, SWAP_ROUTES,
+};
+use crate::swap::perform_swap::{compute_swap_result, SwapResult};
 use crate::{
     helpers::{self, calculate_stableswap_y, StableSwapDirection},
-    state::get_pool_by_identifier,
+    state::{get_pool_by_identifier, get_swap_operations},
     ContractError,
 };
 
@@ -46,25 +71,49 @@ pub fn query_asset_decimals(
 // Simulate a swap with the provided asset to determine the amount of the other asset that would be received
 pub fn query_simulation(
     deps: Deps,
+    env: &Env,
     offer_asset: Coin,
     ask_asset_denom: String,
     pool_identifier: String,
 ) -> Result<SimulationResponse, ContractError> {
-    let pool_info = get_pool_by_identifier(&deps, &pool_identifier)?;
-
-    let (offer_asset_in_pool, ask_asset_in_pool, _, _, offer_decimal, ask_decimal) =
-        get_asset_indexes_in_pool(&pool_info, offer_asset.denom, ask_asset_denom)?;
-
-    let swap_computation = helpers::compute_swap(
-        Uint256::from(pool_info.assets.len() as u128),
-        offer_asset_in_pool.amount,
-        ask_asset_in_pool.amount,
-        offer_asset.amount,
-        pool_info.pool_fees,
-        &pool_info.pool_type,
+    let mut pool_info = get_pool_by_identifier(&deps, &pool_identifier)?;
+    apply_amp_ramp(deps.storage, env, &mut pool_info)?;
+
+    let (
+        offer_asset_in_pool,
+        ask_asset_in_pool,
+        offer_index,
+        ask_index,
         offer_decimal,
         ask_decimal,
-    )?;
+    ) = get_asset_indexes_in_pool(&pool_info, offer_asset.denom, ask_asset_denom)?;
+
+    // the oracle is queried once, for both the swap and the response
+    let oracle_price = get_oracle_price(&deps, env, &pool_info)?;
+
+    let swap_computation = match pool_info.pool_type {
+        PoolType::ConcentratedLiquidity { .. } => {
+            compute_concentrated_swap(deps.storage, &pool_info, offer_index, offer_asset.amount)?.0
+        }
+        _ => {
+            let pool_type = get_effective_pool_type(env, &pool_info, oracle_price)?;
+            helpers::compute_swap(
+                Uint256::from(pool_info.assets.len() as u128),
+                offer_asset_in_pool.amount,
+                ask_asset_in_pool.amount,
+                offer_asset.amount,
+                pool_info.pool_fees.clone(),
+                &pool_type,
+                offer_decimal,
+                ask_decimal,
+                offer_index,
+                ask_index,
+                &get_asset_rates(&deps, env, &pool_type, &pool_info.asset_denoms)?,
+            )?
+        }
+    };
+
+    assert_oracle_price_deviation(&pool_info.pool_type, &swap_computation)?;
 
     Ok(SimulationResponse {
         return_amount: swap_computation.return_amount,
@@ -73,6 +122,7 @@ pub fn query_simulation(
         protocol_fee_amount: swap_computation.protocol_fee_amount,
         burn_fee_amount: swap_computation.burn_fee_amount,
         extra_fees_amount: swap_computation.extra_fees_amount,
+        oracle_price,
     })
 }
 
@@ -80,24 +130,39 @@ pub fn query_simulation(
 /// the number of target tokens.
 pub fn query_reverse_simulation(
     deps: Deps,
+    env: &Env,
     ask_asset: Coin,
     offer_asset_denom: String,
     pool_identifier: String,
 ) -> Result<ReverseSimulationResponse, ContractError> {
-    let pool_info = get_pool_by_identifier(&deps, &pool_identifier)?;
-
-    let (offer_asset_in_pool, ask_asset_in_pool, _, _, offer_decimal, ask_decimal) =
-        get_asset_indexes_in_pool(&pool_info, offer_asset_denom, ask_asset.denom)?;
+    let mut pool_info = get_pool_by_identifier(&deps, &pool_identifier)?;
+    apply_amp_ramp(deps.storage, env, &mut pool_info)?;
+
+    let (
+        offer_asset_in_pool,
+        ask_asset_in_pool,
+        offer_index,
+        ask_index,
+        offer_decimal,
+        ask_decimal,
+    ) = get_asset_indexes_in_pool(&pool_info, offer_asset_denom, ask_asset.denom)?;
 
-    let pool_fees = pool_info.pool_fees;
+    let pool_fees = pool_info.pool_fees.clone();
+    let pool_type =
+        get_effective_pool_type(env, &pool_info, get_oracle_price(&deps, env, &pool_info)?)?;
 
-    match pool_info.pool_type {
-        PoolType::ConstantProduct => {
+    match pool_type {
+        PoolType::ConstantProduct | PoolType::Weighted { .. } | PoolType::SolidlyStable => {
             let offer_amount_computation = helpers::compute_offer_amount(
                 offer_asset_in_pool.amount,
                 ask_asset_in_pool.amount,
                 ask_asset.amount,
                 pool_fees,
+                &pool_type,
+                offer_decimal,
+                ask_decimal,
+                offer_index,
+                ask_index,
             )?;
 
             Ok(ReverseSimulationResponse {
@@ -108,11 +173,35 @@ pub fn query_reverse_simulation(
                 burn_fee_amount: offer_amount_computation.burn_fee_amount,
             })
         }
-        PoolType::StableSwap { amp } => {
+        PoolType::ConcentratedLiquidity { .. } => {
+            let offer_amount_computation = compute_concentrated_offer_amount(
+                deps.storage,
+                &pool_info,
+                offer_index,
+                ask_asset.amount,
+            )?;
+
+            Ok(ReverseSimulationResponse {
+                offer_amount: offer_amount_computation.offer_amount,
+                spread_amount: offer_amount_computation.spread_amount,
+                swap_fee_amount: offer_amount_computation.swap_fee_amount,
+                protocol_fee_amount: offer_amount_computation.protocol_fee_amount,
+                burn_fee_amount: offer_amount_computation.burn_fee_amount,
+            })
+        }
+        PoolType::StableSwap { amp, .. } => {
+            // the balances are scaled by the exchange rates of the assets, so the math below
+            // operates on amounts pegged 1:1
+            let rates = get_asset_rates(&deps, env, &pool_type, &pool_info.asset_denoms)?;
+            let offer_rate = get_asset_rate(&rates, offer_index);
+            let ask_rate = get_asset_rate(&rates, ask_index);
+
             let offer_pool =
-                Decimal256::decimal_with_precision(offer_asset_in_pool.amount, offer_decimal)?;
+                Decimal256::decimal_with_precision(offer_asset_in_pool.amount, offer_decimal)?
+                    .checked_mul(offer_rate)?;
             let ask_pool =
-                Decimal256::decimal_with_precision(ask_asset_in_pool.amount, ask_decimal)?;
+                Decimal256::decimal_with_precision(ask_asset_in_pool.amount, ask_decimal)?
+                    .checked_mul(ask_rate)?;
 
             let before_fees = (Decimal256::one()
                 .checked_sub(pool_fees.protocol_fee.to_decimal_256())?
@@ -125,8 +214,11 @@ pub fn query_reverse_simulation(
                 ask_decimal,
             )?)?;
 
-            let before_fees_offer = before_fees.to_uint256_with_precision(offer_decimal.into())?;
+            let before_fees_offer = before_fees
+                .checked_multiply_ratio(ask_rate, offer_rate)?
+                .to_uint256_with_precision(offer_decimal.into())?;
             let before_fees_ask = before_fees.to_uint256_with_precision(ask_decimal.into())?;
+            let before_fees = before_fees.checked_mul(ask_rate)?;
 
             let max_precision = offer_decimal.max(ask_decimal);
 
@@ -156,6 +248,12 @@ pub fn query_reverse_simulation(
                 ))?,
             };
 
+            // scale the amount back to the offer asset
+            let offer_amount: Uint128 = Decimal256::from_ratio(offer_amount, Uint128::one())
+                .checked_div(offer_rate)?
+                .to_uint_ceil()
+                .try_into()?;
+
             let spread_amount = offer_amount.saturating_sub(Uint128::try_from(before_fees_offer)?);
             let swap_fee_amount = pool_fees.swap_fee.compute(before_fees_ask)?;
             let protocol_fee_amount = pool_fees.protocol_fee.compute(before_fees_ask)?;
@@ -169,6 +267,10 @@ pub fn query_reverse_simulation(
                 burn_fee_amount: burn_fee_amount.try_into()?,
             })
         }
+        // liquidity bootstrapping and oracle pegged pools were resolved into the weighted and
+        // stable swap pools they are at the moment by get_effective_pool_type
+        PoolType::LiquidityBootstrapping { .. } => Err(ContractError::UnresolvedPoolWeights),
+        PoolType::OraclePegged { .. } => Err(ContractError::UnresolvedOraclePrice),
     }
 }
 
@@ -179,12 +281,13 @@ const DEFAULT_LIMIT: u32 = 10;
 /// Gets the pools in the contract. Returns a [PoolsResponse].
 pub fn get_pools(
     deps: Deps,
+    env: &Env,
     pool_identifier: Option<String>,
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> Result<PoolsResponse, ContractError> {
     let pools = if let Some(pool_identifier) = pool_identifier {
-        vec![get_pool(deps, pool_identifier)?]
+        vec![get_pool(deps, env, pool_identifier)?]
     } else {
         let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
         let start = cw_utils::calc_range_start_string(start_after).map(Bound::ExclusiveRaw);
@@ -193,24 +296,36 @@ pub fn get_pools(
             .range(deps.storage, start, None, Order::Ascending)
             .take(limit)
             .map(|item| {
-                let (_, pool) = item?;
-                let total_share = deps.querier.query_supply(&pool.lp_denom)?;
+                let (_, mut pool) = item?;
+                apply_amp_ramp(deps.storage, env, &mut pool)?;
+                let total_share = coin(
+                    get_total_share(&deps, pool.lp_denom.clone())?.u128(),
+                    &pool.lp_denom,
+                );
 
                 Ok(PoolInfoResponse {
                     pool_info: pool,
                     total_share,
                 })
             })
-            .collect::<StdResult<Vec<PoolInfoResponse>>>()?
+            .collect::<Result<Vec<PoolInfoResponse>, ContractError>>()?
     };
 
     Ok(PoolsResponse { pools })
 }
 
 /// Gets the pool info for a given pool identifier. Returns a [PoolInfoResponse].
-fn get_pool(deps: Deps, pool_identifier: String) -> Result<PoolInfoResponse, ContractError> {
-    let pool_info = POOLS.load(deps.storage, &pool_identifier)?;
-    let total_share = deps.querier.query_supply(&pool_info.lp_denom)?;
+fn get_pool(
+    deps: Deps,
+    env: &Env,
+    pool_identifier: String,
+) -> Result<PoolInfoResponse, ContractError> {
+    let mut pool_info = POOLS.load(deps.storage, &pool_identifier)?;
+    apply_amp_ramp(deps.storage, env, &mut pool_info)?;
+    let total_share = coin(
+        get_total_share(&deps, pool_info.lp_denom.clone())?.u128(),
+        &pool_info.lp_denom,
+    );
 
     Ok(PoolInfoResponse {
         pool_info,
@@ -218,10 +333,91 @@ fn get_pool(deps: Deps, pool_identifier: String) -> Result<PoolInfoResponse, Con
     })
 }
 
+/// Gets the current state of a concentrated liquidity pool.
+pub fn query_concentrated_pool_state(
+    deps: Deps,
+    pool_identifier: String,
+) -> Result<ConcentratedPoolStateResponse, ContractError> {
+    let state = CONCENTRATED_LIQUIDITY_STATES
+        .may_load(deps.storage, &pool_identifier)?
+        .ok_or(ContractError::NotAConcentratedLiquidityPool)?;
+
+    Ok(ConcentratedPoolStateResponse {
+        pool_identifier,
+        price: state.sqrt_price.checked_mul(state.sqrt_price)?,
+        sqrt_price: state.sqrt_price,
+        tick: state.tick,
+        liquidity: state.liquidity,
+    })
+}
+
+/// Gets concentrated liquidity positions, with the fees owed to them up to date.
+pub fn query_concentrated_positions(
+    deps: Deps,
+    filter_by: Option<ConcentratedPositionsBy>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<ConcentratedPositionsResponse, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let positions = match filter_by {
+        Some(ConcentratedPositionsBy::Identifier(identifier)) => {
+            vec![get_concentrated_position(&deps, &identifier)?]
+        }
+        Some(ConcentratedPositionsBy::Owner(owner)) => {
+            let owner = deps.api.addr_validate(&owner)?;
+            CONCENTRATED_POSITIONS
+                .idx
+                .owner
+                .prefix(owner.to_string())
+                .range(
+                    deps.storage,
+                    start_after.map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit)
+                .map(|item| Ok(item?.1))
+                .collect::<StdResult<Vec<_>>>()?
+        }
+        None => CONCENTRATED_POSITIONS
+            .range(
+                deps.storage,
+                start_after.as_deref().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| Ok(item?.1))
+            .collect::<StdResult<Vec<_>>>()?,
+    };
+
+    let positions = positions
+        .into_iter()
+        .map(|mut position| {
+            let state =
+                CONCENTRATED_LIQUIDITY_STATES.load(deps.storage, &position.pool_identifier)?;
+            let fee_growth_inside = get_fee_growth_inside(
+                deps.storage,
+                &position.pool_identifier,
+                position.lower_tick,
+                position.upper_tick,
+                &state,
+            )?;
+            update_position_fees(&mut position, fee_growth_inside)?;
+
+            Ok(position)
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+
+    Ok(ConcentratedPositionsResponse { positions })
+}
+
 /// This function iterates over the swap operations, simulates each swap
 /// to get the final amount after all the swaps.
 pub fn simulate_swap_operations(
     deps: Deps,
+    env: &Env,
     offer_amount: Uint128,
     operations: Vec<SwapOperation>,
 ) -> Result<SimulateSwapOperationsResponse, ContractError> {
@@ -239,6 +435,7 @@ pub fn simulate_swap_operations(
             } => {
                 let res = query_simulation(
                     deps,
+                    env,
                     coin(amount.u128(), token_in_denom),
                     token_out_denom,
                     pool_identifier,
@@ -255,6 +452,7 @@ pub fn simulate_swap_operations(
 /// simulates each swap to get the final amount after all the swaps.
 pub fn reverse_simulate_swap_operations(
     deps: Deps,
+    env: &Env,
     ask_amount: Uint128,
     operations: Vec<SwapOperation>,
 ) -> Result<SimulateSwapOperationsResponse, ContractError> {
@@ -274,6 +472,7 @@ pub fn reverse_simulate_swap_operations(
             } => {
                 let res = query_simulation(
                     deps,
+                    env,
                     coin(amount.u128(), token_out_denom),
                     token_in_denom,
                     pool_identifier,
@@ -285,3 +484,408 @@ pub fn reverse_simulate_swap_operations(
 
     Ok(SimulateSwapOperationsResponse { amount })
 }
+
+/// The default amount of swap operations of the routes searched by the best route query.
+const DEFAULT_ROUTE_MAX_HOPS: u32 = 3;
+/// The maximum amount of swap operations of the routes searched by the best route query.
+const MAX_ROUTE_HOPS: u32 = 4;
+/// The default amount of pools scanned by the best route query.
+const DEFAULT_ROUTE_MAX_POOLS: u32 = 50;
+/// The maximum amount of swaps simulated by the best route query across the whole search.
+const MAX_ROUTE_SIMULATIONS: u32 = 200;
+
+/// Searches the pools for the route swapping the offer asset for the ask asset with the best
+/// simulated output. Routes don't go through the same pool nor back to the same asset twice, and
+/// the hops whose simulation fails, e.g. on an empty pool, are skipped.
+///
+/// Only the first `max_pools` pools by identifier are scanned, and the search fails once it has
+/// simulated [MAX_ROUTE_SIMULATIONS] swaps rather than returning a route that may not be the best.
+pub fn query_best_route(
+    deps: Deps,
+    env: &Env,
+    offer_asset: Coin,
+    ask_asset_denom: String,
+    max_hops: Option<u32>,
+    max_pools: Option<u32>,
+) -> Result<BestRouteResponse, ContractError> {
+    ensure!(
+        offer_asset.denom != ask_asset_denom,
+        ContractError::SameAsset
+    );
+
+    let max_hops = max_hops
+        .unwrap_or(DEFAULT_ROUTE_MAX_HOPS)
+        .min(MAX_ROUTE_HOPS) as usize;
+    let max_pools = max_pools.unwrap_or(DEFAULT_ROUTE_MAX_POOLS).min(MAX_LIMIT) as usize;
+
+    let pools = POOLS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(max_pools)
+        .map(|item| item.map(|(_, pool)| pool))
+        .collect::<StdResult<Vec<PoolInfo>>>()?;
+
+    // index the pools by the denoms they hold, so each hop only goes through the pools it can
+    // swap in
+    let mut pools_by_denom: BTreeMap<&str, Vec<&PoolInfo>> = BTreeMap::new();
+    for pool in &pools {
+        for denom in &pool.asset_denoms {
+            pools_by_denom.entry(denom).or_default().push(pool);
+        }
+    }
+
+    let mut simulations_left = MAX_ROUTE_SIMULATIONS;
+    let mut best_route = None;
+    find_best_route(
+        deps,
+        env,
+        &pools_by_denom,
+        &offer_asset,
+        &ask_asset_denom,
+        max_hops,
+        &mut simulations_left,
+        &mut vec![],
+        &mut best_route,
+    )?;
+
+    let (operations, amount) = best_route.ok_or(ContractError::NoRouteFound {
+        offer_asset_denom: offer_asset.denom,
+        ask_asset_denom,
+    })?;
+
+    Ok(BestRouteResponse { operations, amount })
+}
+
+/// Walks the routes starting with `offer_asset` depth first, keeping the one with the best output
+/// in `best_route`. On ties, the route with the fewest operations is kept. Each simulation uses up
+/// one of the `simulations_left`, and the search fails once they run out.
+#[allow(clippy::too_many_arguments)]
+fn find_best_route(
+    deps: Deps,
+    env: &Env,
+    pools_by_denom: &BTreeMap<&str, Vec<&PoolInfo>>,
+    offer_asset: &Coin,
+    ask_asset_denom: &str,
+    hops_left: usize,
+    simulations_left: &mut u32,
+    route: &mut Vec<SwapOperation>,
+    best_route: &mut Option<(Vec<SwapOperation>, Uint128)>,
+) -> Result<(), ContractError> {
+    if hops_left == 0 {
+        return Ok(());
+    }
+
+    let Some(pools) = pools_by_denom.get(offer_asset.denom.as_str()) else {
+        return Ok(());
+    };
+
+    for pool in pools {
+        if route
+            .iter()
+            .any(|operation| operation.get_pool_identifer() == pool.pool_identifier)
+        {
+            continue;
+        }
+
+        for denom in &pool.asset_denoms {
+            let visited = denom == &offer_asset.denom
+                || route.iter().any(|operation| {
+                    operation.get_input_asset_info() == denom
+                        || &operation.get_target_asset_info() == denom
+                });
+            if visited {
+                continue;
+            }
+
+            ensure!(
+                *simulations_left > 0,
+                ContractError::RouteSearchLimitExceeded {
+                    max_simulations: MAX_ROUTE_SIMULATIONS,
+                }
+            );
+            *simulations_left -= 1;
+
+            let Ok(simulation) = query_simulation(
+                deps,
+                env,
+                offer_asset.clone(),
+                denom.clone(),
+                pool.pool_identifier.clone(),
+            ) else {
+                continue;
+            };
+
+            if simulation.return_amount.is_zero() {
+                continue;
+            }
+
+            route.push(SwapOperation::MantraSwap {
+                token_in_denom: offer_asset.denom.clone(),
+                token_out_denom: denom.clone(),
+                pool_identifier: pool.pool_identifier.clone(),
+            });
+
+            if denom == ask_asset_denom {
+                let is_best = match best_route {
+                    Some((best_operations, best_amount)) => {
+                        simulation.return_amount > *best_amount
+                            || (simulation.return_amount == *best_amount
+                                && route.len() < best_operations.len())
+                    }
+                    None => true,
+                };
+
+                if is_best {
+                    *best_route = Some((route.clone(), simulation.return_amount));
+                }
+            } else {
+                find_best_route(
+                    deps,
+                    env,
+                    pools_by_denom,
+                    &coin(simulation.return_amount.u128(), denom),
+                    ask_asset_denom,
+                    hops_left - 1,
+                    simulations_left,
+                    route,
+                    best_route,
+                )?;
+            }
+
+            route.pop();
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the swap route registered to swap the offer asset for the ask asset.
+pub fn get_swap_route(
+    deps: Deps,
+    offer_asset_denom: String,
+    ask_asset_denom: String,
+) -> Result<SwapRouteResponse, ContractError> {
+    let swap_operations =
+        get_swap_operations(&deps, &offer_asset_denom, &ask_asset_denom)?.swap_operations;
+
+    Ok(SwapRouteResponse {
+        swap_route: SwapRoute {
+            offer_asset_denom,
+            ask_asset_denom,
+            swap_operations,
+        },
+    })
+}
+
+/// Gets the registered swap routes, ordered by offer asset denom and ask asset denom.
+pub fn get_swap_routes(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> Result<SwapRoutesResponse, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .as_ref()
+        .map(|(offer_asset_denom, ask_asset_denom)| {
+            Bound::exclusive((offer_asset_denom.as_str(), ask_asset_denom.as_str()))
+        });
+
+    let swap_routes = SWAP_ROUTES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let ((offer_asset_denom, ask_asset_denom), swap_operations) = item?;
+
+            Ok(SwapRoute {
+                offer_asset_denom,
+                ask_asset_denom,
+                swap_operations: swap_operations.swap_operations,
+            })
+        })
+        .collect::<StdResult<Vec<SwapRoute>>>()?;
+
+    Ok(SwapRoutesResponse { swap_routes })
+}
+
+/// Gets the creator of the swap route registered to swap the offer asset for the ask asset.
+pub fn get_swap_route_creator(
+    deps: Deps,
+    offer_asset_denom: String,
+    ask_asset_denom: String,
+) -> Result<SwapRouteCreatorResponse, ContractError> {
+    let creator = get_swap_operations(&deps, &offer_asset_denom, &ask_asset_denom)?.creator;
+
+    Ok(SwapRouteCreatorResponse { creator })
+}
+
+/// Queries the reserve health of the assets of the given pool, or of all the pools if none is
+/// given.
+pub fn query_reserve_health(
+    deps: Deps,
+    env: &Env,
+    pool_identifier: Option<String>,
+) -> Result<ReserveHealthResponse, ContractError> {
+    let denoms = match pool_identifier {
+        Some(pool_identifier) => get_pool_by_identifier(&deps, &pool_identifier)?.asset_denoms,
+        None => {
+            let mut denoms = POOLS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| Ok(item?.1.asset_denoms))
+                .collect::<StdResult<Vec<_>>>()?
+                .concat();
+            denoms.sort();
+            denoms.dedup();
+            denoms
+        }
+    };
+
+    Ok(ReserveHealthResponse {
+        reserves: get_reserve_health(deps, env, denoms)?,
+    })
+}
+
+/// Queries the LP shares an address holds in a pool with internal LP accounting.
+pub fn query_share_balance(
+    deps: Deps,
+    pool_identifier: String,
+    address: String,
+) -> Result<ShareBalanceResponse, ContractError> {
+    let pool = get_pool_by_identifier(&deps, &pool_identifier)?;
+    ensure!(
+        is_internal_lp_denom(&pool.lp_denom),
+        ContractError::NotInternalLpPool { pool_identifier }
+    );
+
+    let address = deps.api.addr_validate(&address)?;
+
+    Ok(ShareBalanceResponse {
+        balance: get_balance(&deps, &address, &pool.lp_denom)?,
+    })
+}
+
+/// Queries the LP shares of a pool with internal LP accounting the spender can transfer on behalf
+/// of the owner.
+pub fn query_share_allowance(
+    deps: Deps,
+    pool_identifier: String,
+    owner: String,
+    spender: String,
+) -> Result<ShareAllowanceResponse, ContractError> {
+    let pool = get_pool_by_identifier(&deps, &pool_identifier)?;
+    ensure!(
+        is_internal_lp_denom(&pool.lp_denom),
+        ContractError::NotInternalLpPool {
+            pool_identifier: pool_identifier.clone(),
+        }
+    );
+
+    let owner = deps.api.addr_validate(&owner)?;
+    let spender = deps.api.addr_validate(&spender)?;
+
+    Ok(ShareAllowanceResponse {
+        allowance: LP_SHARES_ALLOWANCES
+            .may_load(deps.storage, (&pool_identifier, &owner, &spender))?
+            .unwrap_or_default(),
+    })
+}
+
+/// Simulates providing liquidity to a pool. Single asset deposits are swapped into the other assets
+/// of the pool first, the same way the `ProvideLiquidity` message does before depositing.
+pub fn simulate_provide_liquidity(
+    deps: Deps,
+    env: &Env,
+    pool_identifier: String,
+    deposits: Vec<Coin>,
+    slippage_tolerance: Option<Decimal>,
+    max_spread: Option<Decimal>,
+    excess_mode: Option<ExcessDepositMode>,
+) -> Result<SimulateProvideLiquidityResponse, ContractError> {
+    let mut pool = get_pool_by_identifier(&deps, &pool_identifier)?;
+    apply_amp_ramp(deps.storage, env, &mut pool)?;
+
+    let mut excess_mode = excess_mode.unwrap_or(ExcessDepositMode::Donate);
+    let (mut deposits, is_single_side_swap_provision) =
+        validate_deposits(&pool, deposits, &excess_mode)?;
+
+    let mut swaps = vec![];
+    if is_single_side_swap_provision {
+        let (offer_asset_portion, offer_asset_remainder, ask_asset_denoms) =
+            split_single_side_deposit(&deposits[0], &pool.assets)?;
+
+        let mut swapped_assets = vec![offer_asset_remainder];
+        for ask_asset_denom in ask_asset_denoms {
+            let (swap_result, _) = compute_swap_result(
+                deps,
+                env,
+                pool,
+                offer_asset_portion.clone(),
+                ask_asset_denom,
+                None,
+                max_spread,
+            )?;
+            swapped_assets.push(swap_result.return_asset.clone());
+            pool = swap_result.pool_info.clone();
+            swaps.push(to_liquidity_swap(offer_asset_portion.clone(), &swap_result));
+        }
+
+        // the swapped assets are deposited together with the remainder of the offer asset
+        deposits = aggregate_coins(swapped_assets)?;
+        excess_mode = ExcessDepositMode::Donate;
+    }
+
+    let provision = compute_liquidity_provision(
+        deps,
+        env,
+        pool,
+        deposits,
+        &slippage_tolerance,
+        max_spread,
+        &excess_mode,
+    )?;
+
+    if let Some((offer_asset, swap_result)) = &provision.excess_swap {
+        swaps.push(to_liquidity_swap(offer_asset.clone(), swap_result));
+    }
+
+    Ok(SimulateProvideLiquidityResponse {
+        lp_amount: provision.share,
+        deposited_assets: provision.deposits,
+        refunded_assets: provision.excess_refund,
+        slippage: provision.slippage,
+        swaps,
+    })
+}
+
+/// Simulates withdrawing liquidity from a pool, optionally into a single asset.
+pub fn simulate_withdraw_liquidity(
+    deps: Deps,
+    env: &Env,
+    pool_identifier: String,
+    lp_amount: Uint128,
+    target_denom: Option<String>,
+) -> Result<SimulateWithdrawLiquidityResponse, ContractError> {
+    let mut pool = get_pool_by_identifier(&deps, &pool_identifier)?;
+    apply_amp_ramp(deps.storage, env, &mut pool)?;
+
+    let withdrawal = compute_liquidity_withdrawal(deps, env, pool, lp_amount, target_denom)?;
+
+    Ok(SimulateWithdrawLiquidityResponse {
+        returned_assets: withdrawal.refund_assets,
+        swaps: withdrawal
+            .swaps
+            .iter()
+            .map(|(offer_asset, swap_result)| to_liquidity_swap(offer_asset.clone(), swap_result))
+            .collect(),
+    })
+}
+
+/// Converts the result of a swap performed by a liquidity operation into a [`LiquiditySwap`].
+fn to_liquidity_swap(offer_asset: Coin, swap_result: &SwapResult) -> LiquiditySwap {
+    LiquiditySwap {
+        offer_asset,
+        return_asset: swap_result.return_asset.clone(),
+        spread_amount: swap_result.spread_amount,
+        swap_fee_amount: swap_result.swap_fee_asset.amount,
+        protocol_fee_amount: swap_result.protocol_fee_asset.amount,
+        burn_fee_amount: swap_result.burn_fee_asset.amount,
+    }
+}
@@ -1,15 +1,21 @@
 use cosmwasm_std::{
-    attr, coin, ensure, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, MessageInfo, Response, Uint128,
+    attr, coin, ensure, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response,
+    Uint128,
 };
 
 use amm::coin::burn_coin_msg;
 use amm::common::validate_addr_or_default;
-use amm::pool_manager::SwapOperation;
+use amm::pool_manager::{Config, SplitSwapRoute, SwapOperation, SwapRoute};
 
-use crate::{state::CONFIG, swap::perform_swap::perform_swap, ContractError};
+use crate::queries::{query_reverse_simulation, simulate_swap_operations};
+use crate::state::{
+    get_pool_by_identifier, get_swap_operations, SwapOperations, CONFIG, SWAP_ROUTES,
+};
+use crate::swap::commands::adjust_exact_out_offer_amount;
+use crate::{swap::perform_swap::perform_swap, ContractError};
 
 /// Checks that the output of each [`SwapOperation`] acts as the input of the next swap.
-fn assert_operations(operations: Vec<SwapOperation>) -> Result<(), ContractError> {
+pub(crate) fn assert_operations(operations: Vec<SwapOperation>) -> Result<(), ContractError> {
     // check that the output of each swap is the input of the next swap
     let mut previous_output_info = operations
         .first()
@@ -33,6 +39,7 @@ fn assert_operations(operations: Vec<SwapOperation>) -> Result<(), ContractError
 
 pub fn execute_swap_operations(
     mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     operations: Vec<SwapOperation>,
     minimum_receive: Option<Uint128>,
@@ -69,9 +76,484 @@ pub fn execute_swap_operations(
     let receiver =
         validate_addr_or_default(&deps.as_ref(), receiver, info.sender.clone()).to_string();
 
-    // perform each swap operation
-    // we start off with the initial funds
-    let mut previous_swap_output = offer_asset.clone();
+    // perform each swap operation, starting off with the initial funds
+    let SwapOperationsResult {
+        return_asset: previous_swap_output,
+        fee_messages,
+        swap_attributes,
+    } = perform_swap_operations(
+        deps.branch(),
+        &env,
+        &config,
+        offer_asset.clone(),
+        operations,
+        max_spread,
+    )?;
+
+    // Execute minimum amount assertion
+    let receiver_balance = previous_swap_output.amount;
+    if let Some(minimum_receive) = minimum_receive {
+        if receiver_balance < minimum_receive {
+            return Err(ContractError::MinimumReceiveAssertion {
+                minimum_receive,
+                swap_amount: receiver_balance,
+            });
+        }
+    }
+
+    let mut bank_msg: Vec<CosmosMsg> = vec![];
+    if !receiver_balance.is_zero() {
+        bank_msg.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: receiver.clone(),
+            amount: vec![coin(receiver_balance.u128(), target_asset_denom.clone())],
+        }));
+    }
+
+    // send output to recipient
+    Ok(Response::new()
+        .add_messages(bank_msg)
+        .add_messages(fee_messages)
+        .add_attributes(vec![
+            attr("action", "execute_swap_operations".to_string()),
+            attr("sender", info.sender.to_string()),
+            attr("receiver", receiver),
+            attr("offer_info", offer_asset.denom),
+            attr("offer_amount", offer_asset.amount.to_string()),
+            attr("return_denom", target_asset_denom),
+            attr("return_amount", receiver_balance.to_string()),
+        ])
+        .add_attributes(swap_attributes))
+}
+
+/// Swaps the funds sent along the [`SwapOperation`]s for an exact amount of the target asset. The
+/// input each hop needs is found by walking the operations backwards with reverse simulations,
+/// then the swaps are performed forwards. The funds not swapped, and the output beyond the ask
+/// amount if any, are refunded to the sender.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_swap_operations_exact_out(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operations: Vec<SwapOperation>,
+    ask_amount: Uint128,
+    max_offer_amount: Option<Uint128>,
+    receiver: Option<String>,
+    max_spread: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the swap feature is enabled
+    ensure!(
+        config.feature_toggle.swaps_enabled,
+        ContractError::OperationDisabled("swap".to_string())
+    );
+
+    let target_asset_denom = operations
+        .last()
+        .ok_or(ContractError::NoSwapOperationsProvided)?
+        .get_target_asset_info();
+
+    let offer_asset_denom = operations
+        .first()
+        .ok_or(ContractError::NoSwapOperationsProvided)?
+        .get_input_asset_info()
+        .clone();
+
+    let offer_funds = coin(
+        cw_utils::must_pay(&info, &offer_asset_denom)?.u128(),
+        &offer_asset_denom,
+    );
+
+    assert_operations(operations.clone())?;
+
+    // walk the operations backwards to find the input each hop needs
+    let mut offer_amount = ask_amount;
+    for operation in operations.iter().rev() {
+        match operation {
+            SwapOperation::MantraSwap {
+                token_in_denom,
+                token_out_denom,
+                pool_identifier,
+            } => {
+                offer_amount = query_reverse_simulation(
+                    deps.as_ref(),
+                    &env,
+                    coin(offer_amount.u128(), token_out_denom),
+                    token_in_denom.clone(),
+                    pool_identifier.clone(),
+                )?
+                .offer_amount;
+            }
+        }
+    }
+
+    // each reverse simulation rounds on its own, make sure the offer actually returns the ask amount
+    let offer_amount = adjust_exact_out_offer_amount(offer_amount, ask_amount, |amount| {
+        Ok(simulate_swap_operations(deps.as_ref(), &env, amount, operations.clone())?.amount)
+    })?;
+
+    let max_offer_amount = max_offer_amount
+        .unwrap_or(offer_funds.amount)
+        .min(offer_funds.amount);
+    ensure!(
+        offer_amount <= max_offer_amount,
+        ContractError::MaxOfferAmountExceeded {
+            offer_amount,
+            max_offer_amount,
+        }
+    );
+
+    let SwapOperationsResult {
+        return_asset,
+        fee_messages,
+        swap_attributes,
+    } = perform_swap_operations(
+        deps.branch(),
+        &env,
+        &config,
+        coin(offer_amount.u128(), &offer_asset_denom),
+        operations,
+        max_spread,
+    )?;
+
+    // the simulations run on the state before the swaps, so a route going through the same pool
+    // more than once could still fall short
+    ensure!(
+        return_asset.amount >= ask_amount,
+        ContractError::ExactOutputUnreachable
+    );
+
+    let receiver = validate_addr_or_default(&deps.as_ref(), receiver, info.sender.clone());
+
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
+        to_address: receiver.to_string(),
+        amount: vec![coin(ask_amount.u128(), &target_asset_denom)],
+    })];
+
+    let mut refund_assets: Vec<Coin> = vec![
+        coin(
+            offer_funds.amount.checked_sub(offer_amount)?.u128(),
+            &offer_asset_denom,
+        ),
+        coin(
+            return_asset.amount.checked_sub(ask_amount)?.u128(),
+            &target_asset_denom,
+        ),
+    ]
+    .into_iter()
+    .filter(|asset| !asset.amount.is_zero())
+    .collect();
+    refund_assets.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    if !refund_assets.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: refund_assets.clone(),
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_messages(fee_messages)
+        .add_attributes(vec![
+            attr("action", "execute_swap_operations_exact_out".to_string()),
+            attr("sender", info.sender.to_string()),
+            attr("receiver", receiver.to_string()),
+            attr("offer_info", offer_asset_denom),
+            attr("offer_amount", offer_amount.to_string()),
+            attr("return_denom", target_asset_denom),
+            attr("return_amount", ask_amount.to_string()),
+            attr(
+                "refund_assets",
+                refund_assets
+                    .iter()
+                    .map(|asset| asset.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        ])
+        .add_attributes(swap_attributes))
+}
+
+/// Splits the funds sent across the [`SplitSwapRoute`]s according to their weights, performs
+/// the operations of each route and sends the aggregated output to the receiver. The last route
+/// swaps whatever is left after the others, so no funds are left behind because of rounding.
+pub fn execute_split_swap_operations(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    routes: Vec<SplitSwapRoute>,
+    minimum_receive: Option<Uint128>,
+    receiver: Option<String>,
+    max_spread: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the swap feature is enabled
+    ensure!(
+        config.feature_toggle.swaps_enabled,
+        ContractError::OperationDisabled("swap".to_string())
+    );
+
+    let first_route = routes
+        .first()
+        .ok_or(ContractError::NoSwapOperationsProvided)?;
+    let offer_asset_denom = first_route
+        .operations
+        .first()
+        .ok_or(ContractError::NoSwapOperationsProvided)?
+        .get_input_asset_info()
+        .clone();
+    let target_asset_denom = first_route
+        .operations
+        .last()
+        .ok_or(ContractError::NoSwapOperationsProvided)?
+        .get_target_asset_info();
+
+    let mut total_weight = Decimal::zero();
+    for route in &routes {
+        ensure!(
+            !route.weight.is_zero(),
+            ContractError::InvalidSplitSwapRouteWeight
+        );
+
+        assert_operations(route.operations.clone())?;
+
+        // the operations were checked to be non-empty above
+        ensure!(
+            route.operations[0].get_input_asset_info() == &offer_asset_denom
+                && route.operations[route.operations.len() - 1].get_target_asset_info()
+                    == target_asset_denom,
+            ContractError::MismatchedSplitSwapRoutes {
+                offer_asset_denom,
+                ask_asset_denom: target_asset_denom,
+            }
+        );
+
+        total_weight = total_weight.checked_add(route.weight)?;
+    }
+
+    let offer_asset = coin(
+        cw_utils::must_pay(&info, &offer_asset_denom)?.u128(),
+        &offer_asset_denom,
+    );
+
+    let receiver =
+        validate_addr_or_default(&deps.as_ref(), receiver, info.sender.clone()).to_string();
+
+    let mut remaining_offer_amount = offer_asset.amount;
+    let mut return_amount = Uint128::zero();
+    let mut fee_messages = vec![];
+    let mut swap_attributes = vec![];
+
+    let routes_count = routes.len();
+    for (i, route) in routes.into_iter().enumerate() {
+        let route_offer_amount = if i == routes_count - 1 {
+            remaining_offer_amount
+        } else {
+            offer_asset
+                .amount
+                .checked_multiply_ratio(route.weight.atomics(), total_weight.atomics())?
+                .min(remaining_offer_amount)
+        };
+
+        // a route getting nothing to swap, e.g. with dust amounts, is skipped
+        if route_offer_amount.is_zero() {
+            continue;
+        }
+
+        remaining_offer_amount = remaining_offer_amount.checked_sub(route_offer_amount)?;
+
+        let route_result = perform_swap_operations(
+            deps.branch(),
+            &env,
+            &config,
+            coin(route_offer_amount.u128(), &offer_asset_denom),
+            route.operations,
+            max_spread,
+        )?;
+
+        return_amount = return_amount.checked_add(route_result.return_asset.amount)?;
+        fee_messages.extend(route_result.fee_messages);
+        swap_attributes.extend(route_result.swap_attributes);
+    }
+
+    // Execute minimum amount assertion
+    if let Some(minimum_receive) = minimum_receive {
+        if return_amount < minimum_receive {
+            return Err(ContractError::MinimumReceiveAssertion {
+                minimum_receive,
+                swap_amount: return_amount,
+            });
+        }
+    }
+
+    let mut bank_msg: Vec<CosmosMsg> = vec![];
+    if !return_amount.is_zero() {
+        bank_msg.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: receiver.clone(),
+            amount: vec![coin(return_amount.u128(), &target_asset_denom)],
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(bank_msg)
+        .add_messages(fee_messages)
+        .add_attributes(vec![
+            attr("action", "execute_split_swap_operations".to_string()),
+            attr("sender", info.sender.to_string()),
+            attr("receiver", receiver),
+            attr("routes", routes_count.to_string()),
+            attr("offer_info", offer_asset.denom),
+            attr("offer_amount", offer_asset.amount.to_string()),
+            attr("return_denom", target_asset_denom),
+            attr("return_amount", return_amount.to_string()),
+        ])
+        .add_attributes(swap_attributes))
+}
+
+/// Swaps the funds sent for the ask asset through the swap route registered for the pair.
+pub fn swap_via_route(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ask_asset_denom: String,
+    minimum_receive: Option<Uint128>,
+    receiver: Option<String>,
+    max_spread: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let offer_asset = cw_utils::one_coin(&info)?;
+
+    let swap_operations =
+        get_swap_operations(&deps.as_ref(), &offer_asset.denom, &ask_asset_denom)?.swap_operations;
+
+    execute_swap_operations(
+        deps,
+        env,
+        info,
+        swap_operations,
+        minimum_receive,
+        receiver,
+        max_spread,
+    )
+}
+
+/// Registers the given swap routes, with the sender as their creator. Only the owner of the contract
+/// can register routes, so clients can trust them. The operations of each route must be
+/// consecutive, go through existing pools, and swap the route's offer asset for its ask asset.
+pub fn add_swap_routes(
+    deps: DepsMut,
+    info: MessageInfo,
+    swap_routes: Vec<SwapRoute>,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let mut attributes = vec![attr("action", "add_swap_routes")];
+
+    for swap_route in swap_routes {
+        ensure!(
+            swap_route.offer_asset_denom != swap_route.ask_asset_denom,
+            ContractError::SameAsset
+        );
+
+        assert_operations(swap_route.swap_operations.clone())?;
+
+        let malformed_swap_route = || ContractError::MalformedSwapRoute {
+            offer_asset_denom: swap_route.offer_asset_denom.clone(),
+            ask_asset_denom: swap_route.ask_asset_denom.clone(),
+        };
+
+        // the operations were checked to be non-empty above
+        ensure!(
+            swap_route.swap_operations[0].get_input_asset_info() == &swap_route.offer_asset_denom
+                && swap_route.swap_operations[swap_route.swap_operations.len() - 1]
+                    .get_target_asset_info()
+                    == swap_route.ask_asset_denom,
+            malformed_swap_route()
+        );
+
+        for operation in &swap_route.swap_operations {
+            let pool = get_pool_by_identifier(&deps.as_ref(), &operation.get_pool_identifer())?;
+            ensure!(
+                pool.asset_denoms.contains(operation.get_input_asset_info())
+                    && pool
+                        .asset_denoms
+                        .contains(&operation.get_target_asset_info()),
+                malformed_swap_route()
+            );
+        }
+
+        let key = (
+            swap_route.offer_asset_denom.as_str(),
+            swap_route.ask_asset_denom.as_str(),
+        );
+        ensure!(
+            !SWAP_ROUTES.has(deps.storage, key),
+            ContractError::SwapRouteAlreadyExists {
+                offer_asset_denom: swap_route.offer_asset_denom.clone(),
+                ask_asset_denom: swap_route.ask_asset_denom.clone(),
+            }
+        );
+
+        SWAP_ROUTES.save(
+            deps.storage,
+            key,
+            &SwapOperations {
+                creator: info.sender.to_string(),
+                swap_operations: swap_route.swap_operations.clone(),
+            },
+        )?;
+
+        attributes.push(attr("offer_asset_denom", &swap_route.offer_asset_denom));
+        attributes.push(attr("ask_asset_denom", &swap_route.ask_asset_denom));
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+/// Removes the swap routes of the given (offer asset denom, ask asset denom) pairs. Only the
+/// creator of a route or the owner of the contract can remove it.
+pub fn remove_swap_routes(
+    deps: DepsMut,
+    info: MessageInfo,
+    swap_routes: Vec<(String, String)>,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let mut attributes = vec![attr("action", "remove_swap_routes")];
+
+    for (offer_asset_denom, ask_asset_denom) in swap_routes {
+        // make sure the route exists
+        get_swap_operations(&deps.as_ref(), &offer_asset_denom, &ask_asset_denom)?;
+
+        SWAP_ROUTES.remove(deps.storage, (&offer_asset_denom, &ask_asset_denom));
+
+        attributes.push(attr("offer_asset_denom", offer_asset_denom));
+        attributes.push(attr("ask_asset_denom", ask_asset_denom));
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+/// The outcome of performing a sequence of [`SwapOperation`]s.
+struct SwapOperationsResult {
+    /// The output of the last swap.
+    return_asset: Coin,
+    /// The messages burning and sending the fees of the swaps to the fee collector.
+    fee_messages: Vec<CosmosMsg>,
+    /// The attributes describing each swap.
+    swap_attributes: Vec<(&'static str, String)>,
+}
+
+/// Performs the [`SwapOperation`]s in sequence, using the output of each swap as the input of the
+/// next one. The operations must have been checked with [`assert_operations`] already.
+fn perform_swap_operations(
+    mut deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    offer_asset: Coin,
+    operations: Vec<SwapOperation>,
+    max_spread: Option<Decimal>,
+) -> Result<SwapOperationsResult, ContractError> {
+    let mut previous_swap_output = offer_asset;
 
     // stores messages for sending fees after the swaps
     let mut fee_messages = vec![];
@@ -85,11 +567,9 @@ pub fn execute_swap_operations(
                 pool_identifier,
                 ..
             } => {
-                // inside assert_operations() we have already checked that
-                // the output of each swap is the input of the next swap.
-
                 let swap_result = perform_swap(
                     deps.branch(),
+                    env,
                     previous_swap_output.clone(),
                     token_out_denom,
                     pool_identifier,
@@ -128,37 +608,9 @@ pub fn execute_swap_operations(
         }
     }
 
-    // Execute minimum amount assertion
-    let receiver_balance = previous_swap_output.amount;
-    if let Some(minimum_receive) = minimum_receive {
-        if receiver_balance < minimum_receive {
-            return Err(ContractError::MinimumReceiveAssertion {
-                minimum_receive,
-                swap_amount: receiver_balance,
-            });
-        }
-    }
-
-    let mut bank_msg: Vec<CosmosMsg> = vec![];
-    if !receiver_balance.is_zero() {
-        bank_msg.push(CosmosMsg::Bank(BankMsg::Send {
-            to_address: receiver.clone(),
-            amount: vec![coin(receiver_balance.u128(), target_asset_denom.clone())],
-        }));
-    }
-
-    // send output to recipient
-    Ok(Response::new()
-        .add_messages(bank_msg)
-        .add_messages(fee_messages)
-        .add_attributes(vec![
-            attr("action", "execute_swap_operations".to_string()),
-            attr("sender", info.sender.to_string()),
-            attr("receiver", receiver),
-            attr("offer_info", offer_asset.denom),
-            attr("offer_amount", offer_asset.amount.to_string()),
-            attr("return_denom", target_asset_denom),
-            attr("return_amount", receiver_balance.to_string()),
-        ])
-        .add_attributes(swap_attributes))
+    Ok(SwapOperationsResult {
+        return_asset: previous_swap_output,
+        fee_messages,
+        swap_attributes,
+    })
 }
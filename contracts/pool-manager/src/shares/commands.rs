@@ -0,0 +1,201 @@
+use cosmwasm_std::{coin, ensure, from_json, Binary, DepsMut, Env, MessageInfo, Response, Uint128};
+
+use amm::lp_common::is_internal_lp_denom;
+use amm::pool_manager::{ExecuteMsg, PoolInfo, SharesReceiveMsg};
+
+use crate::shares::ledger::move_shares;
+use crate::state::{get_pool_by_identifier, LP_SHARES_ALLOWANCES};
+use crate::ContractError;
+
+/// Transfers LP shares of a pool with internal LP accounting from the sender to the recipient.
+pub fn transfer_shares(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_identifier: String,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    cw_utils::nonpayable(&info)?;
+
+    let pool = get_internal_lp_pool(&deps, &pool_identifier)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    move_shares(
+        deps.storage,
+        &pool_identifier,
+        &info.sender,
+        &recipient,
+        amount,
+    )?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "transfer_shares".to_string()),
+        ("sender", info.sender.to_string()),
+        ("recipient", recipient.to_string()),
+        ("lp_asset", coin(amount.u128(), pool.lp_denom).to_string()),
+    ]))
+}
+
+/// Sends LP shares of a pool with internal LP accounting from the sender to a contract, executing
+/// the given message on it with the shares. When the contract is the pool manager itself, the
+/// message is executed right away with the shares as funds, which is how the withdrawal and
+/// migration messages take internal LP shares.
+pub fn send_shares(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_identifier: String,
+    amount: Uint128,
+    contract: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    cw_utils::nonpayable(&info)?;
+
+    let pool = get_internal_lp_pool(&deps, &pool_identifier)?;
+    let contract = deps.api.addr_validate(&contract)?;
+
+    move_shares(
+        deps.storage,
+        &pool_identifier,
+        &info.sender,
+        &contract,
+        amount,
+    )?;
+
+    let lp_asset = coin(amount.u128(), pool.lp_denom);
+    let attributes = vec![
+        ("action", "send_shares".to_string()),
+        ("sender", info.sender.to_string()),
+        ("contract", contract.to_string()),
+        ("lp_asset", lp_asset.to_string()),
+    ];
+
+    if contract != env.contract.address {
+        return Ok(Response::default()
+            .add_message(
+                SharesReceiveMsg {
+                    sender: info.sender.to_string(),
+                    lp_asset,
+                    msg,
+                }
+                .into_cosmos_msg(contract)?,
+            )
+            .add_attributes(attributes));
+    }
+
+    let msg: ExecuteMsg = from_json(&msg)?;
+    ensure!(
+        matches!(
+            msg,
+            ExecuteMsg::WithdrawLiquidity { .. }
+                | ExecuteMsg::WithdrawLiquidityOneCoin { .. }
+                | ExecuteMsg::WithdrawLiquidityImbalance { .. }
+                | ExecuteMsg::MigrateLiquidity { .. }
+        ),
+        ContractError::InvalidSharesMessage
+    );
+
+    let response = crate::contract::execute(
+        deps.branch(),
+        env,
+        MessageInfo {
+            sender: info.sender,
+            funds: vec![lp_asset],
+        },
+        msg,
+    )?;
+
+    Ok(response.add_attributes(attributes))
+}
+
+/// Sets the amount of LP shares of a pool with internal LP accounting the spender can transfer on
+/// behalf of the sender.
+pub fn approve_shares(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_identifier: String,
+    spender: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    cw_utils::nonpayable(&info)?;
+
+    get_internal_lp_pool(&deps, &pool_identifier)?;
+    let spender = deps.api.addr_validate(&spender)?;
+
+    if amount.is_zero() {
+        LP_SHARES_ALLOWANCES.remove(deps.storage, (&pool_identifier, &info.sender, &spender));
+    } else {
+        LP_SHARES_ALLOWANCES.save(
+            deps.storage,
+            (&pool_identifier, &info.sender, &spender),
+            &amount,
+        )?;
+    }
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "approve_shares".to_string()),
+        ("pool_identifier", pool_identifier),
+        ("owner", info.sender.to_string()),
+        ("spender", spender.to_string()),
+        ("amount", amount.to_string()),
+    ]))
+}
+
+/// Transfers LP shares of a pool with internal LP accounting from the owner to the recipient,
+/// deducting them from the allowance the owner gave to the sender.
+pub fn transfer_shares_from(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_identifier: String,
+    owner: String,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    cw_utils::nonpayable(&info)?;
+
+    let pool = get_internal_lp_pool(&deps, &pool_identifier)?;
+    let owner = deps.api.addr_validate(&owner)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    let allowance = LP_SHARES_ALLOWANCES
+        .may_load(deps.storage, (&pool_identifier, &owner, &info.sender))?
+        .unwrap_or_default();
+
+    let remaining_allowance = allowance
+        .checked_sub(amount)
+        .map_err(|_| ContractError::InsufficientShareAllowance { allowance, amount })?;
+
+    if remaining_allowance.is_zero() {
+        LP_SHARES_ALLOWANCES.remove(deps.storage, (&pool_identifier, &owner, &info.sender));
+    } else {
+        LP_SHARES_ALLOWANCES.save(
+            deps.storage,
+            (&pool_identifier, &owner, &info.sender),
+            &remaining_allowance,
+        )?;
+    }
+
+    move_shares(deps.storage, &pool_identifier, &owner, &recipient, amount)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "transfer_shares_from".to_string()),
+        ("sender", info.sender.to_string()),
+        ("owner", owner.to_string()),
+        ("recipient", recipient.to_string()),
+        ("lp_asset", coin(amount.u128(), pool.lp_denom).to_string()),
+    ]))
+}
+
+/// Gets the pool with the given identifier, ensuring its LP shares are accounted for internally.
+fn get_internal_lp_pool(deps: &DepsMut, pool_identifier: &str) -> Result<PoolInfo, ContractError> {
+    let pool = get_pool_by_identifier(&deps.as_ref(), pool_identifier)?;
+
+    ensure!(
+        is_internal_lp_denom(&pool.lp_denom),
+        ContractError::NotInternalLpPool {
+            pool_identifier: pool_identifier.to_string(),
+        }
+    );
+
+    Ok(pool)
+}
@@ -0,0 +1,217 @@
+use cosmwasm_std::{
+    coin, Addr, Binary, Coin, CosmosMsg, Deps, Env, StdResult, Storage, Uint128, WasmMsg,
+};
+
+use amm::lp_common::{get_internal_lp_denom_parts, is_internal_lp_denom};
+use amm::pool_manager::SharesReceiveMsg;
+
+use crate::state::{LP_SHARES, LP_SHARES_SUPPLY};
+use crate::ContractError;
+
+/// Gets the total supply of an LP denom, from the bank module or, for internal LP denoms, from the
+/// share ledger.
+pub fn get_total_share(deps: &Deps, liquidity_asset: String) -> StdResult<Uint128> {
+    if is_internal_lp_denom(&liquidity_asset) {
+        let (_, pool_identifier) = get_internal_lp_denom_parts(&liquidity_asset)?;
+
+        return Ok(LP_SHARES_SUPPLY
+            .may_load(deps.storage, pool_identifier)?
+            .unwrap_or_default());
+    }
+
+    amm::pool_manager::get_total_share(deps, liquidity_asset)
+}
+
+/// Gets the balance of an address in the given denom, from the bank module or, for internal LP
+/// denoms, from the share ledger.
+pub fn get_balance(deps: &Deps, address: &Addr, denom: &str) -> StdResult<Coin> {
+    if is_internal_lp_denom(denom) {
+        let (_, pool_identifier) = get_internal_lp_denom_parts(denom)?;
+        let balance = LP_SHARES
+            .may_load(deps.storage, (pool_identifier, address))?
+            .unwrap_or_default();
+
+        return Ok(coin(balance.u128(), denom));
+    }
+
+    deps.querier.query_balance(address, denom)
+}
+
+/// Mints LP tokens to the recipient. Internal LP shares are credited in the ledger right away,
+/// otherwise the token factory message minting the tokens is returned.
+pub(crate) fn mint_lp(
+    storage: &mut dyn Storage,
+    env: &Env,
+    lp_denom: &str,
+    recipient: &Addr,
+    amount: Uint128,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if is_internal_lp_denom(lp_denom) {
+        let (_, pool_identifier) = get_internal_lp_denom_parts(lp_denom)?;
+
+        credit_shares(storage, pool_identifier, recipient, amount)?;
+        LP_SHARES_SUPPLY.update(
+            storage,
+            pool_identifier,
+            |supply| -> Result<_, ContractError> {
+                Ok(supply.unwrap_or_default().checked_add(amount)?)
+            },
+        )?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(amm::lp_common::mint_lp_token_msg(
+        lp_denom.to_string(),
+        recipient,
+        &env.contract.address,
+        amount,
+    )?))
+}
+
+/// Burns LP tokens held by the contract. Internal LP shares are debited from the ledger right
+/// away, otherwise the token factory message burning the tokens is returned.
+pub(crate) fn burn_lp(
+    storage: &mut dyn Storage,
+    env: &Env,
+    lp_denom: &str,
+    amount: Uint128,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if is_internal_lp_denom(lp_denom) {
+        let (_, pool_identifier) = get_internal_lp_denom_parts(lp_denom)?;
+
+        debit_shares(storage, pool_identifier, &env.contract.address, amount)?;
+        LP_SHARES_SUPPLY.update(
+            storage,
+            pool_identifier,
+            |supply| -> Result<_, ContractError> {
+                Ok(supply.unwrap_or_default().checked_sub(amount)?)
+            },
+        )?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(amm::lp_common::burn_lp_asset_msg(
+        lp_denom.to_string(),
+        env.contract.address.clone(),
+        amount,
+    )?))
+}
+
+/// Sends LP tokens held by the contract to the recipient. Internal LP shares are moved in the
+/// ledger right away, otherwise the bank message sending the tokens is returned.
+pub(crate) fn transfer_lp(
+    storage: &mut dyn Storage,
+    env: &Env,
+    lp_asset: Coin,
+    recipient: &Addr,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if is_internal_lp_denom(&lp_asset.denom) {
+        let (_, pool_identifier) = get_internal_lp_denom_parts(&lp_asset.denom)?;
+
+        move_shares(
+            storage,
+            pool_identifier,
+            &env.contract.address,
+            recipient,
+            lp_asset.amount,
+        )?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(amm::lp_common::transfer_lp_msg(
+        lp_asset,
+        recipient.as_str(),
+    )?))
+}
+
+/// Executes `msg` on a contract with LP tokens held by the contract attached. Internal LP shares
+/// are moved to the contract in the ledger and the message is wrapped in a
+/// [SharesReceiveMsg], otherwise the tokens are sent as funds.
+pub(crate) fn send_lp(
+    storage: &mut dyn Storage,
+    env: &Env,
+    lp_asset: Coin,
+    contract: &Addr,
+    msg: Binary,
+) -> Result<CosmosMsg, ContractError> {
+    if is_internal_lp_denom(&lp_asset.denom) {
+        let (_, pool_identifier) = get_internal_lp_denom_parts(&lp_asset.denom)?;
+
+        move_shares(
+            storage,
+            pool_identifier,
+            &env.contract.address,
+            contract,
+            lp_asset.amount,
+        )?;
+
+        return Ok(SharesReceiveMsg {
+            sender: env.contract.address.to_string(),
+            lp_asset,
+            msg,
+        }
+        .into_cosmos_msg(contract)?);
+    }
+
+    Ok(WasmMsg::Execute {
+        contract_addr: contract.to_string(),
+        msg,
+        funds: vec![lp_asset],
+    }
+    .into())
+}
+
+/// Moves LP shares of a pool from an address to another in the ledger.
+pub(crate) fn move_shares(
+    storage: &mut dyn Storage,
+    pool_identifier: &str,
+    from: &Addr,
+    to: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    debit_shares(storage, pool_identifier, from, amount)?;
+    credit_shares(storage, pool_identifier, to, amount)
+}
+
+fn credit_shares(
+    storage: &mut dyn Storage,
+    pool_identifier: &str,
+    address: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    LP_SHARES.update(
+        storage,
+        (pool_identifier, address),
+        |balance| -> Result<_, ContractError> {
+            Ok(balance.unwrap_or_default().checked_add(amount)?)
+        },
+    )?;
+
+    Ok(())
+}
+
+fn debit_shares(
+    storage: &mut dyn Storage,
+    pool_identifier: &str,
+    address: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let balance = LP_SHARES
+        .may_load(storage, (pool_identifier, address))?
+        .unwrap_or_default();
+
+    let remaining = balance
+        .checked_sub(amount)
+        .map_err(|_| ContractError::InsufficientShares { balance, amount })?;
+
+    if remaining.is_zero() {
+        LP_SHARES.remove(storage, (pool_identifier, address));
+    } else {
+        LP_SHARES.save(storage, (pool_identifier, address), &remaining)?;
+    }
+
+    Ok(())
+}
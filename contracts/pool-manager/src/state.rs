@@ -1,13 +1,13 @@
 pub use amm::pool_manager::Config;
-use amm::pool_manager::{PoolInfo, SwapOperation};
+use amm::pool_manager::{ConcentratedPosition, PoolInfo, SwapOperation};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Coin, Decimal, Deps};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, UniqueIndex};
+use cosmwasm_std::{Addr, Coin, Decimal, Decimal256, Deps, Int128, Uint128, Uint256};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, UniqueIndex};
 
 use crate::ContractError;
 
-/// Holds information about the single side liquidity provision temporarily until the swap/liquidity
-/// provision is completed
+/// Holds information about the single side liquidity provision temporarily until the swaps/liquidity
+/// provision are completed
 #[cw_serde]
 pub struct SingleSideLiquidityProvisionBuffer {
     /// The receiver of the LP
@@ -15,15 +15,23 @@ pub struct SingleSideLiquidityProvisionBuffer {
     /// The expected offer asset balance in the contract after the single side liquidity provision
     /// is done. Used for validations.
     pub expected_offer_asset_balance_in_contract: Coin,
-    /// The expected ask asset balance in the contract after the single side liquidity provision
-    /// is done. Used for validations.
+    /// The expected ask asset balance in the contract after the ongoing swap is done. Used for
+    /// validations.
     pub expected_ask_asset_balance_in_contract: Coin,
-    /// Half of the offer asset, i.e. the amount of the offer asset that is going to be swapped
-    /// for the ask asset so the LP is provided in balanced proportions.
-    pub offer_asset_half: Coin,
-    /// The expected ask asset after half of the offer asset is swapped for the ask asset. This is
-    /// computed via a swap simulation.
+    /// The amount of the offer asset that is swapped for each of the other assets in the pool so
+    /// the LP is provided in balanced proportions, i.e. half of the offer asset on pools with two
+    /// assets.
+    pub offer_asset_portion: Coin,
+    /// The amount of the offer asset that is not swapped, and is provided as is.
+    pub offer_asset_remainder: Coin,
+    /// The expected ask asset after the ongoing swap of the offer asset portion. This is computed
+    /// via a swap simulation.
     pub expected_ask_asset: Coin,
+    /// The ask assets received from the swaps done so far.
+    pub swapped_assets: Vec<Coin>,
+    /// The denoms of the assets the offer asset portion is yet to be swapped for after the
+    /// ongoing swap.
+    pub pending_ask_asset_denoms: Vec<String>,
     /// The remaining data for the liquidity provision.
     pub liquidity_provision_data: LiquidityProvisionData,
 }
@@ -70,6 +78,24 @@ impl<'a> IndexList<PoolInfo> for PoolIndexes<'a> {
     }
 }
 
+/// The amplification ramp of a stable swap pool. The amplification is interpolated linearly
+/// between `initial_amp` at `initial_time` and `target_amp` at `end_time`.
+#[cw_serde]
+pub struct AmpRamp {
+    /// The amplification when the ramp started.
+    pub initial_amp: u64,
+    /// The timestamp in seconds when the ramp started.
+    pub initial_time: u64,
+    /// The amplification to reach at the end of the ramp.
+    pub target_amp: u64,
+    /// The timestamp in seconds when the ramp ends.
+    pub end_time: u64,
+}
+
+/// The amplification ramps of the stable swap pools, by pool identifier. Once a pool's ramp is
+/// set, it determines the amplification of the pool instead of its pool type.
+pub const AMP_RAMPS: Map<&str, AmpRamp> = Map::new("amp_ramps");
+
 /// Gets the pool given its identifier
 pub fn get_pool_by_identifier(
     deps: &Deps,
@@ -89,5 +115,117 @@ pub struct SwapOperations {
     /// The operations to be executed for a given swap.
     pub swap_operations: Vec<SwapOperation>,
 }
+
+/// The registered swap routes, keyed by (offer asset denom, ask asset denom).
+pub const SWAP_ROUTES: Map<(&str, &str), SwapOperations> = Map::new("swap_routes");
+
+/// Gets the swap route registered to swap the offer asset for the ask asset
+pub fn get_swap_operations(
+    deps: &Deps,
+    offer_asset_denom: &str,
+    ask_asset_denom: &str,
+) -> Result<SwapOperations, ContractError> {
+    SWAP_ROUTES
+        .may_load(deps.storage, (offer_asset_denom, ask_asset_denom))?
+        .ok_or(ContractError::NoSwapRouteForAssets {
+            offer_asset_denom: offer_asset_denom.to_string(),
+            ask_asset_denom: ask_asset_denom.to_string(),
+        })
+}
+
+/// The state of a concentrated liquidity pool.
+#[cw_serde]
+pub struct ConcentratedLiquidityState {
+    /// The current square root of the price of the pool.
+    pub sqrt_price: Decimal256,
+    /// The current tick of the pool, i.e. the greatest tick whose price is lower than or equal
+    /// to the current price.
+    pub tick: i64,
+    /// The liquidity that is active at the current tick.
+    pub liquidity: Uint128,
+    /// The total fee growth per unit of liquidity for each of the pool assets, scaled by
+    /// [crate::concentrated_liquidity::math::FEE_GROWTH_SCALE].
+    pub fee_growth_global: Vec<Uint256>,
+    /// The fees held by the contract for the positions of the pool, for each of the pool assets,
+    /// i.e. the fees distributed by the swaps that haven't been collected yet. They aren't part of
+    /// the pool assets.
+    #[serde(default)]
+    pub fees_held: Vec<Uint128>,
+}
+
+/// An initialized tick of a concentrated liquidity pool, i.e. a tick that is the bound of at
+/// least one position.
+#[cw_serde]
+pub struct TickInfo {
+    /// The total liquidity of the positions that use this tick as a bound.
+    pub liquidity_gross: Uint128,
+    /// The liquidity to add to the active liquidity when the price crosses this tick upwards, or
+    /// to subtract when crossing it downwards.
+    pub liquidity_net: Int128,
+    /// The fee growth per unit of liquidity on the other side of this tick, relative to the
+    /// current tick, for each of the pool assets.
+    pub fee_growth_outside: Vec<Uint256>,
+}
+
+/// The state of the concentrated liquidity pools, keyed by pool identifier.
+pub const CONCENTRATED_LIQUIDITY_STATES: Map<&str, ConcentratedLiquidityState> =
+    Map::new("concentrated_liquidity_states");
+
+/// The initialized ticks of the concentrated liquidity pools, keyed by (pool identifier, tick).
+pub const TICKS: Map<(&str, i64), TickInfo> = Map::new("ticks");
+
+pub const CONCENTRATED_POSITION_ID_COUNTER: Item<u64> =
+    Item::new("concentrated_position_id_counter");
+
+pub const CONCENTRATED_POSITIONS: IndexedMap<
+    &str,
+    ConcentratedPosition,
+    ConcentratedPositionIndexes,
+> = IndexedMap::new(
+    "concentrated_positions",
+    ConcentratedPositionIndexes {
+        owner: MultiIndex::new(
+            |_pk, p| p.owner.to_string(),
+            "concentrated_positions",
+            "concentrated_positions__owner",
+        ),
+    },
+);
+
+pub struct ConcentratedPositionIndexes<'a> {
+    pub owner: MultiIndex<'a, String, ConcentratedPosition, String>,
+}
+
+impl<'a> IndexList<ConcentratedPosition> for ConcentratedPositionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ConcentratedPosition>> + '_> {
+        let v: Vec<&dyn Index<ConcentratedPosition>> = vec![&self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Gets a concentrated liquidity position given its identifier
+pub fn get_concentrated_position(
+    deps: &Deps,
+    identifier: &str,
+) -> Result<ConcentratedPosition, ContractError> {
+    CONCENTRATED_POSITIONS
+        .may_load(deps.storage, identifier)?
+        .ok_or(ContractError::NonExistentConcentratedPosition {
+            identifier: identifier.to_string(),
+        })
+}
+
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const POOL_COUNTER: Item<u64> = Item::new("pool_count");
+
+/// The LP shares held by each address in the pools with internal LP accounting, keyed by pool
+/// identifier and address.
+pub const LP_SHARES: Map<(&str, &Addr), Uint128> = Map::new("lp_shares");
+
+/// The total LP shares of the pools with internal LP accounting, keyed by pool identifier.
+pub const LP_SHARES_SUPPLY: Map<&str, Uint128> = Map::new("lp_shares_supply");
+
+/// The LP shares a spender can transfer on behalf of an owner in the pools with internal LP
+/// accounting, keyed by pool identifier, owner and spender.
+pub const LP_SHARES_ALLOWANCES: Map<(&str, &Addr, &Addr), Uint128> =
+    Map::new("lp_shares_allowances");
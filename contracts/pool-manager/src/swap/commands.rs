@@ -1,17 +1,25 @@
-use cosmwasm_std::Decimal;
-use cosmwasm_std::{ensure, Addr, BankMsg, CosmosMsg, DepsMut, MessageInfo, Response};
+use cosmwasm_std::{coin, Coin, Decimal, Uint128};
+use cosmwasm_std::{ensure, Addr, BankMsg, CosmosMsg, DepsMut, Env, MessageInfo, Response};
 
 use amm::coin::burn_coin_msg;
 use amm::common::validate_addr_or_default;
 
-use crate::state::get_pool_by_identifier;
+use crate::helpers::{apply_amp_ramp, get_metapool_base_pool};
+use crate::liquidity::commands::{add_liquidity_one_coin, remove_liquidity_one_coin};
+use crate::queries::query_reverse_simulation;
+use crate::state::{get_pool_by_identifier, POOLS};
 use crate::{state::CONFIG, ContractError};
 
-use super::perform_swap::perform_swap;
+use super::perform_swap::{compute_swap_result, perform_swap};
+
+/// The maximum amount of times the offer amount of an exact output swap is bumped when rounding
+/// leaves its return short of the ask amount.
+const MAX_EXACT_OUT_ADJUSTMENTS: u8 = 8;
 
 #[allow(clippy::too_many_arguments)]
 pub fn swap(
     mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     sender: Addr,
     ask_asset_denom: String,
@@ -50,6 +58,7 @@ pub fn swap(
     // perform the swap
     let swap_result = perform_swap(
         deps.branch(),
+        &env,
         offer_asset.clone(),
         ask_asset_denom,
         pool_identifier,
@@ -110,3 +119,376 @@ pub fn swap(
         ),
     ]))
 }
+
+/// Swaps the offer asset sent for the exact `ask_asset`. The offer amount needed is computed with
+/// the reverse simulation and bumped while rounding leaves the return short of the ask amount. The
+/// funds not swapped, and the return beyond the ask amount if any, are refunded to the sender.
+pub fn swap_exact_out(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ask_asset: Coin,
+    max_offer_amount: Option<Uint128>,
+    receiver: Option<String>,
+    pool_identifier: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the swap feature is enabled
+    ensure!(
+        config.feature_toggle.swaps_enabled,
+        ContractError::OperationDisabled("swap".to_string())
+    );
+
+    let offer_funds = cw_utils::one_coin(&info)?;
+
+    // ensure offer asset is not the same as ask asset
+    ensure!(
+        offer_funds.denom != ask_asset.denom,
+        ContractError::SameAsset
+    );
+
+    ensure!(!ask_asset.amount.is_zero(), ContractError::ZeroAskAmount);
+
+    // verify that the assets sent match the ones from the pool
+    let mut pool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    ensure!(
+        [ask_asset.denom.clone(), offer_funds.denom.clone()]
+            .iter()
+            .all(|asset| pool
+                .assets
+                .iter()
+                .any(|pool_asset| pool_asset.denom == *asset)),
+        ContractError::AssetMismatch
+    );
+    apply_amp_ramp(deps.storage, &env, &mut pool)?;
+
+    let offer_amount = query_reverse_simulation(
+        deps.as_ref(),
+        &env,
+        ask_asset.clone(),
+        offer_funds.denom.clone(),
+        pool_identifier.clone(),
+    )?
+    .offer_amount;
+
+    // the reverse simulation rounds on its own, make sure the offer actually returns the ask amount
+    let offer_amount = adjust_exact_out_offer_amount(offer_amount, ask_asset.amount, |amount| {
+        let (swap_result, _) = compute_swap_result(
+            deps.as_ref(),
+            &env,
+            pool.clone(),
+            coin(amount.u128(), &offer_funds.denom),
+            ask_asset.denom.clone(),
+            None,
+            None,
+        )?;
+
+        Ok(swap_result.return_asset.amount)
+    })?;
+
+    let max_offer_amount = max_offer_amount
+        .unwrap_or(offer_funds.amount)
+        .min(offer_funds.amount);
+    ensure!(
+        offer_amount <= max_offer_amount,
+        ContractError::MaxOfferAmountExceeded {
+            offer_amount,
+            max_offer_amount,
+        }
+    );
+
+    // perform the swap
+    let swap_result = perform_swap(
+        deps.branch(),
+        &env,
+        coin(offer_amount.u128(), &offer_funds.denom),
+        ask_asset.denom.clone(),
+        pool_identifier,
+        None,
+        None,
+    )?;
+
+    // add messages
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    let receiver = validate_addr_or_default(&deps.as_ref(), receiver, info.sender.clone());
+
+    messages.push(CosmosMsg::Bank(BankMsg::Send {
+        to_address: receiver.clone().into_string(),
+        amount: vec![ask_asset.clone()],
+    }));
+
+    let mut refund_assets: Vec<Coin> = vec![
+        coin(
+            offer_funds.amount.checked_sub(offer_amount)?.u128(),
+            &offer_funds.denom,
+        ),
+        coin(
+            swap_result
+                .return_asset
+                .amount
+                .checked_sub(ask_asset.amount)?
+                .u128(),
+            &ask_asset.denom,
+        ),
+    ]
+    .into_iter()
+    .filter(|asset| !asset.amount.is_zero())
+    .collect();
+    refund_assets.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    if !refund_assets.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: refund_assets.clone(),
+        }));
+    }
+
+    if !swap_result.burn_fee_asset.amount.is_zero() {
+        messages.push(burn_coin_msg(swap_result.burn_fee_asset.clone()));
+    }
+
+    if !swap_result.protocol_fee_asset.amount.is_zero() {
+        messages.push(
+            BankMsg::Send {
+                to_address: config.fee_collector_addr.to_string(),
+                amount: vec![swap_result.protocol_fee_asset.clone()],
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "swap_exact_out".to_string()),
+        ("sender", info.sender.into_string()),
+        ("receiver", receiver.into_string()),
+        ("offer_denom", offer_funds.denom),
+        ("ask_denom", ask_asset.denom),
+        ("offer_amount", offer_amount.to_string()),
+        ("return_amount", ask_asset.amount.to_string()),
+        (
+            "refund_assets",
+            refund_assets
+                .iter()
+                .map(|asset| asset.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        ("spread_amount", swap_result.spread_amount.to_string()),
+        (
+            "swap_fee_amount",
+            swap_result.swap_fee_asset.amount.to_string(),
+        ),
+        (
+            "protocol_fee_amount",
+            swap_result.protocol_fee_asset.amount.to_string(),
+        ),
+        (
+            "burn_fee_amount",
+            swap_result.burn_fee_asset.amount.to_string(),
+        ),
+        (
+            "swap_type",
+            swap_result.pool_info.pool_type.get_label().to_string(),
+        ),
+    ]))
+}
+
+/// Bumps the offer amount of an exact output swap while the return computed by `simulate` falls
+/// short of the ask amount, which happens when the reverse simulation rounds in favour of the
+/// trader.
+pub(crate) fn adjust_exact_out_offer_amount(
+    mut offer_amount: Uint128,
+    ask_amount: Uint128,
+    simulate: impl Fn(Uint128) -> Result<Uint128, ContractError>,
+) -> Result<Uint128, ContractError> {
+    let mut adjustments = 0u8;
+    loop {
+        let return_amount = simulate(offer_amount)?;
+
+        if return_amount >= ask_amount {
+            return Ok(offer_amount);
+        }
+
+        ensure!(
+            adjustments < MAX_EXACT_OUT_ADJUSTMENTS,
+            ContractError::ExactOutputUnreachable
+        );
+        adjustments += 1;
+
+        let shortfall = ask_amount.checked_sub(return_amount)?;
+        offer_amount = offer_amount.checked_add(
+            offer_amount
+                .multiply_ratio(shortfall, ask_amount)
+                .max(Uint128::one()),
+        )?;
+    }
+}
+
+/// Swaps between the asset of a metapool and an asset of its base pool. The swap in the metapool
+/// is priced with the base pool state before the base pool deposit or withdrawal is made.
+#[allow(clippy::too_many_arguments)]
+pub fn swap_underlying(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ask_asset_denom: String,
+    minimum_receive: Option<Uint128>,
+    receiver: Option<String>,
+    max_spread: Option<Decimal>,
+    pool_identifier: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // check if the swap feature is enabled
+    ensure!(
+        config.feature_toggle.swaps_enabled,
+        ContractError::OperationDisabled("swap".to_string())
+    );
+
+    let offer_asset = cw_utils::one_coin(&info)?;
+
+    // ensure offer asset is not the same as ask asset
+    ensure!(
+        offer_asset.denom != ask_asset_denom,
+        ContractError::SameAsset
+    );
+
+    let metapool = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    let mut base_pool = get_metapool_base_pool(&deps.as_ref(), &metapool)?;
+    apply_amp_ramp(deps.storage, &env, &mut base_pool)?;
+
+    let meta_asset_denom = metapool.asset_denoms[0].clone();
+    let base_lp_denom = base_pool.lp_denom.clone();
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    let (return_asset, swap_result) = if offer_asset.denom == meta_asset_denom
+        && base_pool.asset_denoms.contains(&ask_asset_denom)
+    {
+        // check if the withdraw feature is enabled, as the base pool LP tokens are withdrawn
+        ensure!(
+            config.feature_toggle.withdrawals_enabled,
+            ContractError::OperationDisabled("swap_underlying".to_string())
+        );
+
+        // swap the metapool asset for the base pool LP tokens
+        let swap_result = perform_swap(
+            deps.branch(),
+            &env,
+            offer_asset.clone(),
+            base_lp_denom.clone(),
+            pool_identifier,
+            None,
+            max_spread,
+        )?;
+
+        // withdraw the LP tokens from the base pool into the ask asset
+        let (return_asset, _) = remove_liquidity_one_coin(
+            &deps.as_ref(),
+            &env,
+            &mut base_pool,
+            swap_result.return_asset.amount,
+            &ask_asset_denom,
+        )?;
+        POOLS.save(deps.storage, &base_pool.pool_identifier, &base_pool)?;
+
+        messages.push(amm::lp_common::burn_lp_asset_msg(
+            base_lp_denom,
+            env.contract.address.clone(),
+            swap_result.return_asset.amount,
+        )?);
+
+        (return_asset, swap_result)
+    } else if base_pool.asset_denoms.contains(&offer_asset.denom)
+        && ask_asset_denom == meta_asset_denom
+    {
+        // check if the deposits feature is enabled, as the offer asset is deposited in the base pool
+        ensure!(
+            config.feature_toggle.deposits_enabled,
+            ContractError::OperationDisabled("swap_underlying".to_string())
+        );
+
+        // deposit the offer asset in the base pool, the LP tokens stay in the metapool
+        let lp_amount = add_liquidity_one_coin(&deps.as_ref(), &env, &mut base_pool, &offer_asset)?;
+
+        // swap the base pool LP tokens for the metapool asset. The base pool is saved afterwards
+        // so the swap is priced with the virtual price before the deposit.
+        let swap_result = perform_swap(
+            deps.branch(),
+            &env,
+            coin(lp_amount.u128(), &base_lp_denom),
+            ask_asset_denom,
+            pool_identifier,
+            None,
+            max_spread,
+        )?;
+        POOLS.save(deps.storage, &base_pool.pool_identifier, &base_pool)?;
+
+        messages.push(amm::lp_common::mint_lp_token_msg(
+            base_lp_denom,
+            &env.contract.address,
+            &env.contract.address,
+            lp_amount,
+        )?);
+
+        (swap_result.return_asset.clone(), swap_result)
+    } else {
+        return Err(ContractError::AssetMismatch);
+    };
+
+    if let Some(minimum_receive) = minimum_receive {
+        ensure!(
+            return_asset.amount >= minimum_receive,
+            ContractError::MinimumReceiveAssertion {
+                minimum_receive,
+                swap_amount: return_asset.amount,
+            }
+        );
+    }
+
+    let receiver = validate_addr_or_default(&deps.as_ref(), receiver, info.sender.clone());
+
+    if !return_asset.amount.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: receiver.to_string(),
+            amount: vec![return_asset.clone()],
+        }));
+    }
+
+    if !swap_result.burn_fee_asset.amount.is_zero() {
+        messages.push(burn_coin_msg(swap_result.burn_fee_asset.clone()));
+    }
+
+    if !swap_result.protocol_fee_asset.amount.is_zero() {
+        messages.push(
+            BankMsg::Send {
+                to_address: config.fee_collector_addr.to_string(),
+                amount: vec![swap_result.protocol_fee_asset.clone()],
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "swap_underlying".to_string()),
+        ("sender", info.sender.into_string()),
+        ("receiver", receiver.into_string()),
+        ("offer_denom", offer_asset.denom),
+        ("ask_denom", return_asset.denom),
+        ("offer_amount", offer_asset.amount.to_string()),
+        ("return_amount", return_asset.amount.to_string()),
+        ("spread_amount", swap_result.spread_amount.to_string()),
+        (
+            "swap_fee_amount",
+            swap_result.swap_fee_asset.amount.to_string(),
+        ),
+        (
+            "protocol_fee_amount",
+            swap_result.protocol_fee_asset.amount.to_string(),
+        ),
+        (
+            "burn_fee_amount",
+            swap_result.burn_fee_asset.amount.to_string(),
+        ),
+    ]))
+}
@@ -1,12 +1,18 @@
 use std::str::FromStr;
 
 use cosmwasm_std::{
-    Coin, Decimal, Decimal256, DepsMut, Fraction, StdError, StdResult, Uint128, Uint256,
+    Coin, Decimal, Decimal256, Deps, DepsMut, Env, Fraction, StdError, StdResult, Uint128, Uint256,
 };
 
-use amm::pool_manager::PoolInfo;
+use amm::pool_manager::{PoolInfo, PoolType};
 
-use crate::helpers::{aggregate_outgoing_fees, get_asset_indexes_in_pool};
+use crate::concentrated_liquidity::swap::{
+    apply_concentrated_swap, compute_concentrated_swap, ConcentratedSwap,
+};
+use crate::helpers::{
+    aggregate_outgoing_fees, apply_amp_ramp, assert_oracle_price_deviation,
+    get_asset_indexes_in_pool, get_asset_rates, get_effective_pool_type, get_oracle_price,
+};
 use crate::{
     helpers,
     state::{get_pool_by_identifier, POOLS},
@@ -29,6 +35,17 @@ pub struct SwapResult {
     pub spread_amount: Uint128,
 }
 
+/// The changes to apply to the state of a concentrated liquidity pool once a swap is performed.
+#[derive(Debug)]
+pub struct ConcentratedSwapUpdate {
+    /// The result of the swap on the concentrated liquidity pool.
+    pub concentrated_swap: ConcentratedSwap,
+    /// The index of the ask asset in the pool.
+    pub ask_index: usize,
+    /// The fees owed to the positions providing liquidity during the swap.
+    pub lp_fee_amount: Uint128,
+}
+
 /// Attempts to perform a swap from `offer_asset` to the relevant opposing
 /// asset in the pool identified by `pool_identifier`.
 ///
@@ -39,6 +56,7 @@ pub struct SwapResult {
 /// of each field in [`SwapResult`] (besides fields like `spread_amount`).
 pub fn perform_swap(
     deps: DepsMut,
+    env: &Env,
     offer_asset: Coin,
     ask_asset_denom: String,
     pool_identifier: String,
@@ -46,7 +64,53 @@ pub fn perform_swap(
     max_spread: Option<Decimal>,
 ) -> Result<SwapResult, ContractError> {
     let mut pool_info = get_pool_by_identifier(&deps.as_ref(), &pool_identifier)?;
+    apply_amp_ramp(deps.storage, env, &mut pool_info)?;
+
+    let (swap_result, concentrated_swap_update) = compute_swap_result(
+        deps.as_ref(),
+        env,
+        pool_info,
+        offer_asset,
+        ask_asset_denom,
+        belief_price,
+        max_spread,
+    )?;
+
+    if let Some(ConcentratedSwapUpdate {
+        concentrated_swap,
+        ask_index,
+        lp_fee_amount,
+    }) = concentrated_swap_update
+    {
+        apply_concentrated_swap(
+            deps.storage,
+            &pool_identifier,
+            concentrated_swap,
+            ask_index,
+            lp_fee_amount,
+        )?;
+    }
+
+    POOLS.save(deps.storage, &pool_identifier, &swap_result.pool_info)?;
+
+    Ok(swap_result)
+}
 
+/// Computes a swap from `offer_asset` to the relevant opposing asset in the given pool, without
+/// touching the state. The pool in the resulting [`SwapResult`] holds the balances after the swap,
+/// and the changes to the state of concentrated liquidity pools are returned apart.
+///
+/// Both [`perform_swap`] and the liquidity simulations go through this function, so the outcome of
+/// a simulation matches the one of the actual swap.
+pub fn compute_swap_result(
+    deps: Deps,
+    env: &Env,
+    mut pool_info: PoolInfo,
+    offer_asset: Coin,
+    ask_asset_denom: String,
+    belief_price: Option<Decimal>,
+    max_spread: Option<Decimal>,
+) -> Result<(SwapResult, Option<ConcentratedSwapUpdate>), ContractError> {
     let (
         offer_asset_in_pool,
         ask_asset_in_pool,
@@ -57,16 +121,43 @@ pub fn perform_swap(
     ) = get_asset_indexes_in_pool(&pool_info, offer_asset.denom, ask_asset_denom)?;
 
     // compute the swap
-    let swap_computation = helpers::compute_swap(
-        Uint256::from(pool_info.assets.len() as u128),
-        offer_asset_in_pool.amount,
-        ask_asset_in_pool.amount,
-        offer_asset.amount,
-        pool_info.pool_fees.clone(),
-        &pool_info.pool_type,
-        offer_decimal,
-        ask_decimal,
-    )?;
+    let (swap_computation, concentrated_swap) = match pool_info.pool_type {
+        PoolType::ConcentratedLiquidity { .. } => {
+            let (swap_computation, concentrated_swap) = compute_concentrated_swap(
+                deps.storage,
+                &pool_info,
+                offer_index,
+                offer_asset.amount,
+            )?;
+            (swap_computation, Some(concentrated_swap))
+        }
+        _ => {
+            let pool_type = get_effective_pool_type(
+                env,
+                &pool_info,
+                get_oracle_price(&deps, env, &pool_info)?,
+            )?;
+            (
+                helpers::compute_swap(
+                    Uint256::from(pool_info.assets.len() as u128),
+                    offer_asset_in_pool.amount,
+                    ask_asset_in_pool.amount,
+                    offer_asset.amount,
+                    pool_info.pool_fees.clone(),
+                    &pool_type,
+                    offer_decimal,
+                    ask_decimal,
+                    offer_index,
+                    ask_index,
+                    &get_asset_rates(&deps, env, &pool_type, &pool_info.asset_denoms)?,
+                )?,
+                None,
+            )
+        }
+    };
+
+    // swaps on oracle pegged pools can't stray too far from the oracle price
+    assert_oracle_price_deviation(&pool_info.pool_type, &swap_computation)?;
 
     let return_asset = Coin {
         denom: ask_asset_in_pool.denom.clone(),
@@ -84,6 +175,7 @@ pub fn perform_swap(
     )?;
 
     // State changes to the pools balances
+    let mut concentrated_swap_update = None;
     {
         // add the offer amount to the pool
         pool_info.assets[offer_index].amount = pool_info.assets[offer_index]
@@ -98,7 +190,23 @@ pub fn perform_swap(
             .checked_sub(return_asset.amount)?
             .checked_sub(outgoing_fees)?;
 
-        POOLS.save(deps.storage, &pool_identifier, &pool_info)?;
+        // on concentrated liquidity pools the fees that stay in the pool are owed to the
+        // positions providing liquidity during the swap, so they are tracked apart
+        if let Some(concentrated_swap) = concentrated_swap {
+            let lp_fee_amount = swap_computation
+                .swap_fee_amount
+                .checked_add(swap_computation.extra_fees_amount)?;
+
+            pool_info.assets[ask_index].amount = pool_info.assets[ask_index]
+                .amount
+                .checked_sub(lp_fee_amount)?;
+
+            concentrated_swap_update = Some(ConcentratedSwapUpdate {
+                concentrated_swap,
+                ask_index,
+                lp_fee_amount,
+            });
+        }
     }
 
     let burn_fee_asset = Coin {
@@ -116,14 +224,17 @@ pub fn perform_swap(
         amount: swap_computation.swap_fee_amount,
     };
 
-    Ok(SwapResult {
-        return_asset,
-        swap_fee_asset,
-        burn_fee_asset,
-        protocol_fee_asset,
-        pool_info,
-        spread_amount: swap_computation.spread_amount,
-    })
+    Ok((
+        SwapResult {
+            return_asset,
+            swap_fee_asset,
+            burn_fee_asset,
+            protocol_fee_asset,
+            pool_info,
+            spread_amount: swap_computation.spread_amount,
+        },
+        concentrated_swap_update,
+    ))
 }
 
 /// Default swap slippage in case max_spread is not specified
@@ -8,7 +8,7 @@ use common_testing::multi_test::stargate_mock::StargateMock;
 
 use crate::ContractError;
 
-use super::suite::TestingSuite;
+use super::suite::{pool_fees, TestingSuite};
 
 #[test]
 fn instantiate_normal() {
@@ -121,6 +121,10 @@ fn deposit_and_withdraw_sanity_check() {
         .withdraw_liquidity(
             &creator,
             "o.whale.uluna".to_string(),
+            None,
+            None,
+            None,
+            None,
             vec![Coin {
                 denom: lp_denom.clone(),
                 amount: Uint128::from(999_000u128),
@@ -2007,7 +2011,10 @@ mod swapping {
             asset_infos,
             vec![6u8, 6u8],
             pool_fees,
-            PoolType::StableSwap { amp: 100 },
+            PoolType::StableSwap {
+                amp: 100,
+                rate_providers: None,
+            },
             Some("whale.uluna".to_string()),
             vec![coin(1000, "uusd"), coin(8888, "uom")],
             |result| {
@@ -4108,6 +4115,10 @@ mod provide_liquidity {
             .withdraw_liquidity(
                 &creator,
                 "o.whale.uluna".to_string(),
+                None,
+                None,
+                None,
+                None,
                 vec![Coin {
                     denom: lp_denom.clone(),
                     amount: Uint128::from(999_000u128),
@@ -4183,6 +4194,10 @@ mod provide_liquidity {
             .withdraw_liquidity(
                 &other,
                 "o.whale.uluna".to_string(),
+                None,
+                None,
+                None,
+                None,
                 vec![Coin {
                     denom: lp_denom.clone(),
                     amount: Uint128::from(9_798u128),
@@ -4646,6 +4661,10 @@ mod provide_liquidity {
             .withdraw_liquidity(
                 &creator,
                 "p.1".to_string(),
+                None,
+                None,
+                None,
+                None,
                 vec![Coin {
                     denom: lp_denom.clone(),
                     amount: Uint128::new(2_448_488u128),
@@ -4754,7 +4773,10 @@ mod provide_liquidity {
             asset_infos,
             vec![6u8, 6u8, 6u8],
             pool_fees,
-            PoolType::StableSwap { amp: 100 },
+            PoolType::StableSwap {
+                amp: 100,
+                rate_providers: None,
+            },
             Some("whale.uluna.uusd".to_string()),
             vec![coin(1000, "uusd"), coin(8888, "uom")],
             |result| {
@@ -5041,7 +5063,10 @@ mod provide_liquidity {
             asset_infos,
             vec![6u8, 6u8, 6u8],
             pool_fees,
-            PoolType::StableSwap { amp: 100 },
+            PoolType::StableSwap {
+                amp: 100,
+                rate_providers: None,
+            },
             Some("uusdc.uusdt.uusdy".to_string()),
             vec![coin(1000, "uusd"), coin(8888, "uom")],
             |result| {
@@ -5440,7 +5465,10 @@ mod provide_liquidity {
             asset_infos,
             vec![6u8, 6u8, 6u8],
             pool_fees,
-            PoolType::StableSwap { amp: 100 },
+            PoolType::StableSwap {
+                amp: 100,
+                rate_providers: None,
+            },
             Some("whale.uluna.uusd".to_string()),
             vec![coin(1000, "uusd"), coin(8888, "uom")],
             |result| {
@@ -5673,6 +5701,10 @@ mod provide_liquidity {
             .withdraw_liquidity(
                 &alice,
                 "p.1".to_string(),
+                None,
+                None,
+                None,
+                None,
                 vec![lp_shares.borrow().clone()],
                 |result| {
                     result.unwrap();
@@ -6552,7 +6584,10 @@ mod multiple_pools {
                 asset_denoms.clone(),
                 vec![6u8, 6u8],
                 pool_fees.clone(),
-                PoolType::StableSwap { amp: 80 },
+                PoolType::StableSwap {
+                    amp: 80,
+                    rate_providers: None,
+                },
                 Some("stableswap".to_string()),
                 vec![coin(1000, "uusd"), coin(8888, "uom")],
                 |result| {
@@ -6571,7 +6606,10 @@ mod multiple_pools {
                 asset_denoms.clone(),
                 vec![6u8, 6u8, 6u8],
                 pool_fees.clone(),
-                PoolType::StableSwap { amp: 80 },
+                PoolType::StableSwap {
+                    amp: 80,
+                    rate_providers: None,
+                },
                 Some("stableswap".to_string()),
                 vec![coin(1000, "uusd"), coin(8888, "uom")],
                 |result| {
@@ -6596,7 +6634,10 @@ mod multiple_pools {
                 ],
                 vec![6u8, 6u8, 6u8, 6u8, 6u8],
                 pool_fees.clone(),
-                PoolType::StableSwap { amp: 80 },
+                PoolType::StableSwap {
+                    amp: 80,
+                    rate_providers: None,
+                },
                 Some("stableswap".to_string()),
                 vec![coin(1000, "uusd"), coin(8888, "uom")],
                 |result| {
@@ -6620,7 +6661,10 @@ mod multiple_pools {
                 ],
                 vec![6u8, 6u8, 6u8, 6u8],
                 pool_fees.clone(),
-                PoolType::StableSwap { amp: 80 },
+                PoolType::StableSwap {
+                    amp: 80,
+                    rate_providers: None,
+                },
                 Some("stableswap".to_string()),
                 vec![coin(1000, "uusd"), coin(8888, "uom")],
                 |result| {
@@ -6670,7 +6714,10 @@ mod multiple_pools {
                 ],
                 vec![6u8, 6u8, 6u8, 6u8],
                 pool_fees.clone(),
-                PoolType::StableSwap { amp: 80 },
+                PoolType::StableSwap {
+                    amp: 80,
+                    rate_providers: None,
+                },
                 Some("1".to_string()),
                 vec![coin(1000, "uusd"), coin(8888, "uom")],
                 |result| {
@@ -6710,7 +6757,10 @@ mod multiple_pools {
             ],
             vec![6u8, 6u8, 6u8, 6u8],
             pool_fees.clone(),
-            PoolType::StableSwap { amp: 80 },
+            PoolType::StableSwap {
+                amp: 80,
+                rate_providers: None,
+            },
             Some("1".to_string()),
             vec![coin(1000, "uusd"), coin(8888, "uom")],
             |result| {
@@ -6725,3 +6775,6070 @@ mod multiple_pools {
         );
     }
 }
+
+mod weighted_pools {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn cant_create_weighted_pool_with_invalid_weights() {
+        let mut suite = TestingSuite::default_with_balances(
+            vec![
+                coin(1_000_000_000u128, "uom".to_string()),
+                coin(1_000_000_000u128, "uusdc".to_string()),
+                coin(1_000_000_000u128, "uusd".to_string()),
+            ],
+            StargateMock::new("uom".to_string(), "8888".to_string()),
+        );
+        let creator = suite.creator();
+
+        let pool_fees = PoolFee {
+            protocol_fee: Fee {
+                share: Decimal::zero(),
+            },
+            swap_fee: Fee {
+                share: Decimal::permille(3),
+            },
+            burn_fee: Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        };
+
+        suite.instantiate_default();
+
+        for weights in [
+            // weights don't add up to 1
+            vec![Decimal::percent(80), Decimal::percent(30)],
+            // a weight is missing
+            vec![Decimal::one()],
+            // zero weight
+            vec![Decimal::one(), Decimal::zero()],
+        ] {
+            suite.create_pool(
+                &creator,
+                vec!["uom".to_string(), "uusdc".to_string()],
+                vec![6u8, 6u8],
+                pool_fees.clone(),
+                PoolType::Weighted { weights },
+                Some("om.usdc".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidPoolWeights);
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn weighted_pool_provide_swap_and_withdraw() {
+        let mut suite = TestingSuite::default_with_balances(
+            vec![
+                coin(1_000_000_000_000u128, "uom".to_string()),
+                coin(1_000_000_000_000u128, "uusdc".to_string()),
+                coin(1_000_000_000u128, "uusd".to_string()),
+            ],
+            StargateMock::new("uom".to_string(), "8888".to_string()),
+        );
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        let pool_fees = PoolFee {
+            protocol_fee: Fee {
+                share: Decimal::permille(1),
+            },
+            swap_fee: Fee {
+                share: Decimal::permille(2),
+            },
+            burn_fee: Fee {
+                share: Decimal::zero(),
+            },
+            extra_fees: vec![],
+        };
+
+        // 80/20 OM/USDC pool
+        suite.instantiate_default().create_pool(
+            &creator,
+            vec!["uom".to_string(), "uusdc".to_string()],
+            vec![6u8, 6u8],
+            pool_fees,
+            PoolType::Weighted {
+                weights: vec![Decimal::percent(80), Decimal::percent(20)],
+            },
+            Some("om.usdc".to_string()),
+            vec![coin(1000, "uusd"), coin(8888, "uom")],
+            |result| {
+                let response = result.unwrap();
+                assert!(response.events.iter().any(|event| event
+                    .attributes
+                    .iter()
+                    .any(|attr| attr.key == "pool_type" && attr.value == "Weighted")));
+            },
+        );
+
+        let lp_denom = suite.get_lp_denom("o.om.usdc".to_string());
+
+        // the pool holds 4 OM per USDC, so 1 OM = 1 USDC at 80/20
+        suite
+            .provide_liquidity(
+                &creator,
+                "o.om.usdc".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(4_000_000_000u128, "uom".to_string()),
+                    coin(1_000_000_000u128, "uusdc".to_string()),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_lp_supply("o.om.usdc".to_string(), |result| {
+                // 4_000_000_000 ^ 0.8 * 1_000_000_000 ^ 0.2 ~= 3_031_433_133
+                assert_approx_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(3_031_433_133u128),
+                    "0.000001"
+                );
+            })
+            // a proportional deposit gets a proportional share
+            .provide_liquidity(
+                &other,
+                "o.om.usdc".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(400_000_000u128, "uom".to_string()),
+                    coin(100_000_000u128, "uusdc".to_string()),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_amount_of_lp_token("o.om.usdc".to_string(), &other.to_string(), |result| {
+                assert_approx_eq!(result.unwrap(), Uint128::new(303_143_313u128), "0.000001");
+            });
+
+        let simulated_return_amount = RefCell::new(Uint128::zero());
+        suite.query_simulation(
+            "o.om.usdc".to_string(),
+            coin(1_000_000u128, "uom"),
+            "uusdc".to_string(),
+            |result| {
+                let response = result.unwrap();
+                // 1 OM ~= 1 USDC, minus 0.3% fees
+                assert_approx_eq!(response.return_amount, Uint128::new(997_000u128), "0.001");
+                *simulated_return_amount.borrow_mut() = response.return_amount;
+            },
+        );
+
+        suite
+            .swap(
+                &other,
+                "uusdc".to_string(),
+                None,
+                None,
+                None,
+                "o.om.usdc".to_string(),
+                vec![coin(1_000_000u128, "uom".to_string())],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event.attributes.iter().any(|attr| {
+                            attr.key == "return_amount"
+                                && attr.value == simulated_return_amount.borrow().to_string()
+                        })
+                    }));
+                },
+            )
+            .query_reverse_simulation(
+                "o.om.usdc".to_string(),
+                coin(997_000u128, "uusdc"),
+                "uom".to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    // the price moved slightly after the previous swap
+                    assert_approx_eq!(response.offer_amount, Uint128::new(1_000_000u128), "0.002");
+                },
+            )
+            // swaps bigger than 30% of the pool balance are rejected
+            .swap(
+                &other,
+                "uusdc".to_string(),
+                None,
+                Some(Decimal::percent(50)),
+                None,
+                "o.om.usdc".to_string(),
+                vec![coin(2_000_000_000u128, "uom".to_string())],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::MaxWeightedSwapRatioExceeded);
+                },
+            );
+
+        // withdraw all the liquidity of the second provider
+        suite
+            .withdraw_liquidity(
+                &other,
+                "o.om.usdc".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(303_143_313u128, lp_denom.clone())],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_amount_of_lp_token("o.om.usdc".to_string(), &other.to_string(), |result| {
+                assert_eq!(result.unwrap(), Uint128::zero());
+            })
+            .query_pools(Some("o.om.usdc".to_string()), None, None, |result| {
+                let response = result.unwrap();
+                let assets = &response.pools[0].pool_info.assets;
+                // roughly the liquidity provided by the creator plus the swap
+                assert_approx_eq!(assets[0].amount, Uint128::new(4_001_000_000u128), "0.0001");
+                assert_approx_eq!(assets[1].amount, Uint128::new(999_003_000u128), "0.0001");
+            });
+    }
+}
+
+mod concentrated_liquidity {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::{ConcentratedPositionAction, ConcentratedPositionsBy};
+    use cosmwasm_std::{assert_approx_eq, Decimal256};
+
+    use super::*;
+
+    #[test]
+    fn cant_create_invalid_concentrated_liquidity_pools() {
+        let mut suite = TestingSuite::default_with_denoms(&["uusdc", "uusdt"]);
+        let creator = suite.creator();
+
+        suite.instantiate_default();
+
+        for (asset_denoms, tick_spacing, initial_price) in [
+            // more than two assets
+            (
+                vec!["uom".to_string(), "uusdc".to_string(), "uusdt".to_string()],
+                10u64,
+                Decimal::one(),
+            ),
+            // zero tick spacing
+            (
+                vec!["uom".to_string(), "uusdc".to_string()],
+                0u64,
+                Decimal::one(),
+            ),
+            // price out of the supported range
+            (
+                vec!["uom".to_string(), "uusdc".to_string()],
+                10u64,
+                Decimal::from_ratio(1u128, 1_000_000_000_000_000u128),
+            ),
+        ] {
+            let asset_decimals = vec![6u8; asset_denoms.len()];
+            suite.create_pool(
+                &creator,
+                asset_denoms,
+                asset_decimals,
+                pool_fees(Decimal::permille(1), Decimal::permille(2)),
+                PoolType::ConcentratedLiquidity {
+                    tick_spacing,
+                    initial_price,
+                },
+                Some("om.usdc".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidConcentratedLiquidityPool);
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn concentrated_liquidity_positions_swaps_and_fees() {
+        let mut suite = TestingSuite::default_with_balances(
+            vec![
+                coin(1_000_000_000_000u128, "uom".to_string()),
+                coin(1_000_000_000_000u128, "uusdc".to_string()),
+                coin(1_000_000_000u128, "uusd".to_string()),
+            ],
+            StargateMock::new("uom".to_string(), "8888".to_string()),
+        );
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let alice = suite.senders[2].clone();
+
+        suite.instantiate_default().create_pool(
+            &creator,
+            vec!["uom".to_string(), "uusdc".to_string()],
+            vec![6u8, 6u8],
+            pool_fees(Decimal::permille(1), Decimal::permille(2)),
+            PoolType::ConcentratedLiquidity {
+                tick_spacing: 10,
+                initial_price: Decimal::one(),
+            },
+            Some("om.usdc".to_string()),
+            vec![coin(1000, "uusd"), coin(8888, "uom")],
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        let pool_identifier = "o.om.usdc".to_string();
+
+        suite
+            // liquidity must be provided through positions
+            .provide_liquidity(
+                &creator,
+                pool_identifier.clone(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(1_000_000u128, "uom"), coin(1_000_000u128, "uusdc")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::UnsupportedConcentratedLiquidityOperation(_) => {}
+                        _ => panic!("Wrong error type, should return ContractError::UnsupportedConcentratedLiquidityOperation"),
+                    }
+                },
+            )
+            // ticks must be multiples of the tick spacing
+            .manage_concentrated_position(
+                &creator,
+                ConcentratedPositionAction::Create {
+                    pool_identifier: pool_identifier.clone(),
+                    lower_tick: -1005,
+                    upper_tick: 1000,
+                    identifier: None,
+                },
+                vec![coin(1_000_000u128, "uom"), coin(1_000_000u128, "uusdc")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::InvalidTickRange {
+                            lower_tick: -1005,
+                            upper_tick: 1000,
+                        }
+                    );
+                },
+            )
+            // position around the current price, ~[0.905, 1.105)
+            .manage_concentrated_position(
+                &creator,
+                ConcentratedPositionAction::Create {
+                    pool_identifier: pool_identifier.clone(),
+                    lower_tick: -1000,
+                    upper_tick: 1000,
+                    identifier: Some("creator".to_string()),
+                },
+                vec![coin(1_000_000u128, "uom"), coin(1_000_000u128, "uusdc")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            // position above the current price, ~[1.221, 1.349), only takes uom
+            .manage_concentrated_position(
+                &other,
+                ConcentratedPositionAction::Create {
+                    pool_identifier: pool_identifier.clone(),
+                    lower_tick: 2000,
+                    upper_tick: 3000,
+                    identifier: None,
+                },
+                vec![coin(1_000_000u128, "uom"), coin(1_000_000u128, "uusdc")],
+                |result| {
+                    let response = result.unwrap();
+                    // all the uusdc is refunded
+                    assert!(response.events.iter().any(|event| {
+                        event.ty == "transfer"
+                            && event
+                                .attributes
+                                .iter()
+                                .any(|attr| attr.key == "amount" && attr.value == "1000000uusdc")
+                    }));
+                },
+            )
+            // only the owner can expand a position
+            .manage_concentrated_position(
+                &alice,
+                ConcentratedPositionAction::Expand {
+                    identifier: "u-creator".to_string(),
+                },
+                vec![coin(1_000u128, "uom"), coin(1_000u128, "uusdc")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::Unauthorized);
+                },
+            );
+
+        let creator_liquidity = RefCell::new(Uint128::zero());
+        suite
+            .query_concentrated_positions(
+                Some(ConcentratedPositionsBy::Owner(creator.to_string())),
+                None,
+                None,
+                |result| {
+                    let positions = result.unwrap().positions;
+                    assert_eq!(positions.len(), 1);
+                    assert_eq!(positions[0].identifier, "u-creator");
+                    assert!(!positions[0].liquidity.is_zero());
+                    *creator_liquidity.borrow_mut() = positions[0].liquidity;
+                },
+            )
+            .query_concentrated_positions(
+                Some(ConcentratedPositionsBy::Owner(other.to_string())),
+                None,
+                None,
+                |result| {
+                    let positions = result.unwrap().positions;
+                    assert_eq!(positions.len(), 1);
+                    assert_eq!(positions[0].identifier, "p-1");
+                },
+            )
+            .query_concentrated_pool_state(pool_identifier.clone(), |result| {
+                let state = result.unwrap();
+                assert_eq!(state.tick, 0);
+                assert_eq!(state.price, Decimal256::one());
+                // only the creator's position is active
+                assert_eq!(state.liquidity, *creator_liquidity.borrow());
+            });
+
+        // swap within the range of the creator's position
+        let simulated_return_amount = RefCell::new(Uint128::zero());
+        suite
+            .query_simulation(
+                pool_identifier.clone(),
+                coin(10_000u128, "uom"),
+                "uusdc".to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    // ~1:1 minus 0.3% fees and a tiny price impact
+                    assert_approx_eq!(response.return_amount, Uint128::new(9_970u128), "0.001");
+                    assert_eq!(response.swap_fee_amount, Uint128::new(19u128));
+                    *simulated_return_amount.borrow_mut() = response.return_amount;
+                },
+            )
+            .query_reverse_simulation(
+                pool_identifier.clone(),
+                coin(9_970u128, "uusdc"),
+                "uom".to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    assert_approx_eq!(response.offer_amount, Uint128::new(10_000u128), "0.001");
+                },
+            )
+            .swap(
+                &alice,
+                "uusdc".to_string(),
+                None,
+                None,
+                None,
+                pool_identifier.clone(),
+                vec![coin(10_000u128, "uom")],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event.attributes.iter().any(|attr| {
+                            attr.key == "return_amount"
+                                && attr.value == simulated_return_amount.borrow().to_string()
+                        })
+                    }));
+                },
+            )
+            .query_concentrated_pool_state(pool_identifier.clone(), |result| {
+                let state = result.unwrap();
+                // the price of uom went down
+                assert!(state.tick < 0);
+                assert!(state.price < Decimal256::one());
+            });
+
+        // the creator's position accrued the uusdc swap fees
+        suite.query_concentrated_positions(
+            Some(ConcentratedPositionsBy::Identifier("u-creator".to_string())),
+            None,
+            None,
+            |result| {
+                let position = result.unwrap().positions[0].clone();
+                assert_eq!(position.fees_owed[0], coin(0u128, "uom"));
+                assert_approx_eq!(position.fees_owed[1].amount, Uint128::new(19u128), "0.1");
+            },
+        );
+
+        // swap a big amount of uusdc, crossing the upper tick of the creator's position and the
+        // gap without liquidity until the other's position
+        suite
+            .swap(
+                &alice,
+                "uom".to_string(),
+                None,
+                Some(Decimal::percent(50)),
+                None,
+                pool_identifier.clone(),
+                vec![coin(1_300_000u128, "uusdc")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_concentrated_pool_state(pool_identifier.clone(), |result| {
+                let state = result.unwrap();
+                assert!(state.tick >= 2000 && state.tick < 3000);
+                assert!(!state.liquidity.is_zero());
+                assert_ne!(state.liquidity, *creator_liquidity.borrow());
+            })
+            .query_concentrated_positions(
+                Some(ConcentratedPositionsBy::Identifier("p-1".to_string())),
+                None,
+                None,
+                |result| {
+                    let position = result.unwrap().positions[0].clone();
+                    // the other's position accrued uom fees from the part of the swap in its range
+                    assert!(!position.fees_owed[0].amount.is_zero());
+                    assert!(position.fees_owed[1].amount.is_zero());
+                },
+            );
+
+        // the creator withdraws all the liquidity, the position is fully converted to uusdc
+        let uom_balance = RefCell::new(Uint128::zero());
+        let uusdc_balance = RefCell::new(Uint128::zero());
+        suite
+            .query_balance(&creator.to_string(), "uom", |result| {
+                *uom_balance.borrow_mut() = result.unwrap().amount;
+            })
+            .query_balance(&creator.to_string(), "uusdc", |result| {
+                *uusdc_balance.borrow_mut() = result.unwrap().amount;
+            })
+            .manage_concentrated_position(
+                &creator,
+                ConcentratedPositionAction::Withdraw {
+                    identifier: "u-creator".to_string(),
+                    liquidity: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&creator.to_string(), "uom", |result| {
+                // the only uom received are the fees of the second swap
+                let received = result.unwrap().amount - *uom_balance.borrow();
+                assert!(!received.is_zero() && received < Uint128::new(5_000u128));
+            })
+            .query_balance(&creator.to_string(), "uusdc", |result| {
+                // the liquidity provided, plus the uom sold at a higher price, plus fees
+                let received = result.unwrap().amount - *uusdc_balance.borrow();
+                assert!(received > Uint128::new(2_000_000u128));
+            })
+            .query_concentrated_positions(
+                Some(ConcentratedPositionsBy::Owner(creator.to_string())),
+                None,
+                None,
+                |result| {
+                    assert!(result.unwrap().positions.is_empty());
+                },
+            )
+            // the other collects the fees of its position
+            .manage_concentrated_position(
+                &other,
+                ConcentratedPositionAction::CollectFees {
+                    identifier: "p-1".to_string(),
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_concentrated_positions(
+                Some(ConcentratedPositionsBy::Identifier("p-1".to_string())),
+                None,
+                None,
+                |result| {
+                    let position = result.unwrap().positions[0].clone();
+                    assert!(position.fees_owed.iter().all(|fee| fee.amount.is_zero()));
+                },
+            )
+            // swap back down into the range of the other's position only
+            .swap(
+                &alice,
+                "uusdc".to_string(),
+                None,
+                None,
+                None,
+                pool_identifier.clone(),
+                vec![coin(1_000u128, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .manage_concentrated_position(
+                &other,
+                ConcentratedPositionAction::Withdraw {
+                    identifier: "p-1".to_string(),
+                    liquidity: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        suite
+            .query_concentrated_pool_state(pool_identifier.clone(), |result| {
+                assert!(result.unwrap().liquidity.is_zero());
+            })
+            .query_pools(Some(pool_identifier), None, None, |result| {
+                let response = result.unwrap();
+                // only rounding dust is left in the pool
+                assert!(response.pools[0]
+                    .pool_info
+                    .assets
+                    .iter()
+                    .all(|asset| asset.amount < Uint128::new(10u128)));
+            });
+    }
+}
+
+mod stableswap_rate_providers {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::RateProvider;
+    use cosmwasm_std::assert_approx_eq;
+
+    use super::*;
+
+    fn default_suite() -> TestingSuite {
+        TestingSuite::default_with_denoms(&["ustom"])
+    }
+
+    #[test]
+    fn cant_create_pool_with_invalid_rate_providers() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+
+        suite.instantiate_default();
+
+        let rate_provider = suite.instantiate_mock_rate_provider(Decimal::zero());
+
+        for (rate_providers, expected_error) in [
+            // one rate provider per asset is required
+            (
+                vec![RateProvider::Fixed {
+                    rate: Decimal::one(),
+                }],
+                ContractError::InvalidRateProviders,
+            ),
+            // fixed rates can't be zero
+            (
+                vec![
+                    RateProvider::Fixed {
+                        rate: Decimal::one(),
+                    },
+                    RateProvider::Fixed {
+                        rate: Decimal::zero(),
+                    },
+                ],
+                ContractError::InvalidRateProviders,
+            ),
+            // rate provider contracts can't return a zero rate
+            (
+                vec![
+                    RateProvider::Fixed {
+                        rate: Decimal::one(),
+                    },
+                    RateProvider::Contract {
+                        contract_addr: rate_provider.to_string(),
+                    },
+                ],
+                ContractError::InvalidExchangeRate {
+                    denom: "ustom".to_string(),
+                },
+            ),
+        ] {
+            suite.create_pool(
+                &creator,
+                vec!["uom".to_string(), "ustom".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: Some(rate_providers),
+                },
+                Some("om.stom".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, expected_error);
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn swaps_with_fixed_rate() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        // 1 ustom is worth 1.2 uom
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec!["uom".to_string(), "ustom".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: Some(vec![
+                        RateProvider::Fixed {
+                            rate: Decimal::one(),
+                        },
+                        RateProvider::Fixed {
+                            rate: Decimal::percent(120),
+                        },
+                    ]),
+                },
+                Some("om.stom".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.om.stom".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(12_000_000u128, "uom"), coin(10_000_000u128, "ustom")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let simulated_return_amount = RefCell::new(Uint128::zero());
+        suite
+            .query_simulation(
+                "o.om.stom".to_string(),
+                coin(10_000u128, "ustom"),
+                "uom".to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    // the pool is balanced at the exchange rate, so the swap happens at ~1.2
+                    assert_approx_eq!(response.return_amount, Uint128::new(11_988u128), "0.0005");
+                    *simulated_return_amount.borrow_mut() = response.return_amount;
+                },
+            )
+            .query_reverse_simulation(
+                "o.om.stom".to_string(),
+                coin(11_988u128, "uom"),
+                "ustom".to_string(),
+                |result| {
+                    assert_approx_eq!(
+                        result.unwrap().offer_amount,
+                        Uint128::new(10_000u128),
+                        "0.0005"
+                    );
+                },
+            )
+            .swap(
+                &other,
+                "uom".to_string(),
+                None,
+                None,
+                None,
+                "o.om.stom".to_string(),
+                vec![coin(10_000u128, "ustom")],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event.attributes.iter().any(|attr| {
+                            attr.key == "return_amount"
+                                && attr.value == simulated_return_amount.borrow().to_string()
+                        })
+                    }));
+                },
+            )
+            .query_simulation(
+                "o.om.stom".to_string(),
+                coin(12_000u128, "uom"),
+                "ustom".to_string(),
+                |result| {
+                    // and ~1/1.2 the other way around
+                    assert_approx_eq!(
+                        result.unwrap().return_amount,
+                        Uint128::new(9_990u128),
+                        "0.0005"
+                    );
+                },
+            );
+    }
+
+    #[test]
+    fn swaps_and_provides_liquidity_with_rate_provider_contract() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite.instantiate_default();
+
+        let rate_provider = suite.instantiate_mock_rate_provider(Decimal::percent(110));
+        let lp_denom = suite.get_lp_denom("o.om.stom".to_string());
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["uom".to_string(), "ustom".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: Some(vec![
+                        RateProvider::Fixed {
+                            rate: Decimal::one(),
+                        },
+                        RateProvider::Contract {
+                            contract_addr: rate_provider.to_string(),
+                        },
+                    ]),
+                },
+                Some("om.stom".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.om.stom".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(11_000_000u128, "uom"), coin(10_000_000u128, "ustom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_simulation(
+                "o.om.stom".to_string(),
+                coin(10_000u128, "ustom"),
+                "uom".to_string(),
+                |result| {
+                    assert_approx_eq!(
+                        result.unwrap().return_amount,
+                        Uint128::new(10_989u128),
+                        "0.0005"
+                    );
+                },
+            );
+
+        // a deposit balanced at the exchange rate mints shares proportional to the deposit
+        let creator_lp = RefCell::new(Uint128::zero());
+        suite
+            .query_balance(&creator.to_string(), &lp_denom, |result| {
+                *creator_lp.borrow_mut() = result.unwrap().amount;
+            })
+            .provide_liquidity(
+                &other,
+                "o.om.stom".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(1_100_000u128, "uom"), coin(1_000_000u128, "ustom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                // the creator's share excludes the MINIMUM_LIQUIDITY_AMOUNT locked in the pool
+                let expected = creator_lp
+                    .borrow()
+                    .checked_add(MINIMUM_LIQUIDITY_AMOUNT)
+                    .unwrap()
+                    .multiply_ratio(1u128, 10u128);
+                assert_approx_eq!(result.unwrap().amount, expected, "0.0001");
+            });
+
+        // the stOM accrues value, the swaps follow the new rate
+        suite
+            .set_mock_exchange_rate(&rate_provider, Decimal::percent(112), |result| {
+                result.unwrap();
+            })
+            .query_simulation(
+                "o.om.stom".to_string(),
+                coin(10_000u128, "ustom"),
+                "uom".to_string(),
+                |result| {
+                    let return_amount = result.unwrap().return_amount;
+                    assert!(return_amount > Uint128::new(11_100u128));
+                    assert!(return_amount < Uint128::new(11_200u128));
+                },
+            )
+            .swap(
+                &other,
+                "uom".to_string(),
+                None,
+                None,
+                None,
+                "o.om.stom".to_string(),
+                vec![coin(10_000u128, "ustom")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+    }
+}
+
+mod amp_ramping {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::Timestamp;
+
+    use super::*;
+
+    const START_TIME: u64 = 1_720_000_000;
+    const DAY: u64 = 86_400;
+
+    fn assert_amp(suite: &TestingSuite, expected_amp: u64) {
+        suite.query_pools(Some("o.whale.uluna".to_string()), None, None, |result| {
+            let pool_type = result.unwrap().pools[0].pool_info.pool_type.clone();
+            match pool_type {
+                PoolType::StableSwap { amp, .. } => assert_eq!(amp, expected_amp),
+                _ => panic!("Wrong pool type, should be a stable swap pool"),
+            }
+        });
+    }
+
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+        let pool_fees = pool_fees(Decimal::zero(), Decimal::permille(1));
+
+        suite
+            .instantiate_default()
+            .set_time(Timestamp::from_seconds(START_TIME))
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: None,
+                },
+                pool_fees.clone(),
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            )
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees,
+                "whale.uluna.xyk",
+                vec![],
+            );
+
+        suite
+    }
+
+    #[test]
+    fn cant_ramp_amp_with_invalid_params() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite
+            .ramp_amp(
+                &other,
+                "o.whale.uluna".to_string(),
+                200,
+                START_TIME + DAY,
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::OwnershipError { .. } => {}
+                        _ => {
+                            panic!("Wrong error type, should return ContractError::OwnershipError")
+                        }
+                    }
+                },
+            )
+            .ramp_amp(
+                &creator,
+                "o.whale.uluna.xyk".to_string(),
+                200,
+                START_TIME + DAY,
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::NotAStableSwapPool);
+                },
+            )
+            .ramp_amp(
+                &creator,
+                "o.whale.uluna".to_string(),
+                200,
+                START_TIME + DAY - 1,
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::InvalidAmpRampEndTime { min_ramp_time: DAY }
+                    );
+                },
+            )
+            .ramp_amp(
+                &creator,
+                "o.whale.uluna".to_string(),
+                0,
+                START_TIME + DAY,
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::InvalidTargetAmp {
+                            target_amp: 0,
+                            max_amp: 1_000_000
+                        }
+                    );
+                },
+            )
+            .ramp_amp(
+                &creator,
+                "o.whale.uluna".to_string(),
+                1_001,
+                START_TIME + DAY,
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::AmpChangeTooLarge {
+                            current_amp: 100,
+                            target_amp: 1_001,
+                            max_amp_change: 10,
+                        }
+                    );
+                },
+            )
+            .ramp_amp(
+                &creator,
+                "o.whale.uluna".to_string(),
+                9,
+                START_TIME + DAY,
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::AmpChangeTooLarge {
+                            current_amp: 100,
+                            target_amp: 9,
+                            max_amp_change: 10,
+                        }
+                    );
+                },
+            )
+            .ramp_amp(
+                &creator,
+                "o.whale.uluna".to_string(),
+                1_000,
+                START_TIME + DAY,
+                |result| {
+                    result.unwrap();
+                },
+            )
+            // can't ramp again until a day has passed since the last ramp started
+            .ramp_amp(
+                &creator,
+                "o.whale.uluna".to_string(),
+                500,
+                START_TIME + 2 * DAY,
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::AmpRampTooSoon { min_ramp_time: DAY });
+                },
+            );
+    }
+
+    #[test]
+    fn ramps_amp_linearly() {
+        let mut suite = setup();
+        let creator = suite.creator();
+
+        let return_amount_before_ramp = RefCell::new(Uint128::zero());
+        suite.query_simulation(
+            "o.whale.uluna".to_string(),
+            coin(500_000u128, "uwhale"),
+            "uluna".to_string(),
+            |result| {
+                *return_amount_before_ramp.borrow_mut() = result.unwrap().return_amount;
+            },
+        );
+
+        suite.ramp_amp(
+            &creator,
+            "o.whale.uluna".to_string(),
+            1_000,
+            START_TIME + 10 * DAY,
+            |result| {
+                result.unwrap();
+            },
+        );
+        assert_amp(&suite, 100);
+
+        suite.set_time(Timestamp::from_seconds(START_TIME + 5 * DAY));
+        assert_amp(&suite, 550);
+
+        // the higher amp flattens the curve, so there's less slippage
+        suite.query_simulation(
+            "o.whale.uluna".to_string(),
+            coin(500_000u128, "uwhale"),
+            "uluna".to_string(),
+            |result| {
+                assert!(result.unwrap().return_amount > *return_amount_before_ramp.borrow());
+            },
+        );
+
+        // swaps and liquidity provisions use the current amp as well
+        suite
+            .swap(
+                &creator,
+                "uluna".to_string(),
+                None,
+                None,
+                None,
+                "o.whale.uluna".to_string(),
+                vec![coin(1_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.whale.uluna".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(1_000u128, "uwhale"), coin(1_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+        assert_amp(&suite, 550);
+
+        suite.set_time(Timestamp::from_seconds(START_TIME + 10 * DAY));
+        assert_amp(&suite, 1_000);
+
+        suite.set_time(Timestamp::from_seconds(START_TIME + 20 * DAY));
+        assert_amp(&suite, 1_000);
+
+        // ramp down
+        suite.ramp_amp(
+            &creator,
+            "o.whale.uluna".to_string(),
+            200,
+            START_TIME + 24 * DAY,
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        suite.set_time(Timestamp::from_seconds(START_TIME + 21 * DAY));
+        assert_amp(&suite, 800);
+    }
+
+    #[test]
+    fn stops_ramping_amp() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite.ramp_amp(
+            &creator,
+            "o.whale.uluna".to_string(),
+            1_000,
+            START_TIME + 10 * DAY,
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        suite
+            .set_time(Timestamp::from_seconds(START_TIME + 2 * DAY))
+            .stop_ramp_amp(&other, "o.whale.uluna".to_string(), |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::OwnershipError { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::OwnershipError"),
+                }
+            })
+            .stop_ramp_amp(&creator, "o.whale.uluna".to_string(), |result| {
+                result.unwrap();
+            });
+        assert_amp(&suite, 280);
+
+        suite.set_time(Timestamp::from_seconds(START_TIME + 10 * DAY));
+        assert_amp(&suite, 280);
+    }
+}
+
+mod withdraw_liquidity_one_coin {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn withdraws_liquidity_in_one_coin_from_stableswap_pool() {
+        let mut suite = TestingSuite::default_with_denoms(&["uusdc", "uusdt", "udai"]);
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        let pool_fees = pool_fees(Decimal::zero(), Decimal::permille(1));
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string(), "udai".to_string()],
+                vec![6u8, 6u8, 6u8],
+                pool_fees.clone(),
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: None,
+                },
+                Some("3pool".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string()],
+                vec![6u8, 6u8],
+                pool_fees,
+                PoolType::ConstantProduct,
+                Some("xyk".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "uusdc"),
+                    coin(1_000_000u128, "uusdt"),
+                    coin(1_000_000u128, "udai"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &other,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(100_000u128, "uusdc"),
+                    coin(100_000u128, "uusdt"),
+                    coin(100_000u128, "udai"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let lp_denom = suite.get_lp_denom("o.3pool".to_string());
+
+        let lp_balance = RefCell::new(Uint128::zero());
+        suite.query_balance(&other.to_string(), &lp_denom, |result| {
+            *lp_balance.borrow_mut() = result.unwrap().amount;
+        });
+        let lp_balance = *lp_balance.borrow();
+
+        suite
+            .withdraw_liquidity_one_coin(
+                &other,
+                "o.3pool".to_string(),
+                "uusdc".to_string(),
+                Uint128::new(300_000u128),
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::WithdrawAmountBelowMinimum { min_amount, .. } => {
+                            assert_eq!(min_amount, Uint128::new(300_000u128));
+                        }
+                        _ => panic!("Wrong error type, should return ContractError::WithdrawAmountBelowMinimum"),
+                    }
+                },
+            )
+            .withdraw_liquidity_one_coin(
+                &other,
+                "o.3pool".to_string(),
+                "uom".to_string(),
+                Uint128::zero(),
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::AssetMismatch);
+                },
+            )
+            .withdraw_liquidity_one_coin(
+                &other,
+                "o.xyk".to_string(),
+                "uusdc".to_string(),
+                Uint128::zero(),
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::NotAStableSwapPool);
+                },
+            );
+
+        let uusdc_balance = RefCell::new(Uint128::zero());
+        suite
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                *uusdc_balance.borrow_mut() = result.unwrap().amount;
+            })
+            .withdraw_liquidity_one_coin(
+                &other,
+                "o.3pool".to_string(),
+                "uusdc".to_string(),
+                Uint128::new(299_000u128),
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event
+                            .attributes
+                            .iter()
+                            .any(|attr| attr.key == "imbalance_fee" && attr.value != "0uusdc")
+                    }));
+                },
+            )
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                let received = result.unwrap().amount - *uusdc_balance.borrow();
+                // the whole share is withdrawn in uusdc, minus the imbalance fee and the slippage
+                assert!(received < Uint128::new(300_000u128));
+                assert_approx_eq!(received, Uint128::new(300_000u128), "0.002");
+            })
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .query_pools(Some("o.3pool".to_string()), None, None, |result| {
+                let response = result.unwrap();
+                let assets = &response.pools[0].pool_info.assets;
+                // only uusdc left the pool, the imbalance fee stays in it
+                assert!(assets[0].amount > Uint128::new(800_000u128));
+                assert!(assets[0].amount < Uint128::new(801_000u128));
+                assert_eq!(assets[1], coin(1_100_000u128, "uusdt"));
+                assert_eq!(assets[2], coin(1_100_000u128, "udai"));
+            });
+    }
+}
+
+mod withdraw_liquidity_imbalance {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[test]
+    fn withdraws_exact_amounts_from_stableswap_pool() {
+        let mut suite = TestingSuite::default_with_denoms(&["uusdc", "uusdt", "udai"]);
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        let pool_fees = pool_fees(Decimal::zero(), Decimal::permille(1));
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string(), "udai".to_string()],
+                vec![6u8, 6u8, 6u8],
+                pool_fees,
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: None,
+                },
+                Some("3pool".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "uusdc"),
+                    coin(1_000_000u128, "uusdt"),
+                    coin(1_000_000u128, "udai"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &other,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(100_000u128, "uusdc"),
+                    coin(100_000u128, "uusdt"),
+                    coin(100_000u128, "udai"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let lp_denom = suite.get_lp_denom("o.3pool".to_string());
+
+        let lp_balance = RefCell::new(Uint128::zero());
+        suite.query_balance(&other.to_string(), &lp_denom, |result| {
+            *lp_balance.borrow_mut() = result.unwrap().amount;
+        });
+        let lp_balance = *lp_balance.borrow();
+
+        suite
+            .withdraw_liquidity_imbalance(
+                &other,
+                "o.3pool".to_string(),
+                vec![],
+                lp_balance,
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::EmptyAssets);
+                },
+            )
+            .withdraw_liquidity_imbalance(
+                &other,
+                "o.3pool".to_string(),
+                vec![coin(100_000u128, "uom")],
+                lp_balance,
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidWithdrawAmounts);
+                },
+            )
+            .withdraw_liquidity_imbalance(
+                &other,
+                "o.3pool".to_string(),
+                vec![coin(1_100_000u128, "uusdc")],
+                lp_balance,
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidWithdrawAmounts);
+                },
+            )
+            // withdrawing 200_000 of value requires burning more than the 150_000 LP allowed
+            .withdraw_liquidity_imbalance(
+                &other,
+                "o.3pool".to_string(),
+                vec![coin(150_000u128, "uusdc"), coin(50_000u128, "udai")],
+                Uint128::new(150_000u128),
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::LpBurnAboveMaximum { max_lp_burn, .. } => {
+                            assert_eq!(max_lp_burn, Uint128::new(150_000u128));
+                        }
+                        _ => panic!(
+                            "Wrong error type, should return ContractError::LpBurnAboveMaximum"
+                        ),
+                    }
+                },
+            );
+
+        let lp_burned = RefCell::new(Uint128::zero());
+        suite
+            .withdraw_liquidity_imbalance(
+                &other,
+                "o.3pool".to_string(),
+                vec![coin(150_000u128, "uusdc"), coin(50_000u128, "udai")],
+                lp_balance,
+                vec![coin(lp_balance.u128(), &lp_denom)],
+                |result| {
+                    let response = result.unwrap();
+                    for event in response.events {
+                        for attr in event.attributes {
+                            if attr.key == "withdrawn_share" {
+                                *lp_burned.borrow_mut() = attr.value.parse().unwrap();
+                            }
+                        }
+                    }
+                },
+            )
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                // the LP tokens that were not burned are returned
+                assert_eq!(result.unwrap().amount, lp_balance - *lp_burned.borrow());
+            })
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128 - 100_000u128 + 150_000u128)
+                );
+            })
+            .query_balance(&other.to_string(), "uusdt", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128 - 100_000u128)
+                );
+            })
+            .query_balance(&other.to_string(), "udai", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128 - 100_000u128 + 50_000u128)
+                );
+            })
+            .query_pools(Some("o.3pool".to_string()), None, None, |result| {
+                let response = result.unwrap();
+                let assets = &response.pools[0].pool_info.assets;
+                assert_eq!(assets[0], coin(950_000u128, "uusdc"));
+                assert_eq!(assets[1], coin(1_100_000u128, "uusdt"));
+                assert_eq!(assets[2], coin(1_050_000u128, "udai"));
+            });
+
+        // the imbalanced withdrawal burns more LP than the proportional value withdrawn
+        let lp_burned = *lp_burned.borrow();
+        assert!(lp_burned > Uint128::new(200_000u128));
+        assert!(lp_burned < Uint128::new(201_000u128));
+    }
+}
+
+mod solidly_stable_pools {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn cant_create_solidly_stable_pool_with_more_than_two_assets() {
+        let mut suite = TestingSuite::default_with_denoms(&["ustom", "uusdc"]);
+        let creator = suite.creator();
+
+        suite.instantiate_default().create_pool(
+            &creator,
+            vec!["uom".to_string(), "ustom".to_string(), "uusdc".to_string()],
+            vec![6u8, 6u8, 6u8],
+            pool_fees(Decimal::permille(1), Decimal::permille(2)),
+            PoolType::SolidlyStable,
+            Some("om.stom.usdc".to_string()),
+            vec![coin(1000, "uusd"), coin(8888, "uom")],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                assert_eq!(err, ContractError::InvalidSolidlyStablePool);
+            },
+        );
+    }
+
+    #[test]
+    fn solidly_stable_pool_provide_swap_and_withdraw() {
+        let mut suite = TestingSuite::default_with_balances(
+            vec![
+                coin(1_000_000_000_000u128, "uom".to_string()),
+                coin(1_000_000_000_000_000_000_000_000u128, "astom".to_string()),
+                coin(1_000_000_000u128, "uusd".to_string()),
+            ],
+            StargateMock::new("uom".to_string(), "8888".to_string()),
+        );
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        // OM/stOM pool, stOM has 18 decimals
+        suite.instantiate_default().create_pool(
+            &creator,
+            vec!["uom".to_string(), "astom".to_string()],
+            vec![6u8, 18u8],
+            pool_fees(Decimal::permille(1), Decimal::permille(2)),
+            PoolType::SolidlyStable,
+            Some("om.stom".to_string()),
+            vec![coin(1000, "uusd"), coin(8888, "uom")],
+            |result| {
+                let response = result.unwrap();
+                assert!(response.events.iter().any(|event| event
+                    .attributes
+                    .iter()
+                    .any(|attr| attr.key == "pool_type" && attr.value == "SolidlyStable")));
+            },
+        );
+
+        let lp_denom = suite.get_lp_denom("o.om.stom".to_string());
+
+        // 1_000 OM and 1_000 stOM
+        suite
+            .provide_liquidity(
+                &creator,
+                "o.om.stom".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000_000u128, "uom".to_string()),
+                    coin(1_000_000_000_000_000_000_000u128, "astom".to_string()),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &other,
+                "o.om.stom".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(100_000_000u128, "uom".to_string()),
+                    coin(100_000_000_000_000_000_000u128, "astom".to_string()),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_amount_of_lp_token("o.om.stom".to_string(), &other.to_string(), |result| {
+                // sqrt(1_000_000_000 * 10^21) / 10
+                assert_eq!(result.unwrap(), Uint128::new(100_000_000_000_000u128));
+            });
+
+        let simulated_return_amount = RefCell::new(Uint128::zero());
+        suite.query_simulation(
+            "o.om.stom".to_string(),
+            coin(10_000_000u128, "uom"),
+            "astom".to_string(),
+            |result| {
+                let response = result.unwrap();
+                // a ~1% swap stays on the flat part of the curve, i.e. 10 OM ~= 10 stOM minus
+                // 0.3% fees, while a constant product pool would return ~9.88 stOM
+                assert_approx_eq!(
+                    response.return_amount,
+                    Uint128::new(9_970_000_000_000_000_000u128),
+                    "0.0001"
+                );
+                *simulated_return_amount.borrow_mut() = response.return_amount;
+            },
+        );
+
+        suite
+            .swap(
+                &other,
+                "astom".to_string(),
+                None,
+                None,
+                None,
+                "o.om.stom".to_string(),
+                vec![coin(10_000_000u128, "uom".to_string())],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event.attributes.iter().any(|attr| {
+                            attr.key == "return_amount"
+                                && attr.value == simulated_return_amount.borrow().to_string()
+                        })
+                    }));
+                },
+            )
+            .query_reverse_simulation(
+                "o.om.stom".to_string(),
+                coin(9_970_000_000_000_000_000u128, "astom"),
+                "uom".to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    assert_approx_eq!(response.offer_amount, Uint128::new(10_000_000u128), "0.001");
+                },
+            );
+
+        // withdraw all the liquidity of the second provider
+        suite
+            .withdraw_liquidity(
+                &other,
+                "o.om.stom".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(100_000_000_000_000u128, lp_denom.clone())],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_amount_of_lp_token("o.om.stom".to_string(), &other.to_string(), |result| {
+                assert_eq!(result.unwrap(), Uint128::zero());
+            })
+            .query_pools(Some("o.om.stom".to_string()), None, None, |result| {
+                let response = result.unwrap();
+                let assets = &response.pools[0].pool_info.assets;
+                // roughly the liquidity provided by the creator plus the swap
+                assert_approx_eq!(assets[0].amount, Uint128::new(1_009_090_909u128), "0.0001");
+                assert_approx_eq!(
+                    assets[1].amount,
+                    Uint128::new(990_936_363_636_363_636_363u128),
+                    "0.0001"
+                );
+            });
+    }
+}
+
+mod liquidity_bootstrapping_pools {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::{assert_approx_eq, Timestamp};
+
+    use super::*;
+
+    const START_TIME: u64 = 1_720_000_000;
+    const DAY: u64 = 86_400;
+
+    fn pool_type(start_time: u64, end_time: u64) -> PoolType {
+        PoolType::LiquidityBootstrapping {
+            start_weights: vec![Decimal::percent(90), Decimal::percent(10)],
+            end_weights: vec![Decimal::percent(50), Decimal::percent(50)],
+            start_time,
+            end_time,
+        }
+    }
+
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_balances(
+            vec![
+                coin(1_000_000_000_000u128, "ulaunch".to_string()),
+                coin(1_000_000_000_000u128, "uusdc".to_string()),
+                coin(1_000_000_000u128, "uusd".to_string()),
+                coin(1_000_000_000u128, "uom".to_string()),
+            ],
+            StargateMock::new("uom".to_string(), "8888".to_string()),
+        );
+        suite
+            .instantiate_default()
+            .set_time(Timestamp::from_seconds(START_TIME));
+
+        suite
+    }
+
+    #[test]
+    fn cant_create_liquidity_bootstrapping_pool_with_invalid_schedule() {
+        let mut suite = setup();
+        let creator = suite.creator();
+
+        for (pool_type, expected_err) in [
+            // the end time is before the start time
+            (
+                pool_type(START_TIME + DAY, START_TIME),
+                ContractError::InvalidWeightSchedule,
+            ),
+            // the schedule is already over
+            (
+                pool_type(START_TIME - 2 * DAY, START_TIME - DAY),
+                ContractError::InvalidWeightSchedule,
+            ),
+            // the end weights don't add up to 1
+            (
+                PoolType::LiquidityBootstrapping {
+                    start_weights: vec![Decimal::percent(90), Decimal::percent(10)],
+                    end_weights: vec![Decimal::percent(50), Decimal::percent(60)],
+                    start_time: START_TIME,
+                    end_time: START_TIME + DAY,
+                },
+                ContractError::InvalidPoolWeights,
+            ),
+        ] {
+            suite.create_pool(
+                &creator,
+                vec!["ulaunch".to_string(), "uusdc".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(3)),
+                pool_type,
+                Some("launch.usdc".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, expected_err);
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn liquidity_bootstrapping_pool_price_moves_with_weights() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        // the weights go from 90/10 to 50/50 over 4 days
+        suite
+            .create_pool(
+                &creator,
+                vec!["ulaunch".to_string(), "uusdc".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(3)),
+                pool_type(START_TIME, START_TIME + 4 * DAY),
+                Some("launch.usdc".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| event
+                        .attributes
+                        .iter()
+                        .any(|attr| attr.key == "pool_type"
+                            && attr.value == "LiquidityBootstrapping")));
+                },
+            )
+            // 9_000 LAUNCH and 1_000 USDC at 90/10, i.e. 1 LAUNCH = 1 USDC
+            .provide_liquidity(
+                &creator,
+                "o.launch.usdc".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(9_000_000_000u128, "ulaunch".to_string()),
+                    coin(1_000_000_000u128, "uusdc".to_string()),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_simulation(
+                "o.launch.usdc".to_string(),
+                coin(1_000_000u128, "uusdc"),
+                "ulaunch".to_string(),
+                |result| {
+                    assert_approx_eq!(
+                        result.unwrap().return_amount,
+                        Uint128::new(997_000u128),
+                        "0.001"
+                    );
+                },
+            );
+
+        // halfway through the schedule the weights are 70/30, so 1 LAUNCH = 0.259 USDC
+        suite.add_one_day().add_one_day().query_simulation(
+            "o.launch.usdc".to_string(),
+            coin(1_000_000u128, "uusdc"),
+            "ulaunch".to_string(),
+            |result| {
+                // (9_000 / 0.7) / (1_000 / 0.3) = 3.857 LAUNCH per USDC, minus fees
+                assert_approx_eq!(
+                    result.unwrap().return_amount,
+                    Uint128::new(3_845_571u128),
+                    "0.001"
+                );
+            },
+        );
+
+        let simulated_return_amount = RefCell::new(Uint128::zero());
+        suite.query_simulation(
+            "o.launch.usdc".to_string(),
+            coin(1_000_000u128, "uusdc"),
+            "ulaunch".to_string(),
+            |result| {
+                *simulated_return_amount.borrow_mut() = result.unwrap().return_amount;
+            },
+        );
+
+        suite
+            .swap(
+                &other,
+                "ulaunch".to_string(),
+                None,
+                None,
+                None,
+                "o.launch.usdc".to_string(),
+                vec![coin(1_000_000u128, "uusdc".to_string())],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event.attributes.iter().any(|attr| {
+                            attr.key == "return_amount"
+                                && attr.value == simulated_return_amount.borrow().to_string()
+                        })
+                    }));
+                },
+            )
+            .query_reverse_simulation(
+                "o.launch.usdc".to_string(),
+                coin(3_845_571u128, "ulaunch"),
+                "uusdc".to_string(),
+                |result| {
+                    // the price moved slightly after the previous swap
+                    assert_approx_eq!(
+                        result.unwrap().offer_amount,
+                        Uint128::new(1_000_000u128),
+                        "0.005"
+                    );
+                },
+            )
+            // the schedule is stored in the pool as it was created
+            .query_pools(Some("o.launch.usdc".to_string()), None, None, |result| {
+                assert_eq!(
+                    result.unwrap().pools[0].pool_info.pool_type,
+                    pool_type(START_TIME, START_TIME + 4 * DAY)
+                );
+            });
+
+        // after the schedule is over the weights stay at 50/50
+        suite
+            .add_one_day()
+            .add_one_day()
+            .add_one_day()
+            .query_simulation(
+                "o.launch.usdc".to_string(),
+                coin(1_000_000u128, "uusdc"),
+                "ulaunch".to_string(),
+                |result| {
+                    // ~9_000 / ~1_001 = 8.99 LAUNCH per USDC, minus fees
+                    assert_approx_eq!(
+                        result.unwrap().return_amount,
+                        Uint128::new(8_954_600u128),
+                        "0.001"
+                    );
+                },
+            );
+    }
+}
+
+mod metapools {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::{assert_approx_eq, Timestamp};
+
+    use super::*;
+
+    fn stableswap_pool_type() -> PoolType {
+        PoolType::StableSwap {
+            amp: 100,
+            rate_providers: None,
+        }
+    }
+
+    #[test]
+    fn validates_metapools_on_creation() {
+        let mut suite = TestingSuite::default_with_denoms(&["uusdc", "uusdt", "udai", "unew"]);
+        let creator = suite.creator();
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string(), "udai".to_string()],
+                vec![6u8, 6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("3pool".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::ConstantProduct,
+                Some("xyk".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let base_lp_denom = suite.get_lp_denom("o.3pool".to_string());
+        let xyk_lp_denom = suite.get_lp_denom("o.xyk".to_string());
+
+        suite
+            // the base pool LP token must be the second asset
+            .create_pool(
+                &creator,
+                vec![base_lp_denom.clone(), "unew".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("meta".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidMetapool);
+                },
+            )
+            // a metapool pairs a single asset against the base pool LP token
+            .create_pool(
+                &creator,
+                vec![
+                    "unew".to_string(),
+                    "uusdc".to_string(),
+                    base_lp_denom.clone(),
+                ],
+                vec![6u8, 6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("meta".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidMetapool);
+                },
+            )
+            // the base pool must be a stable swap pool
+            .create_pool(
+                &creator,
+                vec!["unew".to_string(), xyk_lp_denom],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("meta".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidMetapool);
+                },
+            )
+            .create_pool(
+                &creator,
+                vec!["unew".to_string(), base_lp_denom],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("meta".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let meta_lp_denom = suite.get_lp_denom("o.meta".to_string());
+
+        // the base pool can't be a metapool itself
+        suite.create_pool(
+            &creator,
+            vec!["uusdt".to_string(), meta_lp_denom],
+            vec![6u8, 6u8],
+            pool_fees(Decimal::zero(), Decimal::permille(1)),
+            stableswap_pool_type(),
+            Some("metameta".to_string()),
+            vec![coin(1000, "uusd"), coin(8888, "uom")],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                assert_eq!(err, ContractError::InvalidMetapool);
+            },
+        );
+    }
+
+    #[test]
+    fn swaps_underlying_assets_through_metapool() {
+        let mut suite = TestingSuite::default_with_denoms(&["uusdc", "uusdt", "udai", "unew"]);
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string(), "udai".to_string()],
+                vec![6u8, 6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("3pool".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "uusdc"),
+                    coin(1_000_000u128, "uusdt"),
+                    coin(1_000_000u128, "udai"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let base_lp_denom = suite.get_lp_denom("o.3pool".to_string());
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["unew".to_string(), base_lp_denom.clone()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("meta".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.meta".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "unew"),
+                    coin(1_000_000u128, &base_lp_denom),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .swap_underlying(
+                &other,
+                "uusdt".to_string(),
+                None,
+                None,
+                None,
+                "o.3pool".to_string(),
+                vec![coin(10_000u128, "uusdc")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::NotAMetapool);
+                },
+            )
+            .swap_underlying(
+                &other,
+                "uusdt".to_string(),
+                None,
+                None,
+                None,
+                "o.meta".to_string(),
+                vec![coin(10_000u128, "uusdc")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::AssetMismatch);
+                },
+            )
+            .swap_underlying(
+                &other,
+                "uusdc".to_string(),
+                Some(Uint128::new(10_000u128)),
+                None,
+                None,
+                "o.meta".to_string(),
+                vec![coin(10_000u128, "unew")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::MinimumReceiveAssertion { .. } => {}
+                        _ => panic!("Wrong error type, should return ContractError::MinimumReceiveAssertion"),
+                    }
+                },
+            );
+
+        // swap the metapool asset for a base pool asset
+        let uusdc_balance = RefCell::new(Uint128::zero());
+        suite
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                *uusdc_balance.borrow_mut() = result.unwrap().amount;
+            })
+            .swap_underlying(
+                &other,
+                "uusdc".to_string(),
+                Some(Uint128::new(9_900u128)),
+                None,
+                None,
+                "o.meta".to_string(),
+                vec![coin(10_000u128, "unew")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                let received = result.unwrap().amount - *uusdc_balance.borrow();
+                // the virtual price of the base pool LP token is 1, so the swap is 1:1 minus fees
+                assert!(received < Uint128::new(10_000u128));
+                assert_approx_eq!(received, Uint128::new(10_000u128), "0.003");
+            });
+
+        // swap a base pool asset for the metapool asset
+        let unew_balance = RefCell::new(Uint128::zero());
+        suite
+            .query_balance(&other.to_string(), "unew", |result| {
+                *unew_balance.borrow_mut() = result.unwrap().amount;
+            })
+            .swap_underlying(
+                &other,
+                "unew".to_string(),
+                Some(Uint128::new(9_900u128)),
+                None,
+                None,
+                "o.meta".to_string(),
+                vec![coin(10_000u128, "udai")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "unew", |result| {
+                let received = result.unwrap().amount - *unew_balance.borrow();
+                assert!(received < Uint128::new(10_000u128));
+                assert_approx_eq!(received, Uint128::new(10_000u128), "0.003");
+            })
+            .query_pools(Some("o.3pool".to_string()), None, None, |result| {
+                let response = result.unwrap();
+                let assets = &response.pools[0].pool_info.assets;
+                assert!(assets[0].amount < Uint128::new(1_000_000u128 - 9_900u128));
+                assert_eq!(assets[1], coin(1_000_000u128, "uusdt"));
+                assert_eq!(assets[2], coin(1_010_000u128, "udai"));
+            });
+
+        // the base pool LP tokens held by the contract are the ones in the metapool, plus the
+        // minimum liquidity of the base pool
+        let meta_lp_balance = RefCell::new(Uint128::zero());
+        suite.query_pools(Some("o.meta".to_string()), None, None, |result| {
+            let response = result.unwrap();
+            *meta_lp_balance.borrow_mut() = response.pools[0].pool_info.assets[1].amount;
+        });
+        let pool_manager_addr = suite.pool_manager_addr.to_string();
+        suite.query_balance(&pool_manager_addr, &base_lp_denom, |result| {
+            assert_eq!(
+                result.unwrap().amount,
+                *meta_lp_balance.borrow() + MINIMUM_LIQUIDITY_AMOUNT
+            );
+        });
+    }
+
+    #[test]
+    fn base_pool_virtual_price_follows_amp_ramp() {
+        const START_TIME: u64 = 1_720_000_000;
+        const DAY: u64 = 86_400;
+
+        let mut suite = TestingSuite::default_with_denoms(&["uusdc", "uusdt", "udai", "unew"]);
+        let creator = suite.creator();
+
+        suite
+            .instantiate_default()
+            .set_time(Timestamp::from_seconds(START_TIME))
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string(), "udai".to_string()],
+                vec![6u8, 6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("3pool".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            // the base pool is imbalanced, so its invariant depends on the amp
+            .provide_liquidity(
+                &creator,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "uusdc"),
+                    coin(100_000u128, "uusdt"),
+                    coin(1_000_000u128, "udai"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let base_lp_denom = suite.get_lp_denom("o.3pool".to_string());
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["unew".to_string(), base_lp_denom.clone()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                stableswap_pool_type(),
+                Some("meta".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.meta".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "unew"),
+                    coin(1_000_000u128, &base_lp_denom),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let return_amount_before_ramp = RefCell::new(Uint128::zero());
+        suite
+            .query_simulation(
+                "o.meta".to_string(),
+                coin(10_000u128, "unew"),
+                base_lp_denom.clone(),
+                |result| {
+                    *return_amount_before_ramp.borrow_mut() = result.unwrap().return_amount;
+                },
+            )
+            .ramp_amp(
+                &creator,
+                "o.3pool".to_string(),
+                1_000,
+                START_TIME + 10 * DAY,
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .set_time(Timestamp::from_seconds(START_TIME + 10 * DAY))
+            // the base pool hasn't been touched since the ramp started, but its virtual price is
+            // computed with the current amp, which raises the invariant of the imbalanced pool
+            .query_simulation(
+                "o.meta".to_string(),
+                coin(10_000u128, "unew"),
+                base_lp_denom,
+                |result| {
+                    assert!(result.unwrap().return_amount < *return_amount_before_ramp.borrow());
+                },
+            );
+    }
+}
+
+mod oracle_pegged_pools {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::{assert_approx_eq, Timestamp};
+
+    use super::*;
+
+    fn default_suite() -> TestingSuite {
+        TestingSuite::default_with_denoms(&["urwa", "uusdc", "uusdt"])
+    }
+
+    fn oracle_pegged(oracle: &Addr) -> PoolType {
+        PoolType::OraclePegged {
+            oracle_addr: oracle.to_string(),
+            amp: 100,
+            max_staleness: 3_600,
+            max_deviation: Decimal::percent(5),
+        }
+    }
+
+    #[test]
+    fn cant_create_invalid_oracle_pegged_pools() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+
+        suite.instantiate_default();
+
+        let oracle = suite.instantiate_mock_oracle(Decimal::percent(200));
+        let broken_oracle = suite.instantiate_mock_oracle(Decimal::zero());
+
+        for (asset_denoms, pool_type, expected_error) in [
+            // oracle pegged pools have two assets
+            (
+                vec!["urwa".to_string(), "uusdc".to_string(), "uusdt".to_string()],
+                oracle_pegged(&oracle),
+                ContractError::InvalidOraclePeggedPool,
+            ),
+            (
+                vec!["urwa".to_string(), "uusdc".to_string()],
+                PoolType::OraclePegged {
+                    oracle_addr: oracle.to_string(),
+                    amp: 100,
+                    max_staleness: 0,
+                    max_deviation: Decimal::percent(5),
+                },
+                ContractError::InvalidOraclePeggedPool,
+            ),
+            (
+                vec!["urwa".to_string(), "uusdc".to_string()],
+                PoolType::OraclePegged {
+                    oracle_addr: oracle.to_string(),
+                    amp: 100,
+                    max_staleness: 3_600,
+                    max_deviation: Decimal::zero(),
+                },
+                ContractError::InvalidOraclePeggedPool,
+            ),
+            (
+                vec!["urwa".to_string(), "uusdc".to_string()],
+                oracle_pegged(&broken_oracle),
+                ContractError::InvalidOraclePrice,
+            ),
+        ] {
+            let decimals = vec![6u8; asset_denoms.len()];
+            suite.create_pool(
+                &creator,
+                asset_denoms,
+                decimals,
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                pool_type,
+                Some("rwa.usdc".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, expected_error);
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn swaps_around_the_oracle_price() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite.instantiate_default();
+
+        // 1 urwa is worth 2 uusdc
+        let oracle = suite.instantiate_mock_oracle(Decimal::percent(200));
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["urwa".to_string(), "uusdc".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                oracle_pegged(&oracle),
+                Some("rwa.usdc".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.rwa.usdc".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(1_000_000u128, "urwa"), coin(2_000_000u128, "uusdc")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let simulated_return_amount = RefCell::new(Uint128::zero());
+        let uusdc_balance = RefCell::new(Uint128::zero());
+        suite
+            .query_simulation(
+                "o.rwa.usdc".to_string(),
+                coin(10_000u128, "urwa"),
+                "uusdc".to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    assert_eq!(response.oracle_price, Some(Decimal::percent(200)));
+                    assert_approx_eq!(response.return_amount, Uint128::new(20_000u128), "0.002");
+                    *simulated_return_amount.borrow_mut() = response.return_amount;
+                },
+            )
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                *uusdc_balance.borrow_mut() = result.unwrap().amount;
+            })
+            .swap(
+                &other,
+                "uusdc".to_string(),
+                None,
+                None,
+                None,
+                "o.rwa.usdc".to_string(),
+                vec![coin(10_000u128, "urwa")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                assert_eq!(
+                    result.unwrap().amount - *uusdc_balance.borrow(),
+                    *simulated_return_amount.borrow()
+                );
+            });
+
+        // the NAV goes up, the curve follows it without waiting for arbitrageurs
+        suite
+            .set_mock_oracle_price(&oracle, Decimal::percent(250), |result| {
+                result.unwrap();
+            })
+            .query_simulation(
+                "o.rwa.usdc".to_string(),
+                coin(10_000u128, "urwa"),
+                "uusdc".to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    assert_eq!(response.oracle_price, Some(Decimal::percent(250)));
+                    assert_approx_eq!(response.return_amount, Uint128::new(25_000u128), "0.005");
+                },
+            );
+
+        // the price can't be used once it's stale
+        suite
+            .add_one_day()
+            .swap(
+                &other,
+                "uusdc".to_string(),
+                None,
+                None,
+                None,
+                "o.rwa.usdc".to_string(),
+                vec![coin(10_000u128, "urwa")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::StaleOraclePrice { max_staleness, .. } => {
+                            assert_eq!(max_staleness, 3_600);
+                        }
+                        _ => panic!("Wrong error type, should return ContractError::StaleOraclePrice"),
+                    }
+                },
+            )
+            .set_mock_oracle_price(&oracle, Decimal::percent(250), |result| {
+                result.unwrap();
+            })
+            // swaps draining the pool far from the oracle price are rejected
+            .swap(
+                &other,
+                "uusdc".to_string(),
+                None,
+                Some(Decimal::percent(50)),
+                None,
+                "o.rwa.usdc".to_string(),
+                vec![coin(900_000u128, "urwa")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::OraclePriceDeviationExceeded { max_deviation, .. } => {
+                            assert_eq!(max_deviation, Decimal::percent(5));
+                        }
+                        _ => panic!("Wrong error type, should return ContractError::OraclePriceDeviationExceeded"),
+                    }
+                },
+            )
+            .swap(
+                &other,
+                "urwa".to_string(),
+                None,
+                None,
+                None,
+                "o.rwa.usdc".to_string(),
+                vec![coin(25_000u128, "uusdc")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        // nor can a price updated after the current block time
+        let block_time = Timestamp::from_seconds(1_800_000_000);
+        suite
+            .set_time(block_time)
+            .set_mock_oracle_price(&oracle, Decimal::percent(250), |result| {
+                result.unwrap();
+            })
+            .set_time(block_time.minus_seconds(60))
+            .swap(
+                &other,
+                "uusdc".to_string(),
+                None,
+                None,
+                None,
+                "o.rwa.usdc".to_string(),
+                vec![coin(10_000u128, "urwa")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::FutureOraclePrice {
+                            updated_at: block_time.seconds(),
+                            block_time: block_time.seconds() - 60,
+                        }
+                    );
+                },
+            )
+            .query_simulation(
+                "o.rwa.usdc".to_string(),
+                coin(10_000u128, "urwa"),
+                "uusdc".to_string(),
+                |result| {
+                    assert!(result
+                        .unwrap_err()
+                        .to_string()
+                        .contains("after the current block time"));
+                },
+            );
+    }
+}
+
+mod multi_asset_single_side_liquidity {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::assert_approx_eq;
+
+    use super::*;
+
+    fn default_suite() -> TestingSuite {
+        TestingSuite::default_with_denoms(&["uusdc", "uusdt", "udai", "uwhale", "uluna", "uosmo"])
+    }
+
+    #[test]
+    fn provides_single_asset_into_stableswap_pool_with_imbalance_fee() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec!["uusdc".to_string(), "uusdt".to_string(), "udai".to_string()],
+                vec![6u8, 6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: None,
+                },
+                Some("3pool".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "uusdc"),
+                    coin(1_000_000u128, "uusdt"),
+                    coin(1_000_000u128, "udai"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &other,
+                "o.3pool".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(30_000u128, "udai")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let lp_denom = suite.get_lp_denom("o.3pool".to_string());
+
+        suite
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                let lp_balance = result.unwrap().amount;
+                // the pool is 1:1 with an LP token per unit, minus the imbalance fee and the slippage
+                assert!(lp_balance < Uint128::new(30_000u128));
+                assert_approx_eq!(lp_balance, Uint128::new(30_000u128), "0.001");
+            })
+            .query_pools(Some("o.3pool".to_string()), None, None, |result| {
+                let response = result.unwrap();
+                let assets = &response.pools[0].pool_info.assets;
+                // the whole deposit is added to the pool, no swap takes place
+                assert_eq!(assets[0], coin(1_000_000u128, "uusdc"));
+                assert_eq!(assets[1], coin(1_000_000u128, "uusdt"));
+                assert_eq!(assets[2], coin(1_030_000u128, "udai"));
+            });
+    }
+
+    #[test]
+    fn provides_single_asset_into_constant_product_pool_with_three_assets() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec![
+                    "uwhale".to_string(),
+                    "uluna".to_string(),
+                    "uosmo".to_string(),
+                ],
+                vec![6u8, 6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::ConstantProduct,
+                Some("whale.luna.osmo".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.whale.luna.osmo".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "uwhale"),
+                    coin(1_000_000u128, "uluna"),
+                    coin(1_000_000u128, "uosmo"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &other,
+                "o.whale.luna.osmo".to_string(),
+                None,
+                None,
+                Some(Decimal::percent(2)),
+                None,
+                vec![coin(30_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let lp_denom = suite.get_lp_denom("o.whale.luna.osmo".to_string());
+
+        suite.query_balance(&other.to_string(), &lp_denom, |result| {
+            let lp_balance = result.unwrap().amount;
+            // a third of the deposit is kept and the rest is swapped into the other two assets,
+            // the LP tokens are minted for the smallest proportional share
+            assert!(lp_balance < Uint128::new(10_000u128));
+            assert_approx_eq!(lp_balance, Uint128::new(10_000u128), "0.03");
+        });
+
+        // every asset that reached the contract ended up in the pool
+        let pool_assets = RefCell::new(vec![]);
+        suite.query_pools(
+            Some("o.whale.luna.osmo".to_string()),
+            None,
+            None,
+            |result| {
+                *pool_assets.borrow_mut() = result.unwrap().pools[0].pool_info.assets.clone();
+            },
+        );
+        assert_eq!(pool_assets.borrow()[0], coin(1_030_000u128, "uwhale"));
+
+        let pool_manager_addr = suite.pool_manager_addr.to_string();
+        for pool_asset in pool_assets.borrow().iter() {
+            suite.query_balance(&pool_manager_addr, &pool_asset.denom, |result| {
+                assert_eq!(&result.unwrap(), pool_asset);
+            });
+        }
+    }
+
+    #[test]
+    fn cant_provide_single_asset_into_weighted_pool_with_three_assets() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec![
+                    "uwhale".to_string(),
+                    "uluna".to_string(),
+                    "uosmo".to_string(),
+                ],
+                vec![6u8, 6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::Weighted {
+                    weights: vec![
+                        Decimal::percent(40),
+                        Decimal::percent(30),
+                        Decimal::percent(30),
+                    ],
+                },
+                Some("weighted".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.weighted".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(1_000_000u128, "uwhale"),
+                    coin(750_000u128, "uluna"),
+                    coin(750_000u128, "uosmo"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &other,
+                "o.weighted".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(30_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::InvalidPoolAssetsForSingleSideLiquidityProvision
+                    );
+                },
+            );
+    }
+}
+
+mod withdraw_liquidity_protection {
+    use std::cell::RefCell;
+
+    use cosmwasm_std::Timestamp;
+
+    use super::*;
+
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .set_time(Timestamp::from_seconds(1_800_000_000))
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::permille(1), Decimal::permille(1)),
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            )
+            .provide_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        suite
+    }
+
+    #[test]
+    fn cant_withdraw_below_minimum_or_after_deadline() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                None,
+                None,
+                Some(Timestamp::from_seconds(1_799_999_999)),
+                None,
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::DeadlineExceeded {
+                            deadline: Timestamp::from_seconds(1_799_999_999),
+                            block_time: Timestamp::from_seconds(1_800_000_000),
+                        }
+                    );
+                },
+            )
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(vec![
+                    coin(100_000u128, "uwhale"),
+                    coin(100_000u128, "uluna"),
+                ]),
+                None,
+                None,
+                None,
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::WithdrawAmountBelowMinimum {
+                            denom: "uluna".to_string(),
+                            amount: Uint128::new(99_999u128),
+                            min_amount: Uint128::new(100_000u128),
+                        }
+                    );
+                },
+            )
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(vec![coin(1u128, "uosmo")]),
+                None,
+                None,
+                None,
+                vec![coin(100_000u128, lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::AssetMismatch);
+                },
+            );
+    }
+
+    #[test]
+    fn withdraws_to_receiver_within_bounds() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let receiver = suite.senders[2].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(vec![coin(99_999u128, "uwhale"), coin(99_999u128, "uluna")]),
+                Some(receiver.to_string()),
+                Some(Timestamp::from_seconds(1_800_000_000)),
+                None,
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert_eq!(result.unwrap().amount, Uint128::zero());
+            })
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_900_000u128));
+            })
+            .query_balance(&receiver.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_099_999u128));
+            })
+            .query_balance(&receiver.to_string(), "uluna", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_099_999u128));
+            });
+    }
+
+    #[test]
+    fn zaps_out_into_single_asset() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            // the minimum amount of the target asset is required
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                None,
+                None,
+                None,
+                Some("uwhale".to_string()),
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::MissingTargetMinimumOut {
+                            target_denom: "uwhale".to_string()
+                        }
+                    );
+                },
+            )
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(vec![coin(0u128, "uwhale"), coin(1u128, "uluna")]),
+                None,
+                None,
+                Some("uwhale".to_string()),
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::MissingTargetMinimumOut {
+                            target_denom: "uwhale".to_string()
+                        }
+                    );
+                },
+            )
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(vec![coin(200_000u128, "uwhale")]),
+                None,
+                None,
+                Some("uwhale".to_string()),
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::WithdrawAmountBelowMinimum { denom, .. } => {
+                            assert_eq!(denom, "uwhale")
+                        }
+                        _ => panic!("Wrong error type, should return WithdrawAmountBelowMinimum"),
+                    }
+                },
+            )
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(vec![coin(1u128, "uosmo")]),
+                None,
+                None,
+                Some("uosmo".to_string()),
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::AssetMismatch);
+                },
+            )
+            .withdraw_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(vec![coin(190_000u128, "uwhale")]),
+                None,
+                None,
+                Some("uwhale".to_string()),
+                vec![coin(100_000u128, lp_denom.clone())],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uluna", |result| {
+                // the luna withdrawn was swapped into whale
+                assert_eq!(result.unwrap().amount, Uint128::new(999_900_000u128));
+            })
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                // 99_999 uwhale withdrawn plus 90_728 uwhale from swapping the 99_999 uluna
+                // against the 1_000_001 of each asset left in the pool, minus fees
+                let amount = result.unwrap().amount;
+                assert_eq!(amount, Uint128::new(999_900_000u128 + 190_727u128));
+            });
+
+        // the pool balances match what the contract holds, with the protocol fee sent out
+        let pool_assets = RefCell::new(vec![]);
+        suite.query_pools(Some("o.whale.uluna".to_string()), None, None, |result| {
+            *pool_assets.borrow_mut() = result.unwrap().pools[0].pool_info.assets.clone();
+        });
+
+        let pool_manager_addr = suite.pool_manager_addr.to_string();
+        for pool_asset in pool_assets.borrow().iter() {
+            suite.query_balance(&pool_manager_addr, &pool_asset.denom, |result| {
+                assert_eq!(&result.unwrap(), pool_asset);
+            });
+        }
+    }
+
+    #[test]
+    fn cant_zap_out_of_non_constant_product_pools() {
+        let mut suite = setup();
+        let creator = suite.creator();
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["uwhale".to_string(), "uluna".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::permille(1), Decimal::permille(1)),
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: None,
+                },
+                Some("stable".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.stable".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        let lp_denom = suite.get_lp_denom("o.stable".to_string());
+
+        suite.withdraw_liquidity(
+            &creator,
+            "o.stable".to_string(),
+            Some(vec![coin(1u128, "uwhale")]),
+            None,
+            None,
+            Some("uwhale".to_string()),
+            vec![coin(100_000u128, lp_denom)],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                assert_eq!(err, ContractError::InvalidPoolTypeForZapOut);
+            },
+        );
+    }
+}
+
+mod excess_deposits {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::ExcessDepositMode;
+    use cosmwasm_std::assert_approx_eq;
+
+    use super::*;
+
+    fn setup(pool_type: PoolType) -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+
+        suite.instantiate_default().create_pool_with_liquidity(
+            &["uwhale", "uluna"],
+            pool_type,
+            pool_fees(Decimal::permille(1), Decimal::permille(2)),
+            "whale.uluna",
+            vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+        );
+
+        suite
+    }
+
+    fn assert_pool_balances_match_contract(suite: &mut TestingSuite) {
+        let pool_assets = RefCell::new(vec![]);
+        suite.query_pools(Some("o.whale.uluna".to_string()), None, None, |result| {
+            *pool_assets.borrow_mut() = result.unwrap().pools[0].pool_info.assets.clone();
+        });
+
+        let pool_manager_addr = suite.pool_manager_addr.to_string();
+        for pool_asset in pool_assets.borrow().iter() {
+            suite.query_balance(&pool_manager_addr, &pool_asset.denom, |result| {
+                assert_eq!(&result.unwrap(), pool_asset);
+            });
+        }
+    }
+
+    #[test]
+    fn refunds_excess_deposit() {
+        let mut suite = setup(PoolType::ConstantProduct);
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            .provide_liquidity_with_excess_mode(
+                &other,
+                "o.whale.uluna".to_string(),
+                ExcessDepositMode::Refund,
+                vec![coin(100_000u128, "uwhale"), coin(150_000u128, "uluna")],
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event.attributes.iter().any(|attribute| {
+                            attribute.key == "excess_refund" && attribute.value == "50000uluna"
+                        })
+                    }));
+                },
+            )
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(100_000u128));
+            })
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_900_000u128));
+            })
+            .query_balance(&other.to_string(), "uluna", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_900_000u128));
+            })
+            .query_pools(Some("o.whale.uluna".to_string()), None, None, |result| {
+                let assets = result.unwrap().pools[0].pool_info.assets.clone();
+                assert_eq!(assets[0], coin(1_100_000u128, "uwhale"));
+                assert_eq!(assets[1], coin(1_100_000u128, "uluna"));
+            });
+
+        assert_pool_balances_match_contract(&mut suite);
+    }
+
+    #[test]
+    fn swaps_excess_deposit_into_liquidity() {
+        let mut suite = setup(PoolType::ConstantProduct);
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite.provide_liquidity_with_excess_mode(
+            &other,
+            "o.whale.uluna".to_string(),
+            ExcessDepositMode::Swap,
+            vec![coin(100_000u128, "uwhale"), coin(104_000u128, "uluna")],
+            |result| {
+                let response = result.unwrap();
+                assert!(response.events.iter().any(|event| {
+                    event
+                        .attributes
+                        .iter()
+                        .any(|attribute| attribute.key == "excess_swap_offer_asset")
+                }));
+            },
+        );
+
+        // the excess luna was swapped, so almost the whole deposit became liquidity
+        suite
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                let lp_balance = result.unwrap().amount;
+                assert!(lp_balance > Uint128::new(101_900u128));
+                assert_approx_eq!(lp_balance, Uint128::new(102_000u128), "0.001");
+            })
+            .query_balance(&other.to_string(), "uluna", |result| {
+                let luna_balance = result.unwrap().amount;
+                // only dust is refunded
+                assert!(luna_balance < Uint128::new(999_896_010u128));
+            });
+
+        assert_pool_balances_match_contract(&mut suite);
+    }
+
+    #[test]
+    fn cant_refund_excess_deposit_on_non_constant_product_pools() {
+        let mut suite = setup(PoolType::StableSwap {
+            amp: 100,
+            rate_providers: None,
+        });
+        let other = suite.senders[1].clone();
+
+        suite.provide_liquidity_with_excess_mode(
+            &other,
+            "o.whale.uluna".to_string(),
+            ExcessDepositMode::Refund,
+            vec![coin(100_000u128, "uwhale"), coin(150_000u128, "uluna")],
+            |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                assert_eq!(err, ContractError::InvalidExcessDepositMode);
+            },
+        );
+    }
+}
+
+mod liquidity_simulations {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::{
+        ExcessDepositMode, SimulateProvideLiquidityResponse, SimulateWithdrawLiquidityResponse,
+    };
+
+    use super::*;
+
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+
+        suite.instantiate_default().create_pool_with_liquidity(
+            &["uwhale", "uluna"],
+            PoolType::ConstantProduct,
+            pool_fees(Decimal::permille(1), Decimal::permille(2)),
+            "whale.uluna",
+            vec![coin(1_000_000u128, "uwhale"), coin(2_000_000u128, "uluna")],
+        );
+
+        suite
+    }
+
+    fn query_balance(suite: &mut TestingSuite, address: &Addr, denom: &str) -> Uint128 {
+        let balance = RefCell::new(Uint128::zero());
+        suite.query_balance(&address.to_string(), denom, |result| {
+            *balance.borrow_mut() = result.unwrap().amount;
+        });
+        balance.into_inner()
+    }
+
+    fn simulate_provide_liquidity(
+        suite: &mut TestingSuite,
+        deposits: Vec<Coin>,
+        excess_mode: Option<ExcessDepositMode>,
+    ) -> SimulateProvideLiquidityResponse {
+        let simulation = RefCell::new(None);
+        suite.query_simulate_provide_liquidity(
+            "o.whale.uluna".to_string(),
+            deposits,
+            None,
+            None,
+            excess_mode,
+            |result| {
+                *simulation.borrow_mut() = Some(result.unwrap());
+            },
+        );
+        simulation.into_inner().unwrap()
+    }
+
+    fn simulate_withdraw_liquidity(
+        suite: &mut TestingSuite,
+        lp_amount: Uint128,
+        target_denom: Option<String>,
+    ) -> SimulateWithdrawLiquidityResponse {
+        let simulation = RefCell::new(None);
+        suite.query_simulate_withdraw_liquidity(
+            "o.whale.uluna".to_string(),
+            lp_amount,
+            target_denom,
+            |result| {
+                *simulation.borrow_mut() = Some(result.unwrap());
+            },
+        );
+        simulation.into_inner().unwrap()
+    }
+
+    #[test]
+    fn simulates_liquidity_provision_with_refund() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        let deposits = vec![coin(100_000u128, "uwhale"), coin(300_000u128, "uluna")];
+        let simulation = simulate_provide_liquidity(
+            &mut suite,
+            deposits.clone(),
+            Some(ExcessDepositMode::Refund),
+        );
+
+        assert_eq!(
+            simulation.deposited_assets,
+            vec![coin(100_000u128, "uwhale"), coin(200_000u128, "uluna")]
+        );
+        assert_eq!(simulation.refunded_assets, vec![coin(100_000u128, "uluna")]);
+        assert!(simulation.swaps.is_empty());
+
+        suite.provide_liquidity_with_excess_mode(
+            &other,
+            "o.whale.uluna".to_string(),
+            ExcessDepositMode::Refund,
+            deposits,
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        assert_eq!(
+            query_balance(&mut suite, &other, &lp_denom),
+            simulation.lp_amount
+        );
+        assert_eq!(
+            query_balance(&mut suite, &other, "uluna"),
+            Uint128::new(999_800_000u128)
+        );
+    }
+
+    #[test]
+    fn simulates_liquidity_provision_with_excess_swap() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        let deposits = vec![coin(100_000u128, "uwhale"), coin(220_000u128, "uluna")];
+        let simulation =
+            simulate_provide_liquidity(&mut suite, deposits.clone(), Some(ExcessDepositMode::Swap));
+
+        assert_eq!(simulation.swaps.len(), 1);
+        assert_eq!(simulation.swaps[0].offer_asset.denom, "uluna");
+        assert_eq!(simulation.swaps[0].return_asset.denom, "uwhale");
+
+        suite.provide_liquidity_with_excess_mode(
+            &other,
+            "o.whale.uluna".to_string(),
+            ExcessDepositMode::Swap,
+            deposits,
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        assert_eq!(
+            query_balance(&mut suite, &other, &lp_denom),
+            simulation.lp_amount
+        );
+    }
+
+    #[test]
+    fn simulates_single_side_liquidity_provision() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        let simulation =
+            simulate_provide_liquidity(&mut suite, vec![coin(10_000u128, "uwhale")], None);
+
+        assert_eq!(simulation.swaps.len(), 1);
+        assert_eq!(simulation.swaps[0].offer_asset, coin(5_000u128, "uwhale"));
+        assert!(!simulation.lp_amount.is_zero());
+
+        suite.provide_liquidity(
+            &other,
+            "o.whale.uluna".to_string(),
+            None,
+            None,
+            None,
+            None,
+            vec![coin(10_000u128, "uwhale")],
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        assert_eq!(
+            query_balance(&mut suite, &other, &lp_denom),
+            simulation.lp_amount
+        );
+    }
+
+    #[test]
+    fn simulates_liquidity_withdrawal() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        let lp_amount = Uint128::new(500_000u128);
+        let simulation = simulate_withdraw_liquidity(&mut suite, lp_amount, None);
+        assert!(simulation.swaps.is_empty());
+
+        let whale_before = query_balance(&mut suite, &creator, "uwhale");
+        let luna_before = query_balance(&mut suite, &creator, "uluna");
+
+        suite.withdraw_liquidity(
+            &creator,
+            "o.whale.uluna".to_string(),
+            None,
+            None,
+            None,
+            None,
+            vec![coin(lp_amount.u128(), lp_denom)],
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        assert_eq!(
+            simulation.returned_assets,
+            vec![
+                coin(
+                    (query_balance(&mut suite, &creator, "uwhale") - whale_before).u128(),
+                    "uwhale"
+                ),
+                coin(
+                    (query_balance(&mut suite, &creator, "uluna") - luna_before).u128(),
+                    "uluna"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn simulates_liquidity_withdrawal_into_single_asset() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        let lp_amount = Uint128::new(500_000u128);
+        let simulation =
+            simulate_withdraw_liquidity(&mut suite, lp_amount, Some("uluna".to_string()));
+        assert_eq!(simulation.swaps.len(), 1);
+        assert_eq!(simulation.returned_assets.len(), 1);
+        assert_eq!(simulation.returned_assets[0].denom, "uluna");
+
+        let whale_before = query_balance(&mut suite, &creator, "uwhale");
+        let luna_before = query_balance(&mut suite, &creator, "uluna");
+
+        suite.withdraw_liquidity(
+            &creator,
+            "o.whale.uluna".to_string(),
+            Some(simulation.returned_assets.clone()),
+            None,
+            None,
+            Some("uluna".to_string()),
+            vec![coin(lp_amount.u128(), lp_denom)],
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        assert_eq!(query_balance(&mut suite, &creator, "uwhale"), whale_before);
+        assert_eq!(
+            query_balance(&mut suite, &creator, "uluna") - luna_before,
+            simulation.returned_assets[0].amount
+        );
+    }
+
+    #[test]
+    fn cant_simulate_liquidity_provision_with_mismatched_assets() {
+        let mut suite = setup();
+
+        suite.query_simulate_provide_liquidity(
+            "o.whale.uluna".to_string(),
+            vec![coin(100_000u128, "uom")],
+            None,
+            None,
+            None,
+            |result| {
+                let err = result.unwrap_err().to_string();
+                assert!(err.contains(&ContractError::AssetMismatch.to_string()));
+            },
+        );
+    }
+}
+
+mod farm_position_liquidity_withdrawals {
+    use amm::farm_manager::{PositionAction, PositionsBy};
+
+    use super::*;
+
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .add_one_epoch()
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            )
+            // other locks its LP tokens into the farm manager
+            .provide_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(86_400u64),
+                Some("farm".to_string()),
+                None,
+                None,
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        suite
+    }
+
+    #[test]
+    fn withdraws_expired_position_into_pool_assets() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            .manage_farm_position(
+                &other,
+                PositionAction::Close {
+                    identifier: "u-farm".to_string(),
+                    lp_asset: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            // the position hasn't expired yet
+            .manage_farm_position(
+                &other,
+                PositionAction::WithdrawLiquidity {
+                    identifier: "u-farm".to_string(),
+                    emergency_unlock: None,
+                    pool_identifier: "o.whale.uluna".to_string(),
+                    min_assets_out: None,
+                    target_denom: None,
+                },
+                vec![],
+                |result| {
+                    let err = result
+                        .unwrap_err()
+                        .downcast::<farm_manager::ContractError>()
+                        .unwrap();
+                    assert!(matches!(
+                        err,
+                        farm_manager::ContractError::PositionNotExpired
+                    ));
+                },
+            )
+            .add_one_day()
+            .add_one_day()
+            // the assets returned are below the minimum
+            .manage_farm_position(
+                &other,
+                PositionAction::WithdrawLiquidity {
+                    identifier: "u-farm".to_string(),
+                    emergency_unlock: None,
+                    pool_identifier: "o.whale.uluna".to_string(),
+                    min_assets_out: Some(vec![coin(100_001u128, "uwhale")]),
+                    target_denom: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap_err();
+                },
+            )
+            .manage_farm_position(
+                &other,
+                PositionAction::WithdrawLiquidity {
+                    identifier: "u-farm".to_string(),
+                    emergency_unlock: None,
+                    pool_identifier: "o.whale.uluna".to_string(),
+                    min_assets_out: Some(vec![
+                        coin(99_999u128, "uwhale"),
+                        coin(99_999u128, "uluna"),
+                    ]),
+                    target_denom: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_999_999u128));
+            })
+            .query_balance(&other.to_string(), "uluna", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_999_999u128));
+            })
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .query_farm_positions(
+                Some(PositionsBy::Receiver(other.to_string())),
+                None,
+                None,
+                None,
+                |result| {
+                    assert!(result.unwrap().positions.is_empty());
+                },
+            );
+    }
+
+    #[test]
+    fn emergency_unlocks_position_into_single_asset() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let farm_manager_addr = suite.farm_manager_addr.clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            .manage_farm_position(
+                &other,
+                PositionAction::WithdrawLiquidity {
+                    identifier: "u-farm".to_string(),
+                    emergency_unlock: Some(true),
+                    pool_identifier: "o.whale.uluna".to_string(),
+                    min_assets_out: Some(vec![coin(170_000u128, "uluna")]),
+                    target_denom: Some("uluna".to_string()),
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                // the withdrawn whale was swapped into luna
+                assert_eq!(result.unwrap().amount, Uint128::new(999_900_000u128));
+            })
+            .query_balance(&other.to_string(), "uluna", |result| {
+                // the position paid a 10% penalty, and the rest was redeemed for luna
+                let luna_balance = result.unwrap().amount;
+                assert!(luna_balance > Uint128::new(1_000_000_000u128 - 100_000u128 + 170_000u128));
+                assert!(luna_balance < Uint128::new(1_000_000_000u128 - 100_000u128 + 180_000u128));
+            })
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .query_balance(&farm_manager_addr.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            });
+    }
+
+    #[test]
+    fn cant_withdraw_position_into_assets_of_another_pool() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["uwhale".to_string(), "uusd".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                PoolType::ConstantProduct,
+                Some("whale.uusd".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .manage_farm_position(
+                &other,
+                PositionAction::WithdrawLiquidity {
+                    identifier: "u-farm".to_string(),
+                    emergency_unlock: Some(true),
+                    pool_identifier: "o.whale.uusd".to_string(),
+                    min_assets_out: None,
+                    target_denom: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap_err();
+                },
+            )
+            .query_farm_positions(
+                Some(PositionsBy::Receiver(other.to_string())),
+                None,
+                None,
+                None,
+                |result| {
+                    assert_eq!(result.unwrap().positions.len(), 1);
+                },
+            );
+    }
+}
+
+mod farm_position_operators {
+    use amm::farm_manager::{Position, PositionAction, PositionsBy};
+
+    use super::*;
+
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+
+        suite
+            .instantiate_default()
+            .add_one_epoch()
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            );
+
+        suite
+    }
+
+    #[test]
+    fn operator_locks_liquidity_for_receiver() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let beneficiary = suite.senders[1].clone();
+        let vault = suite.senders[2].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        // the vault isn't a position operator yet
+        suite
+            .provide_liquidity(
+                &vault,
+                "o.whale.uluna".to_string(),
+                Some(86_400u64),
+                Some("vesting".to_string()),
+                None,
+                Some(beneficiary.to_string()),
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::Unauthorized);
+                },
+            )
+            .update_farm_position_operators(
+                &creator,
+                Some(vec![vault.to_string()]),
+                None,
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &vault,
+                "o.whale.uluna".to_string(),
+                Some(86_400u64),
+                Some("vesting".to_string()),
+                None,
+                Some(beneficiary.to_string()),
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            // the vault can't top up the position of the beneficiary, only create new ones
+            .provide_liquidity(
+                &vault,
+                "o.whale.uluna".to_string(),
+                Some(86_400u64),
+                Some("u-vesting".to_string()),
+                None,
+                Some(beneficiary.to_string()),
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::Unauthorized);
+                },
+            )
+            // the beneficiary can top it up
+            .provide_liquidity(
+                &beneficiary,
+                "o.whale.uluna".to_string(),
+                Some(86_400u64),
+                Some("u-vesting".to_string()),
+                None,
+                None,
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_farm_positions(
+                Some(PositionsBy::Receiver(beneficiary.to_string())),
+                None,
+                None,
+                None,
+                |result| {
+                    let positions = result.unwrap().positions;
+                    assert_eq!(positions.len(), 1);
+                    assert_eq!(
+                        positions[0],
+                        Position {
+                            identifier: "u-vesting".to_string(),
+                            lp_asset: coin(200_000u128, lp_denom.clone()),
+                            unlocking_duration: 86_400,
+                            open: true,
+                            expiring_at: None,
+                            receiver: beneficiary.clone(),
+                        }
+                    );
+                },
+            )
+            .query_farm_positions(
+                Some(PositionsBy::Receiver(vault.to_string())),
+                None,
+                None,
+                None,
+                |result| {
+                    assert!(result.unwrap().positions.is_empty());
+                },
+            )
+            // the position belongs to the beneficiary, not to the vault
+            .manage_farm_position(
+                &vault,
+                PositionAction::Close {
+                    identifier: "u-vesting".to_string(),
+                    lp_asset: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap_err();
+                },
+            )
+            .manage_farm_position(
+                &beneficiary,
+                PositionAction::Close {
+                    identifier: "u-vesting".to_string(),
+                    lp_asset: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            );
+    }
+
+    #[test]
+    fn operator_locks_single_side_liquidity_for_receiver() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let beneficiary = suite.senders[1].clone();
+        let vault = suite.senders[2].clone();
+
+        suite
+            .update_farm_position_operators(
+                &creator,
+                Some(vec![vault.to_string()]),
+                None,
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &vault,
+                "o.whale.uluna".to_string(),
+                Some(86_400u64),
+                None,
+                None,
+                Some(beneficiary.to_string()),
+                vec![coin(10_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_farm_positions(
+                Some(PositionsBy::Receiver(beneficiary.to_string())),
+                None,
+                None,
+                None,
+                |result| {
+                    let positions = result.unwrap().positions;
+                    assert_eq!(positions.len(), 1);
+                    assert_eq!(positions[0].receiver, beneficiary);
+                },
+            );
+    }
+}
+
+mod reserve_reconciliation {
+    use amm::pool_manager::{ConcentratedPositionAction, ReserveHealth};
+
+    use super::*;
+
+    fn default_suite() -> TestingSuite {
+        TestingSuite::default_with_denoms(&["uwhale", "uluna"])
+    }
+
+    #[test]
+    fn skims_tokens_sent_to_the_contract() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let recipient = suite.senders[2].clone();
+        suite.instantiate_default();
+        let pool_manager_addr = suite.pool_manager_addr.clone();
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["uwhale".to_string(), "uluna".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::permille(1), Decimal::permille(2)),
+                PoolType::ConstantProduct,
+                Some("whale.uluna".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.whale.uluna".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .swap(
+                &other,
+                "uluna".to_string(),
+                None,
+                None,
+                None,
+                "o.whale.uluna".to_string(),
+                vec![coin(1_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_reserve_health(None, |result| {
+                let reserves = result.unwrap().reserves;
+                assert_eq!(reserves.len(), 2);
+                assert!(reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            })
+            // nothing to skim
+            .skim(
+                &creator,
+                "o.whale.uluna".to_string(),
+                recipient.to_string(),
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::NothingToSkim {
+                            pool_identifier: "o.whale.uluna".to_string()
+                        }
+                    );
+                },
+            )
+            // whale sent to the contract by mistake
+            .send_tokens(&other, &pool_manager_addr, &[coin(5_000u128, "uwhale")])
+            .query_reserve_health(Some("o.whale.uluna".to_string()), |result| {
+                let reserves = result.unwrap().reserves;
+                assert_eq!(
+                    reserves[0],
+                    ReserveHealth {
+                        denom: "uwhale".to_string(),
+                        accounted: Uint128::new(1_001_000u128),
+                        balance: Uint128::new(1_006_000u128),
+                        surplus: Uint128::new(5_000u128),
+                        deficit: Uint128::zero(),
+                    }
+                );
+                assert!(reserves[1].surplus.is_zero() && reserves[1].deficit.is_zero());
+            })
+            // only the owner can skim
+            .skim(
+                &other,
+                "o.whale.uluna".to_string(),
+                other.to_string(),
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::OwnershipError { .. } => {}
+                        _ => {
+                            panic!("Wrong error type, should return ContractError::OwnershipError")
+                        }
+                    }
+                },
+            )
+            .skim(
+                &creator,
+                "o.whale.uluna".to_string(),
+                recipient.to_string(),
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&recipient.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_005_000u128));
+            })
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn skim_keeps_fees_owed_to_concentrated_positions() {
+        let mut suite = default_suite();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let recipient = suite.senders[2].clone();
+        suite.instantiate_default();
+        let pool_manager_addr = suite.pool_manager_addr.clone();
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["uwhale".to_string(), "uluna".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::permille(1), Decimal::permille(2)),
+                PoolType::ConcentratedLiquidity {
+                    tick_spacing: 10,
+                    initial_price: Decimal::one(),
+                },
+                Some("whale.uluna".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .manage_concentrated_position(
+                &creator,
+                ConcentratedPositionAction::Create {
+                    pool_identifier: "o.whale.uluna".to_string(),
+                    lower_tick: -1000,
+                    upper_tick: 1000,
+                    identifier: Some("creator".to_string()),
+                },
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .swap(
+                &other,
+                "uluna".to_string(),
+                None,
+                Some(Decimal::percent(5)),
+                None,
+                "o.whale.uluna".to_string(),
+                vec![coin(100_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            // the fees owed to the position are accounted for
+            .query_reserve_health(None, |result| {
+                let reserves = result.unwrap().reserves;
+                assert!(reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+                assert!(reserves[0].accounted > Uint128::zero());
+            })
+            .send_tokens(&other, &pool_manager_addr, &[coin(1_000u128, "uluna")])
+            .skim(
+                &creator,
+                "o.whale.uluna".to_string(),
+                recipient.to_string(),
+                |result| {
+                    let response = result.unwrap();
+                    assert!(response.events.iter().any(|event| {
+                        event
+                            .attributes
+                            .iter()
+                            .any(|attr| attr.key == "skimmed" && attr.value == "1000uluna")
+                    }));
+                },
+            )
+            // the position can still collect its fees
+            .manage_concentrated_position(
+                &creator,
+                ConcentratedPositionAction::CollectFees {
+                    identifier: "u-creator".to_string(),
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.deficit.is_zero()));
+            });
+    }
+}
+
+mod liquidity_migrations {
+    use amm::farm_manager::{PositionAction, PositionsBy};
+    use amm::pool_manager::{ConcentratedPositionRange, ConcentratedPositionsBy, SwapOperation};
+    use cosmwasm_std::StdError;
+
+    use super::*;
+
+    /// Sets up a deprecated whale/luna pool with a new fee tier pool for the same assets, both
+    /// seeded by the creator. The other sender provides liquidity to the deprecated pool.
+    fn setup(unlocking_duration: Option<u64>) -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .add_one_epoch()
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::permille(3)),
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            )
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                "whale.uluna.new",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            )
+            .provide_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                unlocking_duration,
+                unlocking_duration.map(|_| "farm".to_string()),
+                None,
+                None,
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+
+        suite
+    }
+
+    #[test]
+    fn migrates_liquidity_between_pools() {
+        let mut suite = setup(None);
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+        let new_lp_denom = suite.get_lp_denom("o.whale.uluna.new".to_string());
+
+        suite
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.whale.uluna".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::SameMigrationPool);
+                },
+            )
+            // the target pool is not a concentrated liquidity pool
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.whale.uluna.new".to_string(),
+                None,
+                None,
+                Some(ConcentratedPositionRange {
+                    lower_tick: -1000,
+                    upper_tick: 1000,
+                    identifier: None,
+                }),
+                None,
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidConcentratedMigration);
+                },
+            )
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.whale.uluna.new".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .query_balance(&other.to_string(), &new_lp_denom, |result| {
+                // the withdrawn assets are rounded down on the way out
+                assert_eq!(result.unwrap().amount, Uint128::new(99_999u128));
+            })
+            // the assets went straight into the new pool, nothing was sent back
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_900_000u128));
+            })
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn migrates_liquidity_into_pool_with_other_assets() {
+        let mut suite = setup(None);
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        for (assets, identifier) in [
+            (vec!["uwhale", "uusd"], "whale.uusd"),
+            (vec!["uluna", "uusd"], "luna.uusd"),
+        ] {
+            suite.create_pool(
+                &creator,
+                assets.iter().map(|denom| denom.to_string()).collect(),
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(3)),
+                PoolType::ConstantProduct,
+                Some(identifier.to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            );
+            suite.provide_liquidity(
+                &creator,
+                format!("o.{identifier}"),
+                None,
+                None,
+                None,
+                None,
+                assets
+                    .iter()
+                    .map(|denom| coin(1_000_000u128, *denom))
+                    .collect(),
+                |result| {
+                    result.unwrap();
+                },
+            );
+        }
+
+        let new_lp_denom = suite.get_lp_denom("o.luna.uusd".to_string());
+
+        let whale_into_usd = SwapOperation::MantraSwap {
+            token_in_denom: "uwhale".to_string(),
+            token_out_denom: "uusd".to_string(),
+            pool_identifier: "o.whale.uusd".to_string(),
+        };
+        let usd_into_whale = SwapOperation::MantraSwap {
+            token_in_denom: "uusd".to_string(),
+            token_out_denom: "uwhale".to_string(),
+            pool_identifier: "o.whale.uusd".to_string(),
+        };
+        let luna_into_usd = SwapOperation::MantraSwap {
+            token_in_denom: "uluna".to_string(),
+            token_out_denom: "uusd".to_string(),
+            pool_identifier: "o.luna.uusd".to_string(),
+        };
+
+        suite
+            // the target pool doesn't hold whale, so it must be swapped
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.luna.uusd".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::MissingMigrationSwapOperations {
+                            denom: "uwhale".to_string()
+                        }
+                    );
+                },
+            )
+            // the swap must end in an asset of the target pool
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.luna.uusd".to_string(),
+                None,
+                None,
+                None,
+                Some(vec![vec![whale_into_usd.clone(), usd_into_whale]]),
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidMigrationSwapOperations);
+                },
+            )
+            // luna is held by the target pool, so it can't be swapped
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.luna.uusd".to_string(),
+                None,
+                None,
+                None,
+                Some(vec![vec![whale_into_usd.clone()], vec![luna_into_usd]]),
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidMigrationSwapOperations);
+                },
+            )
+            // the swap is bounded by the max spread, 1% by default
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.luna.uusd".to_string(),
+                None,
+                None,
+                None,
+                Some(vec![vec![whale_into_usd.clone()]]),
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::Std(StdError::generic_err("Spread limit exceeded"))
+                    );
+                },
+            )
+            // with a deeper pool the swap goes through
+            .provide_liquidity(
+                &creator,
+                "o.whale.uusd".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![
+                    coin(100_000_000u128, "uwhale"),
+                    coin(100_000_000u128, "uusd"),
+                ],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.luna.uusd".to_string(),
+                None,
+                None,
+                None,
+                Some(vec![vec![whale_into_usd]]),
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .query_balance(&other.to_string(), &new_lp_denom, |result| {
+                // the swap fee and spread of the whale swapped into usd are lost along the way
+                assert_eq!(result.unwrap().amount, Uint128::new(99_601u128));
+            })
+            // the assets went straight into the new pool, nothing was sent back
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_900_000u128));
+            })
+            .query_balance(&other.to_string(), "uusd", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_000_000u128));
+            })
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn migrates_liquidity_into_concentrated_position() {
+        let mut suite = setup(None);
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["uwhale".to_string(), "uluna".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::permille(1)),
+                PoolType::ConcentratedLiquidity {
+                    tick_spacing: 10,
+                    initial_price: Decimal::one(),
+                },
+                Some("whale.uluna.cl".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            // the tick range of the position is required
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.whale.uluna.cl".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidConcentratedMigration);
+                },
+            )
+            // concentrated liquidity positions can't be locked in the farm manager
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.whale.uluna.cl".to_string(),
+                Some(86_400u64),
+                None,
+                Some(ConcentratedPositionRange {
+                    lower_tick: -1000,
+                    upper_tick: 1000,
+                    identifier: Some("migrated".to_string()),
+                }),
+                None,
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidConcentratedMigration);
+                },
+            )
+            .migrate_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                "o.whale.uluna.cl".to_string(),
+                None,
+                None,
+                Some(ConcentratedPositionRange {
+                    lower_tick: -1000,
+                    upper_tick: 1000,
+                    identifier: Some("migrated".to_string()),
+                }),
+                None,
+                vec![coin(100_000u128, &lp_denom)],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .query_concentrated_positions(
+                Some(ConcentratedPositionsBy::Identifier(
+                    "u-migrated".to_string(),
+                )),
+                None,
+                None,
+                |result| {
+                    let positions = result.unwrap().positions;
+                    assert_eq!(positions.len(), 1);
+                    assert_eq!(positions[0].owner, other);
+                    assert_eq!(positions[0].pool_identifier, "o.whale.uluna.cl");
+                    assert!(!positions[0].liquidity.is_zero());
+                },
+            )
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn migrates_farm_position_keeping_identifier_and_lock() {
+        let mut suite = setup(Some(86_400u64));
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let farm_manager_addr = suite.farm_manager_addr.clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+        let new_lp_denom = suite.get_lp_denom("o.whale.uluna.new".to_string());
+
+        let migrate_action = PositionAction::MigrateLiquidity {
+            identifier: "u-farm".to_string(),
+            pool_identifier: "o.whale.uluna".to_string(),
+            target_pool_identifier: "o.whale.uluna.new".to_string(),
+            slippage_tolerance: None,
+            max_spread: None,
+            swap_operations: None,
+            excess_mode: None,
+        };
+
+        suite
+            // only the receiver of the position can migrate it
+            .manage_farm_position(&creator, migrate_action.clone(), vec![], |result| {
+                let err = result
+                    .unwrap_err()
+                    .downcast::<farm_manager::ContractError>()
+                    .unwrap();
+                assert!(matches!(err, farm_manager::ContractError::Unauthorized));
+            })
+            .manage_farm_position(&other, migrate_action, vec![], |result| {
+                result.unwrap();
+            })
+            .query_farm_positions(
+                Some(PositionsBy::Receiver(other.to_string())),
+                None,
+                None,
+                None,
+                |result| {
+                    let positions = result.unwrap().positions;
+                    assert_eq!(positions.len(), 1);
+                    assert_eq!(positions[0].identifier, "u-farm");
+                    assert_eq!(positions[0].lp_asset, coin(99_999u128, &new_lp_denom));
+                    assert_eq!(positions[0].unlocking_duration, 86_400u64);
+                    assert!(positions[0].open);
+                },
+            )
+            .query_balance(&farm_manager_addr.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .query_balance(&farm_manager_addr.to_string(), &new_lp_denom, |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(99_999u128));
+            })
+            .query_balance(&other.to_string(), &new_lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            });
+    }
+}
+
+mod internal_lp_shares {
+    use amm::farm_manager::{PositionAction, PositionsBy};
+    use amm::pool_manager::LpTokenMode;
+    use cosmwasm_std::to_json_binary;
+
+    use super::*;
+
+    /// Sets up a whale/luna pool accounting for its LP shares internally, seeded by the creator.
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+
+        suite
+            .with_lp_token_mode(LpTokenMode::Internal)
+            .instantiate_default()
+            .add_one_epoch()
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            );
+
+        suite
+    }
+
+    #[test]
+    fn transfers_internal_lp_shares() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let another = suite.senders[2].clone();
+        let pool_manager_addr = suite.pool_manager_addr.clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite.query_pools(Some("o.whale.uluna".to_string()), None, None, |result| {
+            let pool = result.unwrap().pools[0].clone();
+            assert_eq!(pool.pool_info.lp_denom, lp_denom);
+            assert_eq!(pool.total_share, coin(1_000_000u128, &lp_denom));
+        });
+
+        suite
+            .query_share_balance("o.whale.uluna".to_string(), creator.to_string(), |result| {
+                assert_eq!(result.unwrap().balance, coin(999_000u128, &lp_denom));
+            })
+            .query_share_balance(
+                "o.whale.uluna".to_string(),
+                pool_manager_addr.to_string(),
+                |result| {
+                    assert_eq!(result.unwrap().balance, coin(1_000u128, &lp_denom));
+                },
+            )
+            // nothing is minted through the bank module
+            .query_balance(&creator.to_string(), &lp_denom, |result| {
+                assert!(result.unwrap().amount.is_zero());
+            })
+            .transfer_shares(
+                &other,
+                "o.whale.uluna".to_string(),
+                another.to_string(),
+                Uint128::new(1u128),
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::InsufficientShares {
+                            balance: Uint128::zero(),
+                            amount: Uint128::new(1u128),
+                        }
+                    );
+                },
+            )
+            .transfer_shares(
+                &creator,
+                "o.whale.uluna".to_string(),
+                other.to_string(),
+                Uint128::new(1_000u128),
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .approve_shares(
+                &creator,
+                "o.whale.uluna".to_string(),
+                other.to_string(),
+                Uint128::new(500u128),
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .transfer_shares_from(
+                &other,
+                "o.whale.uluna".to_string(),
+                creator.to_string(),
+                another.to_string(),
+                Uint128::new(600u128),
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::InsufficientShareAllowance {
+                            allowance: Uint128::new(500u128),
+                            amount: Uint128::new(600u128),
+                        }
+                    );
+                },
+            )
+            .transfer_shares_from(
+                &other,
+                "o.whale.uluna".to_string(),
+                creator.to_string(),
+                another.to_string(),
+                Uint128::new(300u128),
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_share_allowance(
+                "o.whale.uluna".to_string(),
+                creator.to_string(),
+                other.to_string(),
+                |result| {
+                    assert_eq!(result.unwrap().allowance, Uint128::new(200u128));
+                },
+            )
+            .query_share_balance("o.whale.uluna".to_string(), creator.to_string(), |result| {
+                assert_eq!(result.unwrap().balance, coin(997_700u128, &lp_denom));
+            })
+            .query_share_balance("o.whale.uluna".to_string(), other.to_string(), |result| {
+                assert_eq!(result.unwrap().balance, coin(1_000u128, &lp_denom));
+            })
+            .query_share_balance("o.whale.uluna".to_string(), another.to_string(), |result| {
+                assert_eq!(result.unwrap().balance, coin(300u128, &lp_denom));
+            });
+    }
+
+    #[test]
+    fn withdraws_and_locks_internal_lp_shares() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let pool_manager_addr = suite.pool_manager_addr.clone();
+        let farm_manager_addr = suite.farm_manager_addr.clone();
+        let lp_denom = suite.get_lp_denom("o.whale.uluna".to_string());
+
+        suite
+            // other locks its LP shares into the farm manager
+            .provide_liquidity(
+                &other,
+                "o.whale.uluna".to_string(),
+                Some(86_400u64),
+                Some("farm".to_string()),
+                None,
+                None,
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uluna")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_farm_positions(
+                Some(PositionsBy::Receiver(other.to_string())),
+                Some(true),
+                None,
+                None,
+                |result| {
+                    let positions = result.unwrap().positions;
+                    assert_eq!(positions.len(), 1);
+                    assert_eq!(positions[0].lp_asset, coin(100_000u128, &lp_denom));
+                },
+            )
+            .query_share_balance(
+                "o.whale.uluna".to_string(),
+                farm_manager_addr.to_string(),
+                |result| {
+                    assert_eq!(result.unwrap().balance, coin(100_000u128, &lp_denom));
+                },
+            )
+            // only withdrawals and migrations can be executed with the shares
+            .send_shares(
+                &creator,
+                "o.whale.uluna".to_string(),
+                Uint128::new(1_000u128),
+                pool_manager_addr.to_string(),
+                to_json_binary(&amm::pool_manager::ExecuteMsg::Skim {
+                    pool_identifier: "o.whale.uluna".to_string(),
+                    recipient: creator.to_string(),
+                })
+                .unwrap(),
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::InvalidSharesMessage);
+                },
+            )
+            .send_shares(
+                &creator,
+                "o.whale.uluna".to_string(),
+                Uint128::new(999_000u128),
+                pool_manager_addr.to_string(),
+                to_json_binary(&amm::pool_manager::ExecuteMsg::WithdrawLiquidity {
+                    pool_identifier: "o.whale.uluna".to_string(),
+                    min_assets_out: None,
+                    receiver: None,
+                    deadline: None,
+                    target_denom: None,
+                })
+                .unwrap(),
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_share_balance("o.whale.uluna".to_string(), creator.to_string(), |result| {
+                assert!(result.unwrap().balance.amount.is_zero());
+            })
+            .query_balance(&creator.to_string(), "uwhale", |result| {
+                // the withdrawn assets are rounded down on the way out
+                assert_eq!(result.unwrap().amount, Uint128::new(999_998_999u128));
+            })
+            .query_pools(Some("o.whale.uluna".to_string()), None, None, |result| {
+                let pool = result.unwrap().pools[0].clone();
+                assert_eq!(pool.total_share, coin(101_000u128, &lp_denom));
+            });
+
+        suite
+            .manage_farm_position(
+                &other,
+                PositionAction::Close {
+                    identifier: "u-farm".to_string(),
+                    lp_asset: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .add_one_day()
+            .add_one_day()
+            .manage_farm_position(
+                &other,
+                PositionAction::Withdraw {
+                    identifier: "u-farm".to_string(),
+                    emergency_unlock: None,
+                },
+                vec![],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_share_balance("o.whale.uluna".to_string(), other.to_string(), |result| {
+                assert_eq!(result.unwrap().balance, coin(100_000u128, &lp_denom));
+            })
+            .query_share_balance(
+                "o.whale.uluna".to_string(),
+                farm_manager_addr.to_string(),
+                |result| {
+                    assert!(result.unwrap().balance.amount.is_zero());
+                },
+            )
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn rejects_share_transfers_on_token_factory_pools() {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna"]);
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+
+        suite
+            .instantiate_default()
+            .create_pool(
+                &creator,
+                vec!["uwhale".to_string(), "uluna".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                PoolType::ConstantProduct,
+                Some("whale.uluna".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .transfer_shares(
+                &creator,
+                "o.whale.uluna".to_string(),
+                other.to_string(),
+                Uint128::new(1_000u128),
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(
+                        err,
+                        ContractError::NotInternalLpPool {
+                            pool_identifier: "o.whale.uluna".to_string(),
+                        }
+                    );
+                },
+            );
+    }
+}
+
+mod exact_output_swaps {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::SwapOperation;
+
+    use super::*;
+
+    /// Sets up a constant product whale/luna pool and a stable swap uusdc/uusdt pool, both charging
+    /// swap and protocol fees.
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna", "uusdc", "uusdt"]);
+        let pool_fees = pool_fees(Decimal::permille(1), Decimal::permille(3));
+
+        suite
+            .instantiate_default()
+            .create_pool_with_liquidity(
+                &["uwhale", "uluna"],
+                PoolType::ConstantProduct,
+                pool_fees.clone(),
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(2_000_000u128, "uluna")],
+            )
+            .create_pool_with_liquidity(
+                &["uusdc", "uusdt"],
+                PoolType::StableSwap {
+                    amp: 100,
+                    rate_providers: None,
+                },
+                pool_fees,
+                "usdc.usdt",
+                vec![coin(5_000_000u128, "uusdc"), coin(5_000_000u128, "uusdt")],
+            );
+
+        suite
+    }
+
+    /// Extracts the offer amount from the response of an exact output swap.
+    fn offer_amount(response: &cw_multi_test::AppResponse) -> Uint128 {
+        response
+            .events
+            .iter()
+            .flat_map(|event| &event.attributes)
+            .find(|attribute| attribute.key == "offer_amount")
+            .map(|attribute| attribute.value.parse().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn swaps_for_exact_output_on_constant_product_pool() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let receiver = suite.senders[2].clone();
+        let fee_collector = suite.fee_collector_addr.clone();
+
+        let simulated_offer_amount = RefCell::new(Uint128::zero());
+        let offer_amount_swapped = RefCell::new(Uint128::zero());
+
+        suite
+            .query_reverse_simulation(
+                "o.whale.uluna".to_string(),
+                coin(10_000u128, "uluna"),
+                "uwhale".to_string(),
+                |result| {
+                    *simulated_offer_amount.borrow_mut() = result.unwrap().offer_amount;
+                },
+            )
+            .swap_exact_out(
+                &other,
+                coin(10_000u128, "uluna"),
+                Some(Uint128::new(1_000u128)),
+                Some(receiver.to_string()),
+                "o.whale.uluna".to_string(),
+                vec![coin(20_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert!(matches!(
+                        err,
+                        ContractError::MaxOfferAmountExceeded { max_offer_amount, .. }
+                            if max_offer_amount == Uint128::new(1_000u128)
+                    ));
+                },
+            )
+            // the funds sent cap the offer too
+            .swap_exact_out(
+                &other,
+                coin(10_000u128, "uluna"),
+                None,
+                Some(receiver.to_string()),
+                "o.whale.uluna".to_string(),
+                vec![coin(1_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert!(matches!(err, ContractError::MaxOfferAmountExceeded { .. }));
+                },
+            )
+            .swap_exact_out(
+                &other,
+                coin(10_000u128, "uluna"),
+                Some(Uint128::new(6_000u128)),
+                Some(receiver.to_string()),
+                "o.whale.uluna".to_string(),
+                vec![coin(20_000u128, "uwhale")],
+                |result| {
+                    *offer_amount_swapped.borrow_mut() = offer_amount(&result.unwrap());
+                },
+            );
+
+        let offer_amount = offer_amount_swapped.into_inner();
+        assert!(offer_amount >= simulated_offer_amount.into_inner());
+
+        suite
+            // the receiver gets exactly the ask amount
+            .query_balance(&receiver.to_string(), "uluna", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_010_000u128));
+            })
+            // only the offer amount was taken from the sender
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128) - offer_amount
+                );
+            })
+            .query_balance(&fee_collector.to_string(), "uluna", |result| {
+                assert!(!result.unwrap().amount.is_zero());
+            })
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn swaps_for_exact_output_through_multiple_hops() {
+        let mut suite = setup();
+        let creator = suite.creator();
+        let other = suite.senders[1].clone();
+        let receiver = suite.senders[2].clone();
+
+        let operations = vec![
+            SwapOperation::MantraSwap {
+                token_in_denom: "uwhale".to_string(),
+                token_out_denom: "uluna".to_string(),
+                pool_identifier: "o.whale.uluna".to_string(),
+            },
+            SwapOperation::MantraSwap {
+                token_in_denom: "uluna".to_string(),
+                token_out_denom: "uusdc".to_string(),
+                pool_identifier: "o.luna.usdc".to_string(),
+            },
+        ];
+
+        suite
+            .create_pool(
+                &creator,
+                vec!["uluna".to_string(), "uusdc".to_string()],
+                vec![6u8, 6u8],
+                pool_fees(Decimal::permille(1), Decimal::permille(3)),
+                PoolType::ConstantProduct,
+                Some("luna.usdc".to_string()),
+                vec![coin(1000, "uusd"), coin(8888, "uom")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .provide_liquidity(
+                &creator,
+                "o.luna.usdc".to_string(),
+                None,
+                None,
+                None,
+                None,
+                vec![coin(2_000_000u128, "uluna"), coin(3_000_000u128, "uusdc")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .execute_swap_operations_exact_out(
+                &other,
+                vec![operations[1].clone(), operations[0].clone()],
+                Uint128::new(5_000u128),
+                None,
+                Some(receiver.to_string()),
+                vec![coin(100_000u128, "uluna")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert!(matches!(
+                        err,
+                        ContractError::NonConsecutiveSwapOperations { .. }
+                    ));
+                },
+            )
+            .execute_swap_operations_exact_out(
+                &other,
+                operations.clone(),
+                Uint128::new(5_000u128),
+                Some(Uint128::new(1_000u128)),
+                Some(receiver.to_string()),
+                vec![coin(100_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert!(matches!(err, ContractError::MaxOfferAmountExceeded { .. }));
+                },
+            );
+
+        let offer_amount_swapped = RefCell::new(Uint128::zero());
+        suite.execute_swap_operations_exact_out(
+            &other,
+            operations,
+            Uint128::new(5_000u128),
+            None,
+            Some(receiver.to_string()),
+            vec![coin(100_000u128, "uwhale")],
+            |result| {
+                *offer_amount_swapped.borrow_mut() = offer_amount(&result.unwrap());
+            },
+        );
+
+        let offer_amount = offer_amount_swapped.into_inner();
+
+        suite
+            .query_balance(&receiver.to_string(), "uusdc", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_005_000u128));
+            })
+            // only the input needed was taken, the rest was refunded
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128) - offer_amount
+                );
+            })
+            // no intermediate asset was left behind
+            .query_balance(&other.to_string(), "uluna", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_000_000u128));
+            })
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn swaps_for_exact_output_on_stable_swap_pool() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let receiver = suite.senders[2].clone();
+        let offer_amount_swapped = RefCell::new(Uint128::zero());
+
+        suite.swap_exact_out(
+            &other,
+            coin(1_000_000u128, "uusdt"),
+            None,
+            Some(receiver.to_string()),
+            "o.usdc.usdt".to_string(),
+            vec![coin(2_000_000u128, "uusdc")],
+            |result| {
+                *offer_amount_swapped.borrow_mut() = offer_amount(&result.unwrap());
+            },
+        );
+
+        let offer_amount = offer_amount_swapped.into_inner();
+
+        suite
+            .query_balance(&receiver.to_string(), "uusdt", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_001_000_000u128));
+            })
+            // the return beyond the ask amount left by rounding is refunded to the sender
+            .query_balance(&other.to_string(), "uusdt", |result| {
+                assert!(result.unwrap().amount >= Uint128::new(1_000_000_000u128));
+            })
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128) - offer_amount
+                );
+            })
+            .query_reserve_health(None, |result| {
+                assert!(result
+                    .unwrap()
+                    .reserves
+                    .iter()
+                    .all(|reserve| reserve.surplus.is_zero() && reserve.deficit.is_zero()));
+            });
+    }
+
+    #[test]
+    fn rejects_exact_output_swaps_asking_for_zero() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+
+        suite
+            .swap_exact_out(
+                &other,
+                coin(0u128, "uluna"),
+                None,
+                None,
+                "o.whale.uluna".to_string(),
+                vec![coin(1_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    assert_eq!(err, ContractError::ZeroAskAmount);
+                },
+            )
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(1_000_000_000u128));
+            });
+    }
+}
+
+mod best_routes {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::SwapOperation;
+
+    use super::*;
+
+    /// Sets up deep whale/luna and luna/usdc pools, a shallow whale/usdc pool and an empty
+    /// whale/usdc pool, none of them charging fees.
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna", "uusdc"]);
+
+        suite.instantiate_default();
+
+        for (asset_denoms, pool_identifier, deposits) in [
+            (
+                ["uwhale", "uluna"],
+                "whale.uluna",
+                vec![coin(1_000_000u128, "uwhale"), coin(1_000_000u128, "uluna")],
+            ),
+            (
+                ["uluna", "uusdc"],
+                "luna.usdc",
+                vec![coin(1_000_000u128, "uluna"), coin(1_000_000u128, "uusdc")],
+            ),
+            (
+                ["uwhale", "uusdc"],
+                "whale.usdc",
+                vec![coin(100_000u128, "uwhale"), coin(100_000u128, "uusdc")],
+            ),
+            (["uwhale", "uusdc"], "whale.usdc.empty", vec![]),
+        ] {
+            suite.create_pool_with_liquidity(
+                &asset_denoms,
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                pool_identifier,
+                deposits,
+            );
+        }
+
+        suite
+    }
+
+    #[test]
+    fn finds_the_route_with_the_best_output() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+
+        let best_route = RefCell::new(None);
+
+        suite
+            .query_best_route(
+                coin(10_000u128, "uwhale"),
+                "uusdc".to_string(),
+                None,
+                None,
+                |result| {
+                    let response = result.unwrap();
+                    // going through the deep pools beats the shallow direct pool
+                    assert_eq!(
+                        response.operations,
+                        vec![
+                            SwapOperation::MantraSwap {
+                                token_in_denom: "uwhale".to_string(),
+                                token_out_denom: "uluna".to_string(),
+                                pool_identifier: "o.whale.uluna".to_string(),
+                            },
+                            SwapOperation::MantraSwap {
+                                token_in_denom: "uluna".to_string(),
+                                token_out_denom: "uusdc".to_string(),
+                                pool_identifier: "o.luna.usdc".to_string(),
+                            },
+                        ]
+                    );
+                    *best_route.borrow_mut() = Some(response);
+                },
+            )
+            // with a single hop, only the direct pool with liquidity is left
+            .query_best_route(
+                coin(10_000u128, "uwhale"),
+                "uusdc".to_string(),
+                Some(1),
+                None,
+                |result| {
+                    let response = result.unwrap();
+                    assert_eq!(
+                        response.operations,
+                        vec![SwapOperation::MantraSwap {
+                            token_in_denom: "uwhale".to_string(),
+                            token_out_denom: "uusdc".to_string(),
+                            pool_identifier: "o.whale.usdc".to_string(),
+                        }]
+                    );
+                    assert_eq!(response.amount, Uint128::new(9_090u128));
+                },
+            )
+            // scanning only the first pool, there's no route
+            .query_best_route(
+                coin(10_000u128, "uwhale"),
+                "uusdc".to_string(),
+                None,
+                Some(1),
+                |result| {
+                    assert!(result
+                        .unwrap_err()
+                        .to_string()
+                        .contains("No route was found"));
+                },
+            )
+            .query_best_route(
+                coin(10_000u128, "uwhale"),
+                "uatom".to_string(),
+                None,
+                None,
+                |result| {
+                    assert!(result
+                        .unwrap_err()
+                        .to_string()
+                        .contains("No route was found"));
+                },
+            );
+
+        let best_route = best_route.into_inner().unwrap();
+
+        // the route can be executed as is, returning the simulated amount
+        suite
+            .execute_swap_operations(
+                &other,
+                best_route.operations,
+                Some(best_route.amount),
+                None,
+                None,
+                vec![coin(10_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uusdc", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128) + best_route.amount
+                );
+            });
+    }
+
+    #[test]
+    fn fails_when_the_search_goes_over_the_simulation_limit() {
+        let denoms = ["ua", "ub", "uc", "ud", "ue"];
+        let mut suite = TestingSuite::default_with_denoms(&denoms);
+
+        suite.instantiate_default();
+
+        // two pools for every pair of denoms
+        for (index, offer_denom) in denoms.iter().enumerate() {
+            for ask_denom in &denoms[index + 1..] {
+                for tier in ["a", "b"] {
+                    suite.create_pool_with_liquidity(
+                        &[offer_denom, ask_denom],
+                        PoolType::ConstantProduct,
+                        pool_fees(Decimal::zero(), Decimal::zero()),
+                        &format!("{offer_denom}.{ask_denom}.{tier}"),
+                        vec![
+                            coin(1_000_000u128, *offer_denom),
+                            coin(1_000_000u128, *ask_denom),
+                        ],
+                    );
+                }
+            }
+        }
+
+        suite
+            // 140 swaps are simulated up to 3 hops
+            .query_best_route(
+                coin(10_000u128, "ua"),
+                "ue".to_string(),
+                None,
+                None,
+                |result| {
+                    assert_eq!(result.unwrap().operations.len(), 1);
+                },
+            )
+            // and 236 up to 4 hops
+            .query_best_route(
+                coin(10_000u128, "ua"),
+                "ue".to_string(),
+                Some(4),
+                None,
+                |result| {
+                    assert!(result
+                        .unwrap_err()
+                        .to_string()
+                        .contains("The route search went over the limit of 200 simulated swaps"));
+                },
+            );
+    }
+}
+
+mod split_swaps {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::{SplitSwapRoute, SwapOperation};
+
+    use super::*;
+
+    /// Sets up two whale/luna pools and a luna/usdc pool with the same reserves, none of them
+    /// charging fees.
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna", "uusdc"]);
+
+        suite.instantiate_default();
+
+        for (asset_denoms, pool_identifier) in [
+            (["uwhale", "uluna"], "whale.uluna.a"),
+            (["uwhale", "uluna"], "whale.uluna.b"),
+            (["uluna", "uusdc"], "luna.usdc"),
+        ] {
+            suite.create_pool_with_liquidity(
+                &asset_denoms,
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                pool_identifier,
+                asset_denoms
+                    .iter()
+                    .map(|denom| coin(1_000_000u128, *denom))
+                    .collect(),
+            );
+        }
+
+        suite
+    }
+
+    fn whale_to_luna_route(pool_identifier: &str, weight: Decimal) -> SplitSwapRoute {
+        SplitSwapRoute {
+            operations: vec![SwapOperation::MantraSwap {
+                token_in_denom: "uwhale".to_string(),
+                token_out_denom: "uluna".to_string(),
+                pool_identifier: pool_identifier.to_string(),
+            }],
+            weight,
+        }
+    }
+
+    #[test]
+    fn splits_the_input_across_routes_by_weight() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+        let max_spread = Some(Decimal::percent(5));
+
+        let expected_return = RefCell::new(Uint128::zero());
+        let single_route_return = RefCell::new(Uint128::zero());
+
+        suite
+            .query_simulation(
+                "o.whale.uluna.a".to_string(),
+                coin(15_000u128, "uwhale"),
+                "uluna".to_string(),
+                |result| {
+                    *expected_return.borrow_mut() += result.unwrap().return_amount;
+                },
+            )
+            .query_simulation(
+                "o.whale.uluna.b".to_string(),
+                coin(5_000u128, "uwhale"),
+                "uluna".to_string(),
+                |result| {
+                    *expected_return.borrow_mut() += result.unwrap().return_amount;
+                },
+            )
+            .query_simulation(
+                "o.whale.uluna.a".to_string(),
+                coin(20_000u128, "uwhale"),
+                "uluna".to_string(),
+                |result| {
+                    *single_route_return.borrow_mut() = result.unwrap().return_amount;
+                },
+            );
+
+        let expected_return = expected_return.into_inner();
+        // splitting the trade across both pools reduces the price impact
+        assert!(expected_return > single_route_return.into_inner());
+
+        suite
+            .execute_split_swap_operations(
+                &other,
+                vec![
+                    whale_to_luna_route("o.whale.uluna.a", Decimal::percent(75)),
+                    whale_to_luna_route("o.whale.uluna.b", Decimal::percent(25)),
+                ],
+                Some(expected_return + Uint128::one()),
+                None,
+                max_spread,
+                vec![coin(20_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::MinimumReceiveAssertion { swap_amount, .. } => {
+                            assert_eq!(swap_amount, expected_return);
+                        }
+                        _ => panic!("Wrong error type, should return ContractError::MinimumReceiveAssertion"),
+                    }
+                },
+            )
+            .execute_split_swap_operations(
+                &other,
+                vec![
+                    whale_to_luna_route("o.whale.uluna.a", Decimal::percent(75)),
+                    whale_to_luna_route("o.whale.uluna.b", Decimal::percent(25)),
+                ],
+                Some(expected_return),
+                None,
+                max_spread,
+                vec![coin(20_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                assert_eq!(result.unwrap().amount, Uint128::new(999_980_000u128));
+            })
+            .query_balance(&other.to_string(), "uluna", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128) + expected_return
+                );
+            });
+
+        // the weights are relative, and the last route swaps whatever is left
+        let uwhale_balance = RefCell::new(Uint128::zero());
+        let pool_manager_addr = suite.pool_manager_addr.clone();
+        suite
+            .execute_split_swap_operations(
+                &other,
+                vec![
+                    whale_to_luna_route("o.whale.uluna.a", Decimal::one()),
+                    whale_to_luna_route("o.whale.uluna.b", Decimal::one()),
+                    whale_to_luna_route("o.whale.uluna.a", Decimal::one()),
+                ],
+                None,
+                None,
+                max_spread,
+                vec![coin(10_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&other.to_string(), "uwhale", |result| {
+                *uwhale_balance.borrow_mut() = result.unwrap().amount;
+            })
+            .query_balance(&pool_manager_addr.to_string(), "uwhale", |result| {
+                // nothing is left behind in the contract besides the pool reserves
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(2_000_000u128 + 20_000u128 + 10_000u128)
+                );
+            });
+
+        assert_eq!(uwhale_balance.into_inner(), Uint128::new(999_970_000u128));
+    }
+
+    #[test]
+    fn rejects_invalid_routes() {
+        let mut suite = setup();
+        let other = suite.senders[1].clone();
+
+        suite
+            .execute_split_swap_operations(
+                &other,
+                vec![],
+                None,
+                None,
+                None,
+                vec![coin(10_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::NoSwapOperationsProvided => {}
+                        _ => panic!("Wrong error type, should return ContractError::NoSwapOperationsProvided"),
+                    }
+                },
+            )
+            .execute_split_swap_operations(
+                &other,
+                vec![
+                    whale_to_luna_route("o.whale.uluna.a", Decimal::one()),
+                    whale_to_luna_route("o.whale.uluna.b", Decimal::zero()),
+                ],
+                None,
+                None,
+                None,
+                vec![coin(10_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::InvalidSplitSwapRouteWeight => {}
+                        _ => panic!("Wrong error type, should return ContractError::InvalidSplitSwapRouteWeight"),
+                    }
+                },
+            )
+            .execute_split_swap_operations(
+                &other,
+                vec![
+                    whale_to_luna_route("o.whale.uluna.a", Decimal::one()),
+                    SplitSwapRoute {
+                        operations: vec![
+                            SwapOperation::MantraSwap {
+                                token_in_denom: "uwhale".to_string(),
+                                token_out_denom: "uluna".to_string(),
+                                pool_identifier: "o.whale.uluna.b".to_string(),
+                            },
+                            SwapOperation::MantraSwap {
+                                token_in_denom: "uluna".to_string(),
+                                token_out_denom: "uusdc".to_string(),
+                                pool_identifier: "o.luna.usdc".to_string(),
+                            },
+                        ],
+                        weight: Decimal::one(),
+                    },
+                ],
+                None,
+                None,
+                None,
+                vec![coin(10_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::MismatchedSplitSwapRoutes {
+                            offer_asset_denom,
+                            ask_asset_denom,
+                        } => {
+                            assert_eq!(offer_asset_denom, "uwhale");
+                            assert_eq!(ask_asset_denom, "uluna");
+                        }
+                        _ => panic!("Wrong error type, should return ContractError::MismatchedSplitSwapRoutes"),
+                    }
+                },
+            );
+    }
+}
+
+mod swap_routes {
+    use std::cell::RefCell;
+
+    use amm::pool_manager::{SwapOperation, SwapRoute};
+
+    use super::*;
+
+    /// Sets up whale/luna and luna/usdc pools with the same reserves, none of them charging fees.
+    fn setup() -> TestingSuite {
+        let mut suite = TestingSuite::default_with_denoms(&["uwhale", "uluna", "uusdc"]);
+
+        suite.instantiate_default();
+
+        for (asset_denoms, pool_identifier) in [
+            (["uwhale", "uluna"], "whale.uluna"),
+            (["uluna", "uusdc"], "luna.usdc"),
+        ] {
+            suite.create_pool_with_liquidity(
+                &asset_denoms,
+                PoolType::ConstantProduct,
+                pool_fees(Decimal::zero(), Decimal::zero()),
+                pool_identifier,
+                asset_denoms
+                    .iter()
+                    .map(|denom| coin(1_000_000u128, *denom))
+                    .collect(),
+            );
+        }
+
+        suite
+    }
+
+    fn whale_to_usdc_route() -> SwapRoute {
+        SwapRoute {
+            offer_asset_denom: "uwhale".to_string(),
+            ask_asset_denom: "uusdc".to_string(),
+            swap_operations: vec![
+                SwapOperation::MantraSwap {
+                    token_in_denom: "uwhale".to_string(),
+                    token_out_denom: "uluna".to_string(),
+                    pool_identifier: "o.whale.uluna".to_string(),
+                },
+                SwapOperation::MantraSwap {
+                    token_in_denom: "uluna".to_string(),
+                    token_out_denom: "uusdc".to_string(),
+                    pool_identifier: "o.luna.usdc".to_string(),
+                },
+            ],
+        }
+    }
+
+    fn luna_to_whale_route() -> SwapRoute {
+        SwapRoute {
+            offer_asset_denom: "uluna".to_string(),
+            ask_asset_denom: "uwhale".to_string(),
+            swap_operations: vec![SwapOperation::MantraSwap {
+                token_in_denom: "uluna".to_string(),
+                token_out_denom: "uwhale".to_string(),
+                pool_identifier: "o.whale.uluna".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn registers_and_swaps_via_routes() {
+        let mut suite = setup();
+        let owner = suite.creator();
+        let alice = suite.senders[1].clone();
+        let bob = suite.senders[2].clone();
+
+        suite
+            .swap_via_route(
+                &bob,
+                "uusdc".to_string(),
+                None,
+                None,
+                vec![coin(1_000u128, "uwhale")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::NoSwapRouteForAssets { .. } => {}
+                        _ => panic!(
+                            "Wrong error type, should return ContractError::NoSwapRouteForAssets"
+                        ),
+                    }
+                },
+            )
+            // only the owner can register routes
+            .add_swap_routes(&alice, vec![whale_to_usdc_route()], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::OwnershipError { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::OwnershipError"),
+                }
+            })
+            .query_swap_routes(None, None, |result| {
+                assert!(result.unwrap().swap_routes.is_empty());
+            })
+            .add_swap_routes(
+                &owner,
+                vec![whale_to_usdc_route(), luna_to_whale_route()],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .add_swap_routes(&owner, vec![whale_to_usdc_route()], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::SwapRouteAlreadyExists {
+                        offer_asset_denom,
+                        ask_asset_denom,
+                    } => {
+                        assert_eq!(offer_asset_denom, "uwhale");
+                        assert_eq!(ask_asset_denom, "uusdc");
+                    }
+                    _ => panic!(
+                        "Wrong error type, should return ContractError::SwapRouteAlreadyExists"
+                    ),
+                }
+            })
+            .query_swap_route("uwhale".to_string(), "uusdc".to_string(), |result| {
+                assert_eq!(result.unwrap().swap_route, whale_to_usdc_route());
+            })
+            .query_swap_route_creator("uwhale".to_string(), "uusdc".to_string(), |result| {
+                assert_eq!(result.unwrap().creator, owner.to_string());
+            })
+            .query_swap_route("uusdc".to_string(), "uwhale".to_string(), |result| {
+                assert!(result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("No swap route exists"));
+            })
+            .query_swap_routes(None, None, |result| {
+                assert_eq!(
+                    result.unwrap().swap_routes,
+                    vec![luna_to_whale_route(), whale_to_usdc_route()]
+                );
+            })
+            .query_swap_routes(None, Some(1), |result| {
+                assert_eq!(result.unwrap().swap_routes, vec![luna_to_whale_route()]);
+            })
+            .query_swap_routes(
+                Some(("uluna".to_string(), "uwhale".to_string())),
+                None,
+                |result| {
+                    assert_eq!(result.unwrap().swap_routes, vec![whale_to_usdc_route()]);
+                },
+            );
+
+        let expected_return = RefCell::new(Uint128::zero());
+        suite
+            .query_simulate_swap_operations(
+                Uint128::new(1_000u128),
+                whale_to_usdc_route().swap_operations,
+                |result| {
+                    *expected_return.borrow_mut() = result.unwrap().amount;
+                },
+            )
+            .swap_via_route(
+                &bob,
+                "uusdc".to_string(),
+                None,
+                None,
+                vec![coin(1_000u128, "uwhale"), coin(1_000u128, "uluna")],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::PaymentError(_) => {}
+                        _ => panic!("Wrong error type, should return ContractError::PaymentError"),
+                    }
+                },
+            )
+            .swap_via_route(
+                &bob,
+                "uusdc".to_string(),
+                None,
+                None,
+                vec![coin(1_000u128, "uwhale")],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .query_balance(&bob.to_string(), "uusdc", |result| {
+                assert_eq!(
+                    result.unwrap().amount,
+                    Uint128::new(1_000_000_000u128) + *expected_return.borrow()
+                );
+            });
+    }
+
+    #[test]
+    fn rejects_invalid_swap_routes() {
+        let mut suite = setup();
+        let owner = suite.creator();
+
+        let mut same_asset_route = luna_to_whale_route();
+        same_asset_route.ask_asset_denom = "uluna".to_string();
+
+        let mut mismatched_route = whale_to_usdc_route();
+        mismatched_route.ask_asset_denom = "uluna".to_string();
+
+        let mut wrong_pool_route = luna_to_whale_route();
+        wrong_pool_route.swap_operations = vec![SwapOperation::MantraSwap {
+            token_in_denom: "uluna".to_string(),
+            token_out_denom: "uwhale".to_string(),
+            pool_identifier: "o.luna.usdc".to_string(),
+        }];
+
+        let mut unexisting_pool_route = luna_to_whale_route();
+        unexisting_pool_route.swap_operations = vec![SwapOperation::MantraSwap {
+            token_in_denom: "uluna".to_string(),
+            token_out_denom: "uwhale".to_string(),
+            pool_identifier: "o.unexisting".to_string(),
+        }];
+
+        let mut empty_route = luna_to_whale_route();
+        empty_route.swap_operations = vec![];
+
+        suite
+            .add_swap_routes(&owner, vec![same_asset_route], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::SameAsset => {}
+                    _ => panic!("Wrong error type, should return ContractError::SameAsset"),
+                }
+            })
+            .add_swap_routes(&owner, vec![empty_route], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::NoSwapOperationsProvided => {}
+                    _ => panic!(
+                        "Wrong error type, should return ContractError::NoSwapOperationsProvided"
+                    ),
+                }
+            })
+            .add_swap_routes(&owner, vec![mismatched_route], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::MalformedSwapRoute { .. } => {}
+                    _ => {
+                        panic!("Wrong error type, should return ContractError::MalformedSwapRoute")
+                    }
+                }
+            })
+            .add_swap_routes(&owner, vec![wrong_pool_route], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::MalformedSwapRoute { .. } => {}
+                    _ => {
+                        panic!("Wrong error type, should return ContractError::MalformedSwapRoute")
+                    }
+                }
+            })
+            .add_swap_routes(&owner, vec![unexisting_pool_route], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::UnExistingPool => {}
+                    _ => panic!("Wrong error type, should return ContractError::UnExistingPool"),
+                }
+            })
+            // a single invalid route fails the whole message
+            .add_swap_routes(
+                &owner,
+                vec![luna_to_whale_route(), luna_to_whale_route()],
+                |result| {
+                    let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                    match err {
+                        ContractError::SwapRouteAlreadyExists { .. } => {}
+                        _ => panic!(
+                            "Wrong error type, should return ContractError::SwapRouteAlreadyExists"
+                        ),
+                    }
+                },
+            )
+            .query_swap_routes(None, None, |result| {
+                assert!(result.unwrap().swap_routes.is_empty());
+            });
+    }
+
+    #[test]
+    fn only_the_owner_removes_swap_routes() {
+        let mut suite = setup();
+        let owner = suite.creator();
+        let alice = suite.senders[1].clone();
+        let bob = suite.senders[2].clone();
+
+        let whale_to_usdc = ("uwhale".to_string(), "uusdc".to_string());
+        let luna_to_whale = ("uluna".to_string(), "uwhale".to_string());
+
+        suite
+            .add_swap_routes(
+                &owner,
+                vec![whale_to_usdc_route(), luna_to_whale_route()],
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .remove_swap_routes(&bob, vec![whale_to_usdc.clone()], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::OwnershipError { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::OwnershipError"),
+                }
+            })
+            .remove_swap_routes(&owner, vec![whale_to_usdc.clone()], |result| {
+                result.unwrap();
+            })
+            .remove_swap_routes(&owner, vec![whale_to_usdc.clone()], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::NoSwapRouteForAssets { .. } => {}
+                    _ => panic!(
+                        "Wrong error type, should return ContractError::NoSwapRouteForAssets"
+                    ),
+                }
+            })
+            // hand the ownership over to alice, the previous owner remains the route's creator
+            .update_ownership(
+                &owner,
+                cw_ownable::Action::TransferOwnership {
+                    new_owner: alice.to_string(),
+                    expiry: None,
+                },
+                |result| {
+                    result.unwrap();
+                },
+            )
+            .update_ownership(&alice, cw_ownable::Action::AcceptOwnership, |result| {
+                result.unwrap();
+            })
+            // the previous owner can't remove nor register routes anymore
+            .remove_swap_routes(&owner, vec![luna_to_whale.clone()], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::OwnershipError { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::OwnershipError"),
+                }
+            })
+            .add_swap_routes(&owner, vec![whale_to_usdc_route()], |result| {
+                let err = result.unwrap_err().downcast::<ContractError>().unwrap();
+                match err {
+                    ContractError::OwnershipError { .. } => {}
+                    _ => panic!("Wrong error type, should return ContractError::OwnershipError"),
+                }
+            })
+            .remove_swap_routes(&alice, vec![luna_to_whale], |result| {
+                result.unwrap();
+            })
+            .query_swap_routes(None, None, |result| {
+                assert!(result.unwrap().swap_routes.is_empty());
+            })
+            .add_swap_routes(&alice, vec![whale_to_usdc_route()], |result| {
+                result.unwrap();
+            })
+            .query_swap_route_creator(whale_to_usdc.0, whale_to_usdc.1, |result| {
+                assert_eq!(result.unwrap().creator, alice.to_string());
+            });
+    }
+}
@@ -0,0 +1,60 @@
+use amm::pool_manager::{OraclePriceResponse, OracleQueryMsg};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw_storage_plus::Item;
+
+/// A mock oracle, returning the same price for any pair of denoms.
+const PRICE: Item<OraclePriceResponse> = Item::new("price");
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub price: Decimal,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Updates the price returned by the mock, as of the current block time.
+    SetPrice { price: Decimal },
+}
+
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    PRICE.save(
+        deps.storage,
+        &OraclePriceResponse {
+            price: msg.price,
+            updated_at: env.block.time.seconds(),
+        },
+    )?;
+    Ok(Response::default())
+}
+
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::SetPrice { price } => PRICE.save(
+            deps.storage,
+            &OraclePriceResponse {
+                price,
+                updated_at: env.block.time.seconds(),
+            },
+        )?,
+    }
+    Ok(Response::default())
+}
+
+pub fn query(deps: Deps, _env: Env, msg: OracleQueryMsg) -> StdResult<Binary> {
+    match msg {
+        OracleQueryMsg::Price { .. } => to_json_binary(&PRICE.load(deps.storage)?),
+    }
+}
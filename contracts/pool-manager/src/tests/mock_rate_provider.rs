@@ -0,0 +1,50 @@
+use amm::pool_manager::{ExchangeRateResponse, RateProviderQueryMsg};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw_storage_plus::Item;
+
+/// A mock rate provider, returning the same exchange rate for any denom.
+const RATE: Item<Decimal> = Item::new("rate");
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub rate: Decimal,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Updates the exchange rate returned by the mock.
+    SetRate { rate: Decimal },
+}
+
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    RATE.save(deps.storage, &msg.rate)?;
+    Ok(Response::default())
+}
+
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::SetRate { rate } => RATE.save(deps.storage, &rate)?,
+    }
+    Ok(Response::default())
+}
+
+pub fn query(deps: Deps, _env: Env, msg: RateProviderQueryMsg) -> StdResult<Binary> {
+    match msg {
+        RateProviderQueryMsg::ExchangeRate { .. } => to_json_binary(&ExchangeRateResponse {
+            rate: RATE.load(deps.storage)?,
+        }),
+    }
+}
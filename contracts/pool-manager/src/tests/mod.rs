@@ -1,2 +1,4 @@
 pub mod integration_tests;
+pub mod mock_oracle;
+pub mod mock_rate_provider;
 pub mod suite;
@@ -1,12 +1,19 @@
 use amm::pool_manager::{
-    Config, FeatureToggle, PoolsResponse, ReverseSimulateSwapOperationsResponse,
-    ReverseSimulationResponse, SimulateSwapOperationsResponse, SimulationResponse, SwapOperation,
+    BestRouteResponse, ConcentratedPoolStateResponse, ConcentratedPositionAction,
+    ConcentratedPositionRange, ConcentratedPositionsBy, ConcentratedPositionsResponse, Config,
+    FeatureToggle, PoolsResponse, ReserveHealthResponse, ReverseSimulateSwapOperationsResponse,
+    ReverseSimulationResponse, ShareAllowanceResponse, ShareBalanceResponse,
+    SimulateProvideLiquidityResponse, SimulateSwapOperationsResponse,
+    SimulateWithdrawLiquidityResponse, SimulationResponse, SplitSwapRoute, SwapOperation,
+    SwapRoute, SwapRouteCreatorResponse, SwapRouteResponse, SwapRoutesResponse,
 };
-use amm::pool_manager::{InstantiateMsg, PoolType};
+use amm::pool_manager::{ExcessDepositMode, InstantiateMsg, LpTokenMode, PoolType};
 use cosmwasm_std::testing::MockStorage;
 use std::cell::RefCell;
 
-use cosmwasm_std::{coin, Addr, Coin, Decimal, Empty, StdResult, Timestamp, Uint128, Uint64};
+use cosmwasm_std::{
+    coin, Addr, Binary, Coin, Decimal, Empty, StdResult, Timestamp, Uint128, Uint64,
+};
 use cw_multi_test::{
     App, AppBuilder, AppResponse, BankKeeper, Contract, ContractWrapper, DistributionKeeper,
     Executor, FailingModule, GovFailingModule, IbcFailingModule, MockApiBech32, StakeKeeper,
@@ -16,7 +23,8 @@ use cw_multi_test::{
 use amm::constants::{LP_SYMBOL, MONTH_IN_SECONDS};
 use amm::epoch_manager::EpochConfig;
 use amm::farm_manager::PositionsResponse;
-use amm::fee::PoolFee;
+use amm::fee::{Fee, PoolFee};
+use amm::lp_common::internal_lp_denom;
 use common_testing::multi_test::stargate_mock::StargateMock;
 
 /// Creates the pool manager contract
@@ -67,6 +75,43 @@ pub fn farm_manager_contract() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
+/// Creates a mock rate provider contract
+fn mock_rate_provider_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        super::mock_rate_provider::execute,
+        super::mock_rate_provider::instantiate,
+        super::mock_rate_provider::query,
+    );
+
+    Box::new(contract)
+}
+
+/// Creates a mock oracle contract
+fn mock_oracle_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        super::mock_oracle::execute,
+        super::mock_oracle::instantiate,
+        super::mock_oracle::query,
+    );
+
+    Box::new(contract)
+}
+
+/// Creates the fees of a pool charging the given protocol and swap fee shares, without burn or
+/// extra fees.
+pub(crate) fn pool_fees(protocol_fee: Decimal, swap_fee: Decimal) -> PoolFee {
+    PoolFee {
+        protocol_fee: Fee {
+            share: protocol_fee,
+        },
+        swap_fee: Fee { share: swap_fee },
+        burn_fee: Fee {
+            share: Decimal::zero(),
+        },
+        extra_fees: vec![],
+    }
+}
+
 type OsmosisTokenFactoryApp = App<
     BankKeeper,
     MockApiBech32,
@@ -87,6 +132,7 @@ pub struct TestingSuite {
     pub pool_manager_addr: Addr,
     pub farm_manager_addr: Addr,
     pub epoch_manager_addr: Addr,
+    lp_token_mode: LpTokenMode,
 }
 
 /// TestingSuite helpers
@@ -116,10 +162,19 @@ impl TestingSuite {
     }
 
     pub(crate) fn get_lp_denom(&self, pool_identifier: String) -> String {
-        format!(
-            "factory/{}/{}.{}",
-            self.pool_manager_addr, pool_identifier, LP_SYMBOL
-        )
+        match self.lp_token_mode {
+            LpTokenMode::TokenFactory => format!(
+                "factory/{}/{}.{}",
+                self.pool_manager_addr, pool_identifier, LP_SYMBOL
+            ),
+            LpTokenMode::Internal => internal_lp_denom(&self.pool_manager_addr, &pool_identifier),
+        }
+    }
+
+    /// Sets the LP token mode the pool manager is instantiated with.
+    pub(crate) fn with_lp_token_mode(&mut self, lp_token_mode: LpTokenMode) -> &mut Self {
+        self.lp_token_mode = lp_token_mode;
+        self
     }
 }
 
@@ -161,9 +216,25 @@ impl TestingSuite {
             pool_manager_addr: Addr::unchecked(""),
             farm_manager_addr: Addr::unchecked(""),
             epoch_manager_addr: Addr::unchecked(""),
+            lp_token_mode: LpTokenMode::default(),
         }
     }
 
+    /// Creates a suite funding each sender with 1_000_000_000 of the given denoms, and of the uusd
+    /// and uom paying the pool creation and token factory fees.
+    pub(crate) fn default_with_denoms(denoms: &[&str]) -> Self {
+        let initial_balance = denoms
+            .iter()
+            .chain(&["uusd", "uom"])
+            .map(|denom| coin(1_000_000_000u128, *denom))
+            .collect();
+
+        Self::default_with_balances(
+            initial_balance,
+            StargateMock::new("uom".to_string(), "8888".to_string()),
+        )
+    }
+
     #[track_caller]
     pub(crate) fn instantiate(
         &mut self,
@@ -174,6 +245,7 @@ impl TestingSuite {
             fee_collector_addr,
             farm_manager_addr,
             pool_creation_fee: coin(1_000, "uusd"),
+            lp_token_mode: Some(self.lp_token_mode.clone()),
         };
 
         let pool_manager_id = self.app.store_code(contract_pool_manager());
@@ -239,6 +311,44 @@ impl TestingSuite {
             .unwrap();
     }
 
+    /// Instantiates a mock rate provider returning the given exchange rate, returning its address.
+    #[track_caller]
+    pub(crate) fn instantiate_mock_rate_provider(&mut self, rate: Decimal) -> Addr {
+        let rate_provider_id = self.app.store_code(mock_rate_provider_contract());
+
+        let creator = self.creator().clone();
+
+        self.app
+            .instantiate_contract(
+                rate_provider_id,
+                creator.clone(),
+                &super::mock_rate_provider::InstantiateMsg { rate },
+                &[],
+                "Mock Rate Provider".to_string(),
+                Some(creator.to_string()),
+            )
+            .unwrap()
+    }
+
+    /// Instantiates a mock oracle returning the given price, returning its address.
+    #[track_caller]
+    pub(crate) fn instantiate_mock_oracle(&mut self, price: Decimal) -> Addr {
+        let oracle_id = self.app.store_code(mock_oracle_contract());
+
+        let creator = self.creator().clone();
+
+        self.app
+            .instantiate_contract(
+                oracle_id,
+                creator.clone(),
+                &super::mock_oracle::InstantiateMsg { price },
+                &[],
+                "Mock Oracle".to_string(),
+                Some(creator.to_string()),
+            )
+            .unwrap()
+    }
+
     fn create_epoch_manager(&mut self) {
         let epoch_manager_id = self.app.store_code(epoch_manager_contract());
 
@@ -307,6 +417,44 @@ impl TestingSuite {
 
 /// execute messages
 impl TestingSuite {
+    #[track_caller]
+    pub(crate) fn set_mock_exchange_rate(
+        &mut self,
+        rate_provider: &Addr,
+        rate: Decimal,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let sender = self.creator();
+
+        result(self.app.execute_contract(
+            sender,
+            rate_provider.clone(),
+            &super::mock_rate_provider::ExecuteMsg::SetRate { rate },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn set_mock_oracle_price(
+        &mut self,
+        oracle: &Addr,
+        price: Decimal,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let sender = self.creator();
+
+        result(self.app.execute_contract(
+            sender,
+            oracle.clone(),
+            &super::mock_oracle::ExecuteMsg::SetPrice { price },
+            &[],
+        ));
+
+        self
+    }
+
     #[track_caller]
     pub(crate) fn update_ownership(
         &mut self,
@@ -345,6 +493,73 @@ impl TestingSuite {
             receiver,
             unlocking_duration,
             lock_position_identifier,
+            excess_mode: None,
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn provide_liquidity_with_excess_mode(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        excess_mode: ExcessDepositMode,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::ProvideLiquidity {
+            pool_identifier,
+            slippage_tolerance: None,
+            max_spread: None,
+            receiver: None,
+            unlocking_duration: None,
+            lock_position_identifier: None,
+            excess_mode: Some(excess_mode),
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn migrate_liquidity(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        target_pool_identifier: String,
+        unlocking_duration: Option<u64>,
+        lock_position_identifier: Option<String>,
+        concentrated_position: Option<ConcentratedPositionRange>,
+        swap_operations: Option<Vec<Vec<SwapOperation>>>,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::MigrateLiquidity {
+            pool_identifier,
+            target_pool_identifier,
+            slippage_tolerance: None,
+            max_spread: None,
+            swap_operations,
+            receiver: None,
+            unlocking_duration,
+            lock_position_identifier,
+            excess_mode: None,
+            concentrated_position,
         };
 
         result(self.app.execute_contract(
@@ -388,6 +603,35 @@ impl TestingSuite {
         self
     }
 
+    #[track_caller]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn swap_exact_out(
+        &mut self,
+        sender: &Addr,
+        ask_asset: Coin,
+        max_offer_amount: Option<Uint128>,
+        receiver: Option<String>,
+        pool_identifier: String,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::SwapExactOut {
+            ask_asset,
+            max_offer_amount,
+            receiver,
+            pool_identifier,
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
     #[track_caller]
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn execute_swap_operations(
@@ -419,44 +663,52 @@ impl TestingSuite {
 
     #[track_caller]
     #[allow(clippy::too_many_arguments)]
-    pub(crate) fn create_pool(
+    pub(crate) fn execute_swap_operations_exact_out(
         &mut self,
         sender: &Addr,
-        asset_denoms: Vec<String>,
-        asset_decimals: Vec<u8>,
-        pool_fees: PoolFee,
-        pool_type: PoolType,
-        pool_identifier: Option<String>,
-        pool_creation_fee_funds: Vec<Coin>,
+        operations: Vec<SwapOperation>,
+        ask_amount: Uint128,
+        max_offer_amount: Option<Uint128>,
+        receiver: Option<String>,
+        funds: Vec<Coin>,
         result: impl Fn(Result<AppResponse, anyhow::Error>),
     ) -> &mut Self {
-        let msg = amm::pool_manager::ExecuteMsg::CreatePool {
-            asset_denoms,
-            asset_decimals,
-            pool_fees,
-            pool_type,
-            pool_identifier,
+        let msg = amm::pool_manager::ExecuteMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer_amount,
+            receiver,
+            max_spread: None,
         };
 
         result(self.app.execute_contract(
             sender.clone(),
             self.pool_manager_addr.clone(),
             &msg,
-            &pool_creation_fee_funds,
+            &funds,
         ));
 
         self
     }
 
     #[track_caller]
-    pub(crate) fn withdraw_liquidity(
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn execute_split_swap_operations(
         &mut self,
         sender: &Addr,
-        pool_identifier: String,
+        routes: Vec<SplitSwapRoute>,
+        minimum_receive: Option<Uint128>,
+        receiver: Option<String>,
+        max_spread: Option<Decimal>,
         funds: Vec<Coin>,
         result: impl Fn(Result<AppResponse, anyhow::Error>),
     ) -> &mut Self {
-        let msg = amm::pool_manager::ExecuteMsg::WithdrawLiquidity { pool_identifier };
+        let msg = amm::pool_manager::ExecuteMsg::ExecuteSplitSwapOperations {
+            routes,
+            minimum_receive,
+            receiver,
+            max_spread,
+        };
 
         result(self.app.execute_contract(
             sender.clone(),
@@ -468,96 +720,586 @@ impl TestingSuite {
         self
     }
 
-    /// Updates the configuration of the contract.
-    ///
-    /// Any parameters which are set to `None` when passed will not update
-    /// the current configuration.
     #[track_caller]
-    pub(crate) fn update_config(
+    pub(crate) fn swap_via_route(
         &mut self,
         sender: &Addr,
-        new_fee_collector_addr: Option<Addr>,
-        new_farm_manager_addr: Option<Addr>,
-        new_pool_creation_fee: Option<Coin>,
-        new_feature_toggle: Option<FeatureToggle>,
+        ask_asset_denom: String,
+        minimum_receive: Option<Uint128>,
+        receiver: Option<String>,
+        funds: Vec<Coin>,
         result: impl Fn(Result<AppResponse, anyhow::Error>),
     ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::SwapViaRoute {
+            ask_asset_denom,
+            minimum_receive,
+            receiver,
+            max_spread: None,
+        };
+
         result(self.app.execute_contract(
             sender.clone(),
             self.pool_manager_addr.clone(),
-            &amm::pool_manager::ExecuteMsg::UpdateConfig {
-                fee_collector_addr: new_fee_collector_addr.map(|addr| addr.to_string()),
-                farm_manager_addr: new_farm_manager_addr.map(|addr| addr.to_string()),
-                pool_creation_fee: new_pool_creation_fee,
-                feature_toggle: new_feature_toggle,
-            },
-            &[],
+            &msg,
+            &funds,
         ));
 
         self
     }
 
-    /// Updates the configuration of the farm manager contract.
-    ///
-    /// Any parameters which are set to `None` when passed will not update
-    /// the current configuration.
     #[track_caller]
-    pub(crate) fn update_farm_manager_config(
+    pub(crate) fn add_swap_routes(
         &mut self,
         sender: &Addr,
-        new_pool_manager_addr: Addr,
+        swap_routes: Vec<SwapRoute>,
         result: impl Fn(Result<AppResponse, anyhow::Error>),
     ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::AddSwapRoutes { swap_routes };
+
         result(self.app.execute_contract(
             sender.clone(),
-            self.farm_manager_addr.clone(),
-            &amm::farm_manager::ExecuteMsg::UpdateConfig {
-                fee_collector_addr: None,
-                epoch_manager_addr: None,
-                pool_manager_addr: Some(new_pool_manager_addr.to_string()),
-                create_farm_fee: None,
-                max_concurrent_farms: None,
-                max_farm_epoch_buffer: None,
-                min_unlocking_duration: None,
-                max_unlocking_duration: None,
-                farm_expiration_time: None,
-                emergency_unlock_penalty: None,
-            },
+            self.pool_manager_addr.clone(),
+            &msg,
             &[],
         ));
 
         self
     }
-}
 
-/// queries
-impl TestingSuite {
-    pub(crate) fn query_ownership(
+    #[track_caller]
+    pub(crate) fn remove_swap_routes(
         &mut self,
-        result: impl Fn(StdResult<cw_ownable::Ownership<String>>),
+        sender: &Addr,
+        swap_routes: Vec<(String, String)>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
     ) -> &mut Self {
-        let ownership_response: StdResult<cw_ownable::Ownership<String>> =
-            self.app.wrap().query_wasm_smart(
-                &self.pool_manager_addr,
-                &amm::pool_manager::QueryMsg::Ownership {},
-            );
+        let msg = amm::pool_manager::ExecuteMsg::RemoveSwapRoutes { swap_routes };
 
-        result(ownership_response);
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &[],
+        ));
 
         self
     }
 
-    pub(crate) fn query_balance(
+    #[track_caller]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn swap_underlying(
         &mut self,
-        addr: &String,
-        denom: impl Into<String>,
-        result: impl Fn(StdResult<Coin>),
-    ) -> &mut Self {
-        let balance_resp: StdResult<Coin> = self.app.wrap().query_balance(addr, denom);
-
-        result(balance_resp);
-
-        self
+        sender: &Addr,
+        ask_asset_denom: String,
+        minimum_receive: Option<Uint128>,
+        receiver: Option<String>,
+        max_spread: Option<Decimal>,
+        pool_identifier: String,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::SwapUnderlying {
+            ask_asset_denom,
+            minimum_receive,
+            receiver,
+            max_spread,
+            pool_identifier,
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn create_pool(
+        &mut self,
+        sender: &Addr,
+        asset_denoms: Vec<String>,
+        asset_decimals: Vec<u8>,
+        pool_fees: PoolFee,
+        pool_type: PoolType,
+        pool_identifier: Option<String>,
+        pool_creation_fee_funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::CreatePool {
+            asset_denoms,
+            asset_decimals,
+            pool_fees,
+            pool_type,
+            pool_identifier,
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &pool_creation_fee_funds,
+        ));
+
+        self
+    }
+
+    /// Creates a pool for the given assets, all with 6 decimals, paying the creation fees from the
+    /// creator, who then provides the `deposits` as initial liquidity unless they're empty.
+    #[track_caller]
+    pub(crate) fn create_pool_with_liquidity(
+        &mut self,
+        asset_denoms: &[&str],
+        pool_type: PoolType,
+        pool_fees: PoolFee,
+        pool_identifier: &str,
+        deposits: Vec<Coin>,
+    ) -> &mut Self {
+        let creator = self.creator();
+        // no token factory fee is charged when the LP shares are accounted for internally
+        let pool_creation_fee_funds = match self.lp_token_mode {
+            LpTokenMode::TokenFactory => vec![coin(1000, "uusd"), coin(8888, "uom")],
+            LpTokenMode::Internal => vec![coin(1000, "uusd")],
+        };
+
+        self.create_pool(
+            &creator,
+            asset_denoms.iter().map(|denom| denom.to_string()).collect(),
+            vec![6u8; asset_denoms.len()],
+            pool_fees,
+            pool_type,
+            Some(pool_identifier.to_string()),
+            pool_creation_fee_funds,
+            |result| {
+                result.unwrap();
+            },
+        );
+
+        if !deposits.is_empty() {
+            self.provide_liquidity(
+                &creator,
+                format!("o.{pool_identifier}"),
+                None,
+                None,
+                None,
+                None,
+                deposits,
+                |result| {
+                    result.unwrap();
+                },
+            );
+        }
+
+        self
+    }
+
+    #[track_caller]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn withdraw_liquidity(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        min_assets_out: Option<Vec<Coin>>,
+        receiver: Option<String>,
+        deadline: Option<Timestamp>,
+        target_denom: Option<String>,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::WithdrawLiquidity {
+            pool_identifier,
+            min_assets_out,
+            receiver,
+            deadline,
+            target_denom,
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn withdraw_liquidity_one_coin(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        denom: String,
+        min_amount: Uint128,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::WithdrawLiquidityOneCoin {
+            pool_identifier,
+            denom,
+            min_amount,
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn withdraw_liquidity_imbalance(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        amounts: Vec<Coin>,
+        max_lp_burn: Uint128,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::WithdrawLiquidityImbalance {
+            pool_identifier,
+            amounts,
+            max_lp_burn,
+        };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
+    /// Updates the configuration of the contract.
+    ///
+    /// Any parameters which are set to `None` when passed will not update
+    /// the current configuration.
+    #[track_caller]
+    pub(crate) fn update_config(
+        &mut self,
+        sender: &Addr,
+        new_fee_collector_addr: Option<Addr>,
+        new_farm_manager_addr: Option<Addr>,
+        new_pool_creation_fee: Option<Coin>,
+        new_feature_toggle: Option<FeatureToggle>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::UpdateConfig {
+                fee_collector_addr: new_fee_collector_addr.map(|addr| addr.to_string()),
+                farm_manager_addr: new_farm_manager_addr.map(|addr| addr.to_string()),
+                pool_creation_fee: new_pool_creation_fee,
+                feature_toggle: new_feature_toggle,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn ramp_amp(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        target_amp: u64,
+        end_time: u64,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::RampAmp {
+                pool_identifier,
+                target_amp,
+                end_time,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn stop_ramp_amp(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::StopRampAmp { pool_identifier },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn skim(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        recipient: String,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::Skim {
+                pool_identifier,
+                recipient,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn transfer_shares(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        recipient: String,
+        amount: Uint128,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::TransferShares {
+                pool_identifier,
+                recipient,
+                amount,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn send_shares(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        amount: Uint128,
+        contract: String,
+        msg: Binary,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::SendShares {
+                pool_identifier,
+                amount,
+                contract,
+                msg,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn approve_shares(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        spender: String,
+        amount: Uint128,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::ApproveShares {
+                pool_identifier,
+                spender,
+                amount,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn transfer_shares_from(
+        &mut self,
+        sender: &Addr,
+        pool_identifier: String,
+        owner: String,
+        recipient: String,
+        amount: Uint128,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &amm::pool_manager::ExecuteMsg::TransferSharesFrom {
+                pool_identifier,
+                owner,
+                recipient,
+                amount,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn send_tokens(
+        &mut self,
+        sender: &Addr,
+        recipient: &Addr,
+        funds: &[Coin],
+    ) -> &mut Self {
+        self.app
+            .send_tokens(sender.clone(), recipient.clone(), funds)
+            .unwrap();
+
+        self
+    }
+
+    /// Updates the configuration of the farm manager contract.
+    ///
+    /// Any parameters which are set to `None` when passed will not update
+    /// the current configuration.
+    #[track_caller]
+    pub(crate) fn update_farm_manager_config(
+        &mut self,
+        sender: &Addr,
+        new_pool_manager_addr: Addr,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.farm_manager_addr.clone(),
+            &amm::farm_manager::ExecuteMsg::UpdateConfig {
+                fee_collector_addr: None,
+                epoch_manager_addr: None,
+                pool_manager_addr: Some(new_pool_manager_addr.to_string()),
+                create_farm_fee: None,
+                max_concurrent_farms: None,
+                max_farm_epoch_buffer: None,
+                min_unlocking_duration: None,
+                max_unlocking_duration: None,
+                farm_expiration_time: None,
+                emergency_unlock_penalty: None,
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn manage_concentrated_position(
+        &mut self,
+        sender: &Addr,
+        action: ConcentratedPositionAction,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::pool_manager::ExecuteMsg::ManageConcentratedPosition { action };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.pool_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn update_farm_position_operators(
+        &mut self,
+        sender: &Addr,
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::farm_manager::ExecuteMsg::UpdatePositionOperators { add, remove };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.farm_manager_addr.clone(),
+            &msg,
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn manage_farm_position(
+        &mut self,
+        sender: &Addr,
+        action: amm::farm_manager::PositionAction,
+        funds: Vec<Coin>,
+        result: impl Fn(Result<AppResponse, anyhow::Error>),
+    ) -> &mut Self {
+        let msg = amm::farm_manager::ExecuteMsg::ManagePosition { action };
+
+        result(self.app.execute_contract(
+            sender.clone(),
+            self.farm_manager_addr.clone(),
+            &msg,
+            &funds,
+        ));
+
+        self
+    }
+}
+
+/// queries
+impl TestingSuite {
+    pub(crate) fn query_ownership(
+        &mut self,
+        result: impl Fn(StdResult<cw_ownable::Ownership<String>>),
+    ) -> &mut Self {
+        let ownership_response: StdResult<cw_ownable::Ownership<String>> =
+            self.app.wrap().query_wasm_smart(
+                &self.pool_manager_addr,
+                &amm::pool_manager::QueryMsg::Ownership {},
+            );
+
+        result(ownership_response);
+
+        self
+    }
+
+    pub(crate) fn query_balance(
+        &mut self,
+        addr: &String,
+        denom: impl Into<String>,
+        result: impl Fn(StdResult<Coin>),
+    ) -> &mut Self {
+        let balance_resp: StdResult<Coin> = self.app.wrap().query_balance(addr, denom);
+
+        result(balance_resp);
+
+        self
     }
 
     pub(crate) fn query_all_balances(
@@ -614,6 +1356,55 @@ impl TestingSuite {
         self
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn query_simulate_provide_liquidity(
+        &mut self,
+        pool_identifier: String,
+        deposits: Vec<Coin>,
+        slippage_tolerance: Option<Decimal>,
+        max_spread: Option<Decimal>,
+        excess_mode: Option<ExcessDepositMode>,
+        result: impl Fn(StdResult<SimulateProvideLiquidityResponse>),
+    ) -> &mut Self {
+        let simulation_response: StdResult<SimulateProvideLiquidityResponse> =
+            self.app.wrap().query_wasm_smart(
+                &self.pool_manager_addr,
+                &amm::pool_manager::QueryMsg::SimulateProvideLiquidity {
+                    pool_identifier,
+                    deposits,
+                    slippage_tolerance,
+                    max_spread,
+                    excess_mode,
+                },
+            );
+
+        result(simulation_response);
+
+        self
+    }
+
+    pub(crate) fn query_simulate_withdraw_liquidity(
+        &mut self,
+        pool_identifier: String,
+        lp_amount: Uint128,
+        target_denom: Option<String>,
+        result: impl Fn(StdResult<SimulateWithdrawLiquidityResponse>),
+    ) -> &mut Self {
+        let simulation_response: StdResult<SimulateWithdrawLiquidityResponse> =
+            self.app.wrap().query_wasm_smart(
+                &self.pool_manager_addr,
+                &amm::pool_manager::QueryMsg::SimulateWithdrawLiquidity {
+                    pool_identifier,
+                    lp_amount,
+                    target_denom,
+                },
+            );
+
+        result(simulation_response);
+
+        self
+    }
+
     pub(crate) fn query_reverse_simulation(
         &mut self,
         pool_identifier: String,
@@ -763,4 +1554,172 @@ impl TestingSuite {
 
         self
     }
+
+    pub(crate) fn query_concentrated_pool_state(
+        &mut self,
+        pool_identifier: String,
+        result: impl Fn(StdResult<ConcentratedPoolStateResponse>),
+    ) -> &mut Self {
+        let response: StdResult<ConcentratedPoolStateResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::ConcentratedPoolState { pool_identifier },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_swap_route(
+        &mut self,
+        offer_asset_denom: String,
+        ask_asset_denom: String,
+        result: impl Fn(StdResult<SwapRouteResponse>),
+    ) -> &mut Self {
+        let response: StdResult<SwapRouteResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::SwapRoute {
+                offer_asset_denom,
+                ask_asset_denom,
+            },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_swap_routes(
+        &mut self,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+        result: impl Fn(StdResult<SwapRoutesResponse>),
+    ) -> &mut Self {
+        let response: StdResult<SwapRoutesResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::SwapRoutes { start_after, limit },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_swap_route_creator(
+        &mut self,
+        offer_asset_denom: String,
+        ask_asset_denom: String,
+        result: impl Fn(StdResult<SwapRouteCreatorResponse>),
+    ) -> &mut Self {
+        let response: StdResult<SwapRouteCreatorResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::SwapRouteCreator {
+                offer_asset_denom,
+                ask_asset_denom,
+            },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_best_route(
+        &mut self,
+        offer_asset: Coin,
+        ask_asset_denom: String,
+        max_hops: Option<u32>,
+        max_pools: Option<u32>,
+        result: impl Fn(StdResult<BestRouteResponse>),
+    ) -> &mut Self {
+        let response: StdResult<BestRouteResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::BestRoute {
+                offer_asset,
+                ask_asset_denom,
+                max_hops,
+                max_pools,
+            },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_reserve_health(
+        &mut self,
+        pool_identifier: Option<String>,
+        result: impl Fn(StdResult<ReserveHealthResponse>),
+    ) -> &mut Self {
+        let response: StdResult<ReserveHealthResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::ReserveHealth { pool_identifier },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_share_balance(
+        &mut self,
+        pool_identifier: String,
+        address: String,
+        result: impl Fn(StdResult<ShareBalanceResponse>),
+    ) -> &mut Self {
+        let response: StdResult<ShareBalanceResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::ShareBalance {
+                pool_identifier,
+                address,
+            },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_share_allowance(
+        &mut self,
+        pool_identifier: String,
+        owner: String,
+        spender: String,
+        result: impl Fn(StdResult<ShareAllowanceResponse>),
+    ) -> &mut Self {
+        let response: StdResult<ShareAllowanceResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::ShareAllowance {
+                pool_identifier,
+                owner,
+                spender,
+            },
+        );
+
+        result(response);
+
+        self
+    }
+
+    pub(crate) fn query_concentrated_positions(
+        &mut self,
+        filter_by: Option<ConcentratedPositionsBy>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        result: impl Fn(StdResult<ConcentratedPositionsResponse>),
+    ) -> &mut Self {
+        let response: StdResult<ConcentratedPositionsResponse> = self.app.wrap().query_wasm_smart(
+            &self.pool_manager_addr,
+            &amm::pool_manager::QueryMsg::ConcentratedPositions {
+                filter_by,
+                start_after,
+                limit,
+            },
+        );
+
+        result(response);
+
+        self
+    }
 }
@@ -5,6 +5,8 @@ use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
 
+use crate::pool_manager::{ExcessDepositMode, SharesReceiveMsg, SwapOperation};
+
 /// The instantiation message
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -71,6 +73,19 @@ pub enum ExecuteMsg {
         /// The penalty for unlocking a position before the unlocking duration finishes. In percentage.
         emergency_unlock_penalty: Option<Decimal>,
     },
+    /// Updates the position operators, i.e. the addresses allowed to create positions on behalf
+    /// of other receivers, like vaults or vesting contracts. Only the owner can update
+    /// them.
+    UpdatePositionOperators {
+        /// The addresses to add as position operators.
+        add: Option<Vec<String>>,
+        /// The addresses to remove from the position operators.
+        remove: Option<Vec<String>>,
+    },
+    /// Receives LP shares of a pool with internal LP accounting, sent by the pool manager. The
+    /// message wrapped in it must be a [ExecuteMsg::ManagePosition] creating or expanding a
+    /// position, which is executed with the received shares.
+    ReceiveShares(SharesReceiveMsg),
 }
 
 /// The migrate message
@@ -127,6 +142,15 @@ pub enum QueryMsg {
         /// The epoch id to get the LP weight for.
         epoch_id: EpochId,
     },
+    /// Retrieves the addresses allowed to create positions on behalf of other receivers.
+    #[returns(PositionOperatorsResponse)]
+    PositionOperators {},
+    /// Retrieves whether an address is allowed to create positions on behalf of other receivers.
+    #[returns(IsPositionOperatorResponse)]
+    IsPositionOperator {
+        /// The address to check.
+        address: String,
+    },
 }
 
 /// Enum to filter farms by identifier, lp denom or the farm asset. Used in the farms query.
@@ -237,6 +261,46 @@ pub enum PositionAction {
         /// unlocked immediately. If the position has not expired, it will pay a penalty.
         emergency_unlock: Option<bool>,
     },
+    /// Withdraws the LP tokens from a position, as `Withdraw` does, and redeems them for the
+    /// underlying pool assets through the pool manager in the same transaction. The assets are
+    /// sent to the receiver of the position.
+    WithdrawLiquidity {
+        /// The identifier of the position.
+        identifier: String,
+        /// Whether to unlock the position in an emergency. If set to true, the position will be
+        /// unlocked immediately. If the position has not expired, it will pay a penalty.
+        emergency_unlock: Option<bool>,
+        /// The identifier of the pool the LP tokens of the position belong to.
+        pool_identifier: String,
+        /// The minimum amounts of the pool assets to receive. If any of the assets returned is
+        /// below its minimum, the withdrawal will not be executed.
+        min_assets_out: Option<Vec<Coin>>,
+        /// The asset to withdraw the liquidity into, in which case a non-zero minimum amount of it
+        /// must be given in `min_assets_out`. Only supported on constant product pools.
+        target_denom: Option<String>,
+    },
+    /// Migrates the LP tokens of an open position to another pool through the pool manager in
+    /// the same transaction. The position keeps its identifier and unlocking duration, holding
+    /// the LP tokens of the target pool afterwards. Pending rewards must be claimed beforehand.
+    MigrateLiquidity {
+        /// The identifier of the position.
+        identifier: String,
+        /// The identifier of the pool the LP tokens of the position belong to.
+        pool_identifier: String,
+        /// The identifier of the pool to migrate the liquidity to.
+        target_pool_identifier: String,
+        /// The slippage tolerance of the liquidity provision to the target pool.
+        slippage_tolerance: Option<Decimal>,
+        /// The maximum spread of each swap performed by the migration, including the ones of the
+        /// liquidity provision to the target pool.
+        max_spread: Option<Decimal>,
+        /// The swap operations for each withdrawn asset the target pool doesn't hold, swapping it
+        /// into one of the assets of the target pool.
+        swap_operations: Option<Vec<Vec<SwapOperation>>>,
+        /// How the excess of an imbalanced deposit into a constant product pool is handled. If not
+        /// provided, the excess is donated to the pool.
+        excess_mode: Option<ExcessDepositMode>,
+    },
 }
 
 // type for the epoch id
@@ -356,6 +420,20 @@ pub struct PositionsResponse {
     pub positions: Vec<Position>,
 }
 
+/// The response for the position operators query
+#[cw_serde]
+pub struct PositionOperatorsResponse {
+    /// The addresses allowed to create positions on behalf of other receivers.
+    pub operators: Vec<Addr>,
+}
+
+/// The response for the is position operator query
+#[cw_serde]
+pub struct IsPositionOperatorResponse {
+    /// Whether the address is a position operator.
+    pub is_operator: bool,
+}
+
 /// The response for the LP weight query
 #[cw_serde]
 pub struct LpWeightResponse {
@@ -1,9 +1,96 @@
 use crate::coin::is_factory_token;
+use crate::constants::LP_SYMBOL;
+use crate::pool_manager::ExecuteMsg;
 use crate::tokenfactory;
-use cosmwasm_std::{ensure, Addr, Coin, CosmosMsg, StdError, StdResult, Uint128};
+use cosmwasm_schema::serde::Serialize;
+use cosmwasm_std::{
+    ensure, to_json_binary, wasm_execute, Addr, BankMsg, Coin, CosmosMsg, StdError, StdResult,
+    Uint128,
+};
 
 pub const MINIMUM_LIQUIDITY_AMOUNT: Uint128 = Uint128::new(1_000u128);
 
+/// The prefix of the LP denoms accounted for in the internal share ledger of the pool manager.
+pub const INTERNAL_LP_PREFIX: &str = "internal";
+
+/// Creates the LP denom of a pool with internal LP accounting, with the following structure:
+/// internal/{pool manager address}/{pool identifier}.LP
+pub fn internal_lp_denom(pool_manager: &Addr, pool_identifier: &str) -> String {
+    format!("{INTERNAL_LP_PREFIX}/{pool_manager}/{pool_identifier}.{LP_SYMBOL}")
+}
+
+/// Verifies if the given denom is the LP denom of a pool with internal LP accounting.
+pub fn is_internal_lp_denom(denom: &str) -> bool {
+    get_internal_lp_denom_parts(denom).is_ok()
+}
+
+/// Gets the pool manager address and the pool identifier of an internal LP denom.
+pub fn get_internal_lp_denom_parts(denom: &str) -> StdResult<(&str, &str)> {
+    let split: Vec<&str> = denom.splitn(3, '/').collect();
+
+    let pool_identifier = match split.as_slice() {
+        [INTERNAL_LP_PREFIX, pool_manager, lp_symbol] if !pool_manager.is_empty() => lp_symbol
+            .strip_suffix(LP_SYMBOL)
+            .and_then(|pool_identifier| pool_identifier.strip_suffix('.'))
+            .filter(|pool_identifier| !pool_identifier.is_empty()),
+        _ => None,
+    }
+    .ok_or_else(|| StdError::generic_err("Invalid internal LP denom"))?;
+
+    Ok((split[1], pool_identifier))
+}
+
+/// Creates the message sending LP tokens to the recipient, through the bank module or, for
+/// internal LP denoms, through the share ledger of the pool manager.
+pub fn transfer_lp_msg(lp_asset: Coin, recipient: &str) -> StdResult<CosmosMsg> {
+    if is_internal_lp_denom(&lp_asset.denom) {
+        let (pool_manager, pool_identifier) = get_internal_lp_denom_parts(&lp_asset.denom)?;
+
+        return Ok(wasm_execute(
+            pool_manager,
+            &ExecuteMsg::TransferShares {
+                pool_identifier: pool_identifier.to_string(),
+                recipient: recipient.to_string(),
+                amount: lp_asset.amount,
+            },
+            vec![],
+        )?
+        .into());
+    }
+
+    Ok(BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: vec![lp_asset],
+    }
+    .into())
+}
+
+/// Creates the message executing `msg` on a contract with LP tokens attached, as funds or, for
+/// internal LP denoms, sent through the share ledger of the pool manager.
+pub fn execute_with_lp_msg(
+    contract_addr: &str,
+    msg: &impl Serialize,
+    lp_asset: Coin,
+) -> StdResult<CosmosMsg> {
+    if is_internal_lp_denom(&lp_asset.denom) {
+        let (pool_manager, pool_identifier) = get_internal_lp_denom_parts(&lp_asset.denom)?;
+
+        return Ok(wasm_execute(
+            pool_manager,
+            &ExecuteMsg::SendShares {
+                pool_identifier: pool_identifier.to_string(),
+                amount: lp_asset.amount,
+                contract: contract_addr.to_string(),
+                msg: to_json_binary(msg)?,
+            },
+            vec![],
+        )?
+        .into());
+    }
+
+    Ok(wasm_execute(contract_addr, msg, vec![lp_asset])?.into())
+}
+
 /// Creates the Mint LP message
 #[allow(unused_variables)]
 pub fn mint_lp_token_msg(
@@ -48,3 +135,29 @@ pub fn burn_lp_asset_msg(
         sender.into_string(),
     ))
 }
+
+#[cfg(test)]
+mod lp_common_tests {
+    use cosmwasm_std::Addr;
+
+    use crate::lp_common::{get_internal_lp_denom_parts, internal_lp_denom, is_internal_lp_denom};
+
+    #[test]
+    fn internal_lp_denom_test() {
+        let denom = internal_lp_denom(&Addr::unchecked("pool_manager"), "o.whale.uluna");
+
+        assert_eq!(denom, "internal/pool_manager/o.whale.uluna.LP");
+        assert_eq!(
+            get_internal_lp_denom_parts(&denom).unwrap(),
+            ("pool_manager", "o.whale.uluna")
+        );
+
+        assert!(!is_internal_lp_denom(
+            "factory/pool_manager/o.whale.uluna.LP"
+        ));
+        assert!(!is_internal_lp_denom("internal/pool_manager/o.whale.uluna"));
+        assert!(!is_internal_lp_denom("internal//o.whale.uluna.LP"));
+        assert!(!is_internal_lp_denom("internal/pool_manager/.LP"));
+        assert!(!is_internal_lp_denom("uom"));
+    }
+}
@@ -1,7 +1,10 @@
 use std::fmt;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Coin, Decimal, Deps, StdError, StdResult, Uint128};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps, StdError, StdResult,
+    Timestamp, Uint128, Uint256, WasmMsg,
+};
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
 
 use crate::coin::is_factory_token;
@@ -21,6 +24,16 @@ pub enum SwapOperation {
     },
 }
 
+/// A route of a split swap, i.e. a sequence of [`SwapOperation`]s swapping a share of the input.
+#[cw_serde]
+pub struct SplitSwapRoute {
+    /// The operations that should be performed in sequence.
+    pub operations: Vec<SwapOperation>,
+    /// The weight of the route, relative to the weights of the other routes. The route swaps
+    /// `weight / total_weight` of the input.
+    pub weight: Decimal,
+}
+
 impl SwapOperation {
     /// Retrieves the `token_in_denom` used for this swap operation.
     pub fn get_input_asset_info(&self) -> &String {
@@ -80,16 +93,124 @@ pub struct PoolInfo {
     pub pool_fees: PoolFee,
 }
 
-/// Possible pool types, it can be either a constant product (xyk) pool or a stable swap pool.
+/// Possible pool types, it can be either a constant product (xyk) pool, a stable swap pool, a
+/// weighted pool, a concentrated liquidity pool, a Solidly stable pool, a liquidity bootstrapping
+/// pool or an oracle pegged pool.
 #[cw_serde]
 pub enum PoolType {
     /// A stable swap pool.
     StableSwap {
         /// The amount of amplification to perform on the constant product part of the swap formula.
         amp: u64,
+        /// The sources of the exchange rates of the assets, provided in the same order as the
+        /// asset denoms. The balances are scaled by these rates before running the stableswap
+        /// math, which allows pairing yield-bearing or liquid staking assets with their
+        /// underlying asset. If not provided, all the assets are pegged 1:1.
+        rate_providers: Option<Vec<RateProvider>>,
     },
     /// xyk pool
     ConstantProduct,
+    /// A weighted (Balancer-style) pool, where each asset has a fixed weight in the invariant
+    /// `prod(balance_i ^ weight_i) = k`.
+    Weighted {
+        /// The normalized weights of the assets, provided in the same order as the asset denoms.
+        /// They must add up to 1.
+        weights: Vec<Decimal>,
+    },
+    /// A concentrated liquidity pool with two assets, where the liquidity is provided within
+    /// price ranges delimited by ticks instead of across the whole curve. The price at a given
+    /// tick `i` is `1.0001^i`, expressed as the amount of the second asset per unit of the first
+    /// asset.
+    ConcentratedLiquidity {
+        /// The spacing between the ticks that can be used as the bounds of a position.
+        tick_spacing: u64,
+        /// The price the pool starts at, i.e. how many units of the second asset one unit of the
+        /// first asset is worth.
+        initial_price: Decimal,
+    },
+    /// A Solidly-style stable pool with two assets, using the invariant `x³y + xy³ = k`. Unlike
+    /// [PoolType::StableSwap], the curve has no amplification, which suits correlated assets that
+    /// are not pegged 1:1.
+    SolidlyStable,
+    /// A liquidity bootstrapping pool, i.e. a weighted pool whose weights move linearly from the
+    /// start weights to the end weights over time. Used for token launches, where the pool starts
+    /// heavily weighted towards the launched token and its price decreases until buyers step in.
+    LiquidityBootstrapping {
+        /// The normalized weights of the assets at the start of the schedule, provided in the same
+        /// order as the asset denoms. They must add up to 1.
+        start_weights: Vec<Decimal>,
+        /// The normalized weights of the assets at the end of the schedule, provided in the same
+        /// order as the asset denoms. They must add up to 1.
+        end_weights: Vec<Decimal>,
+        /// The timestamp in seconds when the weights start moving.
+        start_time: u64,
+        /// The timestamp in seconds when the weights reach the end weights.
+        end_time: u64,
+    },
+    /// An oracle pegged pool with two assets, i.e. a stable swap curve centred on the price of the
+    /// first asset in terms of the second one, read from an oracle contract at swap time. Suits
+    /// assets priced off-chain, such as RWA tokens with a NAV, as the curve follows the price
+    /// updates instead of waiting for arbitrageurs to move it.
+    OraclePegged {
+        /// The oracle contract queried for the price with [OracleQueryMsg].
+        oracle_addr: String,
+        /// The amount of amplification of the curve around the oracle price.
+        amp: u64,
+        /// The maximum age in seconds of the oracle price for it to be used.
+        max_staleness: u64,
+        /// The maximum deviation of the execution price of a swap from the oracle price, i.e.
+        /// the maximum spread of a swap relative to the amount it would return at the oracle
+        /// price.
+        max_deviation: Decimal,
+    },
+}
+
+/// The source of the exchange rate of an asset in a stable swap pool, i.e. how many units of
+/// the pool's reference asset one unit of the asset is worth.
+#[cw_serde]
+pub enum RateProvider {
+    /// A fixed exchange rate.
+    Fixed { rate: Decimal },
+    /// A contract that is queried for the exchange rate with [RateProviderQueryMsg].
+    Contract { contract_addr: String },
+}
+
+/// The query message rate provider contracts must implement.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum RateProviderQueryMsg {
+    /// Retrieves the exchange rate of the given denom.
+    #[returns(ExchangeRateResponse)]
+    ExchangeRate { denom: String },
+}
+
+/// The response of a rate provider contract to [RateProviderQueryMsg::ExchangeRate].
+#[cw_serde]
+pub struct ExchangeRateResponse {
+    /// The exchange rate of the denom.
+    pub rate: Decimal,
+}
+
+/// The query message oracle contracts used by [PoolType::OraclePegged] pools must implement.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OracleQueryMsg {
+    /// Retrieves the price of the base denom in terms of the quote denom, i.e. how many whole
+    /// units of the quote denom one whole unit of the base denom is worth.
+    #[returns(OraclePriceResponse)]
+    Price {
+        base_denom: String,
+        quote_denom: String,
+    },
+}
+
+/// The response of an oracle contract to [OracleQueryMsg::Price].
+#[cw_serde]
+pub struct OraclePriceResponse {
+    /// The price of the base denom in terms of the quote denom.
+    pub price: Decimal,
+    /// The timestamp in seconds when the price was last updated.
+    pub updated_at: u64,
 }
 
 impl PoolType {
@@ -98,10 +219,28 @@ impl PoolType {
         match self {
             PoolType::ConstantProduct => "ConstantProduct",
             PoolType::StableSwap { .. } => "StableSwap",
+            PoolType::Weighted { .. } => "Weighted",
+            PoolType::ConcentratedLiquidity { .. } => "ConcentratedLiquidity",
+            PoolType::SolidlyStable => "SolidlyStable",
+            PoolType::LiquidityBootstrapping { .. } => "LiquidityBootstrapping",
+            PoolType::OraclePegged { .. } => "OraclePegged",
         }
     }
 }
 
+/// How the excess of a deposit that doesn't match the ratio of a constant product pool is handled.
+#[cw_serde]
+pub enum ExcessDepositMode {
+    /// The excess is added to the pool, i.e. donated to the existing liquidity providers.
+    Donate,
+    /// The excess is refunded to the sender.
+    Refund,
+    /// The optimal portion of the excess is swapped into the other asset in the pool, so that the
+    /// whole deposit becomes liquidity. Any dust left after the swap is refunded to the sender.
+    /// Only supported on pools with 2 assets.
+    Swap,
+}
+
 /// The contract configuration.
 #[cw_serde]
 pub struct Config {
@@ -113,6 +252,23 @@ pub struct Config {
     pub pool_creation_fee: Coin,
     //  Whether or not swaps, deposits, and withdrawals are enabled
     pub feature_toggle: FeatureToggle,
+    /// How the LP tokens of the pools created from now on are accounted for.
+    #[serde(default)]
+    pub lp_token_mode: LpTokenMode,
+}
+
+/// How the LP tokens of a pool are accounted for. Each pool keeps the mode it was created with,
+/// which can be told from its LP denom.
+#[cw_serde]
+#[derive(Default)]
+pub enum LpTokenMode {
+    /// The LP tokens are token factory denoms, minted and burned through the token factory module.
+    #[default]
+    TokenFactory,
+    /// The LP tokens are shares tracked in a ledger of the pool manager, for chains without the
+    /// token factory module. The shares are moved with the `TransferShares`, `TransferSharesFrom`
+    /// and `SendShares` messages instead of the bank module.
+    Internal,
 }
 
 #[cw_serde]
@@ -123,6 +279,8 @@ pub struct InstantiateMsg {
     pub farm_manager_addr: String,
     /// How much it costs to create a pool. It helps prevent spamming of new pools.
     pub pool_creation_fee: Coin,
+    /// How the LP tokens of the pools are accounted for. Defaults to the token factory.
+    pub lp_token_mode: Option<LpTokenMode>,
 }
 
 #[cw_serde]
@@ -163,6 +321,9 @@ pub enum ExecuteMsg {
         unlocking_duration: Option<u64>,
         /// The identifier of the position to lock the LP tokens in the farm manager, if any.
         lock_position_identifier: Option<String>,
+        /// How the excess of an imbalanced deposit into a constant product pool is handled. If not
+        /// provided, the excess is donated to the pool.
+        excess_mode: Option<ExcessDepositMode>,
     },
     /// Swap an offer asset to the other
     Swap {
@@ -179,8 +340,91 @@ pub enum ExecuteMsg {
         /// The identifier for the pool to swap in.
         pool_identifier: String,
     },
+    /// Swaps the offer asset sent for an exact amount of the ask asset. Only the offer amount
+    /// needed is taken, the rest of the funds sent is refunded to the sender.
+    SwapExactOut {
+        /// The exact amount of the ask asset to receive. Must be greater than zero.
+        ask_asset: Coin,
+        /// The maximum amount of the offer asset to swap. If the swap requires more, it will not be
+        /// executed. If not provided, the swap is capped by the funds sent.
+        max_offer_amount: Option<Uint128>,
+        /// The recipient of the ask asset. If not provided, the tokens will be sent to the sender
+        /// of the message.
+        receiver: Option<String>,
+        /// The identifier for the pool to swap in.
+        pool_identifier: String,
+    },
     /// Withdraws liquidity from the pool.
-    WithdrawLiquidity { pool_identifier: String },
+    WithdrawLiquidity {
+        /// The identifier for the pool to withdraw liquidity from.
+        pool_identifier: String,
+        /// The minimum amounts of the pool assets to receive. If any of the refunded assets is
+        /// below its minimum, the withdrawal will not be executed.
+        min_assets_out: Option<Vec<Coin>>,
+        /// The recipient of the withdrawn assets. If not provided, the assets will be sent to the
+        /// sender of the message.
+        receiver: Option<String>,
+        /// The time after which the withdrawal can no longer be executed.
+        deadline: Option<Timestamp>,
+        /// The asset to withdraw the liquidity into. If provided, the other assets withdrawn are
+        /// swapped into it in the same pool, and a non-zero minimum amount of it must be given in
+        /// `min_assets_out`. Only supported on constant product pools.
+        target_denom: Option<String>,
+    },
+    /// Withdraws liquidity from a stable swap pool in a single asset, burning the LP tokens sent.
+    /// An imbalance fee is charged, as the withdrawal changes the proportions of the pool.
+    WithdrawLiquidityOneCoin {
+        /// The identifier of the stable swap pool to withdraw liquidity from.
+        pool_identifier: String,
+        /// The denom of the asset to withdraw.
+        denom: String,
+        /// The minimum amount of the asset to receive, otherwise the withdrawal fails.
+        min_amount: Uint128,
+    },
+    /// Withdraws the exact amounts of assets given from a stable swap pool, burning the LP tokens
+    /// needed under the stable swap invariant, imbalance fees included. The LP tokens are sent
+    /// with the message, and the ones not burned are returned to the sender.
+    WithdrawLiquidityImbalance {
+        /// The identifier of the stable swap pool to withdraw liquidity from.
+        pool_identifier: String,
+        /// The amounts of the assets to withdraw.
+        amounts: Vec<Coin>,
+        /// The maximum amount of LP tokens to burn, otherwise the withdrawal fails.
+        max_lp_burn: Uint128,
+    },
+    /// Migrates liquidity from a pool to another in a single transaction. The LP tokens sent are
+    /// burned and the assets withdrawn are provided to the target pool, as with
+    /// `ProvideLiquidity`, or used to create a position if the target pool is a concentrated
+    /// liquidity pool. The withdrawn assets the target pool doesn't hold are swapped into its
+    /// assets first. The excess of the deposit is refunded to the sender, or to the receiver
+    /// when the farm manager migrates the liquidity of a position on its behalf.
+    MigrateLiquidity {
+        /// The identifier of the pool to withdraw the liquidity from.
+        pool_identifier: String,
+        /// The identifier of the pool to provide the withdrawn liquidity to.
+        target_pool_identifier: String,
+        /// The slippage tolerance of the liquidity provision to the target pool.
+        slippage_tolerance: Option<Decimal>,
+        /// The maximum spread of each swap performed by the migration, including the ones of the
+        /// liquidity provision to the target pool.
+        max_spread: Option<Decimal>,
+        /// The swap operations for each withdrawn asset the target pool doesn't hold, swapping it
+        /// into one of the assets of the target pool. Required when the pools don't share all their
+        /// assets.
+        swap_operations: Option<Vec<Vec<SwapOperation>>>,
+        /// The receiver of the new LP tokens. If not provided, they are sent to the sender.
+        receiver: Option<String>,
+        /// The amount of time in seconds to lock the new LP tokens in the farm manager, if any.
+        unlocking_duration: Option<u64>,
+        /// The identifier of the position to lock the new LP tokens in the farm manager, if any.
+        lock_position_identifier: Option<String>,
+        /// How the excess of an imbalanced deposit into a constant product pool is handled. If not
+        /// provided, the excess is donated to the pool.
+        excess_mode: Option<ExcessDepositMode>,
+        /// The position to create when migrating into a concentrated liquidity pool. Required for
+        /// concentrated liquidity pools, whose positions can't be locked nor sent to a receiver.
+        concentrated_position: Option<ConcentratedPositionRange>,
+    },
     /// Execute multiple [`SwapOperations`] to allow for multi-hop swaps.
     ExecuteSwapOperations {
         /// The operations that should be performed in sequence.
@@ -200,6 +444,165 @@ pub enum ExecuteMsg {
         /// If left unspecified, there is no limit to what spread the transaction can incur.
         max_spread: Option<Decimal>,
     },
+    /// Execute multiple [`SwapOperations`] for an exact amount of the output, i.e. the final swap
+    /// operation token. Only the input needed is taken from the funds sent in the [`MessageInfo`],
+    /// the rest is refunded to the sender.
+    ExecuteSwapOperationsExactOut {
+        /// The operations that should be performed in sequence.
+        operations: Vec<SwapOperation>,
+        /// The exact amount of the output to receive.
+        ask_amount: Uint128,
+        /// The maximum amount of the input to swap. If the operations require more, the message
+        /// will not be executed. If not provided, the input is capped by the funds sent.
+        max_offer_amount: Option<Uint128>,
+        /// The (optional) recipient of the output tokens.
+        ///
+        /// If left unspecified, tokens will be sent to the sender of the message.
+        receiver: Option<String>,
+        /// The (optional) maximum spread to incur when performing any swap.
+        ///
+        /// If left unspecified, there is no limit to what spread the transaction can incur.
+        max_spread: Option<Decimal>,
+    },
+    /// Splits the funds sent in the [`MessageInfo`] across several routes swapping the same
+    /// assets, e.g. pools of the same pair with different fee tiers, to reduce the price impact
+    /// of large trades. The output of all the routes is aggregated and sent to the receiver.
+    ExecuteSplitSwapOperations {
+        /// The routes to split the funds across. All of them must start with the same asset and
+        /// end with the same asset.
+        routes: Vec<SplitSwapRoute>,
+        /// The minimum amount of the aggregated output required for the message to succeed.
+        minimum_receive: Option<Uint128>,
+        /// The (optional) recipient of the output tokens.
+        ///
+        /// If left unspecified, tokens will be sent to the sender of the message.
+        receiver: Option<String>,
+        /// The (optional) maximum spread to incur when performing any swap.
+        ///
+        /// If left unspecified, there is no limit to what spread the transaction can incur.
+        max_spread: Option<Decimal>,
+    },
+    /// Swaps the funds sent in the [`MessageInfo`] for the ask asset, following the swap route
+    /// registered for the pair.
+    SwapViaRoute {
+        /// The asset to get after the swap.
+        ask_asset_denom: String,
+        /// The minimum amount of the output required for the message to succeed.
+        minimum_receive: Option<Uint128>,
+        /// The (optional) recipient of the output tokens.
+        ///
+        /// If left unspecified, tokens will be sent to the sender of the message.
+        receiver: Option<String>,
+        /// The (optional) maximum spread to incur when performing any swap.
+        ///
+        /// If left unspecified, there is no limit to what spread the transaction can incur.
+        max_spread: Option<Decimal>,
+    },
+    /// Registers swap routes, i.e. the operations to swap an offer asset for an ask asset. Only
+    /// the owner of the contract can register routes. Only one route can be registered per pair,
+    /// and the sender is recorded as its creator.
+    AddSwapRoutes {
+        /// The swap routes to register.
+        swap_routes: Vec<SwapRoute>,
+    },
+    /// Removes swap routes. Only the owner of the contract can remove routes.
+    RemoveSwapRoutes {
+        /// The pairs of the swap routes to remove, as (offer asset denom, ask asset denom).
+        swap_routes: Vec<(String, String)>,
+    },
+    /// Swaps an asset of a metapool for an asset of its base pool, or the other way around, i.e.
+    /// the metapool asset is swapped for the base pool LP token which is withdrawn into the base
+    /// pool asset, or the base pool asset is deposited into the base pool and the minted LP token
+    /// is swapped for the metapool asset.
+    SwapUnderlying {
+        /// The asset to receive, either the metapool asset or one of the base pool assets.
+        ask_asset_denom: String,
+        /// The minimum amount of the ask asset required for the message to succeed.
+        minimum_receive: Option<Uint128>,
+        /// The (optional) recipient of the output tokens.
+        ///
+        /// If left unspecified, tokens will be sent to the sender of the message.
+        receiver: Option<String>,
+        /// The maximum spread to incur when swapping in the metapool.
+        max_spread: Option<Decimal>,
+        /// The identifier of the metapool.
+        pool_identifier: String,
+    },
+    /// Manages a position in a concentrated liquidity pool.
+    ManageConcentratedPosition {
+        /// The action to perform on the position.
+        action: ConcentratedPositionAction,
+    },
+    /// Ramps the amplification of a stable swap pool linearly from its current value to
+    /// `target_amp`, reached at `end_time`. Only the owner can ramp the amplification.
+    RampAmp {
+        /// The identifier of the stable swap pool.
+        pool_identifier: String,
+        /// The amplification to reach at the end of the ramp.
+        target_amp: u64,
+        /// The timestamp in seconds at which the ramp ends.
+        end_time: u64,
+    },
+    /// Stops the ongoing amplification ramp of a stable swap pool, keeping its current
+    /// amplification. Only the owner can stop a ramp.
+    StopRampAmp {
+        /// The identifier of the stable swap pool.
+        pool_identifier: String,
+    },
+    /// Sends the balances of the assets of a pool the contract holds beyond what the pools account
+    /// for, e.g. tokens sent to the contract by mistake, to the given recipient. Only the owner can
+    /// skim.
+    Skim {
+        /// The identifier of the pool whose assets are skimmed.
+        pool_identifier: String,
+        /// The recipient of the skimmed assets.
+        recipient: String,
+    },
+    /// Transfers LP shares of a pool with internal LP accounting to the recipient.
+    TransferShares {
+        /// The identifier of the pool the shares belong to.
+        pool_identifier: String,
+        /// The recipient of the shares.
+        recipient: String,
+        /// The amount of shares to transfer.
+        amount: Uint128,
+    },
+    /// Transfers LP shares of a pool with internal LP accounting to a contract, executing the
+    /// given message on it, as LP tokens sent as funds would be. The contract must handle
+    /// [SharesReceiverExecuteMsg::ReceiveShares]. If the contract is the pool manager itself, the
+    /// message is a withdrawal or migration message, executed with the shares as funds.
+    SendShares {
+        /// The identifier of the pool the shares belong to.
+        pool_identifier: String,
+        /// The amount of shares to send.
+        amount: Uint128,
+        /// The contract to send the shares to.
+        contract: String,
+        /// The message to execute with the shares.
+        msg: Binary,
+    },
+    /// Sets the amount of LP shares of a pool with internal LP accounting the spender can transfer
+    /// on behalf of the sender.
+    ApproveShares {
+        /// The identifier of the pool the shares belong to.
+        pool_identifier: String,
+        /// The account allowed to transfer the shares.
+        spender: String,
+        /// The amount of shares the spender can transfer.
+        amount: Uint128,
+    },
+    /// Transfers LP shares of a pool with internal LP accounting from the owner to the recipient,
+    /// within the allowance the owner gave to the sender.
+    TransferSharesFrom {
+        /// The identifier of the pool the shares belong to.
+        pool_identifier: String,
+        /// The owner of the shares.
+        owner: String,
+        /// The recipient of the shares.
+        recipient: String,
+        /// The amount of shares to transfer.
+        amount: Uint128,
+    },
     /// Updates the configuration of the contract.
     /// If a field is not specified (i.e., set to `None`), it will not be modified.
     UpdateConfig {
@@ -215,6 +618,54 @@ pub enum ExecuteMsg {
     },
 }
 
+/// The tick range and identifier of a concentrated liquidity position to create.
+#[cw_serde]
+pub struct ConcentratedPositionRange {
+    /// The lower tick of the range, must be a multiple of the pool's tick spacing.
+    pub lower_tick: i64,
+    /// The upper tick of the range, must be a multiple of the pool's tick spacing.
+    pub upper_tick: i64,
+    /// The identifier of the position. If not provided, a random one will be generated.
+    pub identifier: Option<String>,
+}
+
+/// The actions that can be performed on a concentrated liquidity position.
+#[cw_serde]
+pub enum ConcentratedPositionAction {
+    /// Creates a position with the funds sent, providing liquidity within the given tick range.
+    /// The funds that are not needed to provide liquidity at the current price are refunded.
+    Create {
+        /// The identifier of the concentrated liquidity pool.
+        pool_identifier: String,
+        /// The lower tick of the range, must be a multiple of the pool's tick spacing.
+        lower_tick: i64,
+        /// The upper tick of the range, must be a multiple of the pool's tick spacing.
+        upper_tick: i64,
+        /// The identifier of the position. If not provided, a random one will be generated.
+        identifier: Option<String>,
+    },
+    /// Expands an existing position with the funds sent. The funds that are not needed to
+    /// provide liquidity at the current price are refunded.
+    Expand {
+        /// The identifier of the position to expand.
+        identifier: String,
+    },
+    /// Withdraws liquidity from a position, together with the fees accrued by it. The position is
+    /// removed once all its liquidity is withdrawn.
+    Withdraw {
+        /// The identifier of the position to withdraw from.
+        identifier: String,
+        /// The amount of liquidity to withdraw. If not provided, all the liquidity in the position
+        /// is withdrawn.
+        liquidity: Option<Uint128>,
+    },
+    /// Collects the fees accrued by a position.
+    CollectFees {
+        /// The identifier of the position to collect the fees from.
+        identifier: String,
+    },
+}
+
 #[cw_ownable_query]
 #[cw_serde]
 #[derive(QueryResponses)]
@@ -268,6 +719,75 @@ pub enum QueryMsg {
         /// The operations to perform.
         operations: Vec<SwapOperation>,
     },
+    /// Searches the pools for the route swapping the offer asset for the ask asset with the best
+    /// simulated output. The operations returned can be used with `ExecuteSwapOperations`. The
+    /// query fails if the search simulates more than 200 swaps.
+    #[returns(BestRouteResponse)]
+    BestRoute {
+        /// The asset to swap.
+        offer_asset: Coin,
+        /// The asset to get after the swap.
+        ask_asset_denom: String,
+        /// The maximum amount of swap operations of the route. Defaults to 3, and can't be over 4.
+        max_hops: Option<u32>,
+        /// The maximum amount of pools to scan for the route. Only the first pools by identifier
+        /// are scanned, the pools after them are never part of the route. Defaults to 50, and
+        /// can't be over 100.
+        max_pools: Option<u32>,
+    },
+    /// Retrieves the swap route registered to swap the offer asset for the ask asset.
+    #[returns(SwapRouteResponse)]
+    SwapRoute {
+        /// The offer asset denom of the route.
+        offer_asset_denom: String,
+        /// The ask asset denom of the route.
+        ask_asset_denom: String,
+    },
+    /// Retrieves the registered swap routes.
+    #[returns(SwapRoutesResponse)]
+    SwapRoutes {
+        /// An optional parameter specifying what route, as (offer asset denom, ask asset denom),
+        /// to start searching after.
+        start_after: Option<(String, String)>,
+        /// The amount of routes to return. If unspecified, will default to a value specified by
+        /// the contract.
+        limit: Option<u32>,
+    },
+    /// Retrieves the creator of the swap route registered to swap the offer asset for the ask
+    /// asset.
+    #[returns(SwapRouteCreatorResponse)]
+    SwapRouteCreator {
+        /// The offer asset denom of the route.
+        offer_asset_denom: String,
+        /// The ask asset denom of the route.
+        ask_asset_denom: String,
+    },
+    /// Simulates providing liquidity to a pool, going through the same computation as the
+    /// `ProvideLiquidity` message.
+    #[returns(SimulateProvideLiquidityResponse)]
+    SimulateProvideLiquidity {
+        /// The identifier for the pool to provide liquidity for.
+        pool_identifier: String,
+        /// The assets to deposit.
+        deposits: Vec<Coin>,
+        /// The slippage tolerance of the liquidity provision.
+        slippage_tolerance: Option<Decimal>,
+        /// The maximum spread of the swaps performed by the liquidity provision.
+        max_spread: Option<Decimal>,
+        /// How the excess of an imbalanced deposit into a constant product pool is handled.
+        excess_mode: Option<ExcessDepositMode>,
+    },
+    /// Simulates withdrawing liquidity from a pool, going through the same computation as the
+    /// `WithdrawLiquidity` message.
+    #[returns(SimulateWithdrawLiquidityResponse)]
+    SimulateWithdrawLiquidity {
+        /// The identifier for the pool to withdraw liquidity from.
+        pool_identifier: String,
+        /// The amount of LP tokens to withdraw.
+        lp_amount: Uint128,
+        /// The asset to withdraw the liquidity into, if any.
+        target_denom: Option<String>,
+    },
     /// Retrieves the pool information for the given pool identifier.
     #[returns(PoolsResponse)]
     Pools {
@@ -280,6 +800,49 @@ pub enum QueryMsg {
         /// the contract.
         limit: Option<u32>,
     },
+    /// Retrieves the current state of a concentrated liquidity pool.
+    #[returns(ConcentratedPoolStateResponse)]
+    ConcentratedPoolState {
+        /// The identifier of the concentrated liquidity pool.
+        pool_identifier: String,
+    },
+    /// Retrieves concentrated liquidity positions.
+    #[returns(ConcentratedPositionsResponse)]
+    ConcentratedPositions {
+        /// An optional parameter specifying what to filter positions by.
+        filter_by: Option<ConcentratedPositionsBy>,
+        /// An optional parameter specifying what position (identifier) to start searching after.
+        start_after: Option<String>,
+        /// The amount of positions to return. If unspecified, will default to a value specified by
+        /// the contract.
+        limit: Option<u32>,
+    },
+    /// Compares the reserves the pools account for with the balances of the contract, per denom.
+    #[returns(ReserveHealthResponse)]
+    ReserveHealth {
+        /// The identifier of the pool whose assets are checked. If not provided, the assets of all
+        /// the pools are checked.
+        pool_identifier: Option<String>,
+    },
+    /// Retrieves the LP shares an address holds in a pool with internal LP accounting.
+    #[returns(ShareBalanceResponse)]
+    ShareBalance {
+        /// The identifier of the pool the shares belong to.
+        pool_identifier: String,
+        /// The address to query the shares of.
+        address: String,
+    },
+    /// Retrieves the LP shares of a pool with internal LP accounting the spender can transfer on
+    /// behalf of the owner.
+    #[returns(ShareAllowanceResponse)]
+    ShareAllowance {
+        /// The identifier of the pool the shares belong to.
+        pool_identifier: String,
+        /// The owner of the shares.
+        owner: String,
+        /// The account allowed to transfer the shares.
+        spender: String,
+    },
 }
 
 /// The response for the `Config` query.
@@ -330,6 +893,8 @@ pub struct SimulationResponse {
     pub burn_fee_amount: Uint128,
     /// The extra fees amount of the swap.
     pub extra_fees_amount: Uint128,
+    /// The oracle price the swap was computed with, if the pool is an oracle pegged pool.
+    pub oracle_price: Option<Decimal>,
 }
 
 /// ReverseSimulationResponse returns reverse swap simulation response
@@ -347,6 +912,115 @@ pub struct ReverseSimulationResponse {
     pub burn_fee_amount: Uint128,
 }
 
+/// A swap performed as part of a liquidity provision or withdrawal.
+#[cw_serde]
+pub struct LiquiditySwap {
+    /// The asset swapped.
+    pub offer_asset: Coin,
+    /// The asset returned by the swap.
+    pub return_asset: Coin,
+    /// The spread amount of the swap.
+    pub spread_amount: Uint128,
+    /// The swap fee amount of the swap.
+    pub swap_fee_amount: Uint128,
+    /// The protocol fee amount of the swap.
+    pub protocol_fee_amount: Uint128,
+    /// The burn fee amount of the swap.
+    pub burn_fee_amount: Uint128,
+}
+
+/// The response for the `SimulateProvideLiquidity` query.
+#[cw_serde]
+pub struct SimulateProvideLiquidityResponse {
+    /// The LP tokens minted for the deposit.
+    pub lp_amount: Uint128,
+    /// The assets added to the pool.
+    pub deposited_assets: Vec<Coin>,
+    /// The excess of the deposit refunded to the sender.
+    pub refunded_assets: Vec<Coin>,
+    /// The slippage of the deposit, as checked against the slippage tolerance.
+    pub slippage: Decimal256,
+    /// The swaps performed before providing the liquidity, i.e. of single asset deposits into the
+    /// other assets of the pool, or of the excess of a deposit.
+    pub swaps: Vec<LiquiditySwap>,
+}
+
+/// The response for the `SimulateWithdrawLiquidity` query.
+#[cw_serde]
+pub struct SimulateWithdrawLiquidityResponse {
+    /// The assets returned for the LP tokens.
+    pub returned_assets: Vec<Coin>,
+    /// The swaps of the withdrawn assets into the target asset, if any.
+    pub swaps: Vec<LiquiditySwap>,
+}
+
+/// The reserves of a denom the pools account for, compared with the balance of the contract.
+#[cw_serde]
+pub struct ReserveHealth {
+    /// The denom checked.
+    pub denom: String,
+    /// The amount the contract should hold, i.e. the reserves of the pools together with the fees
+    /// owed to concentrated liquidity positions and the LP tokens locked by the contract.
+    pub accounted: Uint128,
+    /// The balance of the contract.
+    pub balance: Uint128,
+    /// The balance held beyond the accounted amount, which can be skimmed.
+    pub surplus: Uint128,
+    /// The accounted amount missing from the balance, which signals an accounting bug.
+    pub deficit: Uint128,
+}
+
+/// The response for the `ReserveHealth` query.
+#[cw_serde]
+pub struct ReserveHealthResponse {
+    /// The reserve health of each denom checked.
+    pub reserves: Vec<ReserveHealth>,
+}
+
+/// The response for the `ShareBalance` query.
+#[cw_serde]
+pub struct ShareBalanceResponse {
+    /// The LP shares held, as a coin of the pool's LP denom.
+    pub balance: Coin,
+}
+
+/// The response for the `ShareAllowance` query.
+#[cw_serde]
+pub struct ShareAllowanceResponse {
+    /// The amount of shares the spender can transfer on behalf of the owner.
+    pub allowance: Uint128,
+}
+
+/// The message the pool manager executes on a contract LP shares are sent to with `SendShares`.
+#[cw_serde]
+pub struct SharesReceiveMsg {
+    /// The account that sent the shares.
+    pub sender: String,
+    /// The shares sent, as a coin of the pool's LP denom.
+    pub lp_asset: Coin,
+    /// The message to execute with the shares.
+    pub msg: Binary,
+}
+
+impl SharesReceiveMsg {
+    /// Creates the message executing [SharesReceiverExecuteMsg::ReceiveShares] on the contract.
+    pub fn into_cosmos_msg(self, contract_addr: impl Into<String>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg: to_json_binary(&SharesReceiverExecuteMsg::ReceiveShares(self))?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+/// The execute message contracts receiving LP shares with `SendShares` must implement.
+#[cw_serde]
+pub enum SharesReceiverExecuteMsg {
+    /// Receives LP shares sent by the pool manager.
+    ReceiveShares(SharesReceiveMsg),
+}
+
 /// Pool feature toggle, can control whether swaps, deposits, and withdrawals are enabled.
 #[cw_serde]
 pub struct FeatureToggle {
@@ -372,6 +1046,102 @@ pub struct ReverseSimulateSwapOperationsResponse {
     pub amount: Uint128,
 }
 
+/// The response for the `BestRoute` query.
+#[cw_serde]
+pub struct BestRouteResponse {
+    /// The operations of the route with the best simulated output.
+    pub operations: Vec<SwapOperation>,
+    /// The simulated amount of the ask asset after the swap operations.
+    pub amount: Uint128,
+}
+
+/// A swap route, i.e. the operations to swap an offer asset for an ask asset.
+#[cw_serde]
+pub struct SwapRoute {
+    /// The asset the route swaps.
+    pub offer_asset_denom: String,
+    /// The asset the route returns.
+    pub ask_asset_denom: String,
+    /// The operations to perform in sequence.
+    pub swap_operations: Vec<SwapOperation>,
+}
+
+/// The response for the `SwapRoute` query.
+#[cw_serde]
+pub struct SwapRouteResponse {
+    /// The swap route registered for the pair.
+    pub swap_route: SwapRoute,
+}
+
+/// The response for the `SwapRoutes` query.
+#[cw_serde]
+pub struct SwapRoutesResponse {
+    /// The registered swap routes.
+    pub swap_routes: Vec<SwapRoute>,
+}
+
+/// The response for the `SwapRouteCreator` query.
+#[cw_serde]
+pub struct SwapRouteCreatorResponse {
+    /// The address that registered the swap route.
+    pub creator: String,
+}
+
+/// The response for the `ConcentratedPoolState` query.
+#[cw_serde]
+pub struct ConcentratedPoolStateResponse {
+    /// The identifier of the concentrated liquidity pool.
+    pub pool_identifier: String,
+    /// The current price of the pool, i.e. how many units of the second asset one unit of the
+    /// first asset is worth.
+    pub price: Decimal256,
+    /// The current square root of the price of the pool.
+    pub sqrt_price: Decimal256,
+    /// The current tick of the pool.
+    pub tick: i64,
+    /// The liquidity that is active at the current tick.
+    pub liquidity: Uint128,
+}
+
+/// The filter for the `ConcentratedPositions` query.
+#[cw_serde]
+pub enum ConcentratedPositionsBy {
+    /// Filters the positions by owner.
+    Owner(String),
+    /// Filters the positions by identifier.
+    Identifier(String),
+}
+
+/// A position in a concentrated liquidity pool.
+#[cw_serde]
+pub struct ConcentratedPosition {
+    /// The identifier of the position.
+    pub identifier: String,
+    /// The identifier of the pool the position provides liquidity to.
+    pub pool_identifier: String,
+    /// The owner of the position.
+    pub owner: Addr,
+    /// The lower tick of the position's range.
+    pub lower_tick: i64,
+    /// The upper tick of the position's range.
+    pub upper_tick: i64,
+    /// The liquidity provided by the position within its range.
+    pub liquidity: Uint128,
+    /// The fee growth per unit of liquidity inside the position's range the last time the
+    /// position was updated, for each of the pool assets.
+    pub fee_growth_inside_last: Vec<Uint256>,
+    /// The fees accrued by the position that haven't been collected yet, as of the last time the
+    /// position was updated.
+    pub fees_owed: Vec<Coin>,
+}
+
+/// The response for the `ConcentratedPositions` query.
+#[cw_serde]
+pub struct ConcentratedPositionsResponse {
+    /// The positions, with their fees owed up to date.
+    pub positions: Vec<ConcentratedPosition>,
+}
+
 /// Gets the total supply of the given liquidity asset
 pub fn get_total_share(deps: &Deps, liquidity_asset: String) -> StdResult<Uint128> {
     if is_factory_token(liquidity_asset.as_str()) {